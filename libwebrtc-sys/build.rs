@@ -166,11 +166,13 @@ fn build_entrypoint(output_dir: String, target_os: String) {
         &"src/stun.rs",
         &"src/sctp/socket.rs",
         &"src/p2p/ice_transport_internal.rs",
+        &"src/p2p/turn_server.rs",
         &"src/async_dns_resolver_factory.rs",
         &"src/pc/session_description.rs",
         &"src/pc/jsep_api.rs",
         &"src/rtc_base/base.rs",
         &"src/rtc_base/certificates.rs",
+        &"src/rtc_base/dtls.rs",
         &"src/shared_bridge.rs",
         &"src/logging.rs",
         &"src/candidate.rs",
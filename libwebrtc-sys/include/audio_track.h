@@ -18,7 +18,7 @@ public:
         return api;
     }
 
-    rust::String id()
+    rust::String id() const
     {
         return rust::String(api->id().c_str());
     }
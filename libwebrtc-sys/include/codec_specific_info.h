@@ -34,6 +34,29 @@ public:
         this->api->end_of_picture = end_of_picture;
     }
 
+    // Temporal layer the encoded frame belongs to or -1 if the encoder did not
+    // assign one (non-scalable stream).
+    int32_t get_temporal_index() const
+    {
+        uint8_t temporal_idx = webrtc::kNoTemporalIdx;
+        switch (api->codecType)
+        {
+        case webrtc::VideoCodecType::kVideoCodecVP8:
+            temporal_idx = api->codecSpecific.VP8.temporalIdx;
+            break;
+        case webrtc::VideoCodecType::kVideoCodecVP9:
+            temporal_idx = api->codecSpecific.VP9.temporal_idx;
+            break;
+        case webrtc::VideoCodecType::kVideoCodecH264:
+            temporal_idx = api->codecSpecific.H264.temporal_idx;
+            break;
+        default:
+            break;
+        }
+
+        return temporal_idx == webrtc::kNoTemporalIdx ? -1 : temporal_idx;
+    }
+
     const webrtc::CodecSpecificInfo& as_ref() const
     {
         return *api.get();
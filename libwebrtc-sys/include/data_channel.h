@@ -121,7 +121,12 @@ public:
         this->api->Close();
     }
 
-    void send(const ArcasDataBuffer& rust_buf);
+    bool send(const ArcasDataBuffer& rust_buf);
+
+    std::unique_ptr<ArcasDataChannel> clone() const
+    {
+        return std::make_unique<ArcasDataChannel>(this->api);
+    }
 };
 
 std::unique_ptr<ArcasDataChannel> gen_unique_data_channel();
@@ -69,3 +69,9 @@ public:
 };
 
 std::unique_ptr<ArcasEncodedImageFactory> create_arcas_encoded_image_factory();
+
+// Spatial layer index of the encoded image or -1 when not assigned.
+int32_t get_encoded_image_spatial_index(const webrtc::EncodedImage& image);
+
+// True when the encoded image is a keyframe.
+bool is_encoded_image_key_frame(const webrtc::EncodedImage& image);
@@ -0,0 +1,115 @@
+#pragma once
+#include "api/transport/stun.h"
+#include "p2p/base/basic_packet_socket_factory.h"
+#include "p2p/base/stun_server.h"
+#include "p2p/base/turn_server.h"
+#include "rtc_base/async_udp_socket.h"
+#include "rtc_base/thread.h"
+#include "rust/cxx.h"
+#include <atomic>
+#include <map>
+
+struct ArcasTurnServerConfig;
+struct ArcasTurnAllocation;
+
+// Answers binding requests as a NAT with `mapped_ip` as its public address
+// would when it is set, so clients on this host gather server reflexive
+// candidates that differ from their host candidates.
+class ArcasMappingStunServer : public cricket::StunServer
+{
+private:
+    rtc::IPAddress _mapped_ip;
+    std::atomic<uint32_t> _binding_requests{0};
+
+public:
+    ArcasMappingStunServer(rtc::AsyncUDPSocket* socket, const rtc::IPAddress& mapped_ip)
+    : cricket::StunServer(socket)
+    , _mapped_ip(mapped_ip)
+    {
+    }
+
+    uint32_t binding_requests() const
+    {
+        return _binding_requests;
+    }
+
+protected:
+    void OnBindingRequest(cricket::StunMessage* message, const rtc::SocketAddress& remote_address) override;
+};
+
+class ArcasStunServer
+{
+private:
+    std::unique_ptr<rtc::Thread> _thread;
+    std::unique_ptr<ArcasMappingStunServer> _server;
+    rtc::SocketAddress _local_address;
+
+public:
+    ArcasStunServer(std::unique_ptr<rtc::Thread> thread)
+    : _thread(std::move(thread))
+    {
+    }
+    ~ArcasStunServer();
+
+    bool Start(const rtc::SocketAddress& address, const rtc::IPAddress& mapped_ip);
+
+    uint32_t binding_requests() const
+    {
+        return _server->binding_requests();
+    }
+
+    rust::String local_address() const
+    {
+        return rust::String(_local_address.ipaddr().ToString());
+    }
+
+    uint16_t local_port() const
+    {
+        return _local_address.port();
+    }
+};
+
+// Long-term credentials, the key is the MD5 of username:realm:password.
+class ArcasTurnAuth : public cricket::TurnAuthInterface
+{
+private:
+    std::map<std::string, std::string> _keys;
+
+public:
+    void add_user(const std::string& username, const std::string& realm, const std::string& password);
+    bool GetKey(const std::string& username, const std::string& realm, std::string* key) override;
+};
+
+class ArcasTurnServer
+{
+private:
+    std::unique_ptr<rtc::Thread> _thread;
+    ArcasTurnAuth _auth;
+    std::unique_ptr<rtc::BasicPacketSocketFactory> _socket_factory;
+    std::unique_ptr<cricket::TurnServer> _server;
+    rtc::SocketAddress _local_address;
+
+public:
+    ArcasTurnServer(std::unique_ptr<rtc::Thread> thread)
+    : _thread(std::move(thread))
+    {
+    }
+    ~ArcasTurnServer();
+
+    bool Start(const ArcasTurnServerConfig& config);
+
+    rust::String local_address() const
+    {
+        return rust::String(_local_address.ipaddr().ToString());
+    }
+
+    uint16_t local_port() const
+    {
+        return _local_address.port();
+    }
+
+    rust::Vec<ArcasTurnAllocation> allocations() const;
+};
+
+std::unique_ptr<ArcasStunServer> create_arcas_stun_server(rust::String address, uint16_t port, rust::String mapped_ip);
+std::unique_ptr<ArcasTurnServer> create_arcas_turn_server(ArcasTurnServerConfig config);
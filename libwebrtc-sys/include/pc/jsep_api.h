@@ -3,6 +3,8 @@
 #include "api/peer_connection_interface.h"
 #include "api/rtc_event_log/rtc_event_log.h"
 #include "api/transport/sctp_transport_factory_interface.h"
+#include "call/rtp_demuxer.h"
+#include "call/rtp_packet_sink_interface.h"
 #include "libwebrtc-sys/include/candidate.h"
 #include "libwebrtc-sys/include/error.h"
 #include "libwebrtc-sys/include/ice_candidate.h"
@@ -11,17 +13,36 @@
 #include "libwebrtc-sys/include/rtc_base/base.h"
 #include "libwebrtc-sys/include/rtc_base/certificates.h"
 #include "libwebrtc-sys/include/session_description.h"
+#include "modules/rtp_rtcp/source/rtp_packet_received.h"
 #include "p2p/base/default_ice_transport_factory.h"
 #include "p2p/client/basic_port_allocator.h"
 #include "pc/jsep_transport_controller.h"
 #include "rust/cxx.h"
 #include <pc/srtp_transport.h>
+#include <map>
 #include <rtti.h>
 
 struct ArcasRustJsepRTCPHandler;
 struct ArcasRustDTLSHandshakeErrorHandler;
 struct ArcasRustJsepTransportControllerObserver;
 struct ArcasCandidateWrapper;
+struct ArcasRustJsepTransportControllerObserverWrapper;
+struct ArcasRustRtpPacketSink;
+enum class ArcasRtpSinkRegistration : std::uint8_t;
+
+class ArcasRtpPacketSink : public webrtc::RtpPacketSinkInterface
+{
+private:
+    rust::Box<ArcasRustRtpPacketSink> _sink;
+
+public:
+    ArcasRtpPacketSink(rust::Box<ArcasRustRtpPacketSink> sink)
+    : _sink(std::move(sink))
+    {
+    }
+
+    void OnRtpPacket(const webrtc::RtpPacketReceived& packet) override;
+};
 
 class ArcasJsepTransportControllerObserver : public webrtc::JsepTransportController::Observer
 {
@@ -156,6 +177,20 @@ private:
     std::unique_ptr<ArcasJsepTransportControllerConfig> _config;
     std::unique_ptr<cricket::PortAllocator> _port_allocator;
     rtc::Thread* const _network_thread;
+    absl::optional<rust::Box<ArcasRustJsepTransportControllerObserverWrapper>> _observer;
+    // By mid, outlive the transports they're registered with unless
+    // unregistered first.
+    std::map<std::string, std::unique_ptr<ArcasRtpPacketSink>> _rtp_sinks;
+
+    template<typename T, typename F>
+    T on_network_thread(F&& f) const
+    {
+        if (_network_thread->IsCurrent())
+        {
+            return f();
+        }
+        return _network_thread->Invoke<T>(RTC_FROM_HERE, std::forward<F>(f));
+    }
 
 public:
     ArcasJsepTransportController(rtc::Thread* network_thread,
@@ -180,6 +215,7 @@ public:
         {
             _transport_controller.reset();
             _port_allocator.reset();
+            _rtp_sinks.clear();
         };
         if (_network_thread)
         {
@@ -258,6 +294,15 @@ public:
         return _transport_controller->GetDataChannelTransport(mid.c_str());
     }
 
+    bool send_rtp(rust::String mid, rust::Slice<const uint8_t> packet);
+    bool send_rtcp(rust::String mid, rust::Slice<const uint8_t> packet);
+    bool rtp_transport_writable(rust::String mid) const;
+    ArcasRtpSinkRegistration register_rtp_sink(rust::String mid,
+                                               rust::Vec<uint8_t> payload_types,
+                                               rust::Box<ArcasRustRtpPacketSink> sink);
+    void unregister_rtp_sink(rust::String mid);
+    void subscribe_observer(rust::Box<ArcasRustJsepTransportControllerObserverWrapper> observer);
+
     void set_ice_config(std::unique_ptr<ArcasP2PIceConfig> config)
     {
         // Ensure we always run this method on the correct thread..
@@ -271,12 +316,12 @@ public:
 
     void set_needs_ice_restart_flag()
     {
-        _transport_controller->SetNeedsIceRestartFlag();
+        on_network_thread<void>([&] { _transport_controller->SetNeedsIceRestartFlag(); });
     }
 
     bool needs_ice_restart(rust::String mid) const
     {
-        return _transport_controller->NeedsIceRestart(mid.c_str());
+        return on_network_thread<bool>([&] { return _transport_controller->NeedsIceRestart(mid.c_str()); });
     }
 
     void maybe_start_gathering()
@@ -291,7 +336,12 @@ public:
 
     std::unique_ptr<ArcasSSLCertificate> get_local_certificate(rust::String mid) const
     {
-        auto cert = _transport_controller->GetLocalCertificate(mid.c_str());
+        auto cert = on_network_thread<rtc::scoped_refptr<rtc::RTCCertificate>>(
+            [&] { return _transport_controller->GetLocalCertificate(mid.c_str()); });
+        if (!cert)
+        {
+            return nullptr;
+        }
         return std::make_unique<ArcasSSLCertificate>(cert);
     }
 
@@ -351,6 +401,10 @@ inline std::unique_ptr<rtc::CopyOnWriteBuffer> create_buffer_with_data(rust::Sli
 {
     return std::make_unique<rtc::CopyOnWriteBuffer>(bytes.data(), bytes.size());
 }
+inline rust::Slice<std::uint8_t const> buffer_data(rtc::CopyOnWriteBuffer const& buffer)
+{
+    return {buffer.cdata(), buffer.size()};
+}
 
 inline void init_port_alloc(cricket::PortAllocator& port_alloc, rtc::Thread& network_thread)
 {
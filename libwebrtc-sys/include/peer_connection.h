@@ -1,5 +1,6 @@
 #pragma once
 #include "api/create_peerconnection_factory.h"
+#include "libwebrtc-sys/include/alias.h"
 #include "libwebrtc-sys/include/audio_track.h"
 #include "libwebrtc-sys/include/data_channel.h"
 #include "libwebrtc-sys/include/ice_candidate.h"
@@ -52,6 +53,21 @@ public:
         api->Close();
     }
 
+    void restart_ice() const
+    {
+        api->RestartIce();
+    }
+
+    ArcasRTCSignalingState signaling_state() const
+    {
+        return api->signaling_state();
+    }
+
+    ArcasPeerConnectionState connection_state() const
+    {
+        return api->peer_connection_state();
+    }
+
     void add_video_track(std::unique_ptr<ArcasVideoTrack> track, rust::Vec<rust::String> rust_stream_ids) const
     {
         std::vector<std::string> stream_ids;
@@ -4,6 +4,7 @@
 #include "audio_device_module.h"
 #include "audio_track.h"
 #include "audio_track_source.h"
+#include "libwebrtc-sys/include/rtc_base/certificates.h"
 #include "peer_connection.h"
 #include "rust_shared.h"
 #include "video_decoder_factory.h"
@@ -67,4 +68,6 @@ public:
 };
 
 std::unique_ptr<webrtc::PeerConnectionInterface::RTCConfiguration> create_rtc_configuration(ArcasPeerConnectionConfig config);
+void add_rtc_configuration_certificate(webrtc::PeerConnectionInterface::RTCConfiguration& config,
+                                       const ArcasSSLCertificate& certificate);
 std::unique_ptr<ArcasPeerConnectionFactory> gen_unique_peer_connection_factory();
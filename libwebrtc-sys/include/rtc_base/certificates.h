@@ -53,6 +53,17 @@ public:
         return rust_vec;
     }
 
+    std::unique_ptr<ArcasSSLCertificate> clone() const
+    {
+        return std::make_unique<ArcasSSLCertificate>(_certificate);
+    }
+
+    // Milliseconds since the epoch.
+    uint64_t expires() const
+    {
+        return _certificate->Expires();
+    }
+
     ArcasRTCCertificatePEM to_pem() const;
 
     rust::String get_fingerprint() const
@@ -66,6 +77,9 @@ std::unique_ptr<ArcasKeyParams> create_arcas_key_params_rsa();
 std::unique_ptr<ArcasKeyParams> create_arcas_key_params_ecdsa();
 std::unique_ptr<rtc::SSLIdentity> create_arcas_ssl_identity_with_key_params(rust::String common_name, std::unique_ptr<ArcasKeyParams> key_params);
 std::unique_ptr<rtc::SSLIdentity> create_arcas_ssl_identity_with_key_type(rust::String common_name, rtc::KeyType key_type);
+std::unique_ptr<rtc::SSLIdentity> create_arcas_ssl_identity_with_lifetime(rust::String common_name,
+                                                                          std::unique_ptr<ArcasKeyParams> key_params,
+                                                                          int64_t lifetime_seconds);
 
 std::unique_ptr<ArcasSSLCertificate> create_arcas_rtc_certificate(std::unique_ptr<rtc::SSLIdentity> identity);
 
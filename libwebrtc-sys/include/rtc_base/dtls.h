@@ -0,0 +1,68 @@
+#pragma once
+#include "libwebrtc-sys/include/rtc_base/certificates.h"
+#include "rtc_base/buffer.h"
+#include "rtc_base/ssl_stream_adapter.h"
+#include "rtc_base/stream.h"
+#include "rtc_base/thread.h"
+#include "rust/cxx.h"
+#include <deque>
+
+struct ArcasRustDtlsCallbacks;
+
+// Datagrams passed between rust and an SSLStreamAdapter, like
+// cricket::StreamInterfaceChannel without an ICE transport underneath.
+class ArcasDtlsPacketStream : public rtc::StreamInterface
+{
+private:
+    ArcasRustDtlsCallbacks& _callbacks;
+    std::deque<rtc::Buffer> _packets;
+
+public:
+    ArcasDtlsPacketStream(ArcasRustDtlsCallbacks& callbacks)
+    : _callbacks(callbacks)
+    {
+    }
+
+    void receive_packet(rust::Slice<const uint8_t> packet);
+
+    rtc::StreamState GetState() const override
+    {
+        return rtc::SS_OPEN;
+    }
+    rtc::StreamResult Read(void* buffer, size_t buffer_len, size_t* read, int* error) override;
+    rtc::StreamResult Write(const void* data, size_t data_len, size_t* written, int* error) override;
+    void Close() override {}
+};
+
+class ArcasDtlsSession : public sigslot::has_slots<>
+{
+private:
+    std::unique_ptr<rtc::Thread> _thread;
+    rust::Box<ArcasRustDtlsCallbacks> _callbacks;
+    // Owned by the adapter.
+    ArcasDtlsPacketStream* _stream;
+    std::unique_ptr<rtc::SSLStreamAdapter> _adapter;
+
+    void OnEvent(rtc::StreamInterface* stream, int events, int error);
+
+public:
+    ArcasDtlsSession(std::unique_ptr<rtc::Thread> thread, rust::Box<ArcasRustDtlsCallbacks> callbacks)
+    : _thread(std::move(thread)), _callbacks(std::move(callbacks)), _stream(nullptr)
+    {
+    }
+    ~ArcasDtlsSession();
+
+    bool Start(const ArcasSSLCertificate& certificate,
+               rtc::SSLRole role,
+               const std::string& peer_digest_algorithm,
+               const std::string& peer_fingerprint);
+
+    void receive_packet(rust::Slice<const uint8_t> packet) const;
+    bool send(rust::Slice<const uint8_t> data) const;
+};
+
+std::unique_ptr<ArcasDtlsSession> create_arcas_dtls_session(const ArcasSSLCertificate& certificate,
+                                                            rtc::SSLRole role,
+                                                            rust::String peer_digest_algorithm,
+                                                            rust::String peer_fingerprint,
+                                                            rust::Box<ArcasRustDtlsCallbacks> callbacks);
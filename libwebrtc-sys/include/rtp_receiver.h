@@ -1,9 +1,11 @@
 #pragma once
 #include "api/rtp_receiver_interface.h"
+#include "audio_track.h"
+#include "video_track.h"
 
 class ArcasRTPReceiver
 {
-private:
+protected:
     rtc::scoped_refptr<webrtc::RtpReceiverInterface> api;
 
 public:
@@ -16,6 +18,13 @@ class ArcasRTPVideoReceiver : public ArcasRTPReceiver
 public:
     ArcasRTPVideoReceiver(rtc::scoped_refptr<webrtc::RtpReceiverInterface> api)
     : ArcasRTPReceiver(api){};
+
+    std::unique_ptr<ArcasVideoTrack> get_track() const
+    {
+        rtc::scoped_refptr<webrtc::VideoTrackInterface> track(
+            static_cast<webrtc::VideoTrackInterface*>(api->track().get()));
+        return std::make_unique<ArcasVideoTrack>(track);
+    }
 };
 
 class ArcasRTPAudioReceiver : public ArcasRTPReceiver
@@ -23,6 +32,13 @@ class ArcasRTPAudioReceiver : public ArcasRTPReceiver
 public:
     ArcasRTPAudioReceiver(rtc::scoped_refptr<webrtc::RtpReceiverInterface> api)
     : ArcasRTPReceiver(api){};
+
+    std::unique_ptr<ArcasAudioTrack> get_track() const
+    {
+        rtc::scoped_refptr<webrtc::AudioTrackInterface> track(
+            static_cast<webrtc::AudioTrackInterface*>(api->track().get()));
+        return std::make_unique<ArcasAudioTrack>(track);
+    }
 };
 
 std::unique_ptr<ArcasRTPReceiver> gen_unique_rtp_receiver();
@@ -132,6 +132,31 @@ public:
         return std::make_unique<ArcasRTCError>(api->SetOfferedRtpHeaderExtensions(view));
     }
 
+    // Offer a header extension which libwebrtc supports but does not offer by
+    // default (e.g. abs-capture-time).
+    std::unique_ptr<ArcasRTCError> enable_header_extension(rust::String uri) const
+    {
+        auto list = api->HeaderExtensionsToOffer();
+        auto found = false;
+        for (auto& item : list)
+        {
+            if (item.uri == std::string(uri))
+            {
+                item.direction = webrtc::RtpTransceiverDirection::kSendRecv;
+                found = true;
+            }
+        }
+
+        if (!found)
+        {
+            return std::make_unique<ArcasRTCError>(
+                webrtc::RTCError(webrtc::RTCErrorType::UNSUPPORTED_PARAMETER, "unsupported header extension"));
+        }
+
+        rtc::ArrayView<webrtc::RtpHeaderExtensionCapability> view(list);
+        return std::make_unique<ArcasRTCError>(api->SetOfferedRtpHeaderExtensions(view));
+    }
+
     void get_stats(rust::Box<ArcasRustRTCStatsCollectorCallback> cb) const;
 };
 
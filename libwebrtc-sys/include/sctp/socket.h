@@ -1,5 +1,4 @@
 #pragma once
-#include <iostream>
 #include "net/dcsctp/socket/dcsctp_socket.h"
 #include "rust/cxx.h"
 
@@ -171,11 +170,6 @@ public:
                     std::unique_ptr<ArcasSctpPacketObserverProxyWrapper> packet_observer,
                     ArcasSctpOptions& options);
 
-    ~ArcasSctpSocket()
-    {
-        std::cout << "Destructor called" << std::endl;
-    }
-
     void receive_packet(rust::Slice<const uint8_t> data);
     void handle_timeout(uint64_t timeout_id);
     void connect();
@@ -183,7 +177,7 @@ public:
     void close();
     dcsctp::SendStatus send(uint16_t stream_id,
                             uint32_t ppid,
-                            rust::Vec<uint8_t> payload,
+                            rust::Slice<const uint8_t> payload,
                             ArcasSctpSendOptions& options);
     dcsctp::ResetStreamsStatus reset_streams(rust::Vec<uint16_t> incoming_streams);
     dcsctp::SocketState state() const;
@@ -1,11 +1,33 @@
 #pragma once
 #include "api/transport/stun.h"
+#include "rtc_base/byte_buffer.h"
 #include "rust/cxx.h"
 
 using ArcasCxxIntegrityStatus = cricket::StunMessage::IntegrityStatus;
 
+struct ArcasStunAttributeValue;
+
+// RFC 5780 NAT behavior discovery attributes, unknown to libwebrtc.
+constexpr int ARCAS_STUN_ATTR_RESPONSE_ORIGIN = 0x802B;
+constexpr int ARCAS_STUN_ATTR_OTHER_ADDRESS = 0x802C;
+
 class ArcasICEMessage : public cricket::IceMessage
 {
+protected:
+    // Unknown comprehension-optional attributes are skipped when reading,
+    // keep the RFC 5780 ones.
+    cricket::StunAttributeValueType GetAttributeValueType(int type) const override
+    {
+        switch (type)
+        {
+        case ARCAS_STUN_ATTR_RESPONSE_ORIGIN:
+        case ARCAS_STUN_ATTR_OTHER_ADDRESS:
+            return cricket::STUN_VALUE_ADDRESS;
+        default:
+            return cricket::IceMessage::GetAttributeValueType(type);
+        }
+    }
+
 public:
     int get_type() const
     {
@@ -38,6 +60,27 @@ public:
     {
         this->SetTransactionID(transaction_id);
     }
+
+    bool rust_read(rust::Slice<const uint8_t> data)
+    {
+        rtc::ByteBufferReader buffer(reinterpret_cast<const char*>(data.data()), data.size());
+        return this->Read(&buffer);
+    }
+
+    rust::Vec<uint8_t> rust_write() const;
+
+    // Attributes in the order they are written, duplicates included.
+    rust::Vec<ArcasStunAttributeValue> rust_attributes() const;
+    // The first attribute of `attr_type`, STUN_VALUE_UNKNOWN if there is none.
+    ArcasStunAttributeValue rust_attribute(uint16_t attr_type) const;
+
+    void rust_add_address(uint16_t attr_type, bool xor_mapped, rust::String ip, uint16_t port);
+    void rust_add_uint32(uint16_t attr_type, uint32_t value);
+    void rust_add_uint64(uint16_t attr_type, uint64_t value);
+    void rust_add_byte_string(uint16_t attr_type, rust::Slice<const uint8_t> value);
+    void rust_add_error_code(int code, rust::String reason);
+    void rust_add_uint16_list(uint16_t attr_type, rust::Slice<const uint16_t> values);
 };
 
 std::unique_ptr<ArcasICEMessage> create_arcas_ice_message();
+bool validate_stun_fingerprint(rust::Slice<const uint8_t> data);
@@ -1,8 +1,14 @@
 #pragma once
+#include "alias.h"
 #include "api/media_stream_interface.h"
+#include "api/video/video_frame.h"
+#include "api/video/video_sink_interface.h"
 #include "rtc_base/logging.h"
 #include "rust/cxx.h"
 
+struct ArcasRustVideoSink;
+struct ArcasReceivedVideoFrameInfo;
+
 class ArcasVideoTrack
 {
 private:
@@ -31,4 +37,22 @@ public:
     }
 };
 
+// Forwards decoded frames of a track to rust.  The sink registers itself with
+// the track on construction and removes itself again when destroyed.
+class ArcasVideoSink : public rtc::VideoSinkInterface<webrtc::VideoFrame>
+{
+private:
+    rtc::scoped_refptr<webrtc::VideoTrackInterface> track_;
+    rust::Box<ArcasRustVideoSink> api_;
+
+public:
+    ArcasVideoSink(rtc::scoped_refptr<webrtc::VideoTrackInterface> track, rust::Box<ArcasRustVideoSink> api);
+    ~ArcasVideoSink();
+
+    void OnFrame(const webrtc::VideoFrame& frame) override;
+};
+
 std::unique_ptr<ArcasVideoTrack> gen_unique_video_track();
+std::unique_ptr<ArcasVideoSink> create_arcas_video_sink(const ArcasVideoTrack& track, rust::Box<ArcasRustVideoSink> sink);
+ArcasReceivedVideoFrameInfo get_received_video_frame_info(const ArcasCxxVideoFrame& frame);
+rust::Vec<uint8_t> copy_video_frame_luma_rows(const ArcasCxxVideoFrame& frame, int32_t rows);
@@ -6,5 +6,6 @@ pub mod ffi {
         type ArcasAudioTrack;
 
         fn gen_unique_audio_track() -> UniquePtr<ArcasAudioTrack>;
+        fn id(self: &ArcasAudioTrack) -> String;
     }
 }
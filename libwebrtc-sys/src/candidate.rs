@@ -43,3 +43,6 @@ pub mod ffi {
         fn create_arcas_candidate() -> UniquePtr<ArcasCandidate>;
     }
 }
+
+// cricket::Candidate is a plain value.
+unsafe impl Send for ffi::ArcasCandidate {}
@@ -13,6 +13,8 @@ pub mod ffi {
         fn set_codec_type(self: &ArcasCodecSpecificInfo, codec_type: ArcasCxxVideoCodecType);
         fn set_end_of_picture(self: &ArcasCodecSpecificInfo, set_end_of_picture: bool);
         fn get_codec_type(self: &ArcasCodecSpecificInfo) -> ArcasCxxVideoCodecType;
+        /// Temporal layer index of the encoded frame or -1 when not assigned.
+        fn get_temporal_index(self: &ArcasCodecSpecificInfo) -> i32;
         #[cxx_name = "as_ref"]
         fn as_cxx_ref(self: &ArcasCodecSpecificInfo) -> &ArcasCxxCodecSpecificInfo;
     }
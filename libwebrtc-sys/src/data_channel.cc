@@ -33,10 +33,10 @@ void ArcasDataChannel::register_observer(ArcasDataChannelObserver* observer)
     this->api->RegisterObserver(observer);
 }
 
-void ArcasDataChannel::send(const ArcasDataBuffer& buffer)
+bool ArcasDataChannel::send(const ArcasDataBuffer& buffer)
 {
     webrtc::DataBuffer webrtc_buffer(rtc::CopyOnWriteBuffer(buffer.ptr, buffer.len), buffer.binary);
-    this->api->Send(webrtc_buffer);
+    return this->api->Send(webrtc_buffer);
 }
 
 std::unique_ptr<ArcasDataChannelObserver>
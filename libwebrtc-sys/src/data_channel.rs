@@ -22,6 +22,7 @@ pub mod ffi {
         max_retransmits: Vec<i32>,
         protocol: String,
         id: Vec<u16>,
+        negotiated: bool,
         priority: Vec<Priority>,
     }
 
@@ -84,7 +85,10 @@ pub mod ffi {
         fn bytes_received(self: &ArcasDataChannel) -> u64;
         fn buffered_amount(self: &ArcasDataChannel) -> u64;
         fn close(self: Pin<&mut ArcasDataChannel>);
-        fn send(self: Pin<&mut ArcasDataChannel>, data: &ArcasDataBuffer);
+        /// False if the channel isn't open or the send queue is full.
+        fn send(self: Pin<&mut ArcasDataChannel>, data: &ArcasDataBuffer) -> bool;
+        /// Another handle to the same channel.
+        fn clone(self: &ArcasDataChannel) -> UniquePtr<ArcasDataChannel>;
 
         fn gen_unique_data_channel() -> UniquePtr<ArcasDataChannel>;
     }
@@ -3,4 +3,14 @@
 std::unique_ptr<ArcasEncodedImageFactory> create_arcas_encoded_image_factory()
 {
     return std::make_unique<ArcasEncodedImageFactory>();
-}
\ No newline at end of file
+}
+
+int32_t get_encoded_image_spatial_index(const webrtc::EncodedImage& image)
+{
+    return image.SpatialIndex().value_or(-1);
+}
+
+bool is_encoded_image_key_frame(const webrtc::EncodedImage& image)
+{
+    return image._frameType == webrtc::VideoFrameType::kVideoFrameKey;
+}
@@ -9,6 +9,12 @@ pub mod ffi {
 
         fn create_arcas_encoded_image_factory() -> UniquePtr<ArcasEncodedImageFactory>;
 
+        /// Spatial layer index of the encoded image or -1 when not assigned.
+        fn get_encoded_image_spatial_index(image: &ArcasCxxEncodedImage) -> i32;
+
+        /// True when the encoded image is a keyframe.
+        fn is_encoded_image_key_frame(image: &ArcasCxxEncodedImage) -> bool;
+
         fn create_empty_encoded_image_buffer(
             self: &ArcasEncodedImageFactory,
         ) -> SharedPtr<ArcasOpaqueEncodedImageBuffer>;
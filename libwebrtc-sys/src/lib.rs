@@ -22,7 +22,7 @@ pub use crate::video_encoding::{
     VIDEO_CODEC_TARGET_BITRATE_OVERSHOOT, VIDEO_CODEC_UNINITIALIZED,
 };
 use crate::video_frame::ffi::{ArcasColorSpace, ArcasVideoFrameRawImageData};
-use crate::video_track::ffi::ArcasVideoTrack;
+use crate::video_track::ffi::{ArcasVideoSink, ArcasVideoTrack};
 use crate::video_track_source::ffi::ArcasVideoTrackSource;
 
 pub mod api;
@@ -59,6 +59,8 @@ pub mod sdp_video_format;
 pub mod session_description;
 pub mod shared_bridge;
 pub mod spatial_layer;
+#[cfg(feature = "serde")]
+mod stats_serde;
 pub mod stun;
 pub mod video_codec;
 pub mod video_decoding;
@@ -80,6 +82,7 @@ pub use crate::video_encoding::{
 };
 
 pub use crate::video_encoder_factory_wrapper::EncodedImageCallbackHandler;
+pub use crate::video_track::{ArcasRustVideoSink, VideoSinkImpl};
 
 pub mod ffi {
     pub use crate::api::ffi::{
@@ -143,7 +146,7 @@ pub mod ffi {
         shared_bridge::ffi::{
             ArcasCxxEncodedImage, ArcasCxxInterLayerPredMode, ArcasCxxRtpTransceiverDirection,
             ArcasCxxSdpVideoFormat, ArcasCxxVideoCodec, ArcasCxxVideoCodecType,
-            ArcasIceConnectionState, ArcasIceGatheringState, ArcasMediaType,
+            ArcasICETransportType, ArcasIceConnectionState, ArcasIceGatheringState, ArcasMediaType,
             ArcasPeerConnectionState, ArcasRTCSignalingState, ArcasRTPTransceiverDirection,
             ArcasSDPSemantics, ArcasSDPType, ArcasSdpVideoFormatInit, ArcasSdpVideoFormatVecInit,
             *,
@@ -212,6 +215,9 @@ unsafe impl Send for ArcasVideoTrack {}
 /// There are special afforances for video tracks returend by CreateVideoTrack.
 /// See peer_connection_factory.cc for details.
 unsafe impl Sync for ArcasVideoTrack {}
+/// Adding and removing sinks is proxied to the worker thread by the track.
+unsafe impl Send for ArcasVideoSink {}
+unsafe impl Sync for ArcasVideoSink {}
 unsafe impl Send for ArcasRTPVideoTransceiver {}
 unsafe impl Sync for ArcasRTPVideoTransceiver {}
 unsafe impl Sync for ArcasPeerConnectionFactory {}
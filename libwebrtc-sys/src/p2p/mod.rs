@@ -1 +1,2 @@
 pub mod ice_transport_internal;
+pub mod turn_server;
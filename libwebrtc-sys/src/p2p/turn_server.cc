@@ -0,0 +1,173 @@
+#include "libwebrtc-sys/include/p2p/turn_server.h"
+#include "libwebrtc-sys/src/p2p/turn_server.rs.h"
+
+namespace
+{
+std::unique_ptr<rtc::Thread> start_server_thread(const char* name)
+{
+    auto thread = rtc::Thread::CreateWithSocketServer();
+    thread->SetName(name, nullptr);
+    thread->Start();
+    return thread;
+}
+
+rust::String proto_to_string(cricket::ProtocolType proto)
+{
+    switch (proto)
+    {
+    case cricket::PROTO_UDP:
+        return "udp";
+    case cricket::PROTO_TCP:
+        return "tcp";
+    case cricket::PROTO_SSLTCP:
+        return "ssltcp";
+    case cricket::PROTO_TLS:
+        return "tls";
+    }
+    return "unknown";
+}
+}  // namespace
+
+void ArcasMappingStunServer::OnBindingRequest(cricket::StunMessage* message,
+                                              const rtc::SocketAddress& remote_address)
+{
+    _binding_requests++;
+    rtc::SocketAddress mapped_address = remote_address;
+    if (!_mapped_ip.IsNil())
+    {
+        mapped_address.SetIP(_mapped_ip);
+    }
+    cricket::StunMessage response;
+    GetStunBindResponse(message, mapped_address, &response);
+    SendResponse(response, remote_address);
+}
+
+ArcasStunServer::~ArcasStunServer()
+{
+    _thread->Invoke<void>(RTC_FROM_HERE, [this]() { _server.reset(); });
+}
+
+bool ArcasStunServer::Start(const rtc::SocketAddress& address, const rtc::IPAddress& mapped_ip)
+{
+    return _thread->Invoke<bool>(RTC_FROM_HERE,
+                                 [&]()
+                                 {
+                                     auto socket = rtc::AsyncUDPSocket::Create(_thread->socketserver(), address);
+                                     if (!socket)
+                                     {
+                                         return false;
+                                     }
+                                     _local_address = socket->GetLocalAddress();
+                                     _server = std::make_unique<ArcasMappingStunServer>(socket, mapped_ip);
+                                     return true;
+                                 });
+}
+
+void ArcasTurnAuth::add_user(const std::string& username, const std::string& realm, const std::string& password)
+{
+    std::string key;
+    if (cricket::ComputeStunCredentialHash(username, realm, password, &key))
+    {
+        _keys[username] = key;
+    }
+}
+
+bool ArcasTurnAuth::GetKey(const std::string& username, const std::string& realm, std::string* key)
+{
+    auto it = _keys.find(username);
+    if (it == _keys.end())
+    {
+        return false;
+    }
+    *key = it->second;
+    return true;
+}
+
+ArcasTurnServer::~ArcasTurnServer()
+{
+    _thread->Invoke<void>(RTC_FROM_HERE,
+                          [this]()
+                          {
+                              _server.reset();
+                              _socket_factory.reset();
+                          });
+}
+
+bool ArcasTurnServer::Start(const ArcasTurnServerConfig& config)
+{
+    auto realm = std::string(config.realm.c_str());
+    for (auto& user : config.users)
+    {
+        _auth.add_user(std::string(user.username.c_str()), realm, std::string(user.password.c_str()));
+    }
+
+    rtc::SocketAddress listen_address(std::string(config.listen_address.c_str()), config.listen_port);
+    rtc::SocketAddress relay_address(std::string(config.relay_address.c_str()), 0);
+
+    return _thread->Invoke<bool>(RTC_FROM_HERE,
+                                 [&]()
+                                 {
+                                     auto socket =
+                                         rtc::AsyncUDPSocket::Create(_thread->socketserver(), listen_address);
+                                     if (!socket)
+                                     {
+                                         return false;
+                                     }
+                                     _local_address = socket->GetLocalAddress();
+                                     _socket_factory =
+                                         std::make_unique<rtc::BasicPacketSocketFactory>(_thread->socketserver());
+                                     _server = std::make_unique<cricket::TurnServer>(_thread.get());
+                                     _server->set_realm(realm);
+                                     _server->set_software("arcas");
+                                     _server->set_auth_hook(&_auth);
+                                     _server->AddInternalSocket(socket, cricket::PROTO_UDP);
+                                     _server->SetExternalSocketFactory(_socket_factory.get(), relay_address);
+                                     return true;
+                                 });
+}
+
+rust::Vec<ArcasTurnAllocation> ArcasTurnServer::allocations() const
+{
+    return _thread->Invoke<rust::Vec<ArcasTurnAllocation>>(
+        RTC_FROM_HERE,
+        [this]()
+        {
+            rust::Vec<ArcasTurnAllocation> result;
+            for (auto& entry : _server->allocations())
+            {
+                auto& connection = entry.first;
+                result.push_back(ArcasTurnAllocation{
+                    rust::String(entry.second->username()),
+                    rust::String(connection.src().ipaddr().ToString()),
+                    static_cast<uint16_t>(connection.src().port()),
+                    proto_to_string(connection.proto()),
+                });
+            }
+            return result;
+        });
+}
+
+std::unique_ptr<ArcasStunServer> create_arcas_stun_server(rust::String address, uint16_t port, rust::String mapped_ip)
+{
+    rtc::IPAddress cxx_mapped_ip;
+    if (!mapped_ip.empty() && !rtc::IPFromString(std::string(mapped_ip.c_str()), &cxx_mapped_ip))
+    {
+        return nullptr;
+    }
+    auto server = std::make_unique<ArcasStunServer>(start_server_thread("arcas_stun_server"));
+    if (!server->Start(rtc::SocketAddress(std::string(address.c_str()), port), cxx_mapped_ip))
+    {
+        return nullptr;
+    }
+    return server;
+}
+
+std::unique_ptr<ArcasTurnServer> create_arcas_turn_server(ArcasTurnServerConfig config)
+{
+    auto server = std::make_unique<ArcasTurnServer>(start_server_thread("arcas_turn_server"));
+    if (!server->Start(config))
+    {
+        return nullptr;
+    }
+    return server;
+}
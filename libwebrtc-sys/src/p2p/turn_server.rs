@@ -0,0 +1,81 @@
+#[cxx::bridge]
+pub mod ffi {
+    #[derive(Debug, Clone)]
+    struct ArcasTurnUser {
+        username: String,
+        password: String,
+    }
+
+    #[derive(Debug)]
+    struct ArcasTurnServerConfig {
+        listen_address: String,
+        listen_port: u16,
+        /// Local address relayed candidates are allocated on.
+        relay_address: String,
+        realm: String,
+        users: Vec<ArcasTurnUser>,
+    }
+
+    #[derive(Debug, Clone)]
+    struct ArcasTurnAllocation {
+        username: String,
+        client_address: String,
+        client_port: u16,
+        protocol: String,
+    }
+
+    unsafe extern "C++" {
+        include!("include/p2p/turn_server.h");
+
+        type ArcasStunServer;
+        type ArcasTurnServer;
+
+        /// Starts a STUN server on its own thread, null if the address can't
+        /// be bound.  Port 0 picks a free port.  A non-empty `mapped_ip`
+        /// replaces the client's address in responses, as behind a NAT.
+        fn create_arcas_stun_server(
+            address: String,
+            port: u16,
+            mapped_ip: String,
+        ) -> UniquePtr<ArcasStunServer>;
+        fn local_address(self: &ArcasStunServer) -> String;
+        fn local_port(self: &ArcasStunServer) -> u16;
+        fn binding_requests(self: &ArcasStunServer) -> u32;
+
+        /// Starts a UDP TURN server on its own thread, null if the address
+        /// can't be bound.
+        fn create_arcas_turn_server(config: ArcasTurnServerConfig) -> UniquePtr<ArcasTurnServer>;
+        fn local_address(self: &ArcasTurnServer) -> String;
+        fn local_port(self: &ArcasTurnServer) -> u16;
+        fn allocations(self: &ArcasTurnServer) -> Vec<ArcasTurnAllocation>;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ffi::*;
+
+    #[test]
+    fn test_create_servers() {
+        let stun = create_arcas_stun_server("127.0.0.1".into(), 0, "".into());
+        assert!(!stun.is_null());
+        assert_eq!(stun.local_address(), "127.0.0.1");
+        assert_ne!(stun.local_port(), 0);
+        assert_eq!(stun.binding_requests(), 0);
+        assert!(create_arcas_stun_server("127.0.0.1".into(), 0, "invalid".into()).is_null());
+
+        let turn = create_arcas_turn_server(ArcasTurnServerConfig {
+            listen_address: "127.0.0.1".into(),
+            listen_port: 0,
+            relay_address: "127.0.0.1".into(),
+            realm: "arcas".into(),
+            users: vec![ArcasTurnUser {
+                username: "user".into(),
+                password: "pass".into(),
+            }],
+        });
+        assert!(!turn.is_null());
+        assert_ne!(turn.local_port(), 0);
+        assert!(turn.allocations().is_empty());
+    }
+}
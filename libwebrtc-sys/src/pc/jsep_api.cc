@@ -97,3 +97,148 @@ std::unique_ptr<ArcasJsepTransportControllerObserver> to_cxx(rust::Box<ArcasRust
 {
     return std::make_unique<ArcasJsepTransportControllerObserver>(std::move(r));
 }
+
+void ArcasRtpPacketSink::OnRtpPacket(const webrtc::RtpPacketReceived& packet)
+{
+    _sink->on_rtp_packet(rust::Slice<const uint8_t>{packet.data(), packet.size()}, packet.arrival_time_ms());
+}
+
+bool ArcasJsepTransportController::send_rtp(rust::String mid, rust::Slice<const uint8_t> packet)
+{
+    return on_network_thread<bool>(
+        [&]
+        {
+            auto transport = _transport_controller->GetRtpTransport(mid.c_str());
+            if (!transport || !transport->IsWritable(false))
+            {
+                return false;
+            }
+            rtc::CopyOnWriteBuffer buffer(packet.data(), packet.size());
+            return transport->SendRtpPacket(&buffer, rtc::PacketOptions(), 0);
+        });
+}
+
+bool ArcasJsepTransportController::send_rtcp(rust::String mid, rust::Slice<const uint8_t> packet)
+{
+    return on_network_thread<bool>(
+        [&]
+        {
+            auto transport = _transport_controller->GetRtpTransport(mid.c_str());
+            if (!transport || !transport->IsWritable(true))
+            {
+                return false;
+            }
+            rtc::CopyOnWriteBuffer buffer(packet.data(), packet.size());
+            return transport->SendRtcpPacket(&buffer, rtc::PacketOptions(), 0);
+        });
+}
+
+bool ArcasJsepTransportController::rtp_transport_writable(rust::String mid) const
+{
+    return on_network_thread<bool>(
+        [&]
+        {
+            auto transport = _transport_controller->GetRtpTransport(mid.c_str());
+            return transport && transport->IsWritable(false);
+        });
+}
+
+ArcasRtpSinkRegistration ArcasJsepTransportController::register_rtp_sink(rust::String mid,
+                                                                         rust::Vec<uint8_t> payload_types,
+                                                                         rust::Box<ArcasRustRtpPacketSink> sink)
+{
+    auto arcas_sink = std::make_unique<ArcasRtpPacketSink>(std::move(sink));
+    return on_network_thread<ArcasRtpSinkRegistration>(
+        [&]
+        {
+            std::string cxx_mid{mid.data(), mid.size()};
+            auto transport = _transport_controller->GetRtpTransport(cxx_mid);
+            if (!transport)
+            {
+                return ArcasRtpSinkRegistration::NoTransport;
+            }
+            if (_rtp_sinks.count(cxx_mid))
+            {
+                return ArcasRtpSinkRegistration::Conflict;
+            }
+            webrtc::RtpDemuxerCriteria criteria;
+            criteria.mid = cxx_mid;
+            for (auto payload_type : payload_types) { criteria.payload_types.insert(payload_type); }
+            // The demuxer refuses criteria overlapping another sink's.
+            if (!transport->RegisterRtpDemuxerSink(criteria, arcas_sink.get()))
+            {
+                return ArcasRtpSinkRegistration::Conflict;
+            }
+            _rtp_sinks[cxx_mid] = std::move(arcas_sink);
+            return ArcasRtpSinkRegistration::Registered;
+        });
+}
+
+void ArcasJsepTransportController::unregister_rtp_sink(rust::String mid)
+{
+    on_network_thread<void>(
+        [&]
+        {
+            auto it = _rtp_sinks.find(std::string{mid.data(), mid.size()});
+            if (it == _rtp_sinks.end())
+            {
+                return;
+            }
+            auto transport = _transport_controller->GetRtpTransport(it->first);
+            if (transport)
+            {
+                transport->UnregisterRtpDemuxerSink(it->second.get());
+            }
+            _rtp_sinks.erase(it);
+        });
+}
+
+static rust::Vec<ArcasCandidateWrapper> to_rust_candidates(const std::vector<cricket::Candidate>& candidates)
+{
+    rust::Vec<ArcasCandidateWrapper> out;
+    for (auto& candidate : candidates) { out.push_back(ArcasCandidateWrapper{std::make_unique<ArcasCandidate>(candidate)}); }
+    return out;
+}
+
+void ArcasJsepTransportController::subscribe_observer(rust::Box<ArcasRustJsepTransportControllerObserverWrapper> observer)
+{
+    _observer = std::move(observer);
+    on_network_thread<void>(
+        [this]
+        {
+            auto& observer = _observer.value();
+            _transport_controller->SubscribeIceConnectionState([&observer](cricket::IceConnectionState state)
+                                                               { observer->ice_connection_state(state); });
+            _transport_controller->SubscribeConnectionState(
+                [&observer](webrtc::PeerConnectionInterface::PeerConnectionState state) { observer->connection_state(state); });
+            _transport_controller->SubscribeStandardizedIceConnectionState(
+                [&observer](webrtc::PeerConnectionInterface::IceConnectionState state)
+                { observer->standardized_ice_connection_state(state); });
+            _transport_controller->SubscribeIceGatheringState([&observer](cricket::IceGatheringState state)
+                                                              { observer->ice_gathering_state(state); });
+            _transport_controller->SubscribeIceCandidateGathered(
+                [&observer](const std::string& mid, const std::vector<cricket::Candidate>& candidates)
+                { observer->ice_candidates_gathered(rust::String{mid.data(), mid.size()}, to_rust_candidates(candidates)); });
+            _transport_controller->SubscribeIceCandidateError(
+                [&observer](const cricket::IceCandidateErrorEvent& event)
+                {
+                    observer->ice_candidate_error(rust::String(event.address.c_str()),
+                                                  rust::String(event.url.c_str()),
+                                                  event.error_code,
+                                                  rust::String(event.error_text.c_str()));
+                });
+            _transport_controller->SubscribeIceCandidatesRemoved([&observer](const std::vector<cricket::Candidate>& candidates)
+                                                                 { observer->ice_candidates_removed(to_rust_candidates(candidates)); });
+            _transport_controller->SubscribeIceCandidatePairChanged(
+                [&observer](const cricket::CandidatePairChangeEvent& event)
+                {
+                    ArcasCandidatePairChangeEventJSEP rust;
+                    rust.local_candidate = std::make_unique<ArcasCandidate>(event.selected_candidate_pair.local);
+                    rust.remote_candidate = std::make_unique<ArcasCandidate>(event.selected_candidate_pair.remote);
+                    rust.last_data_received_ms = event.last_data_received_ms;
+                    rust.reason = rust::String(event.reason.c_str());
+                    rust.estimated_disconnected_time_ms = event.estimated_disconnected_time_ms;
+                    observer->ice_candidate_pair_change(std::move(rust));
+                });
+        });
+}
@@ -24,6 +24,17 @@ pub mod ffi {
         pub(crate) ptr: UniquePtr<ArcasCandidate>,
     }
 
+    /// Outcome of `register_rtp_sink`.
+    #[derive(Debug)]
+    enum ArcasRtpSinkRegistration {
+        Registered,
+        /// There's no transport for the mid yet.
+        NoTransport,
+        /// The mid already has a sink, or the payload types overlap another
+        /// sink's.
+        Conflict,
+    }
+
     struct ArcasCandidatePairChangeEventJSEP {
         local_candidate: UniquePtr<ArcasCandidate>,
         remote_candidate: UniquePtr<ArcasCandidate>,
@@ -121,6 +132,7 @@ pub mod ffi {
 
         fn create_buffer(capacity: u64) -> UniquePtr<CopyOnWriteBuffer>;
         fn create_buffer_with_data(bytes: &[u8]) -> UniquePtr<CopyOnWriteBuffer>;
+        fn buffer_data(buffer: &CopyOnWriteBuffer) -> &[u8];
 
         fn send_rtp_packet(
             transport: Pin<&mut SrtpTransport>,
@@ -194,6 +206,41 @@ pub mod ffi {
             mid: String,
         ) -> *mut DataChannelTransportInterface;
 
+        /// Send an RTP packet on the transport for `mid`, false if there's
+        /// no such transport or it isn't writable.
+        fn send_rtp(
+            self: Pin<&mut ArcasJsepTransportController>,
+            mid: String,
+            packet: &[u8],
+        ) -> bool;
+
+        /// Send an RTCP packet on the transport for `mid`.
+        fn send_rtcp(
+            self: Pin<&mut ArcasJsepTransportController>,
+            mid: String,
+            packet: &[u8],
+        ) -> bool;
+
+        fn rtp_transport_writable(self: &ArcasJsepTransportController, mid: String) -> bool;
+
+        /// Deliver RTP packets for `mid`, or carrying one of `payload_types`,
+        /// to `sink`.  A mid has at most one sink.
+        fn register_rtp_sink(
+            self: Pin<&mut ArcasJsepTransportController>,
+            mid: String,
+            payload_types: Vec<u8>,
+            sink: Box<ArcasRustRtpPacketSink>,
+        ) -> ArcasRtpSinkRegistration;
+
+        /// Stop delivering to and drop the sink registered for `mid`, if any.
+        fn unregister_rtp_sink(self: Pin<&mut ArcasJsepTransportController>, mid: String);
+
+        /// Forward ICE and connection events to `observer`, at most once.
+        fn subscribe_observer(
+            self: Pin<&mut ArcasJsepTransportController>,
+            observer: Box<ArcasRustJsepTransportControllerObserverWrapper>,
+        );
+
         fn set_ice_config(
             self: Pin<&mut ArcasJsepTransportController>,
             config: UniquePtr<ArcasP2PIceConfig>,
@@ -240,6 +287,10 @@ pub mod ffi {
         type ArcasRustDTLSHandshakeErrorHandler;
         type ArcasRustJsepTransportControllerObserver;
         type ArcasRustJsepTransportControllerObserverWrapper;
+        type ArcasRustRtpPacketSink;
+
+        // ArcasRustRtpPacketSink
+        fn on_rtp_packet(self: &ArcasRustRtpPacketSink, packet: &[u8], arrival_time_ms: i64);
 
         // ArcasJsepRTCPHandler
         fn invoke(self: &ArcasRustJsepRTCPHandler, packet: &CopyOnWriteBuffer, packet_time: i64);
@@ -321,6 +372,35 @@ impl ffi::ArcasCandidateWrapper {
     }
 }
 
+impl std::fmt::Debug for ffi::ArcasCandidateWrapper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ArcasCandidateWrapper")
+            .field(&self.ptr.as_ref().map(|candidate| candidate.cxx_to_string()))
+            .finish()
+    }
+}
+
+impl std::fmt::Debug for ffi::ArcasCandidatePairChangeEventJSEP {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArcasCandidatePairChangeEventJSEP")
+            .field(
+                "local_candidate",
+                &self.local_candidate.as_ref().map(|c| c.cxx_to_string()),
+            )
+            .field(
+                "remote_candidate",
+                &self.remote_candidate.as_ref().map(|c| c.cxx_to_string()),
+            )
+            .field("last_data_received_ms", &self.last_data_received_ms)
+            .field("reason", &self.reason)
+            .field(
+                "estimated_disconnected_time_ms",
+                &self.estimated_disconnected_time_ms,
+            )
+            .finish()
+    }
+}
+
 pub trait JsepTransportControllerObserver {
     fn ice_candidates_gathered(&self, mid: String, candidates: Vec<ffi::ArcasCandidateWrapper>);
     fn ice_connection_state(&self, state: ffi::IceConnectionState);
@@ -391,12 +471,27 @@ impl ArcasRustJsepTransportControllerObserverWrapper {
     }
 }
 
+pub struct ArcasRustRtpPacketSink {
+    inner: Box<dyn Fn(&[u8], i64) + Send>,
+}
+
+impl ArcasRustRtpPacketSink {
+    pub fn new(inner: Box<dyn Fn(&[u8], i64) + Send>) -> Self {
+        Self { inner }
+    }
+
+    pub fn on_rtp_packet(&self, packet: &[u8], arrival_time_ms: i64) {
+        (self.inner)(packet, arrival_time_ms);
+    }
+}
+
+// Handlers are called on the network thread.
 pub struct ArcasRustJsepRTCPHandler {
-    inner: Box<dyn Fn(&ffi::CopyOnWriteBuffer, i64)>,
+    inner: Box<dyn Fn(&ffi::CopyOnWriteBuffer, i64) + Send + Sync>,
 }
 
 impl ArcasRustJsepRTCPHandler {
-    pub fn new(inner: Box<dyn Fn(&ffi::CopyOnWriteBuffer, i64)>) -> Self {
+    pub fn new(inner: Box<dyn Fn(&ffi::CopyOnWriteBuffer, i64) + Send + Sync>) -> Self {
         Self { inner }
     }
 
@@ -406,11 +501,11 @@ impl ArcasRustJsepRTCPHandler {
 }
 
 pub struct ArcasRustDTLSHandshakeErrorHandler {
-    inner: Box<dyn Fn(self::ffi::ArcasCxxSSLHandshakeError)>,
+    inner: Box<dyn Fn(self::ffi::ArcasCxxSSLHandshakeError) + Send + Sync>,
 }
 
 impl ArcasRustDTLSHandshakeErrorHandler {
-    pub fn new(inner: Box<dyn Fn(self::ffi::ArcasCxxSSLHandshakeError)>) -> Self {
+    pub fn new(inner: Box<dyn Fn(self::ffi::ArcasCxxSSLHandshakeError) + Send + Sync>) -> Self {
         Self { inner }
     }
 
@@ -420,11 +515,13 @@ impl ArcasRustDTLSHandshakeErrorHandler {
 }
 
 pub type OnTransportChanged = dyn Fn(
-    String,
-    *mut ffi::RtpTransportInternal,
-    UniquePtr<ffi::ArcasDTLSTransport>,
-    *mut ffi::DataChannelTransportInterface,
-) -> bool;
+        String,
+        *mut ffi::RtpTransportInternal,
+        UniquePtr<ffi::ArcasDTLSTransport>,
+        *mut ffi::DataChannelTransportInterface,
+    ) -> bool
+    + Send
+    + Sync;
 
 pub struct ArcasRustJsepTransportControllerObserver {
     inner: Box<OnTransportChanged>,
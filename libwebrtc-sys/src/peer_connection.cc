@@ -160,11 +160,17 @@ std::unique_ptr<ArcasDataChannel> ArcasPeerConnection::create_data_channel(rust:
     {
         init.id = init_rust.id[0];
     }
+    init.negotiated = init_rust.negotiated;
 
     if (init_rust.priority.size() > 0)
     {
         init.priority = init_rust.priority[0];
     }
 
-    return std::make_unique<ArcasDataChannel>(api->CreateDataChannel(cxx_label, &init));
+    auto channel = api->CreateDataChannel(cxx_label, &init);
+    if (!channel)
+    {
+        return nullptr;
+    }
+    return std::make_unique<ArcasDataChannel>(channel);
 }
@@ -94,6 +94,8 @@ pub mod ffi {
             crate::shared_bridge::ffi::ArcasCxxRtpTransceiverDirection;
         type ArcasRTCStatsCollectorCallback;
         type ArcasAudioTrack = crate::audio_track::ffi::ArcasAudioTrack;
+        type ArcasRTCSignalingState = crate::shared_bridge::ffi::ArcasRTCSignalingState;
+        type ArcasPeerConnectionState = crate::shared_bridge::ffi::ArcasPeerConnectionState;
 
         fn gen_shared_peer_connection() -> SharedPtr<ArcasPeerConnection>;
 
@@ -166,6 +168,9 @@ pub mod ffi {
 
         fn add_ice_candidate(self: &ArcasPeerConnection, candidate: UniquePtr<ArcasICECandidate>);
         fn close(self: &ArcasPeerConnection);
+        fn restart_ice(self: &ArcasPeerConnection);
+        fn signaling_state(self: &ArcasPeerConnection) -> ArcasRTCSignalingState;
+        fn connection_state(self: &ArcasPeerConnection) -> ArcasPeerConnectionState;
         fn get_transceivers(
             self: &ArcasPeerConnection,
         ) -> UniquePtr<CxxVector<ArcasRTPTransceiver>>;
@@ -20,7 +20,7 @@ std::unique_ptr<webrtc::PeerConnectionInterface::RTCConfiguration> create_rtc_co
     webrtc::PeerConnectionInterface::IceServers servers;
 
     rtc->sdp_semantics = config.sdp_semantics;
-    rtc->servers = servers;
+    rtc->type = config.ice_transport_type;
 
     for (auto server_config : config.ice_servers)
     {
@@ -38,7 +38,14 @@ std::unique_ptr<webrtc::PeerConnectionInterface::RTCConfiguration> create_rtc_co
         rtc_ice_server.password = std::string(server_config.password.c_str());
         servers.push_back(rtc_ice_server);
     }
+    rtc->servers = servers;
     RTC_LOG(LS_VERBOSE) << "RTC LOG WITH " << servers.size() << " URLS";
 
     return rtc;
 }
+
+void add_rtc_configuration_certificate(webrtc::PeerConnectionInterface::RTCConfiguration& config,
+                                       const ArcasSSLCertificate& certificate)
+{
+    config.certificates.push_back(certificate.get_certificate());
+}
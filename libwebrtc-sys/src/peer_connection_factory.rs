@@ -11,6 +11,7 @@ pub mod ffi {
     struct ArcasPeerConnectionConfig {
         ice_servers: Vec<ArcasICEServer>,
         sdp_semantics: ArcasSDPSemantics,
+        ice_transport_type: ArcasICETransportType,
     }
 
     #[derive(Debug)]
@@ -39,15 +40,21 @@ pub mod ffi {
         type ArcasVideoTrackSource = crate::video_track_source::ffi::ArcasVideoTrackSource;
         type ArcasVideoTrack = crate::video_track::ffi::ArcasVideoTrack;
         type ArcasSDPSemantics = crate::shared_bridge::ffi::ArcasSDPSemantics;
+        type ArcasICETransportType = crate::shared_bridge::ffi::ArcasICETransportType;
         type ArcasCxxBundlePolicy;
         type ArcasCxxRtcpMuxPolicy;
 
         type ArcasAudioTrackSource = crate::audio_track_source::ffi::ArcasAudioTrackSource;
         type ArcasAudioTrack = crate::audio_track::ffi::ArcasAudioTrack;
+        type ArcasSSLCertificate = crate::rtc_base::certificates::ffi::ArcasSSLCertificate;
 
         fn create_rtc_configuration(
             config: ArcasPeerConnectionConfig,
         ) -> UniquePtr<ArcasRTCConfiguration>;
+        fn add_rtc_configuration_certificate(
+            config: Pin<&mut ArcasRTCConfiguration>,
+            certificate: &ArcasSSLCertificate,
+        );
 
         fn gen_unique_peer_connection_factory() -> UniquePtr<ArcasPeerConnectionFactory>;
 
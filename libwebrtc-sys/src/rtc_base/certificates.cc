@@ -25,6 +25,13 @@ std::unique_ptr<rtc::SSLIdentity> create_arcas_ssl_identity_with_key_type(rust::
     return rtc::SSLIdentity::Create(common_name.c_str(), key_type);
 }
 
+std::unique_ptr<rtc::SSLIdentity> create_arcas_ssl_identity_with_lifetime(rust::String common_name,
+                                                                          std::unique_ptr<ArcasKeyParams> key_params,
+                                                                          int64_t lifetime_seconds)
+{
+    return rtc::SSLIdentity::Create(common_name.c_str(), key_params->get_params(), lifetime_seconds);
+}
+
 std::unique_ptr<ArcasSSLCertificate>
 create_arcas_rtc_certificate(std::unique_ptr<rtc::SSLIdentity> identity)
 {
@@ -35,7 +42,12 @@ std::unique_ptr<ArcasSSLCertificate> create_arcas_rtc_certificate_from_pem(rust:
                                                                            rust::String certificate)
 {
     rtc::RTCCertificatePEM pem(private_key.c_str(), certificate.c_str());
-    return std::make_unique<ArcasSSLCertificate>(rtc::RTCCertificate::FromPEM(pem));
+    auto rtc_certificate = rtc::RTCCertificate::FromPEM(pem);
+    if (!rtc_certificate)
+    {
+        return nullptr;
+    }
+    return std::make_unique<ArcasSSLCertificate>(rtc_certificate);
 }
 
 ArcasRTCCertificatePEM ArcasSSLCertificate::to_pem() const
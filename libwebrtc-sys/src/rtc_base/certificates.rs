@@ -60,6 +60,11 @@ pub mod ffi {
             common_name: String,
             key_type: KeyType,
         ) -> UniquePtr<SSLIdentity>;
+        fn create_arcas_ssl_identity_with_lifetime(
+            common_name: String,
+            key_params: UniquePtr<ArcasKeyParams>,
+            lifetime_seconds: i64,
+        ) -> UniquePtr<SSLIdentity>;
 
         fn create_arcas_rtc_certificate(
             identity: UniquePtr<SSLIdentity>,
@@ -71,6 +76,8 @@ pub mod ffi {
         ) -> UniquePtr<ArcasSSLCertificate>;
 
         fn get_fingerprint(self: &ArcasSSLCertificate) -> String;
+        fn clone(self: &ArcasSSLCertificate) -> UniquePtr<ArcasSSLCertificate>;
+        fn expires(self: &ArcasSSLCertificate) -> u64;
 
         // ArcasSSLCertificate
         fn to_pem(self: &ArcasSSLCertificate) -> ArcasRTCCertificatePEM;
@@ -92,4 +99,19 @@ mod tests {
         assert!(!pem.private_key.is_empty());
         assert!(!pem.certificate.is_empty());
     }
+
+    #[test]
+    fn test_certificate_from_pem() {
+        let key_params = ffi::create_arcas_key_params_ecdsa();
+        let identity =
+            ffi::create_arcas_ssl_identity_with_lifetime("arcas".to_string(), key_params, 60 * 60);
+        let cert = ffi::create_arcas_rtc_certificate(identity);
+        let pem = cert.to_pem();
+        let loaded = ffi::create_arcas_rtc_certificate_from_pem(pem.private_key, pem.certificate);
+        assert_eq!(loaded.get_fingerprint(), cert.get_fingerprint());
+        assert_eq!(loaded.expires(), cert.expires());
+
+        let invalid = ffi::create_arcas_rtc_certificate_from_pem("".into(), "".into());
+        assert!(invalid.is_null());
+    }
 }
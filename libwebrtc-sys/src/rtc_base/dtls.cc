@@ -0,0 +1,151 @@
+#include "libwebrtc-sys/include/rtc_base/dtls.h"
+#include "libwebrtc-sys/src/rtc_base/dtls.rs.h"
+#include "rtc_base/ssl_fingerprint.h"
+
+namespace
+{
+// Large enough for any datagram.
+constexpr size_t kMaxDtlsPacketSize = 2048;
+}  // namespace
+
+void ArcasDtlsPacketStream::receive_packet(rust::Slice<const uint8_t> packet)
+{
+    _packets.emplace_back(packet.data(), packet.size());
+    SignalEvent(this, rtc::SE_READ, 0);
+}
+
+rtc::StreamResult ArcasDtlsPacketStream::Read(void* buffer, size_t buffer_len, size_t* read, int* error)
+{
+    if (_packets.empty())
+    {
+        return rtc::SR_BLOCK;
+    }
+
+    auto& packet = _packets.front();
+    auto size = std::min(buffer_len, packet.size());
+    memcpy(buffer, packet.data(), size);
+    _packets.pop_front();
+    if (read)
+    {
+        *read = size;
+    }
+    return rtc::SR_SUCCESS;
+}
+
+rtc::StreamResult ArcasDtlsPacketStream::Write(const void* data, size_t data_len, size_t* written, int* error)
+{
+    _callbacks.send_packet(rust::Slice<const uint8_t>(static_cast<const uint8_t*>(data), data_len));
+    if (written)
+    {
+        *written = data_len;
+    }
+    return rtc::SR_SUCCESS;
+}
+
+ArcasDtlsSession::~ArcasDtlsSession()
+{
+    _thread->Invoke<void>(RTC_FROM_HERE, [this]() { _adapter.reset(); });
+}
+
+bool ArcasDtlsSession::Start(const ArcasSSLCertificate& certificate,
+                             rtc::SSLRole role,
+                             const std::string& peer_digest_algorithm,
+                             const std::string& peer_fingerprint)
+{
+    auto fingerprint = rtc::SSLFingerprint::CreateUniqueFromRfc4572(peer_digest_algorithm, peer_fingerprint);
+    if (!fingerprint)
+    {
+        return false;
+    }
+
+    return _thread->Invoke<bool>(RTC_FROM_HERE,
+                                 [&]()
+                                 {
+                                     auto stream = std::make_unique<ArcasDtlsPacketStream>(*_callbacks);
+                                     _stream = stream.get();
+                                     _adapter = rtc::SSLStreamAdapter::Create(std::move(stream));
+                                     _adapter->SetIdentity(certificate.get_certificate()->identity()->Clone());
+                                     _adapter->SetMode(rtc::SSL_MODE_DTLS);
+                                     _adapter->SetMaxProtocolVersion(rtc::SSL_PROTOCOL_DTLS_12);
+                                     _adapter->SetServerRole(role);
+                                     _adapter->SignalEvent.connect(this, &ArcasDtlsSession::OnEvent);
+                                     if (!_adapter->SetPeerCertificateDigest(fingerprint->algorithm,
+                                                                             fingerprint->digest.cdata(),
+                                                                             fingerprint->digest.size()))
+                                     {
+                                         return false;
+                                     }
+                                     return _adapter->StartSSL() == 0;
+                                 });
+}
+
+void ArcasDtlsSession::OnEvent(rtc::StreamInterface* stream, int events, int error)
+{
+    if (events & rtc::SE_OPEN)
+    {
+        _callbacks->on_connected();
+    }
+
+    if (events & rtc::SE_READ)
+    {
+        uint8_t buffer[kMaxDtlsPacketSize];
+        size_t read;
+        int read_error;
+        rtc::StreamResult result;
+        while ((result = _adapter->Read(buffer, sizeof(buffer), &read, &read_error)) == rtc::SR_SUCCESS)
+        {
+            _callbacks->on_data(rust::Slice<const uint8_t>(buffer, read));
+        }
+        if (result == rtc::SR_EOS || result == rtc::SR_ERROR)
+        {
+            _callbacks->on_closed(read_error);
+        }
+    }
+
+    if (events & rtc::SE_CLOSE)
+    {
+        _callbacks->on_closed(error);
+    }
+}
+
+void ArcasDtlsSession::receive_packet(rust::Slice<const uint8_t> packet) const
+{
+    _thread->Invoke<void>(RTC_FROM_HERE, [&]() { _stream->receive_packet(packet); });
+}
+
+bool ArcasDtlsSession::send(rust::Slice<const uint8_t> data) const
+{
+    return _thread->Invoke<bool>(RTC_FROM_HERE,
+                                 [&]()
+                                 {
+                                     if (_adapter->GetState() != rtc::SS_OPEN)
+                                     {
+                                         return false;
+                                     }
+                                     size_t written;
+                                     int error;
+                                     return _adapter->Write(data.data(), data.size(), &written, &error) ==
+                                            rtc::SR_SUCCESS;
+                                 });
+}
+
+std::unique_ptr<ArcasDtlsSession> create_arcas_dtls_session(const ArcasSSLCertificate& certificate,
+                                                            rtc::SSLRole role,
+                                                            rust::String peer_digest_algorithm,
+                                                            rust::String peer_fingerprint,
+                                                            rust::Box<ArcasRustDtlsCallbacks> callbacks)
+{
+    auto thread = rtc::Thread::Create();
+    thread->SetName("arcas_dtls_session", nullptr);
+    thread->Start();
+
+    auto session = std::make_unique<ArcasDtlsSession>(std::move(thread), std::move(callbacks));
+    if (!session->Start(certificate,
+                        role,
+                        std::string(peer_digest_algorithm.c_str()),
+                        std::string(peer_fingerprint.c_str())))
+    {
+        return nullptr;
+    }
+    return session;
+}
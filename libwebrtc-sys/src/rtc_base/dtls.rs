@@ -0,0 +1,83 @@
+#[cxx::bridge]
+pub mod ffi {
+    unsafe extern "C++" {
+        include!("include/rtc_base/dtls.h");
+
+        type ArcasSSLCertificate = crate::rtc_base::certificates::ffi::ArcasSSLCertificate;
+        #[namespace = "rtc"]
+        type SSLRole = crate::rtc_base::certificates::ffi::SSLRole;
+        type ArcasDtlsSession;
+
+        /// Starts a DTLS 1.2 handshake on its own thread, null if the peer
+        /// fingerprint can't be parsed.  Only a peer presenting the
+        /// certificate with that fingerprint is accepted.
+        fn create_arcas_dtls_session(
+            certificate: &ArcasSSLCertificate,
+            role: SSLRole,
+            peer_digest_algorithm: String,
+            peer_fingerprint: String,
+            callbacks: Box<ArcasRustDtlsCallbacks>,
+        ) -> UniquePtr<ArcasDtlsSession>;
+
+        /// ArcasDtlsSession
+        fn receive_packet(self: &ArcasDtlsSession, packet: &[u8]);
+        /// False before the handshake completes or if the write fails.
+        fn send(self: &ArcasDtlsSession, data: &[u8]) -> bool;
+    }
+
+    extern "Rust" {
+        type ArcasRustDtlsCallbacks;
+
+        fn send_packet(self: &mut ArcasRustDtlsCallbacks, packet: &[u8]);
+        fn on_connected(self: &mut ArcasRustDtlsCallbacks);
+        fn on_data(self: &mut ArcasRustDtlsCallbacks, data: &[u8]);
+        fn on_closed(self: &mut ArcasRustDtlsCallbacks, error: i32);
+    }
+}
+
+/// Called on the session's thread, calling back into the session from
+/// here deadlocks.
+pub trait DtlsCallbacks: Send {
+    /// A DTLS record to send to the peer.
+    fn send_packet(&mut self, packet: &[u8]);
+
+    /// The handshake completed and the peer's certificate matched.
+    fn on_connected(&mut self);
+
+    /// Application data decrypted from the peer.
+    fn on_data(&mut self, data: &[u8]);
+
+    /// The session was closed by the peer or failed, `error` is the SSL
+    /// error, 0 for a clean close.
+    fn on_closed(&mut self, error: i32);
+}
+
+pub struct ArcasRustDtlsCallbacks {
+    callbacks: Box<dyn DtlsCallbacks>,
+}
+
+impl ArcasRustDtlsCallbacks {
+    pub fn new(callbacks: Box<dyn DtlsCallbacks>) -> Self {
+        Self { callbacks }
+    }
+
+    fn send_packet(&mut self, packet: &[u8]) {
+        self.callbacks.send_packet(packet);
+    }
+
+    fn on_connected(&mut self) {
+        self.callbacks.on_connected();
+    }
+
+    fn on_data(&mut self, data: &[u8]) {
+        self.callbacks.on_data(data);
+    }
+
+    fn on_closed(&mut self, error: i32) {
+        self.callbacks.on_closed(error);
+    }
+}
+
+// All calls are marshalled onto the session's own thread.
+unsafe impl Send for ffi::ArcasDtlsSession {}
+unsafe impl Sync for ffi::ArcasDtlsSession {}
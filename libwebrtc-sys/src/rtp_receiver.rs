@@ -6,9 +6,17 @@ pub mod ffi {
         type ArcasRTPReceiver;
         type ArcasRTPVideoReceiver;
         type ArcasRTPAudioReceiver;
+        type ArcasVideoTrack = crate::video_track::ffi::ArcasVideoTrack;
+        type ArcasAudioTrack = crate::audio_track::ffi::ArcasAudioTrack;
 
         fn gen_unique_rtp_receiver() -> UniquePtr<ArcasRTPReceiver>;
         fn gen_unique_rtp_audio_receiver() -> UniquePtr<ArcasRTPAudioReceiver>;
         fn gen_unique_rtp_video_receiver() -> UniquePtr<ArcasRTPVideoReceiver>;
+
+        // ArcasRTPVideoReceiver
+        fn get_track(self: &ArcasRTPVideoReceiver) -> UniquePtr<ArcasVideoTrack>;
+
+        // ArcasRTPAudioReceiver
+        fn get_track(self: &ArcasRTPAudioReceiver) -> UniquePtr<ArcasAudioTrack>;
     }
 }
@@ -69,6 +69,11 @@ pub mod ffi {
             extensions: UniquePtr<CxxVector<ArcasRTPHeaderExtensionCapability>>,
         ) -> UniquePtr<ArcasRTCError>;
 
+        fn enable_header_extension(
+            self: &ArcasRTPVideoTransceiver,
+            uri: String,
+        ) -> UniquePtr<ArcasRTCError>;
+
         // ArcasRTPAudioTransceiver
         fn mid(self: &ArcasRTPAudioTransceiver) -> String;
         fn media_type(self: &ArcasRTPAudioTransceiver) -> ArcasMediaType;
@@ -62,7 +62,7 @@ void ArcasSctpCallbacksProxyWrapper::OnError(dcsctp::ErrorKind error, absl::stri
 
 void ArcasSctpCallbacksProxyWrapper::OnAborted(dcsctp::ErrorKind error, absl::string_view message)
 {
-    _proxy->on_error(error, rust::String(message.data()));
+    _proxy->on_aborted(error, rust::String(message.data()));
 }
 
 void ArcasSctpCallbacksProxyWrapper::OnConnected()
@@ -182,11 +182,10 @@ void ArcasSctpSocket::close()
 
 dcsctp::SendStatus ArcasSctpSocket::send(uint16_t stream_id,
                                          uint32_t ppid,
-                                         rust::Vec<uint8_t> payload,
+                                         rust::Slice<const uint8_t> payload,
                                          ArcasSctpSendOptions& options)
 
 {
-    // TODO: Is there some way to avoid copying these bytes between rust and C++?
     std::vector<uint8_t> cxx_vec(payload.begin(), payload.end());
     dcsctp::DcSctpMessage msg{dcsctp::StreamID(stream_id), dcsctp::PPID(ppid), cxx_vec};
 
@@ -165,7 +165,7 @@ pub mod ffi {
             self: Pin<&mut ArcasSctpSocket>,
             stream_id: u16,
             ppid: u32,
-            payload: Vec<u8>,
+            payload: &[u8],
             options: Pin<&mut ArcasSctpSendOptions>,
         ) -> SendStatus;
         fn reset_streams(self: Pin<&mut ArcasSctpSocket>, streams: Vec<u16>) -> ResetStreamsStatus;
@@ -216,6 +216,18 @@ pub mod ffi {
         kUnifiedPlan,
     }
 
+    #[derive(Debug)]
+    #[repr(u32)]
+    // Which candidates ICE gathers and uses.
+    enum ArcasICETransportType {
+        kNone,
+        // Only TURN relay candidates.
+        kRelay,
+        // Everything but host candidates.
+        kNoHost,
+        kAll,
+    }
+
     #[derive(Debug)]
     struct ArcasSdpVideoFormatInit {
         name: String,
@@ -289,6 +301,7 @@ pub mod ffi {
         type ArcasIceConnectionState;
         type ArcasTlsCertPolicy;
         type ArcasSDPSemantics;
+        type ArcasICETransportType;
         type ArcasCxxEncodedImageCallbackResultError;
         type ArcasCxxRtpTransceiverDirection;
         type ArcasCxxInterLayerPredMode;
@@ -0,0 +1,95 @@
+//! Serialize and Deserialize for the stats structs of the bridge, cxx can't
+//! derive them behind a feature flag.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::peer_connection::ffi::{
+    ArcasAudioReceiverStats, ArcasAudioSenderStats, ArcasVideoReceiverStats, ArcasVideoSenderStats,
+};
+
+macro_rules! remote_impls {
+    ($stats:ty, $remote:ty) => {
+        impl Serialize for $stats {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                <$remote>::serialize(self, serializer)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $stats {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                <$remote>::deserialize(deserializer)
+            }
+        }
+    };
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "ArcasVideoReceiverStats")]
+struct VideoReceiverStats {
+    ssrc: u32,
+    packets_received: u32,
+    packets_lost: i32,
+    packets_repaired: u32,
+    bytes_received: u64,
+    frames_decoded: u32,
+    keyframes_decoded: u32,
+    frames_dropped: u32,
+    total_decode_time: f64,
+    frame_width: u32,
+    frame_height: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "ArcasVideoSenderStats")]
+struct VideoSenderStats {
+    ssrc: u32,
+    packets_sent: u32,
+    bytes_sent: u64,
+    frames_encoded: u32,
+    key_frames_encoded: u32,
+    total_encode_time: f64,
+    frame_width: u32,
+    frame_height: u32,
+    retransmitted_packets_sent: u64,
+    retransmitted_bytes_sent: u64,
+    total_packet_send_delay: f64,
+    nack_count: u32,
+    fir_count: u32,
+    pli_count: u32,
+    quality_limitation_reason: u32,
+    quality_limitation_resolution_changes: u32,
+    remote_packets_lost: i32,
+    remote_jitter: f64,
+    remote_round_trip_time: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "ArcasAudioReceiverStats")]
+struct AudioReceiverStats {
+    ssrc: u32,
+    packets_received: u32,
+    packets_lost: i32,
+    bytes_received: u64,
+    total_samples_received: u64,
+    total_samples_duration: f64,
+    audio_level: f64,
+    total_audio_energy: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "ArcasAudioSenderStats")]
+struct AudioSenderStats {
+    ssrc: u32,
+    packets_sent: u32,
+    bytes_sent: u64,
+    remote_packets_lost: i32,
+    remote_jitter: f64,
+    remote_round_trip_time: f64,
+    audio_level: f64,
+    total_audio_energy: f64,
+}
+
+remote_impls!(ArcasVideoReceiverStats, VideoReceiverStats);
+remote_impls!(ArcasVideoSenderStats, VideoSenderStats);
+remote_impls!(ArcasAudioReceiverStats, AudioReceiverStats);
+remote_impls!(ArcasAudioSenderStats, AudioSenderStats);
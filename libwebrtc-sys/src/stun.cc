@@ -1,6 +1,173 @@
 #include "libwebrtc-sys/include/stun.h"
+#include "libwebrtc-sys/src/stun.rs.h"
+#include "rtc_base/byte_order.h"
 
 std::unique_ptr<ArcasICEMessage> create_arcas_ice_message()
 {
     return std::make_unique<ArcasICEMessage>();
 }
+
+bool validate_stun_fingerprint(rust::Slice<const uint8_t> data)
+{
+    return cricket::StunMessage::ValidateFingerprint(reinterpret_cast<const char*>(data.data()), data.size());
+}
+
+rust::Vec<uint8_t> ArcasICEMessage::rust_write() const
+{
+    rtc::ByteBufferWriter buffer;
+    this->Write(&buffer);
+    rust::Vec<uint8_t> data;
+    data.reserve(buffer.Length());
+    for (size_t i = 0; i < buffer.Length(); i++) { data.push_back(static_cast<uint8_t>(buffer.Data()[i])); }
+    return data;
+}
+
+rust::Vec<ArcasStunAttributeValue> ArcasICEMessage::rust_attributes() const
+{
+    rtc::ByteBufferWriter buffer;
+    this->Write(&buffer);
+    const char* data = buffer.Data();
+
+    rust::Vec<ArcasStunAttributeValue> values;
+    size_t offset = cricket::kStunHeaderSize;
+    while (offset + 4 <= buffer.Length())
+    {
+        uint16_t attr_type = rtc::GetBE16(data + offset);
+        uint16_t length = rtc::GetBE16(data + offset + 2);
+        // Attribute values are padded to a multiple of 4 bytes.
+        size_t padded = std::min<size_t>((length + 3) & ~3, buffer.Length() - offset - 4);
+
+        // Decode every attribute in a message of its own, looking it up by
+        // type would return the first of repeated attributes.
+        rtc::ByteBufferWriter single;
+        single.WriteUInt16(this->type());
+        single.WriteUInt16(static_cast<uint16_t>(4 + padded));
+        single.WriteBytes(data + 4, cricket::kStunHeaderSize - 4);
+        single.WriteBytes(data + offset, 4 + padded);
+        rtc::ByteBufferReader reader(single.Data(), single.Length());
+        ArcasICEMessage message;
+        ArcasStunAttributeValue value = message.rust_attribute(attr_type);
+        if (message.Read(&reader))
+        {
+            value = message.rust_attribute(attr_type);
+        }
+
+        value.raw.clear();
+        for (size_t i = 0; i < std::min<size_t>(length, padded); i++)
+        {
+            value.raw.push_back(static_cast<uint8_t>(data[offset + 4 + i]));
+        }
+        values.push_back(std::move(value));
+        offset += 4 + padded;
+    }
+    return values;
+}
+
+ArcasStunAttributeValue ArcasICEMessage::rust_attribute(uint16_t attr_type) const
+{
+    ArcasStunAttributeValue value;
+    value.attr_type = attr_type;
+    value.value_type = cricket::STUN_VALUE_UNKNOWN;
+    value.port = 0;
+    value.number = 0;
+    value.error_code = 0;
+
+    // The typed getters don't check the value type, only look up the
+    // attribute and check its value type before downcasting.
+    const cricket::StunAttribute* attr = this->GetByteString(attr_type);
+    if (!attr)
+    {
+        return value;
+    }
+
+    value.value_type = attr->value_type();
+    rtc::ByteBufferWriter raw;
+    attr->Write(&raw);
+    for (size_t i = 0; i < attr->length(); i++) { value.raw.push_back(static_cast<uint8_t>(raw.Data()[i])); }
+    switch (attr->value_type())
+    {
+    case cricket::STUN_VALUE_ADDRESS:
+    case cricket::STUN_VALUE_XOR_ADDRESS:
+    {
+        auto address = static_cast<const cricket::StunAddressAttribute*>(attr);
+        value.address = rust::String(address->ipaddr().ToString());
+        value.port = address->port();
+        break;
+    }
+    case cricket::STUN_VALUE_UINT32:
+        value.number = static_cast<const cricket::StunUInt32Attribute*>(attr)->value();
+        break;
+    case cricket::STUN_VALUE_UINT64:
+        value.number = static_cast<const cricket::StunUInt64Attribute*>(attr)->value();
+        break;
+    case cricket::STUN_VALUE_BYTE_STRING:
+    {
+        auto bytes = static_cast<const cricket::StunByteStringAttribute*>(attr);
+        value.bytes.reserve(bytes->length());
+        for (size_t i = 0; i < bytes->length(); i++) { value.bytes.push_back(static_cast<uint8_t>(bytes->bytes()[i])); }
+        break;
+    }
+    case cricket::STUN_VALUE_ERROR_CODE:
+    {
+        auto error = static_cast<const cricket::StunErrorCodeAttribute*>(attr);
+        value.error_code = error->code();
+        value.reason = rust::String(error->reason());
+        break;
+    }
+    case cricket::STUN_VALUE_UINT16_LIST:
+    {
+        auto list = static_cast<const cricket::StunUInt16ListAttribute*>(attr);
+        for (size_t i = 0; i < list->Size(); i++) { value.list.push_back(list->GetType(i)); }
+        break;
+    }
+    default:
+        break;
+    }
+    return value;
+}
+
+void ArcasICEMessage::rust_add_address(uint16_t attr_type, bool xor_mapped, rust::String ip, uint16_t port)
+{
+    std::unique_ptr<cricket::StunAddressAttribute> address;
+    if (xor_mapped)
+    {
+        address = cricket::StunAttribute::CreateXorAddress(attr_type);
+    }
+    else
+    {
+        address = cricket::StunAttribute::CreateAddress(attr_type);
+    }
+    address->SetAddress(rtc::SocketAddress(std::string(ip.c_str()), port));
+    this->AddAttribute(std::move(address));
+}
+
+void ArcasICEMessage::rust_add_uint32(uint16_t attr_type, uint32_t value)
+{
+    this->AddAttribute(std::make_unique<cricket::StunUInt32Attribute>(attr_type, value));
+}
+
+void ArcasICEMessage::rust_add_uint64(uint16_t attr_type, uint64_t value)
+{
+    this->AddAttribute(std::make_unique<cricket::StunUInt64Attribute>(attr_type, value));
+}
+
+void ArcasICEMessage::rust_add_byte_string(uint16_t attr_type, rust::Slice<const uint8_t> value)
+{
+    this->AddAttribute(std::make_unique<cricket::StunByteStringAttribute>(
+        attr_type, reinterpret_cast<const char*>(value.data()), value.size()));
+}
+
+void ArcasICEMessage::rust_add_error_code(int code, rust::String reason)
+{
+    auto error = cricket::StunAttribute::CreateErrorCode();
+    error->SetCode(code);
+    error->SetReason(std::string(reason.c_str()));
+    this->AddAttribute(std::move(error));
+}
+
+void ArcasICEMessage::rust_add_uint16_list(uint16_t attr_type, rust::Slice<const uint16_t> values)
+{
+    auto list = std::make_unique<cricket::StunUInt16ListAttribute>(attr_type, 0);
+    for (auto value : values) { list->AddType(value); }
+    this->AddAttribute(std::move(list));
+}
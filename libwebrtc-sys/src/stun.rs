@@ -126,6 +126,22 @@ pub mod ffi {
         kIntegrityBad, // Message-integrity verification failed
     }
 
+    /// A decoded attribute, which fields are set depends on `value_type`.
+    #[derive(Debug, Clone)]
+    struct ArcasStunAttributeValue {
+        attr_type: u16,
+        value_type: StunAttributeValueType,
+        address: String,
+        port: u16,
+        number: u64,
+        bytes: Vec<u8>,
+        error_code: i32,
+        reason: String,
+        list: Vec<u16>,
+        /// The value as on the wire, without padding.
+        raw: Vec<u8>,
+    }
+
     unsafe extern "C++" {
         include!("include/stun.h");
 
@@ -224,6 +240,34 @@ pub mod ffi {
         unsafe fn read(self: Pin<&mut ArcasICEMessage>, buffer: *mut ByteBufferReader) -> bool;
         #[cxx_name = "SetStunMagicCookie"]
         fn set_stun_magic_cookie(self: Pin<&mut ArcasICEMessage>, magic_cookie: u32);
+        #[cxx_name = "rust_read"]
+        fn read_slice(self: Pin<&mut ArcasICEMessage>, data: &[u8]) -> bool;
+        #[cxx_name = "rust_write"]
+        fn write(self: &ArcasICEMessage) -> Vec<u8>;
+        #[cxx_name = "rust_attributes"]
+        fn attributes(self: &ArcasICEMessage) -> Vec<ArcasStunAttributeValue>;
+        #[cxx_name = "rust_attribute"]
+        fn attribute(self: &ArcasICEMessage, attr_type: u16) -> ArcasStunAttributeValue;
+        #[cxx_name = "rust_add_address"]
+        fn add_address(
+            self: Pin<&mut ArcasICEMessage>,
+            attr_type: u16,
+            xor_mapped: bool,
+            ip: String,
+            port: u16,
+        );
+        #[cxx_name = "rust_add_uint32"]
+        fn add_uint32(self: Pin<&mut ArcasICEMessage>, attr_type: u16, value: u32);
+        #[cxx_name = "rust_add_uint64"]
+        fn add_uint64(self: Pin<&mut ArcasICEMessage>, attr_type: u16, value: u64);
+        #[cxx_name = "rust_add_byte_string"]
+        fn add_byte_string(self: Pin<&mut ArcasICEMessage>, attr_type: u16, value: &[u8]);
+        #[cxx_name = "rust_add_error_code"]
+        fn add_error_code(self: Pin<&mut ArcasICEMessage>, code: i32, reason: String);
+        #[cxx_name = "rust_add_uint16_list"]
+        fn add_uint16_list(self: Pin<&mut ArcasICEMessage>, attr_type: u16, values: &[u16]);
+
+        fn validate_stun_fingerprint(data: &[u8]) -> bool;
     }
 }
 
@@ -21,6 +21,7 @@ pub mod ffi {
 
         // ArcasVideoCodec
         fn get_scalability_mode(self: &ArcasVideoCodec) -> String;
+        fn get_codec_type(self: &ArcasVideoCodec) -> ArcasCxxVideoCodecType;
         fn get_width(self: &ArcasVideoCodec) -> i32;
         fn get_height(self: &ArcasVideoCodec) -> i32;
         fn get_max_bitrate(self: &ArcasVideoCodec) -> u32;
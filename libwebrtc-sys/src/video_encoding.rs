@@ -235,6 +235,9 @@ pub mod ffi {
             bitrate: u32,
         ) -> bool;
 
+        #[cxx_name = "get_sum_bps"]
+        fn get_sum_bps(self: &ArcasCxxVideoBitrateAllocation) -> u32;
+
         #[cxx_name = "HasBitrate"]
         fn has_bitrate(
             self: &ArcasCxxVideoBitrateAllocation,
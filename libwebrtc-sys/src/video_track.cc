@@ -0,0 +1,79 @@
+#include "libwebrtc-sys/include/video_track.h"
+#include "libwebrtc-sys/src/video_track.rs.h"
+
+ArcasVideoSink::ArcasVideoSink(rtc::scoped_refptr<webrtc::VideoTrackInterface> track, rust::Box<ArcasRustVideoSink> api)
+: track_(track)
+, api_(std::move(api))
+{
+    track_->AddOrUpdateSink(this, rtc::VideoSinkWants());
+}
+
+ArcasVideoSink::~ArcasVideoSink()
+{
+    RTC_LOG(LS_VERBOSE) << "~ArcasVideoSink";
+    track_->RemoveSink(this);
+}
+
+void ArcasVideoSink::OnFrame(const webrtc::VideoFrame& frame)
+{
+    api_->on_frame(frame);
+}
+
+std::unique_ptr<ArcasVideoSink> create_arcas_video_sink(const ArcasVideoTrack& track, rust::Box<ArcasRustVideoSink> sink)
+{
+    return std::make_unique<ArcasVideoSink>(track.ref(), std::move(sink));
+}
+
+ArcasReceivedVideoFrameInfo get_received_video_frame_info(const ArcasCxxVideoFrame& frame)
+{
+    // Zero when none of the packets of the frame carried the
+    // abs-capture-time header extension.
+    int64_t absolute_capture_ntp_time_ms = 0;
+    for (const auto& info : frame.packet_infos())
+    {
+        auto absolute_capture_time = info.absolute_capture_time();
+        if (absolute_capture_time.has_value())
+        {
+            // UQ32.32 fixed point NTP timestamp.
+            uint64_t timestamp = absolute_capture_time->absolute_capture_timestamp;
+            absolute_capture_ntp_time_ms = static_cast<int64_t>(
+                (timestamp >> 32) * 1000 + (((timestamp & 0xFFFFFFFF) * 1000) >> 32));
+            break;
+        }
+    }
+
+    return ArcasReceivedVideoFrameInfo{
+        .width = frame.width(),
+        .height = frame.height(),
+        .rtp_timestamp = frame.timestamp(),
+        .ntp_time_ms = frame.ntp_time_ms(),
+        .absolute_capture_ntp_time_ms = absolute_capture_ntp_time_ms,
+    };
+}
+
+rust::Vec<uint8_t> copy_video_frame_luma_rows(const ArcasCxxVideoFrame& frame, int32_t rows)
+{
+    rust::Vec<uint8_t> out;
+    auto buffer = frame.video_frame_buffer();
+    // Passthrough tracks carry native buffers without any pixels.
+    if (!buffer || buffer->type() == webrtc::VideoFrameBuffer::Type::kNative)
+    {
+        return out;
+    }
+
+    auto i420 = buffer->ToI420();
+    if (!i420)
+    {
+        return out;
+    }
+
+    auto height = std::min(rows, i420->height());
+    auto width = i420->width();
+    out.reserve(width * height);
+    for (int row = 0; row < height; row++)
+    {
+        auto start = i420->DataY() + row * i420->StrideY();
+        for (int column = 0; column < width; column++) { out.push_back(start[column]); }
+    }
+    return out;
+}
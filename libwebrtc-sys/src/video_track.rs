@@ -1,9 +1,62 @@
 #[cxx::bridge]
 pub mod ffi {
+    #[derive(Debug, Clone, Copy)]
+    struct ArcasReceivedVideoFrameInfo {
+        width: i32,
+        height: i32,
+        rtp_timestamp: u32,
+        /// Sender capture time estimated from RTCP sender reports, zero until
+        /// the first report arrives.
+        ntp_time_ms: i64,
+        /// Capture time from the abs-capture-time header extension, zero when
+        /// the extension was not negotiated.
+        absolute_capture_ntp_time_ms: i64,
+    }
+
     unsafe extern "C++" {
+        include!("include/alias.h");
         include!("include/video_track.h");
         type ArcasVideoTrack;
+        type ArcasVideoSink;
+        type ArcasCxxVideoFrame = crate::video_frame::ffi::ArcasCxxVideoFrame;
 
         fn gen_unique_video_track() -> UniquePtr<ArcasVideoTrack>;
+        fn id(self: &ArcasVideoTrack) -> String;
+
+        /// The sink is attached to the track until the returned pointer is
+        /// dropped.
+        fn create_arcas_video_sink(
+            track: &ArcasVideoTrack,
+            sink: Box<ArcasRustVideoSink>,
+        ) -> UniquePtr<ArcasVideoSink>;
+        fn get_received_video_frame_info(frame: &ArcasCxxVideoFrame)
+            -> ArcasReceivedVideoFrameInfo;
+        /// Copy up to `rows` rows of the Y plane without padding.  Empty for
+        /// frames without pixel data.
+        fn copy_video_frame_luma_rows(frame: &ArcasCxxVideoFrame, rows: i32) -> Vec<u8>;
+    }
+
+    extern "Rust" {
+        type ArcasRustVideoSink;
+
+        fn on_frame(self: &ArcasRustVideoSink, frame: &ArcasCxxVideoFrame);
+    }
+}
+
+pub trait VideoSinkImpl {
+    fn on_frame(&self, frame: &ffi::ArcasCxxVideoFrame);
+}
+
+pub struct ArcasRustVideoSink {
+    inner: Box<dyn VideoSinkImpl + Send + Sync>,
+}
+
+impl ArcasRustVideoSink {
+    pub fn new(inner: Box<dyn VideoSinkImpl + Send + Sync>) -> Self {
+        Self { inner }
+    }
+
+    pub fn on_frame(&self, frame: &ffi::ArcasCxxVideoFrame) {
+        self.inner.on_frame(frame)
     }
 }
@@ -12,6 +12,15 @@ impl AudioTrack {
         Self { cxx: Some(cxx) }
     }
 
+    pub fn id(&self) -> Result<String> {
+        match self.cxx.as_ref() {
+            Some(cxx) => Ok(cxx.id()),
+            None => Err(WebRTCError::UnexpectedError(
+                "Audio track was already added".into(),
+            )),
+        }
+    }
+
     pub(crate) fn take_cxx(&mut self) -> Result<UniquePtr<ArcasAudioTrack>> {
         match self.cxx.take() {
             Some(x) => Ok(x),
@@ -1,14 +1,35 @@
+use std::{
+    convert::TryFrom,
+    fs::OpenOptions,
+    io::Write,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
 use cxx::UniquePtr;
+pub use libwebrtc_sys::rtc_base::certificates::ffi::KeyType;
 use libwebrtc_sys::rtc_base::certificates::ffi::{
     create_arcas_key_params_ecdsa, create_arcas_key_params_rsa, create_arcas_rtc_certificate,
     create_arcas_rtc_certificate_from_pem, create_arcas_ssl_identity_with_key_params,
-    ArcasKeyParams, ArcasSSLCertificate, KeyType,
+    create_arcas_ssl_identity_with_lifetime, ArcasKeyParams, ArcasRTCCertificatePEM,
+    ArcasSSLCertificate,
 };
 
+use crate::error::{Result, WebRTCError};
+
+pub type RTCCertificatePEM = ArcasRTCCertificatePEM;
+
+/// Common name libwebrtc gives the certificates it generates itself.
+const COMMON_NAME: &str = "WebRTC";
+const PEM_CERTIFICATE_HEADER: &str = "-----BEGIN CERTIFICATE-----";
+
 pub struct KeyParams {
     inner: UniquePtr<ArcasKeyParams>,
 }
 
+// Plain key type and size, nothing thread bound.
+unsafe impl Send for KeyParams {}
+
 pub struct SSLIdentity {
     inner: UniquePtr<libwebrtc_sys::rtc_base::certificates::ffi::SSLIdentity>,
 }
@@ -17,6 +38,10 @@ pub struct SSLCertificate {
     pub(crate) inner: UniquePtr<ArcasSSLCertificate>,
 }
 
+// The underlying RTCCertificate is immutable and thread safe reference counted.
+unsafe impl Send for SSLCertificate {}
+unsafe impl Sync for SSLCertificate {}
+
 impl KeyParams {
     pub fn new(key_type: KeyType) -> Self {
         if key_type == KeyType::KT_ECDSA {
@@ -44,7 +69,125 @@ impl SSLCertificate {
             inner: create_arcas_rtc_certificate(ident.inner),
         }
     }
+
+    /// Load a certificate and its private key saved with `to_pem`.
+    pub fn from_pem(private_key: &str, certificate: &str) -> Result<Self> {
+        let inner = create_arcas_rtc_certificate_from_pem(private_key.into(), certificate.into());
+        if inner.is_null() {
+            return Err(WebRTCError::CertificateError(
+                "invalid PEM private key or certificate".into(),
+            ));
+        }
+        Ok(Self { inner })
+    }
+
+    pub fn to_pem(&self) -> RTCCertificatePEM {
+        self.inner.to_pem()
+    }
+
+    /// Read a file written by `save_pem`, the private key followed by the
+    /// certificate.
+    pub fn load_pem<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let pem = std::fs::read_to_string(path.as_ref()).map_err(|err| {
+            WebRTCError::CertificateError(format!("{}: {}", path.as_ref().display(), err))
+        })?;
+        let split = pem.find(PEM_CERTIFICATE_HEADER).ok_or_else(|| {
+            WebRTCError::CertificateError(format!(
+                "{}: no certificate found",
+                path.as_ref().display()
+            ))
+        })?;
+        let (private_key, certificate) = pem.split_at(split);
+        Self::from_pem(private_key, certificate)
+    }
+
+    /// Write the private key and the certificate to a single file.  The
+    /// file is only readable by its owner on unix.
+    pub fn save_pem<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let pem = self.to_pem();
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        options
+            .open(path.as_ref())
+            .and_then(|mut file| {
+                file.write_all(pem.private_key.as_bytes())?;
+                file.write_all(pem.certificate.as_bytes())
+            })
+            .map_err(|err| {
+                WebRTCError::CertificateError(format!("{}: {}", path.as_ref().display(), err))
+            })
+    }
+
     pub fn get_fingerprint(&self) -> String {
         self.inner.get_fingerprint()
     }
+
+    pub fn expires(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(self.inner.expires())
+    }
+}
+
+impl Clone for SSLCertificate {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// Generate a certificate valid for `expires` from now.  Key generation
+/// runs on a blocking thread, RSA keys in particular take a while.
+pub async fn generate_certificate(
+    key_params: KeyParams,
+    expires: Duration,
+) -> Result<SSLCertificate> {
+    let lifetime = i64::try_from(expires.as_secs())
+        .map_err(|_| WebRTCError::CertificateError(format!("expiry too large: {:?}", expires)))?;
+    tokio::task::spawn_blocking(move || {
+        let identity =
+            create_arcas_ssl_identity_with_lifetime(COMMON_NAME.into(), key_params.inner, lifetime);
+        if identity.is_null() {
+            return Err(WebRTCError::CertificateError(
+                "failed to generate identity".into(),
+            ));
+        }
+        Ok(SSLCertificate {
+            inner: create_arcas_rtc_certificate(identity),
+        })
+    })
+    .await
+    .map_err(|err| WebRTCError::CertificateError(err.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_certificate_pem_round_trip() {
+        let certificate =
+            generate_certificate(KeyParams::new(KeyType::KT_ECDSA), Duration::from_secs(3600))
+                .await
+                .unwrap();
+        let expires_in = certificate
+            .expires()
+            .duration_since(SystemTime::now())
+            .unwrap();
+        assert!(expires_in <= Duration::from_secs(3600));
+        assert!(expires_in > Duration::from_secs(3500));
+
+        let path = std::env::temp_dir().join(format!("arcas-cert-{}.pem", std::process::id()));
+        certificate.save_pem(&path).unwrap();
+        let loaded = SSLCertificate::load_pem(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.get_fingerprint(), certificate.get_fingerprint());
+        assert_eq!(loaded.expires(), certificate.expires());
+
+        let pem = certificate.to_pem();
+        assert!(SSLCertificate::from_pem(&pem.private_key, "").is_err());
+        assert!(SSLCertificate::from_pem(&pem.private_key, &pem.certificate).is_ok());
+    }
 }
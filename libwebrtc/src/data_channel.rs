@@ -1,4 +1,11 @@
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
 use cxx::UniquePtr;
+use futures_core::Stream;
 use libwebrtc_sys::data_channel::{
     ffi::{
         create_arcas_data_channel_observer, ArcasCxxDataState, ArcasDataBuffer, ArcasDataChannel,
@@ -6,9 +13,20 @@ use libwebrtc_sys::data_channel::{
     },
     ArcasDataChannelObserverWrapper, DataChannelObserverImpl,
 };
-use tokio::sync::mpsc::Sender;
+use parking_lot::Mutex;
+use tokio::sync::{
+    mpsc::{unbounded_channel, Sender, UnboundedReceiver, UnboundedSender},
+    Notify,
+};
 
-use crate::{error::Result, send_event};
+use crate::{
+    error::{Result, WebRTCError},
+    send_event,
+};
+
+/// Buffered bytes above which `DataChannel::send` waits for the channel to
+/// drain.
+pub const DEFAULT_BUFFERED_AMOUNT_LOW_THRESHOLD: u64 = 1024 * 1024;
 
 #[derive(Debug, Default)]
 pub struct DataChannelInit {
@@ -38,6 +56,11 @@ pub struct DataChannelInit {
     // Session id ... if not provided one will be automatically generated.
     pub id: Option<u16>,
 
+    /// True if the channel is created on both peers by the application with
+    /// the same `id` rather than announced in-band to the remote peer.
+    /// Requires `id`.
+    pub negotiated: bool,
+
     /// https://w3c.github.io/webrtc-priority/#new-rtcdatachannelinit-member
     pub priority: Option<Priority>,
 }
@@ -60,6 +83,7 @@ impl From<DataChannelInit> for ArcasDataChannelInit {
                 Some(v) => vec![v],
                 None => vec![],
             },
+            negotiated: init.negotiated,
             priority: match init.priority {
                 Some(v) => vec![v],
                 None => vec![],
@@ -68,12 +92,32 @@ impl From<DataChannelInit> for ArcasDataChannelInit {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DataChannelMessage {
     pub data: Vec<u8>,
     pub binary: bool,
 }
 
-type OnMessageCallback = Box<dyn Fn(&[u8], bool)>;
+/// Messages received on a data channel, ends once the channel is closed.
+pub struct DataChannelMessages {
+    rx: UnboundedReceiver<DataChannelMessage>,
+}
+
+impl DataChannelMessages {
+    pub async fn recv(&mut self) -> Option<DataChannelMessage> {
+        self.rx.recv().await
+    }
+}
+
+impl Stream for DataChannelMessages {
+    type Item = DataChannelMessage;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+type OnMessageCallback = Box<dyn Fn(&[u8], bool) + Send>;
 
 #[derive(Default)]
 pub struct DataChannelSenders {
@@ -100,33 +144,156 @@ impl DataChannelObserverImpl for DataChannelSenders {
     }
 }
 
+/// Observer state shared with the `DataChannel`, the observer is called on
+/// the signaling thread.
+struct ChannelEvents {
+    channel: UniquePtr<ArcasDataChannel>,
+    // Cloned out before calling back so callbacks can replace the senders.
+    senders: Mutex<Arc<DataChannelSenders>>,
+    // Buffers messages until `DataChannel::messages` is taken unless an
+    // `on_message` callback is observed first, dropped once the channel is
+    // closed.
+    messages: Mutex<Option<UnboundedSender<DataChannelMessage>>>,
+    // State or buffered amount changed.
+    changed: Notify,
+}
+
+struct ChannelObserver(Arc<ChannelEvents>);
+
+impl ChannelObserver {
+    fn senders(&self) -> Arc<DataChannelSenders> {
+        self.0.senders.lock().clone()
+    }
+}
+
+impl DataChannelObserverImpl for ChannelObserver {
+    fn on_state_change(&self) {
+        if self.0.channel.state() == ArcasCxxDataState::kClosed {
+            self.0.messages.lock().take();
+        }
+        self.0.changed.notify_waiters();
+        self.senders().on_state_change();
+    }
+
+    fn on_message(&self, slice: &[u8], binary: bool) {
+        if let Some(messages) = &*self.0.messages.lock() {
+            let _ = messages.send(DataChannelMessage {
+                data: slice.to_vec(),
+                binary,
+            });
+        }
+        self.senders().on_message(slice, binary);
+    }
+
+    fn on_buffered_amount_change(&self, sent_data_size: u64) {
+        self.0.changed.notify_waiters();
+        self.senders().on_buffered_amount_change(sent_data_size);
+    }
+}
+
 pub struct DataChannel {
     inner: UniquePtr<ArcasDataChannel>,
+    events: Arc<ChannelEvents>,
+    messages: Option<UnboundedReceiver<DataChannelMessage>>,
+    messages_taken: bool,
+    buffered_amount_low_threshold: u64,
 
-    // Held for C++
-    #[allow(unused)]
-    current_observer: Option<UniquePtr<ArcasDataChannelObserver>>,
+    // Held for C++, registered on first use so libwebrtc queues messages
+    // received until then.
+    observer: Option<UniquePtr<ArcasDataChannelObserver>>,
 }
 
+// DataChannelInterface is a proxy, calls are marshalled onto the signaling
+// thread.
+unsafe impl Send for DataChannel {}
+unsafe impl Sync for DataChannel {}
+
 impl DataChannel {
     pub(crate) fn new(inner: UniquePtr<ArcasDataChannel>) -> Self {
+        let (tx, rx) = unbounded_channel();
+        let events = Arc::new(ChannelEvents {
+            channel: inner.clone(),
+            senders: Mutex::new(Arc::new(DataChannelSenders::default())),
+            messages: Mutex::new(Some(tx)),
+            changed: Notify::new(),
+        });
         Self {
             inner,
-            current_observer: None,
+            events,
+            messages: Some(rx),
+            messages_taken: false,
+            buffered_amount_low_threshold: DEFAULT_BUFFERED_AMOUNT_LOW_THRESHOLD,
+            observer: None,
         }
     }
 
-    pub fn observe(&mut self, senders: DataChannelSenders) {
-        let mut wrapper = create_arcas_data_channel_observer(Box::new(
-            ArcasDataChannelObserverWrapper::new(Box::new(senders)),
+    fn ensure_observer(&mut self) {
+        if self.observer.is_some() {
+            return;
+        }
+        let mut observer = create_arcas_data_channel_observer(Box::new(
+            ArcasDataChannelObserverWrapper::new(Box::new(ChannelObserver(self.events.clone()))),
         ));
-        self.inner.pin_mut().unregister_observer();
         unsafe {
             self.inner
                 .pin_mut()
-                .register_observer(wrapper.pin_mut().get_unchecked_mut());
+                .register_observer(observer.pin_mut().get_unchecked_mut());
+        }
+        self.observer = Some(observer);
+    }
+
+    /// Replace the senders events are forwarded to.  Observing `on_message`
+    /// before taking `messages` stops buffering for the stream.
+    pub fn observe(&mut self, senders: DataChannelSenders) {
+        if senders.on_message.is_some() && !self.messages_taken {
+            self.messages = None;
+            self.events.messages.lock().take();
+        }
+        *self.events.senders.lock() = Arc::new(senders);
+        self.ensure_observer();
+    }
+
+    /// Stream of received messages, including those received before this
+    /// was called unless they went to an `on_message` callback.  Can only be
+    /// taken once.
+    pub fn messages(&mut self) -> Result<DataChannelMessages> {
+        if self.messages_taken {
+            return Err(WebRTCError::TakeError("data channel messages".into()));
+        }
+        self.messages_taken = true;
+        let rx = match self.messages.take() {
+            Some(rx) => rx,
+            None => {
+                let (tx, rx) = unbounded_channel();
+                *self.events.messages.lock() = Some(tx);
+                rx
+            }
+        };
+        self.ensure_observer();
+        // No state change is observed for a channel closed before the
+        // observer was registered.
+        if self.inner.state() == ArcasCxxDataState::kClosed {
+            self.events.messages.lock().take();
+        }
+        Ok(DataChannelMessages { rx })
+    }
+
+    /// Wait for the channel to open, fails if it closes first.
+    pub async fn await_open(&mut self) -> Result<()> {
+        self.ensure_observer();
+        loop {
+            let changed = self.events.changed.notified();
+            let state = self.inner.state();
+            if state == ArcasCxxDataState::kOpen {
+                return Ok(());
+            } else if state != ArcasCxxDataState::kConnecting {
+                return Err(WebRTCError::DataChannelError(format!(
+                    "closed before opening: {:?}",
+                    state
+                )));
+            }
+            changed.await;
         }
-        self.current_observer = Some(wrapper);
     }
 
     pub fn label(&self) -> String {
@@ -147,6 +314,7 @@ impl DataChannel {
     pub fn protocol(&self) -> String {
         self.inner.protocol()
     }
+    /// True if the channel was created on both peers with a pre-agreed id.
     pub fn negotiated(&self) -> bool {
         self.inner.negotiated()
     }
@@ -185,6 +353,15 @@ impl DataChannel {
         self.inner.buffered_amount()
     }
 
+    pub fn buffered_amount_low_threshold(&self) -> u64 {
+        self.buffered_amount_low_threshold
+    }
+
+    /// Buffered bytes above which `send` waits for the channel to drain.
+    pub fn set_buffered_amount_low_threshold(&mut self, threshold: u64) {
+        self.buffered_amount_low_threshold = threshold;
+    }
+
     /// Explicitly close the data channel.
     ///
     /// NOTE: This does not need to be called as on drop this will be invoked.
@@ -192,25 +369,293 @@ impl DataChannel {
         self.inner.pin_mut().close();
     }
 
-    /// Send a message over the data channel.
+    /// Send a message over the data channel, first waiting for the buffered
+    /// amount to drop to the low threshold.
     ///
     /// # Arguments
     ///
     /// * `data` - The data to send.
     /// * `binary` - true = binary, false = utf8 string.
     ///
-    pub fn send(&mut self, data: &[u8], binary: bool) {
+    pub async fn send(&mut self, data: &[u8], binary: bool) -> Result<()> {
+        self.ensure_observer();
+        loop {
+            let changed = self.events.changed.notified();
+            let state = self.inner.state();
+            if state != ArcasCxxDataState::kOpen {
+                return Err(WebRTCError::DataChannelError(format!(
+                    "can't send in state {:?}",
+                    state
+                )));
+            }
+            if self.inner.buffered_amount() <= self.buffered_amount_low_threshold {
+                break;
+            }
+            changed.await;
+        }
+
         let buffer = ArcasDataBuffer {
             ptr: data.as_ptr(),
             len: data.len(),
             binary,
         };
-        self.inner.pin_mut().send(&buffer);
+        if !self.inner.pin_mut().send(&buffer) {
+            return Err(WebRTCError::DataChannelError(format!(
+                "failed to send {} bytes",
+                data.len()
+            )));
+        }
+        Ok(())
     }
 }
 
 impl Drop for DataChannel {
     fn drop(&mut self) {
         self.inner.pin_mut().close();
+        // The observer is freed with this.
+        if self.observer.is_some() {
+            self.inner.pin_mut().unregister_observer();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc::channel;
+
+    use super::*;
+    use crate::{
+        factory::Factory,
+        loopback::{connect_pair, LoopbackReceivers},
+        peer_connection::{PeerConnectionConfig, SDPSemantic},
+        peer_connection_observer::ObserverSenders,
+    };
+
+    #[tokio::test]
+    async fn test_messages_with_backpressure() {
+        let factory1 = Factory::new();
+        let factory2 = Factory::new();
+        let pc_factory1 = factory1.create_peer_connection_factory().unwrap();
+        let pc_factory2 = factory2.create_peer_connection_factory().unwrap();
+
+        let (senders1, rx1) = LoopbackReceivers::create();
+        let pc1 = pc_factory1
+            .create_peer_connection(
+                PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]),
+                senders1,
+            )
+            .unwrap();
+        let mut dc1 = pc1
+            .create_data_channel("test".into(), DataChannelInit::default())
+            .unwrap();
+        let mut messages1 = dc1.messages().unwrap();
+        assert!(dc1.messages().is_err());
+
+        let (dc_tx, mut dc_rx) = channel(1);
+        let (senders2, rx2) = LoopbackReceivers::create();
+        let pc2 = pc_factory2
+            .create_peer_connection(
+                PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]),
+                ObserverSenders {
+                    data_channel: Some(dc_tx),
+                    ..senders2
+                },
+            )
+            .unwrap();
+        let _pair = connect_pair(&pc1, &pc2, rx1, rx2).await.unwrap();
+
+        dc1.await_open().await.unwrap();
+        let mut dc2 = dc_rx.recv().await.unwrap();
+        let mut messages2 = dc2.messages().unwrap();
+        dc2.await_open().await.unwrap();
+
+        // Keep at most one chunk buffered so sends wait for the channel to
+        // drain.
+        let chunk = vec![7u8; 64 * 1024];
+        dc1.set_buffered_amount_low_threshold(chunk.len() as u64);
+        for _ in 0..32 {
+            dc1.send(&chunk, true).await.unwrap();
+            assert!(dc1.buffered_bytes() <= 2 * chunk.len() as u64);
+        }
+        for _ in 0..32 {
+            let message = messages2.recv().await.unwrap();
+            assert_eq!(message.data, chunk);
+            assert!(message.binary);
+        }
+
+        dc2.send(b"reply", false).await.unwrap();
+        assert_eq!(
+            messages1.recv().await.unwrap(),
+            DataChannelMessage {
+                data: b"reply".to_vec(),
+                binary: false,
+            }
+        );
+
+        // The stream ends and sends fail once the channel is closed.
+        dc2.close();
+        assert!(messages1.recv().await.is_none());
+        assert!(dc1.send(b"late", true).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_messages_before_taken() {
+        let factory = Factory::new();
+        let pc_factory = factory.create_peer_connection_factory().unwrap();
+
+        let (senders1, rx1) = LoopbackReceivers::create();
+        let pc1 = pc_factory
+            .create_peer_connection(
+                PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]),
+                senders1,
+            )
+            .unwrap();
+        let mut dc1 = pc1
+            .create_data_channel("test".into(), DataChannelInit::default())
+            .unwrap();
+
+        let (dc_tx, mut dc_rx) = channel(1);
+        let (senders2, rx2) = LoopbackReceivers::create();
+        let pc2 = pc_factory
+            .create_peer_connection(
+                PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]),
+                ObserverSenders {
+                    data_channel: Some(dc_tx),
+                    ..senders2
+                },
+            )
+            .unwrap();
+        let _pair = connect_pair(&pc1, &pc2, rx1, rx2).await.unwrap();
+
+        // The observer is registered here, messages are taken later.
+        dc1.await_open().await.unwrap();
+        let mut dc2 = dc_rx.recv().await.unwrap();
+        dc2.await_open().await.unwrap();
+
+        dc1.send(b"early", true).await.unwrap();
+        while dc2.messages_received() == 0 {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let mut messages2 = dc2.messages().unwrap();
+        assert_eq!(messages2.recv().await.unwrap().data, b"early".to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_on_message_only() {
+        let factory = Factory::new();
+        let pc_factory = factory.create_peer_connection_factory().unwrap();
+
+        let (senders1, rx1) = LoopbackReceivers::create();
+        let pc1 = pc_factory
+            .create_peer_connection(
+                PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]),
+                senders1,
+            )
+            .unwrap();
+        let mut dc1 = pc1
+            .create_data_channel("test".into(), DataChannelInit::default())
+            .unwrap();
+
+        let (dc_tx, mut dc_rx) = channel(1);
+        let (senders2, rx2) = LoopbackReceivers::create();
+        let pc2 = pc_factory
+            .create_peer_connection(
+                PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]),
+                ObserverSenders {
+                    data_channel: Some(dc_tx),
+                    ..senders2
+                },
+            )
+            .unwrap();
+        let _pair = connect_pair(&pc1, &pc2, rx1, rx2).await.unwrap();
+
+        dc1.await_open().await.unwrap();
+        let mut dc2 = dc_rx.recv().await.unwrap();
+        let (message_tx, mut message_rx) = unbounded_channel();
+        dc2.observe(DataChannelSenders {
+            on_message: Some(Box::new(move |data, _binary| {
+                let _ = message_tx.send(data.len());
+            })),
+            ..DataChannelSenders::default()
+        });
+        dc2.await_open().await.unwrap();
+
+        let chunk = vec![1u8; 16 * 1024];
+        for _ in 0..200 {
+            dc1.send(&chunk, true).await.unwrap();
+        }
+        for _ in 0..200 {
+            assert_eq!(message_rx.recv().await.unwrap(), chunk.len());
+        }
+        // Nothing is kept for a stream nobody took.
+        assert!(dc2.messages.is_none());
+        assert!(dc2.events.messages.lock().is_none());
+
+        // Taking the stream later still works for new messages.
+        let mut messages2 = dc2.messages().unwrap();
+        assert!(dc2.messages().is_err());
+        dc1.send(b"late", true).await.unwrap();
+        assert_eq!(messages2.recv().await.unwrap().data, b"late".to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_negotiated() {
+        let factory1 = Factory::new();
+        let factory2 = Factory::new();
+        let pc_factory1 = factory1.create_peer_connection_factory().unwrap();
+        let pc_factory2 = factory2.create_peer_connection_factory().unwrap();
+
+        let (senders1, rx1) = LoopbackReceivers::create();
+        let pc1 = pc_factory1
+            .create_peer_connection(
+                PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]),
+                senders1,
+            )
+            .unwrap();
+        let (senders2, rx2) = LoopbackReceivers::create();
+        let pc2 = pc_factory2
+            .create_peer_connection(
+                PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]),
+                senders2,
+            )
+            .unwrap();
+
+        assert!(pc1
+            .create_data_channel(
+                "no-id".into(),
+                DataChannelInit {
+                    negotiated: true,
+                    ..DataChannelInit::default()
+                },
+            )
+            .is_err());
+
+        let negotiated = |id| DataChannelInit {
+            id: Some(id),
+            negotiated: true,
+            ordered: Some(true),
+            ..DataChannelInit::default()
+        };
+        let mut channels = vec![];
+        for id in 0..4 {
+            let mut dc1 = pc1
+                .create_data_channel(format!("channel-{}", id), negotiated(id))
+                .unwrap();
+            let mut dc2 = pc2
+                .create_data_channel(format!("channel-{}", id), negotiated(id))
+                .unwrap();
+            assert!(dc1.negotiated());
+            assert_eq!(dc1.id(), id as i32);
+            let messages2 = dc2.messages().unwrap();
+            channels.push((dc1, dc2, messages2));
+        }
+        let _pair = connect_pair(&pc1, &pc2, rx1, rx2).await.unwrap();
+
+        for (id, (dc1, dc2, messages2)) in channels.iter_mut().enumerate() {
+            dc1.await_open().await.unwrap();
+            dc2.await_open().await.unwrap();
+            dc1.send(&[id as u8], true).await.unwrap();
+            assert_eq!(messages2.recv().await.unwrap().data, vec![id as u8]);
+        }
     }
 }
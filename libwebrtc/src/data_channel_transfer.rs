@@ -0,0 +1,740 @@
+//! Payloads larger than the SCTP max message size sent over a data channel
+//! as a sequence of chunks.
+//!
+//! Every frame is one binary data channel message, integers are big-endian:
+//!
+//! ```text
+//! START   0x01 | transfer id (u32) | total length (u64) | chunk size (u32) | CRC-32 (u32) | name (UTF-8)
+//! CHUNK   0x02 | transfer id (u32) | chunk index (u32) | data
+//! CANCEL  0x03 | transfer id (u32)
+//! ```
+//!
+//! A transfer is a START followed by `ceil(total length / chunk size)`
+//! CHUNKs, chunk `i` holding the payload from `i * chunk size`.  Every chunk
+//! but the last is exactly `chunk size` bytes.  The CRC-32 is the IEEE one
+//! zlib computes over the whole payload.  The sender sends CANCEL instead
+//! of the remaining chunks to abandon a transfer.
+//!
+//! Data channels are unordered by default, so chunks may arrive in any
+//! order and CHUNKs or a CANCEL may overtake their START.  Chunks arriving
+//! early are buffered within the same limits as transfers in progress.
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    convert::TryInto,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use crate::{
+    data_channel::{DataChannel, DataChannelMessages},
+    error::{Result, WebRTCError},
+};
+
+const START: u8 = 0x01;
+const CHUNK: u8 = 0x02;
+const CANCEL: u8 = 0x03;
+
+const START_HEADER_LEN: usize = 21;
+const CHUNK_HEADER_LEN: usize = 9;
+
+/// Transfers that ended early remembered, so their late frames are dropped
+/// rather than buffered for a START.
+const MAX_ENDED_TRANSFERS: usize = 64;
+
+#[derive(Debug, Clone)]
+pub struct DataChannelTransferConfig {
+    /// Payload bytes per chunk, 16 KiB is safe with every browser.
+    pub chunk_size: u32,
+    /// Buffered bytes above which sending waits for the channel to drain.
+    pub buffered_amount_low_threshold: u64,
+    /// Largest incoming transfer accepted.
+    pub max_transfer_size: u64,
+    /// Incoming transfers in progress at once, including those with chunks
+    /// waiting for their START.
+    pub max_concurrent_transfers: usize,
+    /// Bytes of incoming transfers buffered at once, across transfers.
+    pub max_buffered_bytes: u64,
+}
+
+impl Default for DataChannelTransferConfig {
+    fn default() -> Self {
+        Self {
+            chunk_size: 16 * 1024,
+            buffered_amount_low_threshold: 256 * 1024,
+            max_transfer_size: 1024 * 1024 * 1024,
+            max_concurrent_transfers: 16,
+            max_buffered_bytes: 1024 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferDirection {
+    Outgoing,
+    Incoming,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferProgress {
+    pub id: u32,
+    pub direction: TransferDirection,
+    pub bytes: u64,
+    pub total: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceivedTransfer {
+    pub id: u32,
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// Cancels the transfer `DataChannelTransfer::send` is running.
+#[derive(Clone, Default)]
+pub struct TransferCanceller(Arc<AtomicBool>);
+
+impl TransferCanceller {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+type OnProgressCallback = Box<dyn FnMut(TransferProgress) + Send>;
+
+struct Incoming {
+    name: String,
+    checksum: u32,
+    chunk_size: usize,
+    total: u64,
+    chunks: usize,
+    // Chunks received in order, grown as they arrive rather than allocated
+    // up front for the advertised length.
+    data: Vec<u8>,
+    next_index: usize,
+    // Chunks received ahead of `next_index`.
+    pending: BTreeMap<usize, Vec<u8>>,
+    bytes: u64,
+}
+
+/// CHUNK frames that arrived before their transfer's START.
+#[derive(Default)]
+struct Early {
+    frames: Vec<Vec<u8>>,
+    bytes: u64,
+}
+
+/// Sends and receives chunked transfers over a data channel, owning its
+/// message stream.
+pub struct DataChannelTransfer {
+    channel: DataChannel,
+    messages: DataChannelMessages,
+    config: DataChannelTransferConfig,
+    next_id: u32,
+    incoming: HashMap<u32, Incoming>,
+    early: HashMap<u32, Early>,
+    // Cancelled or failed transfers, oldest first.
+    ended: VecDeque<u32>,
+    // Bytes buffered across `incoming` and `early`.
+    buffered: u64,
+    canceller: TransferCanceller,
+    on_progress: Option<OnProgressCallback>,
+}
+
+impl DataChannelTransfer {
+    /// Fails if the channel's messages were already taken.
+    pub fn new(mut channel: DataChannel, config: DataChannelTransferConfig) -> Result<Self> {
+        if config.chunk_size == 0 {
+            return Err(WebRTCError::DataChannelError(
+                "chunk size must be positive".into(),
+            ));
+        }
+        let messages = channel.messages()?;
+        channel.set_buffered_amount_low_threshold(config.buffered_amount_low_threshold);
+        Ok(Self {
+            channel,
+            messages,
+            config,
+            next_id: 1,
+            incoming: HashMap::new(),
+            early: HashMap::new(),
+            ended: VecDeque::new(),
+            buffered: 0,
+            canceller: TransferCanceller::default(),
+            on_progress: None,
+        })
+    }
+
+    pub fn channel(&mut self) -> &mut DataChannel {
+        &mut self.channel
+    }
+
+    /// Called as chunks are sent and received.
+    pub fn on_progress(&mut self, callback: impl FnMut(TransferProgress) + Send + 'static) {
+        self.on_progress = Some(Box::new(callback));
+    }
+
+    pub fn canceller(&self) -> TransferCanceller {
+        self.canceller.clone()
+    }
+
+    /// Send `data` as one transfer, returning its id once every chunk has
+    /// been queued on the channel.
+    pub async fn send(&mut self, name: &str, data: &[u8]) -> Result<u32> {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.canceller.0.store(false, Ordering::SeqCst);
+
+        let chunk_size = self.config.chunk_size;
+        let mut start = Vec::with_capacity(START_HEADER_LEN + name.len());
+        start.push(START);
+        start.extend_from_slice(&id.to_be_bytes());
+        start.extend_from_slice(&(data.len() as u64).to_be_bytes());
+        start.extend_from_slice(&chunk_size.to_be_bytes());
+        start.extend_from_slice(&crc32(data).to_be_bytes());
+        start.extend_from_slice(name.as_bytes());
+        self.channel.send(&start, true).await?;
+
+        let mut bytes = 0;
+        for (index, chunk) in data.chunks(chunk_size as usize).enumerate() {
+            if self.canceller.0.swap(false, Ordering::SeqCst) {
+                let mut cancel = vec![CANCEL];
+                cancel.extend_from_slice(&id.to_be_bytes());
+                self.channel.send(&cancel, true).await?;
+                return Err(WebRTCError::DataChannelError(format!(
+                    "transfer {} cancelled",
+                    id
+                )));
+            }
+
+            let mut frame = Vec::with_capacity(CHUNK_HEADER_LEN + chunk.len());
+            frame.push(CHUNK);
+            frame.extend_from_slice(&id.to_be_bytes());
+            frame.extend_from_slice(&(index as u32).to_be_bytes());
+            frame.extend_from_slice(chunk);
+            self.channel.send(&frame, true).await?;
+
+            bytes += chunk.len() as u64;
+            self.progress(TransferProgress {
+                id,
+                direction: TransferDirection::Outgoing,
+                bytes,
+                total: data.len() as u64,
+            });
+        }
+        Ok(id)
+    }
+
+    /// The next completed transfer, or None once the channel is closed.
+    ///
+    /// Malformed frames, failed integrity checks and transfers the peer
+    /// cancelled are returned as errors, later transfers are still received.
+    pub async fn recv(&mut self) -> Result<Option<ReceivedTransfer>> {
+        while let Some(message) = self.messages.recv().await {
+            if !message.binary {
+                return Err(WebRTCError::DataChannelError(
+                    "unexpected text message".into(),
+                ));
+            }
+            if let Some(transfer) = self.handle_frame(&message.data)? {
+                return Ok(Some(transfer));
+            }
+        }
+        Ok(None)
+    }
+
+    fn handle_frame(&mut self, frame: &[u8]) -> Result<Option<ReceivedTransfer>> {
+        let (kind, id) = match frame {
+            [kind, id @ ..] if id.len() >= 4 => (*kind, read_u32(id)),
+            _ => return Err(malformed("frame too short")),
+        };
+        match kind {
+            START => {
+                if frame.len() < START_HEADER_LEN {
+                    return Err(malformed("START too short"));
+                }
+                let total = u64::from_be_bytes(frame[5..13].try_into().unwrap());
+                let chunk_size = read_u32(&frame[13..]) as usize;
+                let checksum = read_u32(&frame[17..]);
+                let name = String::from_utf8(frame[START_HEADER_LEN..].to_vec())
+                    .map_err(|_| malformed("name isn't UTF-8"))?;
+                let max_size = self
+                    .config
+                    .max_transfer_size
+                    .min(self.config.max_buffered_bytes);
+                if total > max_size {
+                    return Err(WebRTCError::DataChannelError(format!(
+                        "transfer {} of {} bytes exceeds the maximum {}",
+                        id, total, max_size
+                    )));
+                }
+                if chunk_size == 0 {
+                    return Err(malformed("zero chunk size"));
+                }
+                if self.ended.contains(&id) {
+                    // Cancelled or failed before its START arrived.
+                    return Ok(None);
+                }
+                if self.incoming.contains_key(&id) {
+                    return Err(WebRTCError::DataChannelError(format!(
+                        "transfer {} already in progress",
+                        id
+                    )));
+                }
+
+                let chunks = ((total + chunk_size as u64 - 1) / chunk_size as u64) as usize;
+                let incoming = Incoming {
+                    name,
+                    checksum,
+                    chunk_size,
+                    total,
+                    chunks,
+                    data: vec![],
+                    next_index: 0,
+                    pending: BTreeMap::new(),
+                    bytes: 0,
+                };
+                let early = self.remove_early(id).unwrap_or_default();
+                if chunks == 0 {
+                    return Self::complete(id, incoming).map(Some);
+                }
+                if self.incoming.len() + self.early.len() >= self.config.max_concurrent_transfers {
+                    return Err(WebRTCError::DataChannelError(format!(
+                        "transfer {} exceeds {} concurrent transfers",
+                        id, self.config.max_concurrent_transfers
+                    )));
+                }
+                self.incoming.insert(id, incoming);
+
+                let mut received = None;
+                for frame in early.frames {
+                    if let Some(transfer) = self.handle_frame(&frame)? {
+                        received = Some(transfer);
+                    }
+                }
+                Ok(received)
+            }
+            CHUNK => {
+                if frame.len() < CHUNK_HEADER_LEN {
+                    return Err(malformed("CHUNK too short"));
+                }
+                if !self.incoming.contains_key(&id) {
+                    return self.buffer_early(id, frame);
+                }
+                let index = read_u32(&frame[5..]) as usize;
+                let chunk = &frame[CHUNK_HEADER_LEN..];
+                let incoming = self.incoming.get_mut(&id).unwrap();
+
+                let offset = (index as u64).saturating_mul(incoming.chunk_size as u64);
+                let expected_len =
+                    (incoming.chunk_size as u64).min(incoming.total.saturating_sub(offset));
+                if index >= incoming.chunks
+                    || index < incoming.next_index
+                    || incoming.pending.contains_key(&index)
+                    || chunk.len() as u64 != expected_len
+                {
+                    self.end(id);
+                    return Err(WebRTCError::DataChannelError(format!(
+                        "invalid chunk {} of transfer {}",
+                        index, id
+                    )));
+                }
+                if self.buffered + chunk.len() as u64 > self.config.max_buffered_bytes {
+                    self.end(id);
+                    return Err(WebRTCError::DataChannelError(format!(
+                        "transfer {} exceeds {} buffered bytes",
+                        id, self.config.max_buffered_bytes
+                    )));
+                }
+
+                if index == incoming.next_index {
+                    incoming.data.extend_from_slice(chunk);
+                    incoming.next_index += 1;
+                    while let Some(chunk) = incoming.pending.remove(&incoming.next_index) {
+                        incoming.data.extend_from_slice(&chunk);
+                        incoming.next_index += 1;
+                    }
+                } else {
+                    incoming.pending.insert(index, chunk.to_vec());
+                }
+                incoming.bytes += chunk.len() as u64;
+                self.buffered += chunk.len() as u64;
+
+                let progress = TransferProgress {
+                    id,
+                    direction: TransferDirection::Incoming,
+                    bytes: incoming.bytes,
+                    total: incoming.total,
+                };
+                let done = incoming.next_index == incoming.chunks;
+                self.progress(progress);
+                if !done {
+                    return Ok(None);
+                }
+                let incoming = self.remove_incoming(id).unwrap();
+                Self::complete(id, incoming).map(Some)
+            }
+            CANCEL => {
+                if self.ended.contains(&id) {
+                    return Ok(None);
+                }
+                self.end(id);
+                Err(WebRTCError::DataChannelError(format!(
+                    "transfer {} cancelled by peer",
+                    id
+                )))
+            }
+            _ => Err(malformed("unknown frame type")),
+        }
+    }
+
+    /// Hold a CHUNK for a transfer whose START hasn't arrived yet.
+    fn buffer_early(&mut self, id: u32, frame: &[u8]) -> Result<Option<ReceivedTransfer>> {
+        if self.ended.contains(&id) {
+            return Ok(None);
+        }
+        if !self.early.contains_key(&id)
+            && self.incoming.len() + self.early.len() >= self.config.max_concurrent_transfers
+        {
+            self.end(id);
+            return Err(WebRTCError::DataChannelError(format!(
+                "transfer {} exceeds {} concurrent transfers",
+                id, self.config.max_concurrent_transfers
+            )));
+        }
+        let len = (frame.len() - CHUNK_HEADER_LEN) as u64;
+        if self.buffered + len > self.config.max_buffered_bytes {
+            self.end(id);
+            return Err(WebRTCError::DataChannelError(format!(
+                "transfer {} exceeds {} buffered bytes",
+                id, self.config.max_buffered_bytes
+            )));
+        }
+        let early = self.early.entry(id).or_default();
+        early.frames.push(frame.to_vec());
+        early.bytes += len;
+        self.buffered += len;
+        Ok(None)
+    }
+
+    fn remove_incoming(&mut self, id: u32) -> Option<Incoming> {
+        let incoming = self.incoming.remove(&id)?;
+        self.buffered -= incoming.bytes;
+        Some(incoming)
+    }
+
+    fn remove_early(&mut self, id: u32) -> Option<Early> {
+        let early = self.early.remove(&id)?;
+        self.buffered -= early.bytes;
+        Some(early)
+    }
+
+    /// Drop a cancelled or failed transfer, ignoring its frames still in
+    /// flight.
+    fn end(&mut self, id: u32) {
+        self.remove_incoming(id);
+        self.remove_early(id);
+        if self.ended.len() == MAX_ENDED_TRANSFERS {
+            self.ended.pop_front();
+        }
+        self.ended.push_back(id);
+    }
+
+    fn complete(id: u32, incoming: Incoming) -> Result<ReceivedTransfer> {
+        if crc32(&incoming.data) != incoming.checksum {
+            return Err(WebRTCError::DataChannelError(format!(
+                "transfer {} failed its integrity check",
+                id
+            )));
+        }
+        Ok(ReceivedTransfer {
+            id,
+            name: incoming.name,
+            data: incoming.data,
+        })
+    }
+
+    fn progress(&mut self, progress: TransferProgress) {
+        if let Some(callback) = &mut self.on_progress {
+            callback(progress);
+        }
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes(bytes[..4].try_into().unwrap())
+}
+
+fn malformed(reason: &str) -> WebRTCError {
+    WebRTCError::DataChannelError(format!("malformed transfer frame: {}", reason))
+}
+
+/// CRC-32 (IEEE 802.3), as zlib and PNG compute it.
+fn crc32(data: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (i, entry) in table.iter_mut().enumerate() {
+        let mut crc = i as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+        *entry = crc;
+    }
+
+    !data.iter().fold(!0u32, |crc, byte| {
+        table[((crc ^ *byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use parking_lot::Mutex;
+    use tokio::sync::mpsc::channel;
+
+    use super::*;
+    use crate::{
+        data_channel::DataChannelInit,
+        factory::Factory,
+        loopback::{connect_pair, LoopbackReceivers},
+        peer_connection::{
+            PeerConnection, PeerConnectionConfig, PeerConnectionFactory, SDPSemantic,
+        },
+        peer_connection_observer::ObserverSenders,
+    };
+
+    fn start_frame(id: u32, total: u64, chunk_size: u32, checksum: u32) -> Vec<u8> {
+        let mut frame = vec![START];
+        frame.extend_from_slice(&id.to_be_bytes());
+        frame.extend_from_slice(&total.to_be_bytes());
+        frame.extend_from_slice(&chunk_size.to_be_bytes());
+        frame.extend_from_slice(&checksum.to_be_bytes());
+        frame
+    }
+
+    fn chunk_frame(id: u32, index: u32, data: &[u8]) -> Vec<u8> {
+        let mut frame = vec![CHUNK];
+        frame.extend_from_slice(&id.to_be_bytes());
+        frame.extend_from_slice(&index.to_be_bytes());
+        frame.extend_from_slice(data);
+        frame
+    }
+
+    /// A transfer over a channel that is never connected, frames are handed
+    /// to it directly.  The peer connection and factories are returned to
+    /// keep them alive, in drop order.
+    fn unconnected_transfer(
+        config: DataChannelTransferConfig,
+    ) -> (
+        DataChannelTransfer,
+        PeerConnection,
+        PeerConnectionFactory,
+        Factory,
+    ) {
+        let factory = Factory::new();
+        let pc_factory = factory.create_peer_connection_factory().unwrap();
+        let pc = pc_factory
+            .create_peer_connection(
+                PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]),
+                ObserverSenders::default(),
+            )
+            .unwrap();
+        let channel = pc
+            .create_data_channel("transfer".into(), DataChannelInit::default())
+            .unwrap();
+        let transfer = DataChannelTransfer::new(channel, config).unwrap();
+        (transfer, pc, pc_factory, factory)
+    }
+
+    #[test]
+    fn test_incoming_limits() {
+        let (mut transfer, _pc, _pc_factory, _factory) =
+            unconnected_transfer(DataChannelTransferConfig {
+                max_concurrent_transfers: 2,
+                max_buffered_bytes: 8,
+                ..DataChannelTransferConfig::default()
+            });
+
+        // Larger than can ever be buffered.
+        assert!(transfer.handle_frame(&start_frame(1, 9, 4, 0)).is_err());
+
+        // Nothing is allocated for the advertised length up front.
+        transfer.handle_frame(&start_frame(1, 8, 4, 0)).unwrap();
+        assert_eq!(transfer.incoming[&1].data.capacity(), 0);
+
+        // A START reusing an id in progress doesn't replace it.
+        transfer.handle_frame(&chunk_frame(1, 1, b"5678")).unwrap();
+        assert!(transfer.handle_frame(&start_frame(1, 4, 4, 0)).is_err());
+        assert_eq!(transfer.incoming[&1].bytes, 4);
+        assert_eq!(transfer.buffered, 4);
+
+        // Concurrent transfers are limited.
+        transfer.handle_frame(&start_frame(2, 8, 4, 0)).unwrap();
+        assert!(transfer.handle_frame(&start_frame(3, 4, 4, 0)).is_err());
+        assert!(!transfer.incoming.contains_key(&3));
+
+        // As is the total buffered across transfers, the transfer going over
+        // is dropped.
+        transfer.handle_frame(&chunk_frame(2, 0, b"abcd")).unwrap();
+        assert!(transfer.handle_frame(&chunk_frame(2, 1, b"efgh")).is_err());
+        assert!(!transfer.incoming.contains_key(&2));
+        assert_eq!(transfer.buffered, 4);
+
+        // Chunks out of order are reassembled once the gap is filled.
+        let checksum = crc32(b"12345678");
+        transfer.incoming.get_mut(&1).unwrap().checksum = checksum;
+        let received = transfer
+            .handle_frame(&chunk_frame(1, 0, b"1234"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(received.data, b"12345678".to_vec());
+        assert_eq!(transfer.buffered, 0);
+        assert!(transfer.incoming.is_empty());
+    }
+
+    #[test]
+    fn test_frames_before_start() {
+        let (mut transfer, _pc, _pc_factory, _factory) =
+            unconnected_transfer(DataChannelTransferConfig {
+                max_concurrent_transfers: 2,
+                max_buffered_bytes: 8,
+                ..DataChannelTransferConfig::default()
+            });
+
+        // Chunks overtaking their START are held until it arrives.
+        assert!(transfer
+            .handle_frame(&chunk_frame(1, 1, b"5678"))
+            .unwrap()
+            .is_none());
+        assert!(transfer
+            .handle_frame(&chunk_frame(1, 0, b"1234"))
+            .unwrap()
+            .is_none());
+        assert_eq!(transfer.buffered, 8);
+        let received = transfer
+            .handle_frame(&start_frame(1, 8, 4, crc32(b"12345678")))
+            .unwrap()
+            .unwrap();
+        assert_eq!(received.data, b"12345678".to_vec());
+        assert_eq!(transfer.buffered, 0);
+        assert!(transfer.early.is_empty());
+        assert!(transfer.incoming.is_empty());
+
+        // Within the buffered bytes limit.
+        transfer.handle_frame(&chunk_frame(2, 0, b"abcd")).unwrap();
+        transfer.handle_frame(&chunk_frame(3, 0, b"efgh")).unwrap();
+        assert!(transfer.handle_frame(&chunk_frame(3, 1, b"ijkl")).is_err());
+        assert_eq!(transfer.buffered, 4);
+
+        // And the concurrent transfers limit.
+        transfer.handle_frame(&start_frame(4, 8, 4, 0)).unwrap();
+        assert!(transfer.handle_frame(&chunk_frame(5, 0, b"mnop")).is_err());
+        assert_eq!(transfer.early.len(), 1);
+
+        // A CANCEL overtaking its START drops the chunks, the START and any
+        // later chunks are ignored.
+        assert!(transfer.handle_frame(&[CANCEL, 0, 0, 0, 2]).is_err());
+        assert_eq!(transfer.buffered, 0);
+        assert!(transfer.early.is_empty());
+        assert!(transfer
+            .handle_frame(&start_frame(2, 8, 4, 0))
+            .unwrap()
+            .is_none());
+        assert!(transfer
+            .handle_frame(&chunk_frame(2, 1, b"qrst"))
+            .unwrap()
+            .is_none());
+        assert!(!transfer.incoming.contains_key(&2));
+        assert!(transfer.early.is_empty());
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[tokio::test]
+    async fn test_transfer() {
+        let factory1 = Factory::new();
+        let factory2 = Factory::new();
+        let pc_factory1 = factory1.create_peer_connection_factory().unwrap();
+        let pc_factory2 = factory2.create_peer_connection_factory().unwrap();
+
+        let (senders1, rx1) = LoopbackReceivers::create();
+        let pc1 = pc_factory1
+            .create_peer_connection(
+                PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]),
+                senders1,
+            )
+            .unwrap();
+        // Unordered, the default.
+        let dc1 = pc1
+            .create_data_channel("transfer".into(), DataChannelInit::default())
+            .unwrap();
+
+        let (dc_tx, mut dc_rx) = channel(1);
+        let (senders2, rx2) = LoopbackReceivers::create();
+        let pc2 = pc_factory2
+            .create_peer_connection(
+                PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]),
+                ObserverSenders {
+                    data_channel: Some(dc_tx),
+                    ..senders2
+                },
+            )
+            .unwrap();
+        let _pair = connect_pair(&pc1, &pc2, rx1, rx2).await.unwrap();
+
+        let mut sender =
+            DataChannelTransfer::new(dc1, DataChannelTransferConfig::default()).unwrap();
+        sender.channel().await_open().await.unwrap();
+        let mut receiver =
+            DataChannelTransfer::new(dc_rx.recv().await.unwrap(), Default::default()).unwrap();
+        let progress = Arc::new(Mutex::new(vec![]));
+        let received_progress = progress.clone();
+        receiver.on_progress(move |progress| received_progress.lock().push(progress));
+
+        // Larger than the SCTP max message size.
+        let payload: Vec<u8> = (0..3 * 1024 * 1024 + 7).map(|i| i as u8).collect();
+        let id = sender.send("blob", &payload).await.unwrap();
+        let transfer = receiver.recv().await.unwrap().unwrap();
+        assert_eq!(transfer.id, id);
+        assert_eq!(transfer.name, "blob");
+        assert!(transfer.data == payload);
+        let last = *progress.lock().last().unwrap();
+        assert_eq!(last.bytes, payload.len() as u64);
+        assert_eq!(last.direction, TransferDirection::Incoming);
+
+        sender.send("empty", b"").await.unwrap();
+        assert!(receiver.recv().await.unwrap().unwrap().data.is_empty());
+
+        // Cancelled after the first chunk.
+        let canceller = sender.canceller();
+        sender.on_progress(move |_| canceller.cancel());
+        assert!(sender.send("cancelled", &payload).await.is_err());
+        assert!(receiver.recv().await.is_err());
+        assert!(receiver.incoming.is_empty());
+
+        // Corrupted chunks fail the integrity check.
+        let mut start = vec![START];
+        start.extend_from_slice(&9u32.to_be_bytes());
+        start.extend_from_slice(&4u64.to_be_bytes());
+        start.extend_from_slice(&4u32.to_be_bytes());
+        start.extend_from_slice(&crc32(b"data").to_be_bytes());
+        assert!(receiver.handle_frame(&start).unwrap().is_none());
+        let mut chunk = vec![CHUNK];
+        chunk.extend_from_slice(&9u32.to_be_bytes());
+        chunk.extend_from_slice(&0u32.to_be_bytes());
+        chunk.extend_from_slice(b"dada");
+        assert!(receiver.handle_frame(&chunk).is_err());
+        assert!(receiver.handle_frame(&[CHUNK, 0]).is_err());
+
+        drop(sender);
+        assert!(receiver.recv().await.unwrap().is_none());
+    }
+}
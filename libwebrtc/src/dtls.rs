@@ -0,0 +1,233 @@
+//! DTLS over datagrams the caller carries, authenticated by certificate
+//! fingerprints exchanged out of band as in SDP.
+
+use cxx::UniquePtr;
+use libwebrtc_sys::rtc_base::{
+    certificates::ffi::SSLRole,
+    dtls::{
+        ffi::{create_arcas_dtls_session, ArcasDtlsSession},
+        ArcasRustDtlsCallbacks, DtlsCallbacks,
+    },
+};
+use tokio::sync::{
+    mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    watch,
+};
+
+use crate::{
+    certificate::SSLCertificate,
+    error::{Result, WebRTCError},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DtlsRole {
+    Client,
+    Server,
+}
+
+#[derive(Clone)]
+pub struct DtlsConfig {
+    pub certificate: SSLCertificate,
+    pub role: DtlsRole,
+    /// Fingerprint of the peer's certificate in the form
+    /// `SSLCertificate::get_fingerprint` returns, e.g. `sha-256 AB:CD:..`.
+    pub peer_fingerprint: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DtlsState {
+    Connecting,
+    Connected,
+    Closed,
+    /// The handshake or an established session failed with this SSL error.
+    Failed(i32),
+}
+
+pub struct DtlsReceivers {
+    /// DTLS records to deliver to the peer.
+    pub packets: UnboundedReceiver<Vec<u8>>,
+    /// Application data decrypted from the peer.
+    pub data: UnboundedReceiver<Vec<u8>>,
+}
+
+struct Callbacks {
+    packets: UnboundedSender<Vec<u8>>,
+    data: UnboundedSender<Vec<u8>>,
+    state: watch::Sender<DtlsState>,
+}
+
+impl DtlsCallbacks for Callbacks {
+    fn send_packet(&mut self, packet: &[u8]) {
+        let _ = self.packets.send(packet.to_vec());
+    }
+
+    fn on_connected(&mut self) {
+        let _ = self.state.send(DtlsState::Connected);
+    }
+
+    fn on_data(&mut self, data: &[u8]) {
+        let _ = self.data.send(data.to_vec());
+    }
+
+    fn on_closed(&mut self, error: i32) {
+        let state = if error == 0 {
+            DtlsState::Closed
+        } else {
+            DtlsState::Failed(error)
+        };
+        let _ = self.state.send(state);
+    }
+}
+
+/// A DTLS 1.2 session running on its own thread until dropped.  Records
+/// from the peer go into `handle_packet`, records for the peer come out of
+/// `DtlsReceivers::packets`.
+pub struct DtlsSession {
+    inner: UniquePtr<ArcasDtlsSession>,
+    state: watch::Receiver<DtlsState>,
+}
+
+impl DtlsSession {
+    /// Start the handshake, the client sends the first flight right away.
+    pub fn start(config: &DtlsConfig) -> Result<(Self, DtlsReceivers)> {
+        let (algorithm, fingerprint) =
+            config.peer_fingerprint.split_once(' ').ok_or_else(|| {
+                WebRTCError::DtlsError(format!("invalid fingerprint {}", config.peer_fingerprint))
+            })?;
+        let role = match config.role {
+            DtlsRole::Client => SSLRole::SSL_CLIENT,
+            DtlsRole::Server => SSLRole::SSL_SERVER,
+        };
+
+        let (packets_tx, packets) = unbounded_channel();
+        let (data_tx, data) = unbounded_channel();
+        let (state_tx, state) = watch::channel(DtlsState::Connecting);
+        let callbacks = Callbacks {
+            packets: packets_tx,
+            data: data_tx,
+            state: state_tx,
+        };
+        let inner = create_arcas_dtls_session(
+            &config.certificate.inner,
+            role,
+            algorithm.into(),
+            fingerprint.into(),
+            Box::new(ArcasRustDtlsCallbacks::new(Box::new(callbacks))),
+        );
+        if inner.is_null() {
+            return Err(WebRTCError::DtlsError(format!(
+                "failed to start DTLS with fingerprint {}",
+                config.peer_fingerprint
+            )));
+        }
+
+        Ok((Self { inner, state }, DtlsReceivers { packets, data }))
+    }
+
+    /// A record received from the peer.
+    pub fn handle_packet(&self, packet: &[u8]) {
+        self.inner.receive_packet(packet);
+    }
+
+    /// Encrypt and send `data` as one record.
+    pub fn send(&self, data: &[u8]) -> Result<()> {
+        if !self.inner.send(data) {
+            return Err(WebRTCError::DtlsError(format!(
+                "failed to send in state {:?}",
+                self.state()
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn state(&self) -> DtlsState {
+        *self.state.borrow()
+    }
+
+    /// Wait for the handshake to complete.
+    pub async fn wait_connected(&self) -> Result<()> {
+        let mut state = self.state.clone();
+        loop {
+            let current = *state.borrow();
+            match current {
+                DtlsState::Connecting => {}
+                DtlsState::Connected => return Ok(()),
+                DtlsState::Closed | DtlsState::Failed(_) => {
+                    return Err(WebRTCError::DtlsError(format!(
+                        "handshake ended in state {:?}",
+                        current
+                    )))
+                }
+            }
+            state
+                .changed()
+                .await
+                .map_err(|_| WebRTCError::DtlsError("session dropped".into()))?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use super::*;
+    use crate::certificate::{generate_certificate, KeyParams, KeyType};
+
+    async fn forward(mut packets: UnboundedReceiver<Vec<u8>>, to: Arc<DtlsSession>) {
+        while let Some(packet) = packets.recv().await {
+            to.handle_packet(&packet);
+        }
+    }
+
+    async fn certificate() -> SSLCertificate {
+        generate_certificate(KeyParams::new(KeyType::KT_ECDSA), Duration::from_secs(60))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_dtls_session() {
+        let (client_cert, server_cert, other_cert) = (
+            certificate().await,
+            certificate().await,
+            certificate().await,
+        );
+        let config = |certificate: &SSLCertificate, role, peer: &SSLCertificate| DtlsConfig {
+            certificate: certificate.clone(),
+            role,
+            peer_fingerprint: peer.get_fingerprint(),
+        };
+
+        let (server, server_rx) =
+            DtlsSession::start(&config(&server_cert, DtlsRole::Server, &client_cert)).unwrap();
+        let (client, mut client_rx) =
+            DtlsSession::start(&config(&client_cert, DtlsRole::Client, &server_cert)).unwrap();
+        let (server, client) = (Arc::new(server), Arc::new(client));
+        assert!(client.send(b"early").is_err());
+        tokio::spawn(forward(server_rx.packets, client.clone()));
+        tokio::spawn(forward(client_rx.packets, server.clone()));
+
+        client.wait_connected().await.unwrap();
+        server.wait_connected().await.unwrap();
+        server.send(b"hello").unwrap();
+        assert_eq!(client_rx.data.recv().await.unwrap(), b"hello".to_vec());
+
+        // A server expecting a different certificate rejects the client.
+        let (server, server_rx) =
+            DtlsSession::start(&config(&server_cert, DtlsRole::Server, &other_cert)).unwrap();
+        let (client, client_rx) =
+            DtlsSession::start(&config(&client_cert, DtlsRole::Client, &server_cert)).unwrap();
+        let (server, client) = (Arc::new(server), Arc::new(client));
+        tokio::spawn(forward(server_rx.packets, client.clone()));
+        tokio::spawn(forward(client_rx.packets, server.clone()));
+        assert!(server.wait_connected().await.is_err());
+
+        assert!(DtlsSession::start(&DtlsConfig {
+            certificate: client_cert,
+            role: DtlsRole::Client,
+            peer_fingerprint: "invalid".into(),
+        })
+        .is_err());
+    }
+}
@@ -1,40 +1,91 @@
-use std::{thread::sleep, time::Duration};
+use crossbeam_channel::{unbounded, Receiver, Sender};
 
-use crossbeam_channel::{unbounded, Receiver};
-
-use crate::{error::Result, now, ok_or_return, video_frame::EmptyVideoFrame};
+use crate::{
+    error::{Result, WebRTCError},
+    media_clock::{MediaClock, VIDEO_CLOCK_RATE_HZ},
+    ok_or_return,
+    video_frame::EmptyVideoFrame,
+};
 
 // Helper utility for generating empty frame data for the passthrough/reactive
 // encoders.  This is useful as it tricks libwebrtc into thinking there is real
 // image data coming through the pipeline.
 pub struct EmptyFrameProducer {
     pub fps: u32,
+    clock: MediaClock,
+    cancel_rx: Option<Receiver<()>>,
+    cancel_tx: Sender<()>,
 }
 
 impl EmptyFrameProducer {
     pub fn new(fps: u32) -> Result<Self> {
-        Ok(Self { fps })
+        Self::with_clock(fps, MediaClock::new()?)
+    }
+
+    /// Produce frames paced and timestamped by a shared clock.
+    pub fn with_clock(fps: u32, clock: MediaClock) -> Result<Self> {
+        let (cancel_tx, cancel_rx) = crossbeam_channel::bounded::<()>(1);
+        Ok(Self {
+            fps,
+            clock,
+            cancel_rx: Some(cancel_rx),
+            cancel_tx,
+        })
     }
 
     pub fn start(&mut self) -> Result<Receiver<EmptyVideoFrame>> {
         let (result_tx, result_rx) = unbounded::<EmptyVideoFrame>();
-        let fps = self.fps;
-
-        std::thread::spawn(move || loop {
-            let sleep_time = 1_000 / fps as u64;
-            let now_ms = ok_or_return!(now::now());
+        let cancel_rx = self
+            .cancel_rx
+            .take()
+            .ok_or_else(|| WebRTCError::UnexpectedError("cannot call start twice".into()))?;
+        let mut pacer = self.clock.pacer(self.fps, cancel_rx);
 
-            // XXX: Creating a raw frame should never fail...
-            let raw_frame = ok_or_return!(EmptyVideoFrame::create(now_ms));
-            ok_or_return!(result_tx.send(raw_frame));
-
-            sleep(Duration::from_millis(sleep_time));
+        std::thread::spawn(move || {
+            while let Some(timestamp) = pacer.wait(VIDEO_CLOCK_RATE_HZ) {
+                // XXX: Creating a raw frame should never fail...
+                let raw_frame = ok_or_return!(EmptyVideoFrame::create_with_timestamp(timestamp));
+                ok_or_return!(result_tx.send(raw_frame));
+            }
         });
 
         Ok(result_rx)
     }
 
     pub fn cancel(&self) {
-        // todo: kill the thread...
+        let _ = self.cancel_tx.try_send(());
+    }
+}
+
+impl Drop for EmptyFrameProducer {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    #[test]
+    fn test_cancel_stops_producing() {
+        let mut producer = EmptyFrameProducer::new(100).unwrap();
+        let rx = producer.start().unwrap();
+        let _frame = rx.recv().unwrap();
+
+        producer.cancel();
+        // The producer thread exits and drops its sender.
+        let deadline = Instant::now() + Duration::from_secs(1);
+        loop {
+            match rx.recv_deadline(deadline) {
+                Ok(_) => continue,
+                Err(err) => {
+                    assert!(err.is_disconnected());
+                    break;
+                }
+            }
+        }
     }
 }
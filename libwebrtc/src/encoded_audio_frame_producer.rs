@@ -1,50 +1,89 @@
-use std::thread;
+use std::{thread, time::Duration};
 
-use crate::error::Result;
+use crate::{
+    error::Result,
+    media_clock::{CaptureTimestamp, MediaClock, AUDIO_CLOCK_RATE_HZ},
+};
 use bytes::Bytes;
 use crossbeam_channel::{select, Receiver, Sender};
 
+// Duration of each opus packet, opusenc's frame-size.
+const OPUS_FRAME_SIZE_MS: u32 = 10;
+
+/// An encoded audio packet and the time it was captured.
+#[derive(Debug, Clone)]
+pub struct EncodedAudioFrame {
+    pub data: Bytes,
+    pub timestamp: CaptureTimestamp,
+}
+
 pub struct GStreamerOpusAudioFrameProducer {
     channels: usize,
     sample_rate_hz: i32,
     gstreamer_waveform: usize,
+    clock: MediaClock,
     cancel_tx: Option<Sender<()>>,
 }
 
 pub trait EncodedAudioFrameProducer {
-    fn start(&mut self) -> Result<Receiver<Bytes>>;
+    fn start(&mut self) -> Result<Receiver<EncodedAudioFrame>>;
     fn cancel(&mut self);
 }
 
 impl GStreamerOpusAudioFrameProducer {
-    pub fn new(channels: usize, sample_rate_hz: i32, gstreamer_waveform: usize) -> Self {
-        Self {
+    pub fn new(channels: usize, sample_rate_hz: i32, gstreamer_waveform: usize) -> Result<Self> {
+        Ok(Self {
             channels,
             sample_rate_hz,
             gstreamer_waveform,
+            clock: MediaClock::new()?,
             cancel_tx: None,
-        }
+        })
+    }
+
+    /// Timestamp packets with a shared clock so they line up with other
+    /// producers.  GStreamer still paces the packets.
+    ///
+    /// The first packet is stamped when it arrives and every later one by
+    /// the samples before it, so jitter in delivery doesn't reach the RTP
+    /// timestamps.
+    pub fn with_clock(mut self, clock: MediaClock) -> Self {
+        self.clock = clock;
+        self
     }
 }
 
 impl EncodedAudioFrameProducer for GStreamerOpusAudioFrameProducer {
-    fn start(&mut self) -> Result<Receiver<Bytes>> {
-        let (tx, rx) = crossbeam_channel::unbounded::<Bytes>();
+    fn start(&mut self) -> Result<Receiver<EncodedAudioFrame>> {
+        let (tx, rx) = crossbeam_channel::unbounded::<EncodedAudioFrame>();
         let encoded_rx = media_pipeline::create_and_start_appsink_pipeline(
             & format!(
-                "audiotestsrc wave={} ! audio/x-raw,format=S16LE,channels={},rate={},is-live=true ! opusenc frame-size=10",
+                "audiotestsrc wave={} ! audio/x-raw,format=S16LE,channels={},rate={},is-live=true ! opusenc frame-size={}",
                 self.gstreamer_waveform,
                 self.channels,
                 self.sample_rate_hz,
+                OPUS_FRAME_SIZE_MS,
             ),
         )?;
         let (cancel_tx, cancel_rx) = crossbeam_channel::bounded::<()>(1);
         self.cancel_tx = Some(cancel_tx);
+        let clock = self.clock.clone();
+        // Opus RTP timestamps count 48kHz samples whatever the input rate.
+        let frame_samples = (AUDIO_CLOCK_RATE_HZ * OPUS_FRAME_SIZE_MS / 1000) as u64;
+        let mut first_packet_at = None;
+        let mut samples = 0u64;
         thread::spawn(move || loop {
             select! {
                 recv(encoded_rx) -> result => {
                     if let Ok(res) = result {
-                        let _ = tx.try_send(res.freeze());
+                        let first_packet = *first_packet_at.get_or_insert_with(|| clock.elapsed());
+                        let elapsed = first_packet
+                            + Duration::from_micros(samples * 1_000_000 / AUDIO_CLOCK_RATE_HZ as u64);
+                        samples += frame_samples;
+                        let _ = tx.try_send(EncodedAudioFrame {
+                            data: res.freeze(),
+                            timestamp: clock.capture_timestamp_at(elapsed, AUDIO_CLOCK_RATE_HZ),
+                        });
                     }
                 }
 
@@ -78,7 +117,7 @@ mod tests {
     use super::GStreamerOpusAudioFrameProducer;
     /* #[test] */
     fn test_create_encoded_audio_frame_producer() {
-        let mut producer = GStreamerOpusAudioFrameProducer::new(2, 8000, 0);
+        let mut producer = GStreamerOpusAudioFrameProducer::new(2, 8000, 0).unwrap();
         let rx = producer.start().unwrap();
         // few of the initial samples seem to be arriving early,
         // but subsequent samples arrive in 10ms intervals
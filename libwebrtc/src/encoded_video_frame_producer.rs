@@ -1,9 +1,12 @@
+use std::{collections::VecDeque, sync::Arc};
+
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use log::{debug, warn};
+use parking_lot::Mutex;
 
 use crate::{
     error::{Result, WebRTCError},
-    now::now,
+    media_clock::{CaptureTimestamp, MediaClock, VIDEO_CLOCK_RATE_HZ},
     ok_or_return,
     video_codec::VideoCodec,
     video_encoder::{
@@ -17,6 +20,10 @@ pub const DEFAULT_WIDTH: i32 = 720;
 pub const DEFAULT_HEIGHT: i32 = 480;
 pub const DEFAULT_FPS: u32 = 30;
 
+// Capture timestamps of frames queued for encoding but not yet output, kept
+// bounded in case the encoder drops frames.
+const MAX_PENDING_TIMESTAMPS: usize = 64;
+
 fn create_video_encoder(codec: VideoCodec, settings: VideoEncoderSettings) -> Result<VideoEncoder> {
     let factory = VideoEncoderFactory::new();
     let codec_name = codec.codec_name();
@@ -53,6 +60,19 @@ impl EncodedFrameProducerProducer {
     pub fn new(
         codec: VideoCodec,
         settings: VideoEncoderSettings,
+    ) -> Result<EncodedFrameProducerProducer> {
+        Self::new_with_clock(codec, settings, MediaClock::new()?)
+    }
+
+    /// Like `new`, but encoded frames the encoder can't be matched to a raw
+    /// frame are stamped with `clock` rather than a clock of their own.
+    ///
+    /// Encoded frames otherwise keep the capture timestamp of their raw
+    /// frame, matched by RTP timestamp.
+    pub fn new_with_clock(
+        codec: VideoCodec,
+        settings: VideoEncoderSettings,
+        clock: MediaClock,
     ) -> Result<EncodedFrameProducerProducer> {
         let (encoded_tx, encoded_rx) = unbounded::<EncodedVideoFrame>();
         let (encoder_err_tx, encoder_err_rx) = unbounded::<WebRTCError>();
@@ -62,8 +82,10 @@ impl EncodedFrameProducerProducer {
         let (init_tx, init_rx) = unbounded::<Result<()>>();
         let width = codec.primary.width;
         let height = codec.primary.height;
+        let pending = Arc::new(Mutex::new(VecDeque::<CaptureTimestamp>::new()));
 
         let encoder_thread_err_tx = encoder_err_tx.clone();
+        let queued = pending.clone();
         std::thread::spawn(move || {
             let init_result = create_video_encoder(codec, settings);
             let mut encoder = match init_result {
@@ -90,6 +112,13 @@ impl EncodedFrameProducerProducer {
             };
 
             while let Ok(frame) = raw_frame_rx.recv() {
+                {
+                    let mut queued = queued.lock();
+                    if queued.len() == MAX_PENDING_TIMESTAMPS {
+                        queued.pop_front();
+                    }
+                    queued.push_back(frame.timestamp());
+                }
                 let encode_info =
                     encoder.encode(frame, vec![FrameTypes::KeyFrame, FrameTypes::DeltaFrame]);
 
@@ -121,8 +150,15 @@ impl EncodedFrameProducerProducer {
         std::thread::spawn(move || {
             while let Ok(encoder_result) = encoder_output_rx.recv() {
                 let (encoded_image, codec_specific_info) = encoder_result;
-                let now_ms = ok_or_return!(now());
-                match EncodedVideoFrame::create(encoded_image, codec_specific_info, now_ms) {
+                let timestamp = encoded_image
+                    .as_ref()
+                    .and_then(|image| take_pending(&mut pending.lock(), image.timestamp()))
+                    .unwrap_or_else(|| clock.capture_timestamp(VIDEO_CLOCK_RATE_HZ));
+                match EncodedVideoFrame::create_with_timestamp(
+                    encoded_image,
+                    codec_specific_info,
+                    timestamp,
+                ) {
                     Ok(encoded_frame) => {
                         ok_or_return!(encoded_tx.send(encoded_frame));
                     }
@@ -154,12 +190,40 @@ impl EncodedFrameProducerProducer {
     }
 }
 
+/// The capture timestamp of the raw frame with `rtp_timestamp`.  Frames
+/// queued before it were dropped by the encoder and are forgotten, it stays
+/// queued for the other spatial layers of the same frame.
+fn take_pending(
+    pending: &mut VecDeque<CaptureTimestamp>,
+    rtp_timestamp: u32,
+) -> Option<CaptureTimestamp> {
+    let index = pending
+        .iter()
+        .position(|timestamp| timestamp.rtp_timestamp == rtp_timestamp)?;
+    pending.drain(..index);
+    pending.front().copied()
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::raw_video_frame_producer::{GStreamerRawFrameProducer, RawFrameProducer};
+    use std::time::Duration;
+
+    use crate::{
+        encoded_audio_frame_producer::{
+            EncodedAudioFrameProducer, GStreamerOpusAudioFrameProducer,
+        },
+        media_clock::AUDIO_CLOCK_RATE_HZ,
+        raw_video_frame_producer::{GStreamerRawFrameProducer, RawFrameProducer},
+    };
 
     use super::*;
 
+    // Unix time of RTP timestamp zero, the same for every stream of a clock.
+    fn rtp_origin_ms(timestamp: CaptureTimestamp, clock_rate_hz: u32) -> i64 {
+        timestamp.capture_time_ms as i64
+            - (timestamp.rtp_timestamp as u64 * 1000 / clock_rate_hz as u64) as i64
+    }
+
     #[test]
     fn test_gstreamer_frame_producer_init() {
         let codec = VideoCodec::vp9(DEFAULT_WIDTH, DEFAULT_HEIGHT, DEFAULT_FPS);
@@ -174,4 +238,58 @@ mod tests {
             let _ = encoder.encoded_rx.recv().unwrap();
         }
     }
+
+    #[test]
+    fn test_audio_video_timestamps_aligned() {
+        let clock = MediaClock::new().unwrap();
+        let codec = VideoCodec::vp9(DEFAULT_WIDTH, DEFAULT_HEIGHT, DEFAULT_FPS);
+        let mut raw_frames = GStreamerRawFrameProducer::default_pipeline(&codec)
+            .unwrap()
+            .with_clock(clock.clone());
+        let encoder = EncodedFrameProducerProducer::new_with_clock(
+            codec,
+            VideoEncoderSettings::default(),
+            clock.clone(),
+        )
+        .unwrap();
+        let mut audio = GStreamerOpusAudioFrameProducer::new(2, 48000, 0)
+            .unwrap()
+            .with_clock(clock.clone());
+        let raw_frames_rx = raw_frames.start().unwrap();
+        let audio_rx = audio.start().unwrap();
+
+        let mut captured = vec![];
+        let mut last_audio: Option<CaptureTimestamp> = None;
+        for _ in 0..10 {
+            let frame = raw_frames_rx.recv().unwrap();
+            captured.push(frame.timestamp());
+            encoder.queue(frame).unwrap();
+            // Encoded frames keep the capture time of their raw frame.
+            let video = encoder.encoded_rx.recv().unwrap().timestamp();
+            assert!(captured.contains(&video));
+
+            // Audio packets arrive faster than video frames, use the latest.
+            // Each is 10ms of samples after the one before.
+            let first_audio = audio_rx.recv_timeout(Duration::from_secs(1)).unwrap();
+            let mut audio = first_audio.timestamp;
+            for packet in std::iter::once(first_audio).chain(audio_rx.try_iter()) {
+                if let Some(last_audio) = last_audio {
+                    assert_eq!(
+                        packet.timestamp.rtp_timestamp,
+                        last_audio.rtp_timestamp + AUDIO_CLOCK_RATE_HZ / 100
+                    );
+                }
+                last_audio = Some(packet.timestamp);
+                audio = packet.timestamp;
+            }
+
+            // Both streams count RTP time from the same instant...
+            let video_origin = rtp_origin_ms(video, VIDEO_CLOCK_RATE_HZ);
+            let audio_origin = rtp_origin_ms(audio, AUDIO_CLOCK_RATE_HZ);
+            assert!((video_origin - audio_origin).abs() <= 2);
+            // ...and packets produced together carry close capture times.
+            assert!((video.capture_time_ms as i64 - audio.capture_time_ms as i64).abs() < 500);
+            assert!(video.capture_time_ms <= clock.unix_time_ms());
+        }
+    }
 }
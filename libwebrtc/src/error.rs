@@ -47,6 +47,9 @@ pub enum WebRTCError {
     #[error("Unsupported video codec: {0}")]
     VideoCodecUnsupportedType(String),
 
+    #[error("Invalid scalable video coding configuration: {0}")]
+    InvalidSvcConfig(String),
+
     #[error("Video encoder failed to initialize")]
     VideoEncoderFailedInit,
 
@@ -85,6 +88,33 @@ pub enum WebRTCError {
 
     #[error("Failed to set transceiver direction")]
     FailedToSetDirection,
+
+    #[error("HTTP signaling failed: {0}")]
+    HttpSignalingError(String),
+
+    #[error("Certificate error: {0}")]
+    CertificateError(String),
+
+    #[error("STUN error: {0}")]
+    StunError(String),
+
+    #[error("SCTP error: {0}")]
+    SctpError(String),
+
+    #[error("DTLS error: {0}")]
+    DtlsError(String),
+
+    #[error("Data channel error: {0}")]
+    DataChannelError(String),
+
+    #[error("Transport error: {0}")]
+    TransportError(String),
+
+    #[error("RTP error: {0}")]
+    RtpError(String),
+
+    #[error("RTP sink conflict: {0}")]
+    RtpSinkConflict(String),
 }
 
 impl<T> From<SendError<T>> for WebRTCError {
@@ -99,6 +129,12 @@ impl<T> From<tokio::sync::mpsc::error::SendError<T>> for WebRTCError {
     }
 }
 
+impl From<reqwest::Error> for WebRTCError {
+    fn from(value: reqwest::Error) -> Self {
+        WebRTCError::HttpSignalingError(value.to_string())
+    }
+}
+
 impl From<RecvError> for WebRTCError {
     fn from(value: RecvError) -> Self {
         WebRTCError::ReceiveError(value.to_string())
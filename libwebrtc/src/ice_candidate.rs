@@ -1,9 +1,11 @@
 use cxx::UniquePtr;
-use libwebrtc_sys::ffi::ArcasICECandidate;
+use libwebrtc_sys::ffi::{create_arcas_ice_candidate, ArcasICECandidate};
 use libwebrtc_sys::p2p::ice_transport_internal::ffi::{
     create_arcas_p2p_ice_config, ArcasP2PIceConfig,
 };
 
+use crate::error::{Result, WebRTCError};
+
 pub struct ICECandidate {
     cxx_ptr: UniquePtr<ArcasICECandidate>,
 }
@@ -16,6 +18,19 @@ impl ICECandidate {
         Self { cxx_ptr }
     }
 
+    /// Parse a candidate received from the remote peer, `sdp` is the
+    /// `candidate:` attribute without the leading `a=`.
+    pub fn from_sdp(sdp_mid: String, sdp_mline_index: u32, sdp: String) -> Result<Self> {
+        let result = create_arcas_ice_candidate(sdp_mid, sdp_mline_index, sdp);
+        if !result.ok {
+            return Err(WebRTCError::SdpParseError(
+                result.error.description,
+                result.error.line,
+            ));
+        }
+        Ok(Self::new(result.candidate))
+    }
+
     pub fn take_cxx(self) -> UniquePtr<ArcasICECandidate> {
         self.cxx_ptr
     }
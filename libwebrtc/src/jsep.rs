@@ -1,10 +1,12 @@
 use crate::certificate::SSLCertificate;
-use crate::error::Result;
+use crate::dtls::DtlsRole;
+use crate::error::{Result, WebRTCError};
 use crate::ice_candidate::P2pIceConfig;
 use crate::rtc_base::{RTCNetworkManager, RTCThread};
 use crate::sdp::{SDPType, SessionDescription};
 use crate::transport::IceTransportFactory;
 use cxx::UniquePtr;
+use futures_core::Stream;
 use libwebrtc_sys::async_dns_resolver_factory::ffi::create_arcas_cxx_async_dns_resolver_factory;
 use libwebrtc_sys::candidate::ffi::CandidateComponent;
 use libwebrtc_sys::error::ffi::ArcasRTCError;
@@ -16,31 +18,212 @@ use libwebrtc_sys::pc::jsep_api::ffi::DataChannelTransportInterface;
 use libwebrtc_sys::pc::jsep_api::ffi::RtpTransportInternal;
 use libwebrtc_sys::pc::jsep_api::ffi::SdpType;
 use libwebrtc_sys::pc::jsep_api::ffi::{
-    create_arcas_cxx_port_allocator, create_arcas_jsep_transport_controller,
+    buffer_data, create_arcas_cxx_port_allocator, create_arcas_jsep_transport_controller,
     create_arcas_jsep_transport_controller_config, create_buffer_with_data, get_transport_name,
-    init_port_alloc, is_writable, send_rtp_packet, set_rtp_params, ArcasCandidateWrapper,
-    ArcasJsepTransportController, ArcasJsepTransportControllerConfig, ArcasSSLCertificate,
-    CopyOnWriteBuffer, PortAllocator,
+    init_port_alloc, is_writable, send_rtp_packet, set_rtp_params,
+    ArcasCandidatePairChangeEventJSEP, ArcasCandidateWrapper, ArcasIceConnectionState,
+    ArcasJsepTransportController, ArcasJsepTransportControllerConfig, ArcasPeerConnectionState,
+    ArcasRtpSinkRegistration, ArcasSSLCertificate, IceConnectionState, IceGatheringState,
+    PortAllocator, SSLRole,
 };
 use libwebrtc_sys::pc::jsep_api::{ArcasRustDTLSHandshakeErrorHandler, OnTransportChanged};
 use libwebrtc_sys::pc::jsep_api::{
     ArcasRustJsepRTCPHandler, ArcasRustJsepTransportControllerObserver,
+    ArcasRustJsepTransportControllerObserverWrapper, ArcasRustRtpPacketSink,
+    JsepTransportControllerObserver,
 };
 use libwebrtc_sys::rtc_base::certificates::ffi::create_arcas_key_params_rsa;
 use libwebrtc_sys::rtc_base::certificates::ffi::create_arcas_rtc_certificate;
 use libwebrtc_sys::rtc_base::certificates::ffi::create_arcas_ssl_identity_with_key_params;
 use libwebrtc_sys::rtc_base::certificates::ffi::create_arcas_ssl_identity_with_key_type;
 use libwebrtc_sys::rtc_base::certificates::ffi::KeyType;
+use parking_lot::Mutex;
 use std::borrow::Borrow;
-use std::ops::Deref;
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::sync::{Arc, Weak};
+use std::task::{Context, Poll};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
 
+/// Events from a `TransportController`, see `TransportController::events`.
+#[derive(Debug)]
+pub enum TransportEvent {
+    /// The transport used for `mid` was created, replaced or removed.
+    TransportChanged {
+        mid: String,
+    },
+    IceCandidatesGathered {
+        mid: String,
+        candidates: Vec<ArcasCandidateWrapper>,
+    },
+    IceCandidatesRemoved(Vec<ArcasCandidateWrapper>),
+    IceCandidateError {
+        address: String,
+        url: String,
+        error_code: i32,
+        error_text: String,
+    },
+    IceCandidatePairChanged(ArcasCandidatePairChangeEventJSEP),
+    IceConnectionState(IceConnectionState),
+    StandardizedIceConnectionState(ArcasIceConnectionState),
+    ConnectionState(ArcasPeerConnectionState),
+    IceGatheringState(IceGatheringState),
+    DtlsHandshakeError(ArcasCxxSSLHandshakeError),
+    /// RTCP from any transport, it isn't demultiplexed by mid as compound
+    /// packets on a bundled transport cover every mid.
+    RtcpReceived {
+        packet: Vec<u8>,
+        packet_time_us: i64,
+    },
+}
+
+/// Stream of `TransportEvent`s, ends when the controller is dropped.
+pub struct TransportEvents {
+    rx: UnboundedReceiver<TransportEvent>,
+}
+
+impl TransportEvents {
+    pub async fn recv(&mut self) -> Option<TransportEvent> {
+        self.rx.recv().await
+    }
+}
+
+impl Stream for TransportEvents {
+    type Item = TransportEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RtpPacket {
+    /// The decrypted packet, header included.
+    pub data: Vec<u8>,
+    pub arrival_time_ms: i64,
+}
+
+/// Stream of RTP packets received for a mid, see
+/// `TransportController::receive_rtp`.  Dropping it unregisters the mid's
+/// sink so the mid can be received again.
+pub struct RtpPackets {
+    mid: String,
+    rx: UnboundedReceiver<RtpPacket>,
+    controller: Weak<Mutex<ControllerPtr>>,
+}
+
+impl RtpPackets {
+    pub async fn recv(&mut self) -> Option<RtpPacket> {
+        self.rx.recv().await
+    }
+}
+
+impl Stream for RtpPackets {
+    type Item = RtpPacket;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+impl Drop for RtpPackets {
+    fn drop(&mut self) {
+        // The controller drops its sinks itself when it goes first.
+        if let Some(controller) = self.controller.upgrade() {
+            controller
+                .lock()
+                .pin_mut()
+                .unregister_rtp_sink(self.mid.clone());
+        }
+    }
+}
+
+struct EventForwarder(UnboundedSender<TransportEvent>);
+
+impl JsepTransportControllerObserver for EventForwarder {
+    fn ice_candidates_gathered(&self, mid: String, candidates: Vec<ArcasCandidateWrapper>) {
+        let _ = self
+            .0
+            .send(TransportEvent::IceCandidatesGathered { mid, candidates });
+    }
+
+    fn ice_connection_state(&self, state: IceConnectionState) {
+        let _ = self.0.send(TransportEvent::IceConnectionState(state));
+    }
+
+    fn connection_state(&self, state: ArcasPeerConnectionState) {
+        let _ = self.0.send(TransportEvent::ConnectionState(state));
+    }
+
+    fn standardized_ice_connection_state(&self, state: ArcasIceConnectionState) {
+        let _ = self
+            .0
+            .send(TransportEvent::StandardizedIceConnectionState(state));
+    }
+
+    fn ice_gathering_state(&self, state: IceGatheringState) {
+        let _ = self.0.send(TransportEvent::IceGatheringState(state));
+    }
+
+    fn ice_candidate_error(
+        &self,
+        address: String,
+        url: String,
+        error_code: i32,
+        error_text: String,
+    ) {
+        let _ = self.0.send(TransportEvent::IceCandidateError {
+            address,
+            url,
+            error_code,
+            error_text,
+        });
+    }
+
+    fn ice_candidates_removed(&self, candidates: Vec<ArcasCandidateWrapper>) {
+        let _ = self
+            .0
+            .send(TransportEvent::IceCandidatesRemoved(candidates));
+    }
+
+    fn ice_candidate_pair_change(&self, event: ArcasCandidatePairChangeEventJSEP) {
+        let _ = self.0.send(TransportEvent::IceCandidatePairChanged(event));
+    }
+}
+
+struct ControllerPtr(UniquePtr<ArcasJsepTransportController>);
+
+// JsepTransportController marshals calls onto the network thread.
+unsafe impl Send for ControllerPtr {}
+
+impl Deref for ControllerPtr {
+    type Target = UniquePtr<ArcasJsepTransportController>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for ControllerPtr {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// ICE, DTLS and SRTP transports negotiated by SDP, without any media
+/// engine on top.
 pub struct TransportController {
-    inner: UniquePtr<ArcasJsepTransportController>,
-    // port_alloc: UniquePtr<PortAllocator>,
+    // Shared with `RtpPackets` so they can unregister their sinks.
+    inner: Arc<Mutex<ControllerPtr>>,
+    events: Option<UnboundedReceiver<TransportEvent>>,
 }
+
 pub struct TransportControllerConfig {
     inner: UniquePtr<ArcasJsepTransportControllerConfig>,
+    events_tx: UnboundedSender<TransportEvent>,
+    events_rx: UnboundedReceiver<TransportEvent>,
 }
+
 fn err_ptr_2_result(err_ptr: UniquePtr<ArcasRTCError>) -> Result<()> {
     if err_ptr.is_null() {
         Ok(())
@@ -64,7 +247,7 @@ impl TransportController {
         init_port_alloc(port_alloc.pin_mut(), network_thread.inner.pin_mut());
         let async_dns_resolver_factory = create_arcas_cxx_async_dns_resolver_factory();
         let net_thr = unsafe { network_thread.inner.pin_mut().get_unchecked_mut() };
-        let inner = unsafe {
+        let mut inner = unsafe {
             create_arcas_jsep_transport_controller(
                 net_thr,
                 port_alloc,
@@ -72,14 +255,34 @@ impl TransportController {
                 config.inner,
             )
         };
-        return Self { inner };
+        inner.pin_mut().subscribe_observer(Box::new(
+            ArcasRustJsepTransportControllerObserverWrapper::new(Box::new(EventForwarder(
+                config.events_tx,
+            ))),
+        ));
+        Self {
+            inner: Arc::new(Mutex::new(ControllerPtr(inner))),
+            events: Some(config.events_rx),
+        }
     }
+
+    /// Stream of events, including those emitted before this was called.
+    /// Can only be taken once.
+    pub fn events(&mut self) -> Result<TransportEvents> {
+        let rx = self
+            .events
+            .take()
+            .ok_or_else(|| WebRTCError::TakeError("transport events".into()))?;
+        Ok(TransportEvents { rx })
+    }
+
     pub fn set_local_description(&mut self, sdp: &SessionDescription) -> Result<()> {
         let typ = sdp.get_type();
         // let copy = create_arcas_session_description(typ.into(), sdp.to_string());
         // if copy.ok {
         let err_ptr: UniquePtr<ArcasRTCError> = self
             .inner
+            .lock()
             .pin_mut()
             // .set_local_description(typ.into(), copy.session);
             .set_local_description(typ.into(), sdp.cxx_sdp.deref());
@@ -94,6 +297,7 @@ impl TransportController {
         // if copy.ok {
         let err_ptr: UniquePtr<ArcasRTCError> = self
             .inner
+            .lock()
             .pin_mut()
             .set_remote_description(typ.into(), sdp.cxx_sdp.deref());
         // .set_remote_description(typ.into(), copy.session);
@@ -102,61 +306,213 @@ impl TransportController {
         //     Err(copy.error.into())
         // }
     }
+    /// Undo descriptions applied since the last stable state.
+    pub fn rollback(&mut self) -> Result<()> {
+        err_ptr_2_result(self.inner.lock().pin_mut().rollback_transports())
+    }
     pub fn set_local_certificate(&mut self, certificate: SSLCertificate) {
         self.inner
+            .lock()
             .pin_mut()
             .set_local_certificate(certificate.inner);
     }
+    /// The certificate the transport for `mid` uses, once it exists.
+    pub fn local_certificate(&self, mid: &str) -> Option<SSLCertificate> {
+        let inner = self.inner.lock().get_local_certificate(mid.into());
+        if inner.is_null() {
+            None
+        } else {
+            Some(SSLCertificate { inner })
+        }
+    }
+    /// Our DTLS role for `mid`, known once the answer is applied.
+    pub fn dtls_role(&self, mid: &str) -> Option<DtlsRole> {
+        self.inner
+            .lock()
+            .get_dtls_role(mid.into())
+            .first()
+            .map(|role| {
+                if *role == SSLRole::SSL_CLIENT {
+                    DtlsRole::Client
+                } else {
+                    DtlsRole::Server
+                }
+            })
+    }
     pub fn set_ice_config(&mut self, ice_config: P2pIceConfig) {
-        self.inner.pin_mut().set_ice_config(ice_config.cxx_ptr);
+        self.inner
+            .lock()
+            .pin_mut()
+            .set_ice_config(ice_config.cxx_ptr);
     }
     pub fn add_remote_candidates(
         &mut self,
         mid: String,
         candidates: Vec<ArcasCandidateWrapper>,
     ) -> Result<()> {
-        let err_ptr = self.inner.pin_mut().add_remote_candidates(mid, candidates);
+        let err_ptr = self
+            .inner
+            .lock()
+            .pin_mut()
+            .add_remote_candidates(mid, candidates);
+        err_ptr_2_result(err_ptr)
+    }
+    pub fn remove_remote_candidates(
+        &mut self,
+        candidates: Vec<ArcasCandidateWrapper>,
+    ) -> Result<()> {
+        let err_ptr = self
+            .inner
+            .lock()
+            .pin_mut()
+            .remove_remote_candidates(candidates);
         err_ptr_2_result(err_ptr)
     }
     pub fn maybe_start_gathering(&mut self) {
-        self.inner.pin_mut().maybe_start_gathering();
+        self.inner.lock().pin_mut().maybe_start_gathering();
+    }
+    /// Restart ICE on every transport, the next local description needs new
+    /// ICE credentials.
+    pub fn restart_ice(&mut self) {
+        self.inner.lock().pin_mut().set_needs_ice_restart_flag();
+    }
+    pub fn needs_ice_restart(&self, mid: &str) -> bool {
+        self.inner.lock().needs_ice_restart(mid.into())
+    }
+    /// True once the RTP transport for `mid` can send.
+    pub fn is_writable(&self, mid: &str) -> bool {
+        self.inner.lock().rtp_transport_writable(mid.into())
+    }
+    /// Protect and send a complete RTP packet on the transport for `mid`.
+    pub fn send_rtp(&mut self, mid: &str, packet: &[u8]) -> Result<()> {
+        if !self.inner.lock().pin_mut().send_rtp(mid.into(), packet) {
+            return Err(WebRTCError::TransportError(format!(
+                "failed to send RTP for mid {}",
+                mid
+            )));
+        }
+        Ok(())
+    }
+    /// Protect and send a compound RTCP packet on the transport for `mid`.
+    pub fn send_rtcp(&mut self, mid: &str, packet: &[u8]) -> Result<()> {
+        if !self.inner.lock().pin_mut().send_rtcp(mid.into(), packet) {
+            return Err(WebRTCError::TransportError(format!(
+                "failed to send RTCP for mid {}",
+                mid
+            )));
+        }
+        Ok(())
+    }
+    /// RTP packets received for `mid`, matched by the MID header extension
+    /// or, without it, by payload type.  The transport for `mid` must exist,
+    /// so call this after applying the descriptions.
+    pub fn receive_rtp(&mut self, mid: &str, payload_types: &[u8]) -> Result<RtpPackets> {
+        let (tx, rx) = unbounded_channel();
+        let sink = ArcasRustRtpPacketSink::new(Box::new(move |packet, arrival_time_ms| {
+            let _ = tx.send(RtpPacket {
+                data: packet.to_vec(),
+                arrival_time_ms,
+            });
+        }));
+        let registration = self.inner.lock().pin_mut().register_rtp_sink(
+            mid.into(),
+            payload_types.to_vec(),
+            Box::new(sink),
+        );
+        if registration == ArcasRtpSinkRegistration::NoTransport {
+            return Err(WebRTCError::TransportError(format!(
+                "no RTP transport for mid {}",
+                mid
+            )));
+        }
+        if registration != ArcasRtpSinkRegistration::Registered {
+            return Err(WebRTCError::RtpSinkConflict(format!(
+                "mid {} or its payload types are already being received",
+                mid
+            )));
+        }
+        Ok(RtpPackets {
+            mid: mid.into(),
+            rx,
+            controller: Arc::downgrade(&self.inner),
+        })
     }
 }
 
 impl TransportControllerConfig {
+    /// A config forwarding transport changes, RTCP and DTLS handshake errors
+    /// to `TransportController::events`.
     pub fn new() -> TransportControllerConfig {
-        TransportControllerConfig {
+        let (events_tx, events_rx) = unbounded_channel();
+        let mut config = TransportControllerConfig {
             inner: create_arcas_jsep_transport_controller_config(),
-        }
+            events_tx,
+            events_rx,
+        };
+
+        let tx = config.events_tx.clone();
+        config.set_transport_observer(Box::new(move |mid, _, _, _| {
+            let _ = tx.send(TransportEvent::TransportChanged { mid });
+            true
+        }));
+        let tx = config.events_tx.clone();
+        config.set_rtcp_handler(Box::new(move |packet, packet_time_us| {
+            let _ = tx.send(TransportEvent::RtcpReceived {
+                packet: packet.to_vec(),
+                packet_time_us,
+            });
+        }));
+        let tx = config.events_tx.clone();
+        config.set_dtls_handshake_error_handler(Box::new(move |error| {
+            let _ = tx.send(TransportEvent::DtlsHandshakeError(error));
+        }));
+        config
     }
-    fn set_transport_observer(&mut self, callback: Box<OnTransportChanged>) {
+
+    /// Replaces forwarding `TransportEvent::TransportChanged`.  Called on the
+    /// network thread.
+    ///
+    /// The pointers are only valid on the network thread during the call.
+    pub fn set_transport_observer(&mut self, callback: Box<OnTransportChanged>) {
         let cb = ArcasRustJsepTransportControllerObserver::new(callback);
         let cb = Box::new(cb);
         let cb = to_cxx(cb);
         self.inner.pin_mut().set_transport_observer(cb);
     }
 
-    fn set_rtcp_handler(
-        &mut self,
-        callback: Box<dyn Fn(&libwebrtc_sys::pc::jsep_api::ffi::CopyOnWriteBuffer, i64)>,
-    ) {
-        let cb = ArcasRustJsepRTCPHandler::new(callback);
+    /// Replaces forwarding `TransportEvent::RtcpReceived`.  Called on the
+    /// network thread with the packet and its arrival time in microseconds.
+    pub fn set_rtcp_handler(&mut self, callback: Box<dyn Fn(&[u8], i64) + Send + Sync>) {
+        let cb = ArcasRustJsepRTCPHandler::new(Box::new(move |packet, packet_time_us| {
+            callback(buffer_data(packet), packet_time_us)
+        }));
         let cb = Box::new(cb);
         self.inner.pin_mut().set_rtcp_handler(cb);
     }
-    fn set_dtls_handshake_error_handler(
+
+    /// Replaces forwarding `TransportEvent::DtlsHandshakeError`.  Called on
+    /// the network thread.
+    pub fn set_dtls_handshake_error_handler(
         &mut self,
-        callback: Box<dyn Fn(ArcasCxxSSLHandshakeError)>,
+        callback: Box<dyn Fn(ArcasCxxSSLHandshakeError) + Send + Sync>,
     ) {
         let cb = ArcasRustDTLSHandshakeErrorHandler::new(callback);
         let cb = Box::new(cb);
         self.inner.pin_mut().set_dtls_handshake_error_handler(cb);
     }
-    fn set_ice_transport_factory(&mut self, factory: IceTransportFactory) {
+
+    pub fn set_ice_transport_factory(&mut self, factory: IceTransportFactory) {
         self.inner
             .pin_mut()
             .set_ice_transport_factory(factory.inner);
     }
+
+    /// Pick the DTLS role again when ICE restarts.
+    pub fn set_redetermine_role_on_ice_restart(&mut self, restart: bool) {
+        self.inner
+            .pin_mut()
+            .set_redetermine_role_on_ice_restart(restart);
+    }
 }
 
 impl Default for TransportControllerConfig {
@@ -192,7 +548,7 @@ pub mod test {
         println!("called back: {}", txt);
         true
     }
-    fn different_callback(_b: &CopyOnWriteBuffer, _i: i64) {}
+    fn different_callback(_b: &[u8], _i: i64) {}
     fn a_third(_e: ArcasCxxSSLHandshakeError) {}
     fn offer_text(fingerprint: String) -> String {
         return format!(
@@ -379,7 +735,7 @@ a=ssrc:2566872679 label:Testlabel
         let err = tc2.add_remote_candidates("0".to_string(), vec![cand1]);
         assert!(err.is_ok());
 
-        let mut transport = tc1.inner.get_srtp_transport("0".into());
+        let mut transport = tc1.inner.lock().get_srtp_transport("0".into());
         let transport_name = unsafe { get_transport_name(&*transport, net_thread.inner.pin_mut()) };
         assert_eq!(transport_name, "0".to_string());
 
@@ -394,7 +750,7 @@ a=ssrc:2566872679 label:Testlabel
             net_thread.inner.pin_mut(),
         );
 
-        let mut transport2 = tc2.inner.get_srtp_transport("0".into());
+        let mut transport2 = tc2.inner.lock().get_srtp_transport("0".into());
         let transport2_name =
             unsafe { get_transport_name(&*transport2, net_thread.inner.pin_mut()) };
         assert_eq!(transport2_name, "0".to_string());
@@ -428,6 +784,218 @@ a=ssrc:2566872679 label:Testlabel
         assert!(sent);
          */
     }
+    #[tokio::test]
+    async fn test_transport_controller_api() {
+        let mut net_man = RTCNetworkManager::default();
+        let mut net_thread = RTCThread::with_socket_server();
+        net_thread.start();
+
+        let mut tc = TransportController::new(
+            forwarding_config(),
+            &mut net_man,
+            &mut net_thread,
+            3342,
+            3342,
+        );
+        let mut events = tc.events().unwrap();
+        assert!(tc.events().is_err());
+        assert!(matches!(
+            tc.receive_rtp("0", &[96]),
+            Err(WebRTCError::TransportError(_))
+        ));
+
+        let cert = SSLCertificate::new(SSLIdentity::new(
+            "common name".to_string(),
+            KeyParams::new(KeyType::KT_RSA),
+        ));
+        let fingerprint = cert.get_fingerprint();
+        tc.set_local_certificate(cert);
+        let offer = SessionDescription::new(SDPType::Offer, offer_text(fingerprint)).unwrap();
+        tc.set_local_description(&offer).unwrap();
+
+        match events.recv().await.unwrap() {
+            TransportEvent::TransportChanged { mid } => assert_eq!(mid, "0"),
+            other => panic!("expected the transport for mid 0, got {:?}", other),
+        }
+        assert!(tc.local_certificate("0").is_some());
+        assert!(tc.local_certificate("1").is_none());
+        // Not negotiated until the answer is applied.
+        assert_eq!(tc.dtls_role("0"), None);
+        assert!(!tc.is_writable("0"));
+        assert!(tc.send_rtp("0", b"\x80\x60\x00\x01").is_err());
+        let packets = tc.receive_rtp("0", &[96]).unwrap();
+        assert!(matches!(
+            tc.receive_rtp("0", &[96]),
+            Err(WebRTCError::RtpSinkConflict(_))
+        ));
+        drop(packets);
+        let _packets = tc.receive_rtp("0", &[96]).unwrap();
+
+        assert!(!tc.needs_ice_restart("0"));
+        tc.restart_ice();
+        assert!(tc.needs_ice_restart("0"));
+
+        tc.maybe_start_gathering();
+        loop {
+            if let TransportEvent::IceGatheringState(state) = events.recv().await.unwrap() {
+                assert_eq!(state, IceGatheringState::kIceGatheringGathering);
+                break;
+            }
+        }
+    }
+
+    fn rtp_packet(sequence_number: u16, ssrc: u32) -> Vec<u8> {
+        let mut packet = vec![0x80, 96];
+        packet.extend_from_slice(&sequence_number.to_be_bytes());
+        packet.extend_from_slice(&90_000u32.to_be_bytes());
+        packet.extend_from_slice(&ssrc.to_be_bytes());
+        packet.extend_from_slice(b"payload");
+        packet
+    }
+
+    // An empty receiver report.
+    fn rtcp_packet(ssrc: u32) -> Vec<u8> {
+        let mut packet = vec![0x80, 201, 0x00, 0x01];
+        packet.extend_from_slice(&ssrc.to_be_bytes());
+        packet
+    }
+
+    async fn next_rtcp(events: &mut TransportEvents) -> Vec<u8> {
+        loop {
+            if let TransportEvent::RtcpReceived { packet, .. } = events.recv().await.unwrap() {
+                return packet;
+            }
+        }
+    }
+
+    async fn within<T>(future: impl std::future::Future<Output = T>) -> T {
+        tokio::time::timeout(time::Duration::from_secs(5), future)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_transport_controller_rtp() {
+        let mut net_man = RTCNetworkManager::default();
+        let mut net_thread = RTCThread::with_socket_server();
+        net_thread.start();
+
+        let mut tc1 = TransportController::new(
+            forwarding_config(),
+            &mut net_man,
+            &mut net_thread,
+            3343,
+            3343,
+        );
+        let mut tc2 = TransportController::new(
+            forwarding_config(),
+            &mut net_man,
+            &mut net_thread,
+            3344,
+            3344,
+        );
+        let mut events1 = tc1.events().unwrap();
+        let mut events2 = tc2.events().unwrap();
+
+        let cert1 = SSLCertificate::new(SSLIdentity::new(
+            "offerer".to_string(),
+            KeyParams::new(KeyType::KT_RSA),
+        ));
+        let offer =
+            SessionDescription::new(SDPType::Offer, offer_text(cert1.get_fingerprint())).unwrap();
+        tc1.set_local_certificate(cert1);
+        let cert2 = SSLCertificate::new(SSLIdentity::new(
+            "answerer".to_string(),
+            KeyParams::new(KeyType::KT_RSA),
+        ));
+        let answer_sdp = answer_text(cert2.get_fingerprint())
+            .replace("a=ice-ufrag:UUEB", "a=ice-ufrag:VVFC")
+            .replace(
+                "a=ice-pwd:oBEHxMxyOKOvLLu13/fDqA68",
+                "a=ice-pwd:pCFIyNyzPLPwMMv24/gErB79",
+            );
+        let answer = SessionDescription::new(SDPType::Answer, answer_sdp).unwrap();
+        tc2.set_local_certificate(cert2);
+
+        tc1.set_local_description(&offer).unwrap();
+        tc2.set_remote_description(&offer.copy_to_remote().unwrap())
+            .unwrap();
+        tc2.set_local_description(&answer).unwrap();
+        tc1.set_remote_description(&answer.copy_to_remote().unwrap())
+            .unwrap();
+        tc1.maybe_start_gathering();
+        tc2.maybe_start_gathering();
+
+        // Trickle candidates across until DTLS completes on both sides.
+        let deadline = time::Instant::now() + time::Duration::from_secs(10);
+        while !(tc1.is_writable("0") && tc2.is_writable("0")) {
+            assert!(
+                time::Instant::now() < deadline,
+                "transports never connected"
+            );
+            let event = tokio::time::timeout(time::Duration::from_millis(100), async {
+                tokio::select! {
+                    event = events1.recv() => (true, event.unwrap()),
+                    event = events2.recv() => (false, event.unwrap()),
+                }
+            })
+            .await;
+            match event {
+                Ok((true, TransportEvent::IceCandidatesGathered { mid, candidates })) => {
+                    tc2.add_remote_candidates(mid, candidates).unwrap()
+                }
+                Ok((false, TransportEvent::IceCandidatesGathered { mid, candidates })) => {
+                    tc1.add_remote_candidates(mid, candidates).unwrap()
+                }
+                _ => {}
+            }
+        }
+
+        // The answer is passive, so the offerer is the DTLS client.
+        assert_eq!(tc1.dtls_role("0"), Some(DtlsRole::Client));
+        assert_eq!(tc2.dtls_role("0"), Some(DtlsRole::Server));
+
+        let mut packets1 = tc1.receive_rtp("0", &[96]).unwrap();
+        let mut packets2 = tc2.receive_rtp("0", &[96]).unwrap();
+
+        tc1.send_rtp("0", &rtp_packet(1, 1111)).unwrap();
+        assert_eq!(
+            within(packets2.recv()).await.unwrap().data,
+            rtp_packet(1, 1111)
+        );
+        tc2.send_rtp("0", &rtp_packet(1, 2222)).unwrap();
+        assert_eq!(
+            within(packets1.recv()).await.unwrap().data,
+            rtp_packet(1, 2222)
+        );
+
+        tc1.send_rtcp("0", &rtcp_packet(1111)).unwrap();
+        assert_eq!(within(next_rtcp(&mut events2)).await, rtcp_packet(1111));
+        tc2.send_rtcp("0", &rtcp_packet(2222)).unwrap();
+        assert_eq!(within(next_rtcp(&mut events1)).await, rtcp_packet(2222));
+
+        // Dropping the stream frees the mid for a new one.
+        assert!(matches!(
+            tc2.receive_rtp("0", &[96]),
+            Err(WebRTCError::RtpSinkConflict(_))
+        ));
+        drop(packets2);
+        let mut packets2 = tc2.receive_rtp("0", &[96]).unwrap();
+        tc1.send_rtp("0", &rtp_packet(2, 1111)).unwrap();
+        assert_eq!(
+            within(packets2.recv()).await.unwrap().data,
+            rtp_packet(2, 1111)
+        );
+    }
+
+    // The default config, which forwards everything to `events`, with an
+    // ICE transport factory.
+    fn forwarding_config() -> TransportControllerConfig {
+        let mut cfg = TransportControllerConfig::default();
+        cfg.set_ice_transport_factory(IceTransportFactory::default());
+        cfg
+    }
+
     fn plain_config() -> TransportControllerConfig {
         let mut cfg = TransportControllerConfig::default();
         cfg.set_transport_observer(Box::new(some_callback));
@@ -0,0 +1,290 @@
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+
+use cxx::UniquePtr;
+use libwebrtc_sys::{
+    ffi::{
+        copy_video_frame_luma_rows, create_arcas_video_sink, get_received_video_frame_info,
+        ArcasCxxVideoFrame, ArcasReceivedVideoFrameInfo, ArcasVideoSink,
+    },
+    ArcasRustVideoSink, VideoSinkImpl,
+};
+use parking_lot::Mutex;
+
+use crate::{
+    error::{Result, WebRTCError},
+    media_clock::{MediaClock, NTP_UNIX_OFFSET_MS},
+    test_pattern_frame_producer::BurnIn,
+    video_track::VideoTrack,
+};
+
+/// Header extension carrying the capture time of a frame, see
+/// `VideoTransceiver::enable_absolute_capture_time`.
+pub const ABSOLUTE_CAPTURE_TIME_URI: &str =
+    "http://www.webrtc.org/experiments/rtp-hdrext/abs-capture-time";
+
+// Latest samples kept per track for the percentiles.
+const LATENCY_WINDOW_SIZE: usize = 10_000;
+
+/// Where the capture time of a received frame came from, in order of
+/// preference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureTimeSource {
+    /// The abs-capture-time header extension.
+    AbsoluteCaptureTime,
+    /// The burn in of a `TestPatternFrameProducer`.
+    BurnIn,
+    /// Estimated by libwebrtc from RTCP sender reports.  Less precise than
+    /// the others as it relies on the RTP timestamps of the sender.
+    SenderReport,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyPercentiles {
+    pub min: Duration,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+/// Glass to glass latency of a single track.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatencyStats {
+    pub track_id: String,
+    /// Source of the most recent measurement.
+    pub source: Option<CaptureTimeSource>,
+    pub frames_measured: u64,
+    /// Frames received without any capture time to measure against.
+    pub frames_without_capture_time: u64,
+    /// Over the latest `LATENCY_WINDOW_SIZE` frames, `None` before the first
+    /// measurement.
+    pub percentiles: Option<LatencyPercentiles>,
+}
+
+/// Rolling window of latency samples.
+#[derive(Debug, Default)]
+pub struct LatencyRecorder {
+    samples: VecDeque<Duration>,
+    source: Option<CaptureTimeSource>,
+    frames_measured: u64,
+    frames_without_capture_time: u64,
+}
+
+impl LatencyRecorder {
+    pub fn record(&mut self, latency: Duration, source: CaptureTimeSource) {
+        if self.samples.len() == LATENCY_WINDOW_SIZE {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(latency);
+        self.source = Some(source);
+        self.frames_measured += 1;
+    }
+
+    pub fn record_missing(&mut self) {
+        self.frames_without_capture_time += 1;
+    }
+
+    pub fn percentiles(&self) -> Option<LatencyPercentiles> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<Duration> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        // Nearest rank.
+        let rank = |percentile: usize| {
+            let index = (sorted.len() * percentile).div_ceil(100);
+            sorted[index.max(1) - 1]
+        };
+        Some(LatencyPercentiles {
+            min: sorted[0],
+            p50: rank(50),
+            p90: rank(90),
+            p99: rank(99),
+            max: sorted[sorted.len() - 1],
+        })
+    }
+
+    pub fn stats(&self, track_id: String) -> LatencyStats {
+        LatencyStats {
+            track_id,
+            source: self.source,
+            frames_measured: self.frames_measured,
+            frames_without_capture_time: self.frames_without_capture_time,
+            percentiles: self.percentiles(),
+        }
+    }
+}
+
+/// Capture time of a received frame in unix milliseconds.  `luma` holds the
+/// top rows of the Y plane and may be empty.
+pub(crate) fn capture_time_ms(
+    info: &ArcasReceivedVideoFrameInfo,
+    luma: &[u8],
+) -> Option<(u64, CaptureTimeSource)> {
+    let from_ntp = |ntp_time_ms: i64| {
+        if ntp_time_ms > NTP_UNIX_OFFSET_MS {
+            Some((ntp_time_ms - NTP_UNIX_OFFSET_MS) as u64)
+        } else {
+            None
+        }
+    };
+
+    if let Some(capture_time_ms) = from_ntp(info.absolute_capture_ntp_time_ms) {
+        return Some((capture_time_ms, CaptureTimeSource::AbsoluteCaptureTime));
+    }
+    if let Some(burn_in) = BurnIn::decode(info.width, info.height, luma) {
+        return Some((burn_in.capture_time_ms, CaptureTimeSource::BurnIn));
+    }
+    from_ntp(info.ntp_time_ms)
+        .map(|capture_time_ms| (capture_time_ms, CaptureTimeSource::SenderReport))
+}
+
+struct LatencySink {
+    clock: MediaClock,
+    recorder: Arc<Mutex<LatencyRecorder>>,
+}
+
+impl VideoSinkImpl for LatencySink {
+    fn on_frame(&self, frame: &ArcasCxxVideoFrame) {
+        let now_ms = self.clock.unix_time_ms();
+        let info = get_received_video_frame_info(frame);
+        let luma = match BurnIn::luma_rows(info.width, info.height) {
+            Some(rows) => copy_video_frame_luma_rows(frame, rows as i32),
+            None => Vec::new(),
+        };
+
+        let mut recorder = self.recorder.lock();
+        match capture_time_ms(&info, &luma) {
+            Some((capture_time_ms, source)) => {
+                // Clocks of different hosts may disagree, never report
+                // negative latency.
+                let latency = Duration::from_millis(now_ms.saturating_sub(capture_time_ms));
+                recorder.record(latency, source);
+            }
+            None => recorder.record_missing(),
+        }
+    }
+}
+
+/// Measures glass to glass latency of a received video track.
+///
+/// Capture times are taken from the abs-capture-time header extension when
+/// the sender enabled it, otherwise from the burn in of a
+/// `TestPatternFrameProducer`, otherwise from RTCP sender reports.  Latency is
+/// measured against `clock`, which should be the sender's clock when both ends
+/// run in the same process, or a clock on a host synchronized with the sender.
+///
+/// The monitor stops receiving frames once dropped.
+pub struct LatencyMonitor {
+    track_id: String,
+    recorder: Arc<Mutex<LatencyRecorder>>,
+    _cxx_sink: UniquePtr<ArcasVideoSink>,
+}
+
+impl LatencyMonitor {
+    pub fn new(track: &VideoTrack, clock: MediaClock) -> Result<Self> {
+        let cxx_track = track.cxx_track.as_ref().ok_or_else(|| {
+            WebRTCError::UnexpectedError("cannot monitor a video track after it was added".into())
+        })?;
+        let recorder = Arc::new(Mutex::new(LatencyRecorder::default()));
+        let sink = LatencySink {
+            clock,
+            recorder: recorder.clone(),
+        };
+        let cxx_sink =
+            create_arcas_video_sink(cxx_track, Box::new(ArcasRustVideoSink::new(Box::new(sink))));
+
+        Ok(Self {
+            track_id: cxx_track.id(),
+            recorder,
+            _cxx_sink: cxx_sink,
+        })
+    }
+
+    pub fn stats(&self) -> LatencyStats {
+        self.recorder.lock().stats(self.track_id.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_pattern_frame_producer::{TestPattern, TestPatternGenerator};
+
+    use super::*;
+
+    fn frame_info(
+        absolute_capture_ntp_time_ms: i64,
+        ntp_time_ms: i64,
+    ) -> ArcasReceivedVideoFrameInfo {
+        ArcasReceivedVideoFrameInfo {
+            width: 640,
+            height: 480,
+            rtp_timestamp: 0,
+            ntp_time_ms,
+            absolute_capture_ntp_time_ms,
+        }
+    }
+
+    #[test]
+    fn test_latency_percentiles() {
+        let mut recorder = LatencyRecorder::default();
+        assert_eq!(recorder.percentiles(), None);
+
+        for ms in (1..=100).rev() {
+            recorder.record(Duration::from_millis(ms), CaptureTimeSource::BurnIn);
+        }
+        recorder.record_missing();
+
+        let stats = recorder.stats("video".into());
+        assert_eq!(stats.frames_measured, 100);
+        assert_eq!(stats.frames_without_capture_time, 1);
+        assert_eq!(stats.source, Some(CaptureTimeSource::BurnIn));
+        assert_eq!(
+            stats.percentiles,
+            Some(LatencyPercentiles {
+                min: Duration::from_millis(1),
+                p50: Duration::from_millis(50),
+                p90: Duration::from_millis(90),
+                p99: Duration::from_millis(99),
+                max: Duration::from_millis(100),
+            })
+        );
+    }
+
+    #[test]
+    fn test_latency_window() {
+        let mut recorder = LatencyRecorder::default();
+        recorder.record(Duration::from_secs(10), CaptureTimeSource::SenderReport);
+        for _ in 0..LATENCY_WINDOW_SIZE {
+            recorder.record(Duration::from_millis(5), CaptureTimeSource::SenderReport);
+        }
+        let percentiles = recorder.percentiles().unwrap();
+        assert_eq!(percentiles.max, Duration::from_millis(5));
+        assert_eq!(
+            recorder.stats("video".into()).frames_measured,
+            LATENCY_WINDOW_SIZE as u64 + 1
+        );
+    }
+
+    #[test]
+    fn test_capture_time_sources() {
+        let capture_time_ms = 1_650_000_000_000u64;
+        let ntp_time_ms = capture_time_ms as i64 + NTP_UNIX_OFFSET_MS;
+        let generator = TestPatternGenerator::new(640, 480, TestPattern::SmpteBars);
+        let luma = generator.render(1, capture_time_ms + 5);
+
+        assert_eq!(
+            super::capture_time_ms(&frame_info(ntp_time_ms, ntp_time_ms + 10), &luma),
+            Some((capture_time_ms, CaptureTimeSource::AbsoluteCaptureTime))
+        );
+        assert_eq!(
+            super::capture_time_ms(&frame_info(0, ntp_time_ms + 10), &luma),
+            Some((capture_time_ms + 5, CaptureTimeSource::BurnIn))
+        );
+        assert_eq!(
+            super::capture_time_ms(&frame_info(0, ntp_time_ms + 10), &[]),
+            Some((capture_time_ms + 10, CaptureTimeSource::SenderReport))
+        );
+        assert_eq!(super::capture_time_ms(&frame_info(0, 0), &[]), None);
+    }
+}
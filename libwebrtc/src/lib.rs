@@ -1,8 +1,10 @@
 pub mod audio_encoder_pool;
 pub mod audio_track;
 pub mod audio_track_source;
-mod certificate;
+pub mod certificate;
 pub mod data_channel;
+pub mod data_channel_transfer;
+pub mod dtls;
 pub mod empty_frame_producer;
 pub mod encoded_audio_frame_producer;
 pub mod encoded_video_frame_producer;
@@ -10,7 +12,11 @@ pub mod error;
 pub mod factory;
 pub mod ice_candidate;
 pub mod jsep;
+pub mod latency;
+pub mod load_harness;
+pub mod loopback;
 mod macros;
+pub mod media_clock;
 pub mod media_type;
 pub mod now;
 pub mod passthrough_video_decoder;
@@ -22,15 +28,27 @@ pub mod raw_video_frame_producer;
 pub mod reactive_video_encoder;
 pub mod rtc_base;
 pub mod rtc_error;
+pub mod rtp_packetizer;
+pub mod sctp;
+pub mod sctp_over_udp;
 pub mod sdp;
+pub mod sdp_model;
 pub mod sdp_video_format;
+#[cfg(feature = "serde")]
+mod serialization;
 pub mod shared_audio_encoder;
 pub mod shared_audio_encoder_factory;
+pub mod stun;
+pub mod stun_client;
+pub mod test_pattern_frame_producer;
 pub mod transceiver;
 mod transport;
+pub mod turn_server;
 pub mod video_codec;
 pub mod video_encoder;
 pub mod video_encoder_pool;
 pub mod video_frame;
 pub mod video_track;
 pub mod video_track_source;
+pub mod whep;
+pub mod whip;
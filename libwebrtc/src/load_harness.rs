@@ -0,0 +1,696 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use tokio::{
+    sync::mpsc::{channel, unbounded_channel, Receiver, UnboundedReceiver, UnboundedSender},
+    task::JoinHandle,
+    time::{sleep, timeout},
+};
+
+use crate::{
+    empty_frame_producer::EmptyFrameProducer,
+    error::{Result, WebRTCError},
+    factory::Factory,
+    ice_candidate::ICECandidate,
+    latency::{LatencyMonitor, LatencyStats},
+    media_clock::MediaClock,
+    peer_connection::{PeerConnection, PeerConnectionConfig, PeerConnectionFactory, SDPSemantic},
+    peer_connection_observer::{ConnectionState, ObserverSenders},
+    test_pattern_frame_producer::{TestPattern, TestPatternFrameProducer},
+    transceiver::{TransceiverDirection, TransceiverInit},
+    video_encoder_pool::{
+        BitrateTier, RawFrameProducerFactory, VideoEncoderControllerMetrics, VideoEncoderPool,
+    },
+    video_track_source::VideoTrackSource,
+};
+
+// How often the ramp is re-evaluated between samples.
+const RAMP_TICK: Duration = Duration::from_millis(50);
+const ICE_BUFFER_SIZE: usize = 100;
+
+/// One stage of a ramp schedule: move linearly from the current number of
+/// peer pairs to `peers` over `ramp`, then hold for `hold`.  Ramping down
+/// closes the most recently connected pairs first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RampStep {
+    pub peers: usize,
+    pub ramp: Duration,
+    pub hold: Duration,
+}
+
+impl RampStep {
+    pub fn new(peers: usize, ramp: Duration, hold: Duration) -> Self {
+        Self { peers, ramp, hold }
+    }
+
+    /// Peer pairs wanted `elapsed` into this step when it started with `from`.
+    pub fn target(&self, from: usize, elapsed: Duration) -> usize {
+        if elapsed >= self.ramp {
+            return self.peers;
+        }
+        let progress = elapsed.as_secs_f64() / self.ramp.as_secs_f64();
+        let delta = (self.peers as f64 - from as f64) * progress;
+        (from as f64 + delta).round() as usize
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.ramp + self.hold
+    }
+}
+
+pub struct LoadHarnessConfig {
+    /// Number of `Factory`s, each with its own signaling, worker and network
+    /// threads.  Peer pairs are spread across them round robin.
+    pub factories: usize,
+    /// Frame rate of the frames pushed into every sender track.
+    pub fps: u32,
+    pub ramp: Vec<RampStep>,
+    pub sample_interval: Duration,
+    /// Peer pairs not connected within this time count as failed.
+    pub connect_timeout: Duration,
+    /// Frame source of the encoder pool.  Defaults to a moving box test
+    /// pattern on the harness clock so receivers can measure latency from the
+    /// burn in.
+    pub frame_producer_factory: Option<RawFrameProducerFactory>,
+    pub tiers: Vec<BitrateTier>,
+}
+
+impl Default for LoadHarnessConfig {
+    fn default() -> Self {
+        Self {
+            factories: 4,
+            fps: 30,
+            ramp: vec![RampStep::new(
+                10,
+                Duration::from_secs(10),
+                Duration::from_secs(30),
+            )],
+            sample_interval: Duration::from_secs(1),
+            connect_timeout: Duration::from_secs(10),
+            frame_producer_factory: None,
+            tiers: vec![],
+        }
+    }
+}
+
+/// Aggregate state of the harness over one sample interval.  Rates are
+/// averages per connected peer pair.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadSample {
+    /// Time since the harness started.
+    pub elapsed: Duration,
+    pub active_peers: usize,
+    pub attempted: u64,
+    pub connected: u64,
+    pub failed: u64,
+    /// Share of finished connection attempts which succeeded.
+    pub success_rate: f64,
+    pub average_setup_time: Duration,
+    pub p95_setup_time: Duration,
+    pub send_bitrate_bps: f64,
+    pub receive_bitrate_bps: f64,
+    /// Frames decoded per second by each receiver.
+    pub receive_fps: f64,
+    /// Process CPU time per peer pair, where 1.0 is a full core.  Includes
+    /// the receivers as they run in the same process.  `None` where process
+    /// CPU time is unavailable or no peers are connected.
+    pub cpu_per_peer: Option<f64>,
+    /// Median of the per track p50 glass to glass latency.
+    pub latency_p50: Option<Duration>,
+    /// Worst per track p99 glass to glass latency.
+    pub latency_p99: Option<Duration>,
+    pub encoders: Vec<VideoEncoderControllerMetrics>,
+}
+
+/// A peer pair still connected at the end of the run.  Rates are over the
+/// last sample interval.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PeerPairReport {
+    pub send_bitrate_bps: f64,
+    pub receive_bitrate_bps: f64,
+    pub receive_fps: f64,
+    /// Latency of the receiver track.
+    pub latency: Option<LatencyStats>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadReport {
+    pub samples: Vec<LoadSample>,
+    pub peers: Vec<PeerPairReport>,
+}
+
+struct FactoryGroup {
+    // Keep the threads alive for the peer connection factories.
+    _api: Factory,
+    sender: PeerConnectionFactory,
+    receiver: PeerConnectionFactory,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct PeerCounters {
+    bytes_sent: u64,
+    bytes_received: u64,
+    frames_decoded: u64,
+}
+
+impl PeerCounters {
+    fn delta(&self, previous: &PeerCounters) -> PeerCounters {
+        PeerCounters {
+            bytes_sent: self.bytes_sent.saturating_sub(previous.bytes_sent),
+            bytes_received: self.bytes_received.saturating_sub(previous.bytes_received),
+            frames_decoded: self.frames_decoded.saturating_sub(previous.frames_decoded),
+        }
+    }
+}
+
+struct PeerPair {
+    sender: Arc<PeerConnection>,
+    receiver: Arc<PeerConnection>,
+    latency: Option<LatencyMonitor>,
+    counters: PeerCounters,
+    // Counted over the last sample interval, in seconds.
+    last_delta: PeerCounters,
+    last_interval: f64,
+    // Pushes frames into the sender track until dropped.
+    _frames: EmptyFrameProducer,
+    ice_tasks: Vec<JoinHandle<()>>,
+}
+
+impl Drop for PeerPair {
+    fn drop(&mut self) {
+        self.ice_tasks.iter().for_each(|task| task.abort());
+    }
+}
+
+struct SampleState {
+    at: Instant,
+    cpu_time: Option<Duration>,
+}
+
+/// Spins up sender/receiver peer pairs following a ramp schedule and reports
+/// connection success, setup time, bitrate, fps, CPU and latency.
+///
+/// Senders use the reactive encoder backed by a shared `VideoEncoderPool`,
+/// receivers decode normally.  Both ends run in this process and connect over
+/// loopback without signaling or STUN.
+pub struct LoadHarness {
+    // Peers are declared first so they are torn down before the pool.
+    peers: Vec<PeerPair>,
+    groups: Vec<Arc<FactoryGroup>>,
+    pool: VideoEncoderPool,
+    config: LoadHarnessConfig,
+    clock: MediaClock,
+    pending: usize,
+    next_peer: usize,
+    connected: u64,
+    failed: u64,
+    setup_times: Vec<Duration>,
+    results_tx: UnboundedSender<Result<(PeerPair, Duration)>>,
+    results_rx: UnboundedReceiver<Result<(PeerPair, Duration)>>,
+}
+
+impl LoadHarness {
+    pub fn new(config: LoadHarnessConfig) -> Result<Self> {
+        let clock = MediaClock::new()?;
+        let frame_producer_factory = match config.frame_producer_factory.clone() {
+            Some(factory) => factory,
+            None => test_pattern_frame_producer_factory(clock.clone()),
+        };
+        let (pool, pool_tx) = VideoEncoderPool::create_with_tiers(
+            move |controller_id, description| frame_producer_factory(controller_id, description),
+            config.tiers.clone(),
+        )?;
+
+        let groups = (0..config.factories.max(1))
+            .map(|_| -> Result<Arc<FactoryGroup>> {
+                let api = Factory::new();
+                let sender = api.create_peer_connection_factory_reactive(pool_tx.clone())?;
+                let receiver = api.create_peer_connection_factory()?;
+                Ok(Arc::new(FactoryGroup {
+                    _api: api,
+                    sender,
+                    receiver,
+                }))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let (results_tx, results_rx) = unbounded_channel();
+        Ok(Self {
+            peers: vec![],
+            groups,
+            pool,
+            config,
+            clock,
+            pending: 0,
+            next_peer: 0,
+            connected: 0,
+            failed: 0,
+            setup_times: vec![],
+            results_tx,
+            results_rx,
+        })
+    }
+
+    /// Run the whole ramp schedule and tear all peers down.
+    pub async fn run(mut self) -> Result<LoadReport> {
+        let start = Instant::now();
+        let mut samples = vec![];
+        let mut last_sample = SampleState {
+            at: start,
+            cpu_time: process_cpu_time(),
+        };
+        let mut next_sample = start + self.config.sample_interval;
+
+        for step in self.config.ramp.clone() {
+            let step_start = Instant::now();
+            let from = self.peers.len() + self.pending;
+            loop {
+                self.collect_connected();
+                let elapsed = step_start.elapsed();
+                let target = step.target(from, elapsed);
+                while self.peers.len() + self.pending < target {
+                    self.spawn_peer();
+                }
+                if self.peers.len() > target {
+                    self.peers.truncate(target);
+                }
+
+                if Instant::now() >= next_sample {
+                    samples.push(self.sample(start, &mut last_sample).await);
+                    next_sample += self.config.sample_interval;
+                }
+                if elapsed >= step.duration() {
+                    break;
+                }
+                sleep(RAMP_TICK.min(next_sample.saturating_duration_since(Instant::now()))).await;
+            }
+        }
+
+        let peers = self
+            .peers
+            .iter()
+            .map(|peer| PeerPairReport {
+                send_bitrate_bps: rate(peer.last_delta.bytes_sent * 8, peer.last_interval),
+                receive_bitrate_bps: rate(peer.last_delta.bytes_received * 8, peer.last_interval),
+                receive_fps: rate(peer.last_delta.frames_decoded, peer.last_interval),
+                latency: peer.latency.as_ref().map(|monitor| monitor.stats()),
+            })
+            .collect();
+        Ok(LoadReport { samples, peers })
+    }
+
+    fn spawn_peer(&mut self) {
+        let group = self.groups[self.next_peer % self.groups.len()].clone();
+        let index = self.next_peer;
+        let fps = self.config.fps;
+        let clock = self.clock.clone();
+        let connect_timeout = self.config.connect_timeout;
+        let results_tx = self.results_tx.clone();
+        self.next_peer += 1;
+        self.pending += 1;
+
+        tokio::spawn(async move {
+            let started = Instant::now();
+            let result = timeout(connect_timeout, connect_peer(index, &group, fps, clock))
+                .await
+                .unwrap_or_else(|_| {
+                    Err(WebRTCError::Cancel(format!(
+                        "peer pair {} did not connect within {:?}",
+                        index, connect_timeout
+                    )))
+                })
+                .map(|peer| (peer, started.elapsed()));
+            // The harness is gone when this fails, the peer is dropped.
+            let _ = results_tx.send(result);
+        });
+    }
+
+    fn collect_connected(&mut self) {
+        while let Ok(result) = self.results_rx.try_recv() {
+            self.pending -= 1;
+            match result {
+                Ok((peer, setup_time)) => {
+                    self.connected += 1;
+                    self.setup_times.push(setup_time);
+                    self.peers.push(peer);
+                }
+                Err(err) => {
+                    log::debug!("load harness peer failed: {}", err);
+                    self.failed += 1;
+                }
+            }
+        }
+    }
+
+    async fn sample(&mut self, start: Instant, last: &mut SampleState) -> LoadSample {
+        let now = Instant::now();
+        let interval = now.duration_since(last.at).as_secs_f64();
+        let cpu_time = process_cpu_time();
+
+        let handles: Vec<_> = self
+            .peers
+            .iter()
+            .map(|peer| {
+                let (sender, receiver) = (peer.sender.clone(), peer.receiver.clone());
+                tokio::spawn(async move { peer_counters(&sender, &receiver).await })
+            })
+            .collect();
+        let mut total = PeerCounters::default();
+        let mut measured = 0usize;
+        for (peer, handle) in self.peers.iter_mut().zip(handles) {
+            if let Ok(Ok(counters)) = handle.await {
+                let delta = counters.delta(&peer.counters);
+                total.bytes_sent += delta.bytes_sent;
+                total.bytes_received += delta.bytes_received;
+                total.frames_decoded += delta.frames_decoded;
+                peer.counters = counters;
+                peer.last_delta = delta;
+                peer.last_interval = interval;
+                measured += 1;
+            }
+        }
+        let per_peer = |value: u64| {
+            if measured == 0 {
+                0.0
+            } else {
+                rate(value, interval) / measured as f64
+            }
+        };
+
+        let cpu_per_peer = match (cpu_time, last.cpu_time) {
+            (Some(cpu_time), Some(last_cpu_time)) if !self.peers.is_empty() && interval > 0.0 => {
+                let used = cpu_time.saturating_sub(last_cpu_time).as_secs_f64();
+                Some(used / interval / self.peers.len() as f64)
+            }
+            _ => None,
+        };
+        *last = SampleState { at: now, cpu_time };
+
+        let latency: Vec<_> = self
+            .peers
+            .iter()
+            .filter_map(|peer| peer.latency.as_ref())
+            .filter_map(|monitor| monitor.stats().percentiles)
+            .collect();
+        let mut latency_p50: Vec<_> = latency.iter().map(|latency| latency.p50).collect();
+        latency_p50.sort_unstable();
+
+        let finished = self.connected + self.failed;
+        LoadSample {
+            elapsed: now.duration_since(start),
+            active_peers: self.peers.len(),
+            attempted: self.next_peer as u64,
+            connected: self.connected,
+            failed: self.failed,
+            success_rate: if finished == 0 {
+                0.0
+            } else {
+                self.connected as f64 / finished as f64
+            },
+            average_setup_time: average(&self.setup_times),
+            p95_setup_time: percentile(&self.setup_times, 95),
+            send_bitrate_bps: per_peer(total.bytes_sent * 8),
+            receive_bitrate_bps: per_peer(total.bytes_received * 8),
+            receive_fps: per_peer(total.frames_decoded),
+            cpu_per_peer,
+            latency_p50: latency_p50.get(latency_p50.len() / 2).copied(),
+            latency_p99: latency.iter().map(|latency| latency.p99).max(),
+            encoders: self.pool.metrics(),
+        }
+    }
+}
+
+fn test_pattern_frame_producer_factory(clock: MediaClock) -> RawFrameProducerFactory {
+    Arc::new(move |_controller_id, description| {
+        let producer = TestPatternFrameProducer::with_clock(
+            &description.to_video_codec(),
+            TestPattern::MovingBox,
+            clock.clone(),
+        )?;
+        Ok(Box::new(producer))
+    })
+}
+
+fn loopback_config() -> PeerConnectionConfig {
+    PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![])
+}
+
+async fn connect_peer(
+    index: usize,
+    group: &FactoryGroup,
+    fps: u32,
+    clock: MediaClock,
+) -> Result<PeerPair> {
+    let (sender_ice_tx, sender_ice_rx) = channel(ICE_BUFFER_SIZE);
+    let (receiver_ice_tx, receiver_ice_rx) = channel(ICE_BUFFER_SIZE);
+    let (state_tx, mut state_rx) = channel(ICE_BUFFER_SIZE);
+    let (track_tx, mut track_rx) = channel(1);
+
+    let sender = group.sender.create_peer_connection(
+        loopback_config(),
+        ObserverSenders {
+            ice_candidate: Some(sender_ice_tx),
+            ..ObserverSenders::default()
+        },
+    )?;
+    let receiver = group.receiver.create_peer_connection(
+        loopback_config(),
+        ObserverSenders {
+            ice_candidate: Some(receiver_ice_tx),
+            connection_state: Some(state_tx),
+            video_track: Some(track_tx),
+            ..ObserverSenders::default()
+        },
+    )?;
+
+    let stream_id = format!("load-{}", index);
+    let (source, source_writer) = VideoTrackSource::create();
+    let track = group
+        .sender
+        .create_video_track(stream_id.clone(), &source)?;
+    let mut transceiver = sender
+        .add_video_transceiver(
+            TransceiverInit::new(vec![stream_id], TransceiverDirection::SendOnly),
+            track,
+        )
+        .await?;
+    transceiver.enable_absolute_capture_time()?;
+
+    let mut frames = EmptyFrameProducer::with_clock(fps, clock.clone())?;
+    let frames_rx = frames.start()?;
+    // NOTE: Frames are pushed from a thread as the rx is blocking.
+    std::thread::spawn(move || {
+        while let Ok(frame) = frames_rx.recv() {
+            if source_writer.push_empty_frame(frame).is_err() {
+                return;
+            }
+        }
+    });
+
+    let offer = sender.create_offer().await?;
+    let remote_offer = offer.copy_to_remote()?;
+    sender.set_local_description(offer).await?;
+    receiver.set_remote_description(remote_offer).await?;
+    let answer = receiver.create_answer().await?;
+    let remote_answer = answer.copy_to_remote()?;
+    receiver.set_local_description(answer).await?;
+    sender.set_remote_description(remote_answer).await?;
+
+    let sender = Arc::new(sender);
+    let receiver = Arc::new(receiver);
+    let ice_tasks = vec![
+        tokio::spawn(forward_candidates(sender_ice_rx, receiver.clone())),
+        tokio::spawn(forward_candidates(receiver_ice_rx, sender.clone())),
+    ];
+
+    loop {
+        match state_rx.recv().await {
+            Some(ConnectionState::Connected) => break,
+            Some(ConnectionState::Failed) | Some(ConnectionState::Closed) | None => {
+                ice_tasks.iter().for_each(|task| task.abort());
+                return Err(WebRTCError::UnexpectedError(format!(
+                    "peer pair {} failed to connect",
+                    index
+                )));
+            }
+            Some(_) => {}
+        }
+    }
+
+    // The track is announced while applying the offer, well before the
+    // connection, but wait for it so every pair is measured.
+    let latency = match track_rx.recv().await {
+        Some(transceiver) => Some(LatencyMonitor::new(&transceiver.receiver_track(), clock)?),
+        None => None,
+    };
+
+    Ok(PeerPair {
+        sender,
+        receiver,
+        latency,
+        counters: PeerCounters::default(),
+        last_delta: PeerCounters::default(),
+        last_interval: 0.0,
+        _frames: frames,
+        ice_tasks,
+    })
+}
+
+async fn forward_candidates(mut rx: Receiver<ICECandidate>, pc: Arc<PeerConnection>) {
+    while let Some(candidate) = rx.recv().await {
+        if pc.add_ice_candidate(candidate).await.is_err() {
+            return;
+        }
+    }
+}
+
+async fn peer_counters(sender: &PeerConnection, receiver: &PeerConnection) -> Result<PeerCounters> {
+    let sent = sender.get_stats().await?;
+    let received = receiver.get_stats().await?;
+    Ok(PeerCounters {
+        bytes_sent: sent
+            .video_sender_stats
+            .iter()
+            .map(|stats| stats.bytes_sent)
+            .sum(),
+        bytes_received: received
+            .video_receiver_stats
+            .iter()
+            .map(|stats| stats.bytes_received)
+            .sum(),
+        frames_decoded: received
+            .video_receiver_stats
+            .iter()
+            .map(|stats| stats.frames_decoded as u64)
+            .sum(),
+    })
+}
+
+/// Per second over `interval` seconds.
+fn rate(value: u64, interval: f64) -> f64 {
+    if interval <= 0.0 {
+        0.0
+    } else {
+        value as f64 / interval
+    }
+}
+
+fn average(values: &[Duration]) -> Duration {
+    if values.is_empty() {
+        return Duration::from_secs(0);
+    }
+    values.iter().sum::<Duration>() / values.len() as u32
+}
+
+// Nearest rank percentile.
+fn percentile(values: &[Duration], percentile: usize) -> Duration {
+    if values.is_empty() {
+        return Duration::from_secs(0);
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable();
+    let index = (sorted.len() * percentile).div_ceil(100);
+    sorted[index.max(1) - 1]
+}
+
+fn process_cpu_time() -> Option<Duration> {
+    let stat = std::fs::read_to_string("/proc/self/stat").ok()?;
+    parse_cpu_time(&stat)
+}
+
+// utime and stime are fields 14 and 15 of /proc/<pid>/stat, counted in
+// USER_HZ (1/100s) ticks.  The command name in field 2 may contain spaces so
+// fields are counted from its closing parenthesis.
+fn parse_cpu_time(stat: &str) -> Option<Duration> {
+    let rest = &stat[stat.rfind(')')? + 1..];
+    let mut fields = rest.split_whitespace().skip(11);
+    let utime: u64 = fields.next()?.parse().ok()?;
+    let stime: u64 = fields.next()?.parse().ok()?;
+    Some(Duration::from_millis((utime + stime) * 10))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ramp_step_target() {
+        let step = RampStep::new(10, Duration::from_secs(10), Duration::from_secs(5));
+        assert_eq!(step.target(0, Duration::from_secs(0)), 0);
+        assert_eq!(step.target(0, Duration::from_secs(5)), 5);
+        assert_eq!(step.target(0, Duration::from_secs(10)), 10);
+        assert_eq!(step.target(0, Duration::from_secs(12)), 10);
+        assert_eq!(step.duration(), Duration::from_secs(15));
+
+        // Ramping down.
+        let step = RampStep::new(0, Duration::from_secs(4), Duration::from_secs(0));
+        assert_eq!(step.target(8, Duration::from_secs(1)), 6);
+        assert_eq!(step.target(8, Duration::from_secs(4)), 0);
+
+        // Without a ramp the target applies immediately.
+        let step = RampStep::new(3, Duration::from_secs(0), Duration::from_secs(1));
+        assert_eq!(step.target(0, Duration::from_secs(0)), 3);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_run() {
+        let harness = LoadHarness::new(LoadHarnessConfig {
+            factories: 1,
+            fps: 15,
+            ramp: vec![RampStep::new(
+                2,
+                Duration::from_secs(0),
+                Duration::from_secs(4),
+            )],
+            sample_interval: Duration::from_secs(1),
+            ..LoadHarnessConfig::default()
+        })
+        .unwrap();
+        let report = harness.run().await.unwrap();
+
+        assert!(report.samples.len() >= 3);
+        assert!(report
+            .samples
+            .windows(2)
+            .all(|pair| pair[0].elapsed < pair[1].elapsed));
+        let last = report.samples.last().unwrap();
+        assert_eq!(last.attempted, 2);
+        assert_eq!(last.connected, 2);
+        assert_eq!(last.failed, 0);
+        assert_eq!(last.active_peers, 2);
+        assert_eq!(last.success_rate, 1.0);
+        assert!(last.average_setup_time > Duration::from_secs(0));
+        assert!(last.receive_fps > 0.0);
+        assert!(last.receive_bitrate_bps > 0.0);
+
+        // Every pair is sending, receiving and measured.
+        assert_eq!(report.peers.len(), 2);
+        for peer in &report.peers {
+            assert!(peer.send_bitrate_bps > 0.0, "{:?}", peer);
+            assert!(peer.receive_bitrate_bps > 0.0, "{:?}", peer);
+            assert!(peer.receive_fps > 0.0, "{:?}", peer);
+            let latency = peer.latency.as_ref().unwrap();
+            assert!(latency.frames_measured > 0, "{:?}", peer);
+            assert!(latency.percentiles.is_some(), "{:?}", peer);
+        }
+    }
+
+    #[test]
+    fn test_setup_time_stats() {
+        let setup_times: Vec<_> = (1..=20).map(Duration::from_millis).collect();
+        assert_eq!(average(&setup_times), Duration::from_micros(10_500));
+        assert_eq!(percentile(&setup_times, 95), Duration::from_millis(19));
+        assert_eq!(percentile(&[], 95), Duration::from_secs(0));
+    }
+
+    #[test]
+    fn test_parse_cpu_time() {
+        let stat =
+            "1234 (load (test) 1) S 1 1234 1234 0 -1 4194560 5000 0 0 0 150 25 0 0 20 0 12 0";
+        assert_eq!(parse_cpu_time(stat), Some(Duration::from_millis(1750)));
+        assert_eq!(parse_cpu_time("garbage"), None);
+        assert!(process_cpu_time().is_some() || !cfg!(target_os = "linux"));
+    }
+}
@@ -0,0 +1,270 @@
+use log::warn;
+use tokio::{
+    sync::{
+        mpsc::{channel, Receiver},
+        watch,
+    },
+    task::JoinHandle,
+};
+
+use crate::{
+    error::{Result, WebRTCError},
+    ice_candidate::ICECandidate,
+    peer_connection::PeerConnection,
+    peer_connection_observer::{ConnectionState, ObserverSenders},
+};
+
+pub const LOOPBACK_BUFFER_SIZE: usize = 100;
+
+/// Observer events `connect_pair` needs from each peer connection.
+pub struct LoopbackReceivers {
+    pub ice_candidate: Receiver<ICECandidate>,
+    pub connection_state: Receiver<ConnectionState>,
+    pub renegotiation_needed: Receiver<()>,
+}
+
+impl LoopbackReceivers {
+    /// Observer senders wired to a new set of receivers.  The remaining
+    /// senders (data channels, tracks) can be filled in with struct update
+    /// syntax before creating the peer connection.
+    pub fn create() -> (ObserverSenders, Self) {
+        let (ice_tx, ice_candidate) = channel(LOOPBACK_BUFFER_SIZE);
+        let (state_tx, connection_state) = channel(LOOPBACK_BUFFER_SIZE);
+        let (renegotiation_tx, renegotiation_needed) = channel(LOOPBACK_BUFFER_SIZE);
+        (
+            ObserverSenders {
+                ice_candidate: Some(ice_tx),
+                connection_state: Some(state_tx),
+                renegotiation_needed: Some(renegotiation_tx),
+                ..ObserverSenders::default()
+            },
+            Self {
+                ice_candidate,
+                connection_state,
+                renegotiation_needed,
+            },
+        )
+    }
+}
+
+/// Two peer connections kept connected in process.  Candidates are trickled
+/// and renegotiation on either side is answered until this is dropped.
+pub struct LoopbackPair {
+    states: [watch::Receiver<ConnectionState>; 2],
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl LoopbackPair {
+    /// Latest connection state of each peer connection.
+    pub fn connection_states(&self) -> (ConnectionState, ConnectionState) {
+        (
+            self.states[0].borrow().clone(),
+            self.states[1].borrow().clone(),
+        )
+    }
+
+    /// Wait until both peer connections are connected, e.g. after an ICE
+    /// restart.
+    pub async fn wait_connected(&mut self) -> Result<()> {
+        for state in self.states.iter_mut() {
+            wait_for_connected(state).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for LoopbackPair {
+    fn drop(&mut self) {
+        self.tasks.iter().for_each(|task| task.abort());
+    }
+}
+
+/// Offer from `offerer`, answer from `answerer`.
+pub async fn negotiate(offerer: &PeerConnection, answerer: &PeerConnection) -> Result<()> {
+    let offer = offerer.create_offer().await?;
+    let remote_offer = offer.copy_to_remote()?;
+    offerer.set_local_description(offer).await?;
+    answerer.set_remote_description(remote_offer).await?;
+    let answer = answerer.create_answer().await?;
+    let remote_answer = answer.copy_to_remote()?;
+    answerer.set_local_description(answer).await?;
+    offerer.set_remote_description(remote_answer).await
+}
+
+/// Negotiate `pc1` with `pc2` (`pc1` offers) and wait until both are
+/// connected.  `rx1` and `rx2` are the receivers for the observers the
+/// peer connections were created with, see `LoopbackReceivers::create`.
+pub async fn connect_pair(
+    pc1: &PeerConnection,
+    pc2: &PeerConnection,
+    rx1: LoopbackReceivers,
+    rx2: LoopbackReceivers,
+) -> Result<LoopbackPair> {
+    let LoopbackReceivers {
+        ice_candidate: ice1,
+        connection_state: state1,
+        renegotiation_needed: mut renegotiation1,
+    } = rx1;
+    let LoopbackReceivers {
+        ice_candidate: ice2,
+        connection_state: state2,
+        renegotiation_needed: mut renegotiation2,
+    } = rx2;
+
+    negotiate(pc1, pc2).await?;
+    // Anything added before connecting is covered by the first offer.
+    drain(&mut renegotiation1);
+    drain(&mut renegotiation2);
+
+    let (state1_tx, state1_rx) = watch::channel(ConnectionState::New);
+    let (state2_tx, state2_rx) = watch::channel(ConnectionState::New);
+    let tasks = vec![
+        tokio::spawn(forward_candidates(ice1, pc2.clone())),
+        tokio::spawn(forward_candidates(ice2, pc1.clone())),
+        tokio::spawn(forward_states(state1, state1_tx)),
+        tokio::spawn(forward_states(state2, state2_tx)),
+        tokio::spawn(renegotiate(
+            pc1.clone(),
+            pc2.clone(),
+            renegotiation1,
+            renegotiation2,
+        )),
+    ];
+
+    let mut pair = LoopbackPair {
+        states: [state1_rx, state2_rx],
+        tasks,
+    };
+    pair.wait_connected().await?;
+    Ok(pair)
+}
+
+async fn wait_for_connected(state: &mut watch::Receiver<ConnectionState>) -> Result<()> {
+    loop {
+        let current = state.borrow().clone();
+        match current {
+            ConnectionState::Connected => return Ok(()),
+            ConnectionState::Failed | ConnectionState::Closed => {
+                return Err(WebRTCError::UnexpectedError(format!(
+                    "loopback peer connection {:?}",
+                    current
+                )))
+            }
+            _ => {}
+        }
+        state
+            .changed()
+            .await
+            .map_err(|_| WebRTCError::UnexpectedError("loopback connection state closed".into()))?;
+    }
+}
+
+/// Candidates of a restarted ICE session can arrive before the new remote
+/// description, libwebrtc holds on to them until the credentials match.
+async fn forward_candidates(mut rx: Receiver<ICECandidate>, pc: PeerConnection) {
+    while let Some(candidate) = rx.recv().await {
+        if let Err(err) = pc.add_ice_candidate(candidate).await {
+            warn!("loopback failed to add candidate: {}", err);
+        }
+    }
+}
+
+async fn forward_states(mut rx: Receiver<ConnectionState>, tx: watch::Sender<ConnectionState>) {
+    while let Some(state) = rx.recv().await {
+        if tx.send(state).is_err() {
+            return;
+        }
+    }
+}
+
+async fn renegotiate(
+    pc1: PeerConnection,
+    pc2: PeerConnection,
+    mut rx1: Receiver<()>,
+    mut rx2: Receiver<()>,
+) {
+    loop {
+        // Negotiations run one at a time so the two sides never glare.
+        let result = tokio::select! {
+            Some(()) = rx1.recv() => {
+                drain(&mut rx1);
+                negotiate(&pc1, &pc2).await
+            }
+            Some(()) = rx2.recv() => {
+                drain(&mut rx2);
+                negotiate(&pc2, &pc1).await
+            }
+            else => return,
+        };
+        if let Err(err) = result {
+            warn!("loopback renegotiation failed: {}", err);
+        }
+    }
+}
+
+fn drain(rx: &mut Receiver<()>) {
+    while rx.try_recv().is_ok() {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        factory::Factory,
+        peer_connection::{PeerConnectionConfig, SDPSemantic},
+        transceiver::{TransceiverDirection, TransceiverInit},
+        video_track_source::VideoTrackSource,
+    };
+
+    #[tokio::test]
+    async fn test_renegotiation_from_answerer() {
+        let factory1 = Factory::new();
+        let factory2 = Factory::new();
+        let pc_factory1 = factory1.create_peer_connection_factory().unwrap();
+        let pc_factory2 = factory2.create_peer_connection_factory().unwrap();
+
+        let (track_tx, mut track_rx) = channel(1);
+        let (senders1, rx1) = LoopbackReceivers::create();
+        let pc1 = pc_factory1
+            .create_peer_connection(
+                PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]),
+                ObserverSenders {
+                    video_track: Some(track_tx),
+                    ..senders1
+                },
+            )
+            .unwrap();
+        let (senders2, rx2) = LoopbackReceivers::create();
+        let pc2 = pc_factory2
+            .create_peer_connection(
+                PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]),
+                senders2,
+            )
+            .unwrap();
+
+        let _dc = pc1
+            .create_data_channel("test".into(), Default::default())
+            .unwrap();
+        let mut pair = connect_pair(&pc1, &pc2, rx1, rx2).await.unwrap();
+        assert_eq!(
+            pair.connection_states(),
+            (ConnectionState::Connected, ConnectionState::Connected)
+        );
+
+        // pc2 answered the first offer, adding a track makes it offer.
+        let (source, _source_write) = VideoTrackSource::create();
+        let track = pc_factory2
+            .create_video_track("renegotiated".into(), &source)
+            .unwrap();
+        pc2.add_video_transceiver(
+            TransceiverInit::new(vec!["0".into()], TransceiverDirection::SendOnly),
+            track,
+        )
+        .await
+        .unwrap();
+
+        let transceiver = track_rx.recv().await.unwrap();
+        assert_eq!(transceiver.receiver_track().id().unwrap(), "renegotiated");
+        pair.wait_connected().await.unwrap();
+    }
+}
@@ -0,0 +1,218 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crossbeam_channel::{select, Receiver, TryRecvError};
+
+use crate::{error::Result, now};
+
+/// RTP clock rate used for all video payloads.
+pub const VIDEO_CLOCK_RATE_HZ: u32 = 90_000;
+/// RTP clock rate of opus (and most wideband audio) payloads.
+pub const AUDIO_CLOCK_RATE_HZ: u32 = 48_000;
+
+// Milliseconds between the NTP epoch (1900) and the unix epoch (1970).
+pub(crate) const NTP_UNIX_OFFSET_MS: i64 = 2_208_988_800_000;
+
+/// Timestamps attached to a single captured frame or audio packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaptureTimestamp {
+    /// Unix time in milliseconds, monotonic for a given clock.
+    pub capture_time_ms: u64,
+    /// Same instant on the NTP timescale, as used by RTCP sender reports.
+    pub ntp_time_ms: i64,
+    /// RTP timestamp at the clock rate of the media.
+    pub rtp_timestamp: u32,
+}
+
+#[derive(Debug)]
+struct MediaClockInner {
+    start: Instant,
+    start_unix_ms: u64,
+}
+
+/// Shared time base for producers.  All clones share the same origin so the
+/// timestamps of audio and video produced with them line up with each other.
+///
+/// Wall clock time is sampled once at creation, everything after that is
+/// derived from a monotonic `Instant` so timestamps never go backwards.
+#[derive(Debug, Clone)]
+pub struct MediaClock {
+    inner: Arc<MediaClockInner>,
+}
+
+impl MediaClock {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            inner: Arc::new(MediaClockInner {
+                start: Instant::now(),
+                start_unix_ms: now::now()?,
+            }),
+        })
+    }
+
+    /// Time since the clock was created.
+    pub fn elapsed(&self) -> Duration {
+        self.inner.start.elapsed()
+    }
+
+    pub fn unix_time_ms(&self) -> u64 {
+        self.unix_time_ms_at(self.elapsed())
+    }
+
+    pub fn ntp_time_ms(&self) -> i64 {
+        self.unix_time_ms() as i64 + NTP_UNIX_OFFSET_MS
+    }
+
+    /// RTP timestamp for `clock_rate_hz`, starting at zero when the clock was
+    /// created and wrapping like RTP timestamps do.
+    pub fn rtp_timestamp(&self, clock_rate_hz: u32) -> u32 {
+        rtp_timestamp_at(self.elapsed(), clock_rate_hz)
+    }
+
+    pub fn capture_timestamp(&self, clock_rate_hz: u32) -> CaptureTimestamp {
+        self.capture_timestamp_at(self.elapsed(), clock_rate_hz)
+    }
+
+    /// Pacer ticking `fps` times per second on this clock until `cancel_rx`
+    /// receives a value or is disconnected.
+    pub fn pacer(&self, fps: u32, cancel_rx: Receiver<()>) -> FramePacer {
+        FramePacer::new(self.clone(), fps, cancel_rx)
+    }
+
+    fn unix_time_ms_at(&self, elapsed: Duration) -> u64 {
+        self.inner.start_unix_ms + elapsed.as_millis() as u64
+    }
+
+    /// Timestamp of the instant `elapsed` after the clock was created.
+    pub(crate) fn capture_timestamp_at(
+        &self,
+        elapsed: Duration,
+        clock_rate_hz: u32,
+    ) -> CaptureTimestamp {
+        let capture_time_ms = self.unix_time_ms_at(elapsed);
+        CaptureTimestamp {
+            capture_time_ms,
+            ntp_time_ms: capture_time_ms as i64 + NTP_UNIX_OFFSET_MS,
+            rtp_timestamp: rtp_timestamp_at(elapsed, clock_rate_hz),
+        }
+    }
+}
+
+fn rtp_timestamp_at(elapsed: Duration, clock_rate_hz: u32) -> u32 {
+    (elapsed.as_micros() * clock_rate_hz as u128 / 1_000_000) as u32
+}
+
+/// Paces frame production on a `MediaClock`.
+///
+/// Deadlines are computed from the clock rather than by sleeping a fixed
+/// interval after each frame, so time spent producing a frame or oversleeping
+/// does not accumulate into drift.  When a producer falls more than a frame
+/// behind the missed ticks are skipped rather than produced in a burst.
+pub struct FramePacer {
+    clock: MediaClock,
+    interval: Duration,
+    next_tick: Duration,
+    skipped_ticks: u64,
+    cancel_rx: Receiver<()>,
+}
+
+impl FramePacer {
+    pub fn new(clock: MediaClock, fps: u32, cancel_rx: Receiver<()>) -> Self {
+        let next_tick = clock.elapsed();
+        Self {
+            clock,
+            interval: Duration::from_secs(1) / fps.max(1),
+            next_tick,
+            skipped_ticks: 0,
+            cancel_rx,
+        }
+    }
+
+    /// Block until the next frame is due and return its capture timestamp at
+    /// `clock_rate_hz`.  Returns `None` once the pacer is cancelled.
+    pub fn wait(&mut self, clock_rate_hz: u32) -> Option<CaptureTimestamp> {
+        let now = self.clock.elapsed();
+        if now < self.next_tick {
+            select! {
+                recv(self.cancel_rx) -> _ => return None,
+                default(self.next_tick - now) => {},
+            }
+        } else if !matches!(self.cancel_rx.try_recv(), Err(TryRecvError::Empty)) {
+            return None;
+        }
+
+        let tick = self.next_tick;
+        self.next_tick += self.interval;
+        let now = self.clock.elapsed();
+        while self.next_tick <= now {
+            self.next_tick += self.interval;
+            self.skipped_ticks += 1;
+        }
+
+        Some(self.clock.capture_timestamp_at(tick, clock_rate_hz))
+    }
+
+    /// Ticks dropped because the producer fell behind.
+    pub fn skipped_ticks(&self) -> u64 {
+        self.skipped_ticks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_timestamps_are_aligned() {
+        let clock = MediaClock::new().unwrap();
+        let timestamp = clock.capture_timestamp_at(Duration::from_secs(2), VIDEO_CLOCK_RATE_HZ);
+        assert_eq!(timestamp.rtp_timestamp, 180_000);
+        assert_eq!(
+            timestamp.ntp_time_ms - timestamp.capture_time_ms as i64,
+            NTP_UNIX_OFFSET_MS
+        );
+        assert_eq!(
+            clock
+                .capture_timestamp_at(Duration::from_secs(2), AUDIO_CLOCK_RATE_HZ)
+                .rtp_timestamp,
+            96_000
+        );
+
+        let first = clock.capture_timestamp(VIDEO_CLOCK_RATE_HZ);
+        let second = clock.clone().capture_timestamp(VIDEO_CLOCK_RATE_HZ);
+        assert!(second.capture_time_ms >= first.capture_time_ms);
+        assert!(second.rtp_timestamp >= first.rtp_timestamp);
+    }
+
+    #[test]
+    fn test_pacer_does_not_drift() {
+        let clock = MediaClock::new().unwrap();
+        let (_cancel_tx, cancel_rx) = crossbeam_channel::bounded::<()>(1);
+        let mut pacer = clock.pacer(100, cancel_rx);
+        let first = pacer.wait(VIDEO_CLOCK_RATE_HZ).unwrap();
+        let mut last = first;
+        for _ in 0..50 {
+            // Simulate uneven work between frames.
+            std::thread::sleep(Duration::from_millis(3));
+            last = pacer.wait(VIDEO_CLOCK_RATE_HZ).unwrap();
+        }
+        // 50 ticks of 10ms at 90kHz, give or take rounding of the start time.
+        let elapsed_rtp = (last.rtp_timestamp - first.rtp_timestamp) as i64;
+        assert!((elapsed_rtp - 50 * 900).abs() <= 1);
+        assert!(clock.elapsed() < Duration::from_millis(600));
+    }
+
+    #[test]
+    fn test_pacer_cancel() {
+        let clock = MediaClock::new().unwrap();
+        let (cancel_tx, cancel_rx) = crossbeam_channel::bounded::<()>(1);
+        let mut pacer = clock.pacer(1, cancel_rx);
+        assert!(pacer.wait(VIDEO_CLOCK_RATE_HZ).is_some());
+        cancel_tx.send(()).unwrap();
+        let start = Instant::now();
+        assert!(pacer.wait(VIDEO_CLOCK_RATE_HZ).is_none());
+        assert!(start.elapsed() < Duration::from_millis(500));
+    }
+}
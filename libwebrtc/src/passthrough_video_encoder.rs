@@ -202,7 +202,7 @@ mod tests {
             EncodedFrameProducerProducer, DEFAULT_HEIGHT, DEFAULT_WIDTH,
         },
         factory::Factory,
-        peer_connection::PeerConnectionConfig,
+        peer_connection::{PeerConnectionConfig, SDPSemantic},
         peer_connection_observer::ObserverSenders,
         raw_video_frame_producer::{GStreamerRawFrameProducer, RawFrameProducer},
         video_codec::VideoCodec,
@@ -214,8 +214,8 @@ mod tests {
     async fn test_custom_video_encoder_factory() {
         let api_factory = Factory::new();
         let api_factory2 = Factory::new();
-        let config = PeerConnectionConfig::default();
-        let config2 = PeerConnectionConfig::default();
+        let config = PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]);
+        let config2 = PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]);
 
         let (ice_tx, mut ice_rx) = channel(100);
         let (ice_tx2, mut ice_rx2) = channel(100);
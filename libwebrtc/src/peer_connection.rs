@@ -5,10 +5,11 @@ use libwebrtc_sys::ffi::ArcasAudioSenderStats;
 use libwebrtc_sys::{
     data_channel::ffi::{ArcasDataChannelInit, Priority},
     ffi::{
-        audio_transceiver_from_base, create_rtc_configuration, video_transceiver_from_base,
-        ArcasAudioReceiverStats, ArcasICEServer, ArcasMediaType, ArcasPeerConnection,
-        ArcasPeerConnectionConfig, ArcasPeerConnectionFactory, ArcasRTCConfiguration,
-        ArcasSDPSemantics, ArcasVideoReceiverStats, ArcasVideoSenderStats,
+        add_rtc_configuration_certificate, audio_transceiver_from_base, create_rtc_configuration,
+        video_transceiver_from_base, ArcasAudioReceiverStats, ArcasICEServer,
+        ArcasICETransportType, ArcasMediaType, ArcasPeerConnection, ArcasPeerConnectionConfig,
+        ArcasPeerConnectionFactory, ArcasPeerConnectionState, ArcasRTCConfiguration,
+        ArcasRTCSignalingState, ArcasSDPSemantics, ArcasVideoReceiverStats, ArcasVideoSenderStats,
     },
     ArcasRustCreateSessionDescriptionObserver, ArcasRustRTCStatsCollectorCallback,
     ArcasRustSetSessionDescriptionObserver,
@@ -24,6 +25,7 @@ pub type AudioSenderStats = ArcasAudioSenderStats;
 pub type AudioReceiverStats = ArcasAudioReceiverStats;
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PeerConnectionStats {
     pub video_sender_stats: Vec<VideoSenderStats>,
     pub video_receiver_stats: Vec<VideoReceiverStats>,
@@ -50,6 +52,7 @@ impl PeerConnectionStats {
 use crate::{
     audio_track::AudioTrack,
     audio_track_source::AudioTrackSource,
+    certificate::SSLCertificate,
     data_channel::{DataChannel, DataChannelInit},
     error::{aracs_rtc_error_to_err, Result, WebRTCError},
     ice_candidate::ICECandidate,
@@ -57,7 +60,7 @@ use crate::{
     peer_connection_observer::{ConnectionState, ObserverSenders, PeerConnectionObserver},
     rx_recv_async_or_err,
     sdp::SessionDescription,
-    transceiver::{AudioTransceiver, TransceiverInit, VideoTransceiver},
+    transceiver::{AudioTransceiver, TransceiverDirection, TransceiverInit, VideoTransceiver},
     video_track::VideoTrack,
     video_track_source::VideoTrackSource,
 };
@@ -88,9 +91,33 @@ impl From<SDPSemantic> for ArcasSDPSemantics {
     }
 }
 
+/// Which candidates ICE gathers, `Relay` only uses TURN allocations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ICETransportPolicy {
+    None,
+    Relay,
+    NoHost,
+    All,
+}
+
+impl From<ICETransportPolicy> for ArcasICETransportType {
+    fn from(value: ICETransportPolicy) -> Self {
+        match value {
+            ICETransportPolicy::None => ArcasICETransportType::kNone,
+            ICETransportPolicy::Relay => ArcasICETransportType::kRelay,
+            ICETransportPolicy::NoHost => ArcasICETransportType::kNoHost,
+            ICETransportPolicy::All => ArcasICETransportType::kAll,
+        }
+    }
+}
+
 pub struct PeerConnectionConfig {
     pub sdp_semantics: SDPSemantic,
     pub ice_servers: Vec<ICEServer>,
+    pub ice_transport_policy: ICETransportPolicy,
+    /// DTLS certificates, libwebrtc generates one per peer connection when
+    /// empty.  Reusing a saved certificate keeps the fingerprint stable.
+    pub certificates: Vec<SSLCertificate>,
 }
 
 impl PeerConnectionConfig {
@@ -98,19 +125,38 @@ impl PeerConnectionConfig {
         Self {
             sdp_semantics,
             ice_servers,
+            ice_transport_policy: ICETransportPolicy::All,
+            certificates: vec![],
         }
     }
+
+    pub fn with_ice_transport_policy(mut self, policy: ICETransportPolicy) -> Self {
+        self.ice_transport_policy = policy;
+        self
+    }
+
+    pub fn with_certificate(mut self, certificate: SSLCertificate) -> Self {
+        self.certificates.push(certificate);
+        self
+    }
 }
 
 impl From<PeerConnectionConfig> for UniquePtr<ArcasRTCConfiguration> {
     fn from(value: PeerConnectionConfig) -> UniquePtr<ArcasRTCConfiguration> {
-        create_rtc_configuration(ArcasPeerConnectionConfig {
+        let mut config = create_rtc_configuration(ArcasPeerConnectionConfig {
             sdp_semantics: value.sdp_semantics.into(),
             ice_servers: value.ice_servers,
-        })
+            ice_transport_type: value.ice_transport_policy.into(),
+        });
+        for certificate in value.certificates.iter() {
+            add_rtc_configuration_certificate(config.pin_mut(), &certificate.inner);
+        }
+        config
     }
 }
 
+/// Google's public STUN server, gathering contacts it over the network.  Use
+/// no ICE servers, or a local `turn_server::StunServer`, to stay offline.
 impl Default for PeerConnectionConfig {
     fn default() -> Self {
         Self {
@@ -120,6 +166,8 @@ impl Default for PeerConnectionConfig {
                 username: "".into(),
                 password: "".into(),
             }],
+            ice_transport_policy: ICETransportPolicy::All,
+            certificates: vec![],
         }
     }
 }
@@ -163,18 +211,31 @@ impl PeerConnectionFactory {
     }
 }
 
+struct PeerConnectionInner {
+    // Keep observer alive for c++
+    #[allow(unused)]
+    observer: Mutex<PeerConnectionObserver>,
+
+    cxx_pc: SharedPtr<ArcasPeerConnection>,
+}
+
+impl Drop for PeerConnectionInner {
+    fn drop(&mut self) {
+        // Stop underlying tasks in libwebrtc from continuing to run on a deallocated object.
+        self.cxx_pc.close();
+    }
+}
+
 /// NOTE: Unlike the factories these peer connection objects are tokio friendly.
 ///
 /// # Thread Safety
 ///
 /// In C++ all calls are redirected to the right thread.  this means we can pass
-/// around and share peer connection objects.
+/// around and share peer connection objects.  Clones refer to the same
+/// connection, which is closed once the last clone is dropped.
+#[derive(Clone)]
 pub struct PeerConnection {
-    // Keep observer alive for c++
-    #[allow(unused)]
-    observer: Arc<Mutex<PeerConnectionObserver>>,
-
-    cxx_pc: SharedPtr<ArcasPeerConnection>,
+    inner: Arc<PeerConnectionInner>,
 }
 
 impl<'a> PeerConnection {
@@ -183,16 +244,27 @@ impl<'a> PeerConnection {
         cxx_pc: SharedPtr<ArcasPeerConnection>,
     ) -> Self {
         Self {
-            observer: Arc::new(Mutex::new(observer)),
-            cxx_pc,
+            inner: Arc::new(PeerConnectionInner {
+                observer: Mutex::new(observer),
+                cxx_pc,
+            }),
         }
     }
 
+    pub fn signaling_state(&self) -> ArcasRTCSignalingState {
+        self.inner.cxx_pc.signaling_state()
+    }
+
+    pub fn connection_state(&self) -> ArcasPeerConnectionState {
+        self.inner.cxx_pc.connection_state()
+    }
+
     pub async fn create_offer(&self) -> Result<SessionDescription> {
         let (tx, mut rx) = channel(1);
         let tx_err = tx.clone();
 
-        self.cxx_pc
+        self.inner
+            .cxx_pc
             .create_offer(Box::new(ArcasRustCreateSessionDescriptionObserver::new(
                 Box::new(move |session_description| {
                     ok_or_return!(tx.blocking_send(Ok(session_description)));
@@ -213,7 +285,8 @@ impl<'a> PeerConnection {
         let (tx, mut rx) = channel(1);
         let tx_err = tx.clone();
 
-        self.cxx_pc
+        self.inner
+            .cxx_pc
             .create_answer(Box::new(ArcasRustCreateSessionDescriptionObserver::new(
                 Box::new(move |session_description| {
                     ok_or_return!(tx.blocking_send(Ok(session_description)));
@@ -235,7 +308,7 @@ impl<'a> PeerConnection {
         let (tx, mut rx) = channel(1);
         let tx_err = tx.clone();
 
-        self.cxx_pc.set_local_description(
+        self.inner.cxx_pc.set_local_description(
             Box::new(ArcasRustSetSessionDescriptionObserver::new(
                 Box::new(move || {
                     ok_or_return!(tx.blocking_send(Ok(())));
@@ -261,7 +334,7 @@ impl<'a> PeerConnection {
         let (tx, mut rx) = channel(1);
         let tx_err = tx.clone();
 
-        self.cxx_pc.set_remote_description(
+        self.inner.cxx_pc.set_remote_description(
             Box::new(ArcasRustSetSessionDescriptionObserver::new(
                 Box::new(move || {
                     ok_or_return!(tx.blocking_send(Ok(())));
@@ -290,6 +363,7 @@ impl<'a> PeerConnection {
         let cxx_track = track.take_cxx()?;
         let cxx_init = init.take_cxx();
         let transceiver = self
+            .inner
             .cxx_pc
             .add_video_transceiver_with_track(cxx_track, cxx_init);
 
@@ -304,18 +378,47 @@ impl<'a> PeerConnection {
         let cxx_track = track.take_cxx()?;
         let cxx_init = init.take_cxx();
         let transceiver = self
+            .inner
             .cxx_pc
             .add_audio_transceiver_with_track(cxx_track, cxx_init);
         Ok(AudioTransceiver::new(transceiver))
     }
 
+    /// Add a transceiver that only receives video, no local track is
+    /// attached.
+    pub async fn add_recv_only_video_transceiver(&self) -> Result<VideoTransceiver> {
+        let transceiver = self.inner.cxx_pc.add_video_transceiver();
+        if transceiver.is_null() {
+            return Err(WebRTCError::UnexpectedError(
+                "could not add video transceiver".into(),
+            ));
+        }
+        let mut transceiver = VideoTransceiver::new(transceiver);
+        transceiver.set_direction(TransceiverDirection::RecvOnly)?;
+        Ok(transceiver)
+    }
+
+    /// Add a transceiver that only receives audio, no local track is
+    /// attached.
+    pub async fn add_recv_only_audio_transceiver(&self) -> Result<AudioTransceiver> {
+        let transceiver = self.inner.cxx_pc.add_audio_transceiver();
+        if transceiver.is_null() {
+            return Err(WebRTCError::UnexpectedError(
+                "could not add audio transceiver".into(),
+            ));
+        }
+        let mut transceiver = AudioTransceiver::new(transceiver);
+        transceiver.set_direction(TransceiverDirection::RecvOnly)?;
+        Ok(transceiver)
+    }
+
     pub async fn add_video_track(
         &self,
         stream_ids: Vec<String>,
         mut track: VideoTrack,
     ) -> Result<()> {
         let cxx_track = track.take_cxx()?;
-        self.cxx_pc.add_video_track(cxx_track, stream_ids);
+        self.inner.cxx_pc.add_video_track(cxx_track, stream_ids);
         Ok(())
     }
 
@@ -325,13 +428,14 @@ impl<'a> PeerConnection {
         mut track: AudioTrack,
     ) -> Result<()> {
         let cxx_track = track.take_cxx()?;
-        self.cxx_pc.add_audio_track(cxx_track, stream_ids);
+        self.inner.cxx_pc.add_audio_track(cxx_track, stream_ids);
         Ok(())
     }
 
     pub async fn get_stats(&self) -> Result<PeerConnectionStats> {
         let (tx, mut rx) = channel(STATS_BUFFER_SIZE);
-        self.cxx_pc
+        self.inner
+            .cxx_pc
             .get_stats(Box::new(ArcasRustRTCStatsCollectorCallback::new(Box::new(
                 move |video_receiver_stats,
                       audio_receiver_stats,
@@ -352,12 +456,17 @@ impl<'a> PeerConnection {
 
     pub async fn add_ice_candidate(&self, candidate: ICECandidate) -> Result<()> {
         let cxx_candidate = candidate.take_cxx();
-        self.cxx_pc.add_ice_candidate(cxx_candidate);
+        self.inner.cxx_pc.add_ice_candidate(cxx_candidate);
         Ok(())
     }
 
+    /// Make the next offer restart ICE with fresh credentials.
+    pub fn restart_ice(&self) {
+        self.inner.cxx_pc.restart_ice();
+    }
+
     pub fn get_transceivers(&self) -> (Vec<VideoTransceiver>, Vec<AudioTransceiver>) {
-        let cxx_vec = self.cxx_pc.get_transceivers();
+        let cxx_vec = self.inner.cxx_pc.get_transceivers();
         let (mut video, mut audio) = (vec![], vec![]);
         cxx_vec
             .into_iter()
@@ -374,20 +483,29 @@ impl<'a> PeerConnection {
     }
 
     pub fn create_data_channel(&self, label: String, init: DataChannelInit) -> Result<DataChannel> {
+        if init.negotiated && init.id.is_none() {
+            return Err(WebRTCError::DataChannelError(format!(
+                "negotiated data channel {} requires an id",
+                label
+            )));
+        }
+
         let cxx_init = init.into();
-        let channel = self.cxx_pc.create_data_channel(label, &cxx_init);
+        let channel = self
+            .inner
+            .cxx_pc
+            .create_data_channel(label.clone(), &cxx_init);
+        if channel.is_null() {
+            return Err(WebRTCError::DataChannelError(format!(
+                "failed to create data channel {}",
+                label
+            )));
+        }
 
         Ok(DataChannel::new(channel))
     }
 }
 
-impl Drop for PeerConnection {
-    fn drop(&mut self) {
-        // Stop underlying tasks in libwebrtc from continuing to run on a deallocated object.
-        self.cxx_pc.close();
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use std::time::Duration;
@@ -399,6 +517,7 @@ mod tests {
     use crate::{
         data_channel::DataChannelSenders,
         factory::{Factory, FactoryConfig},
+        loopback::{connect_pair, LoopbackReceivers},
         passthrough_video_decoder_factory::PassthroughVideoDecoderFactory,
         raw_video_frame_producer::{GStreamerRawFrameProducer, RawFrameProducer},
         reactive_video_encoder::ReactiveVideoEncoderFactory,
@@ -424,11 +543,51 @@ mod tests {
 
         {
             pc_factory1
-                .create_peer_connection(PeerConnectionConfig::default(), ObserverSenders::default())
+                .create_peer_connection(
+                    PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]),
+                    ObserverSenders::default(),
+                )
                 .unwrap();
         }
     }
 
+    #[test]
+    async fn test_drop_clone() {
+        let factory = Factory::new();
+        let pc_factory = factory.create_peer_connection_factory().unwrap();
+
+        let (senders1, rx1) = LoopbackReceivers::create();
+        let (senders2, rx2) = LoopbackReceivers::create();
+        let pc1 = pc_factory
+            .create_peer_connection(
+                PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]),
+                senders1,
+            )
+            .unwrap();
+        let pc2 = pc_factory
+            .create_peer_connection(
+                PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]),
+                senders2,
+            )
+            .unwrap();
+        let _dc = pc1
+            .create_data_channel("test".into(), DataChannelInit::default())
+            .unwrap();
+
+        let mut pair = connect_pair(&pc1, &pc2, rx1, rx2).await.unwrap();
+        pair.wait_connected().await.unwrap();
+
+        // The loopback tasks hold clones too, they are dropped once aborted.
+        drop(pc1.clone());
+        drop(pair);
+        sleep(Duration::from_millis(100)).await;
+
+        for pc in [&pc1, &pc2].iter() {
+            assert_ne!(pc.signaling_state(), ArcasRTCSignalingState::kClosed);
+            assert_ne!(pc.connection_state(), ArcasPeerConnectionState::kClosed);
+        }
+    }
+
     #[tokio::test]
     async fn test_drops_drop_factory() {
         // Create some threads to run the peer connections.
@@ -452,17 +611,13 @@ mod tests {
         let pc_factory1 = factory1.create_peer_connection_factory().unwrap();
         let pc_factory2 = factory2.create_peer_connection_factory().unwrap();
 
-        let (ice_tx, mut pc1_ice) = channel(100);
-        let (ice_tx2, mut pc2_ice) = channel(100);
         let (dc_tx, mut dc_rx) = channel(1);
 
+        let (senders1, rx1) = LoopbackReceivers::create();
         let pc1 = pc_factory1
             .create_peer_connection(
-                PeerConnectionConfig::default(),
-                ObserverSenders {
-                    ice_candidate: Some(ice_tx),
-                    ..ObserverSenders::default()
-                },
+                PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]),
+                senders1,
             )
             .unwrap();
 
@@ -477,13 +632,13 @@ mod tests {
             )
             .unwrap();
 
+        let (senders2, rx2) = LoopbackReceivers::create();
         let pc2 = pc_factory2
             .create_peer_connection(
-                PeerConnectionConfig::default(),
+                PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]),
                 ObserverSenders {
-                    ice_candidate: Some(ice_tx2),
                     data_channel: Some(dc_tx),
-                    ..ObserverSenders::default()
+                    ..senders2
                 },
             )
             .unwrap();
@@ -503,24 +658,11 @@ mod tests {
             assert!(video_transceivers.len() == 1);
         }
 
-        let offer = pc1.create_offer().await.unwrap();
-        let remote_offer = offer.copy_to_remote().unwrap();
-        pc1.set_local_description(offer).await.unwrap();
-        pc2.set_remote_description(remote_offer).await.unwrap();
-        let answer = pc2.create_answer().await.unwrap();
-        let remote_answer = answer.copy_to_remote().unwrap();
-        pc2.set_local_description(answer).await.unwrap();
-        pc1.set_remote_description(remote_answer).await.unwrap();
-
         let codec = VideoCodec::vp9_default();
         let mut producer = GStreamerRawFrameProducer::default_pipeline(&codec).unwrap();
         let rx = producer.start().unwrap();
 
-        let pc1_candidate = pc1_ice.recv().await.unwrap();
-        let pc2_candidate = pc2_ice.recv().await.unwrap();
-
-        pc1.add_ice_candidate(pc2_candidate).await.unwrap();
-        pc2.add_ice_candidate(pc1_candidate).await.unwrap();
+        let _pair = connect_pair(&pc1, &pc2, rx1, rx2).await.unwrap();
 
         // NOTE: Here we use a thread this is because the rx is blocking and it will jam up tokio
         // if we mix those apis with crossbeam.
@@ -560,19 +702,15 @@ mod tests {
         let pc_factory1 = factory1.create_peer_connection_factory().unwrap();
         let pc_factory2 = factory2.create_peer_connection_factory().unwrap();
 
-        let (ice_tx, mut pc1_ice) = channel(100);
-        let (ice_tx2, mut pc2_ice) = channel(100);
         let (dc_tx, mut dc_rx) = channel(1);
         let (dc1_msg_tx, mut dc1_msg_rx) = channel(1);
         let (dc1_state_tx, mut dc1_state_rx) = channel(100);
 
+        let (senders1, rx1) = LoopbackReceivers::create();
         let pc1 = pc_factory1
             .create_peer_connection(
-                PeerConnectionConfig::default(),
-                ObserverSenders {
-                    ice_candidate: Some(ice_tx),
-                    ..ObserverSenders::default()
-                },
+                PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]),
+                senders1,
             )
             .unwrap();
 
@@ -595,31 +733,18 @@ mod tests {
             ..DataChannelSenders::default()
         });
 
+        let (senders2, rx2) = LoopbackReceivers::create();
         let pc2 = pc_factory2
             .create_peer_connection(
-                PeerConnectionConfig::default(),
+                PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]),
                 ObserverSenders {
-                    ice_candidate: Some(ice_tx2),
                     data_channel: Some(dc_tx),
-                    ..ObserverSenders::default()
+                    ..senders2
                 },
             )
             .unwrap();
 
-        let offer = pc1.create_offer().await.unwrap();
-        let remote_offer = offer.copy_to_remote().unwrap();
-        pc1.set_local_description(offer).await.unwrap();
-        pc2.set_remote_description(remote_offer).await.unwrap();
-        let answer = pc2.create_answer().await.unwrap();
-        let remote_answer = answer.copy_to_remote().unwrap();
-        pc2.set_local_description(answer).await.unwrap();
-        pc1.set_remote_description(remote_answer).await.unwrap();
-
-        let pc1_candidate = pc1_ice.recv().await.unwrap();
-        let pc2_candidate = pc2_ice.recv().await.unwrap();
-
-        pc1.add_ice_candidate(pc2_candidate).await.unwrap();
-        pc2.add_ice_candidate(pc1_candidate).await.unwrap();
+        let _pair = connect_pair(&pc1, &pc2, rx1, rx2).await.unwrap();
 
         dc1_state_rx.recv().await.unwrap();
         assert_eq!(dc.state(), ArcasCxxDataState::kOpen);
@@ -627,7 +752,7 @@ mod tests {
         let mut dc2 = dc_rx.recv().await.unwrap();
 
         assert_eq!(dc2.label(), "test");
-        dc2.send(b"hello", true);
+        dc2.send(b"hello", true).await.unwrap();
         let msg = dc1_msg_rx.recv().await.unwrap();
         assert_eq!(msg.as_slice(), b"hello");
 
@@ -642,7 +767,10 @@ mod tests {
     async fn test_create_peer_connection_with_factory_config() {
         use libwebrtc_sys::video_decoding::VideoDecoderFactoryImpl;
         use libwebrtc_sys::video_encoding::VideoEncoderFactoryImpl;
-        let (_, enc_tx) = video_encoder_pool::VideoEncoderPool::create().unwrap();
+        let (_pool, enc_tx) = video_encoder_pool::VideoEncoderPool::create(
+            video_encoder_pool::default_raw_frame_producer,
+        )
+        .unwrap();
         let video_encoder_factory: Option<Box<dyn VideoEncoderFactoryImpl>> = Some(Box::new(
             ReactiveVideoEncoderFactory::create(enc_tx).unwrap(),
         ));
@@ -656,7 +784,47 @@ mod tests {
         let api = Factory::new();
         let pc_factory = api.create_factory_with_config(config).unwrap();
         let _ = pc_factory
-            .create_peer_connection(PeerConnectionConfig::default(), ObserverSenders::default())
+            .create_peer_connection(
+                PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]),
+                ObserverSenders::default(),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    async fn test_peer_connection_certificate() {
+        use crate::{
+            certificate::{generate_certificate, KeyParams, KeyType},
+            sdp_model::{Sdp, SdpAttributes},
+        };
+        use std::convert::TryFrom;
+
+        let certificate =
+            generate_certificate(KeyParams::new(KeyType::KT_ECDSA), Duration::from_secs(3600))
+                .await
+                .unwrap();
+        let api = Factory::new();
+        let pc_factory = api.create_peer_connection_factory().unwrap();
+        let pc = pc_factory
+            .create_peer_connection(
+                PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![])
+                    .with_certificate(certificate.clone()),
+                ObserverSenders::default(),
+            )
+            .unwrap();
+        let _dc = pc
+            .create_data_channel("test".into(), Default::default())
             .unwrap();
+
+        let offer = pc.create_offer().await.unwrap();
+        let sdp = Sdp::try_from(&offer).unwrap();
+        let fingerprint = sdp.media[0]
+            .fingerprint()
+            .or_else(|| sdp.fingerprint())
+            .unwrap();
+        assert_eq!(
+            format!("{} {}", fingerprint.algorithm, fingerprint.value),
+            certificate.get_fingerprint()
+        );
     }
 }
@@ -49,6 +49,7 @@ pub struct ObserverSenders {
     pub ice_candidate: Option<Sender<ICECandidate>>,
     pub data_channel: Option<Sender<DataChannel>>,
     pub video_track: Option<Sender<VideoTransceiver>>,
+    pub renegotiation_needed: Option<Sender<()>>,
 }
 
 impl PeerConnectionObserverImpl for ObserverSenders {
@@ -62,7 +63,9 @@ impl PeerConnectionObserverImpl for ObserverSenders {
         send_event!(self.data_channel, DataChannel::new(data_channel));
     }
 
-    fn on_renegotiation_needed(&self) {}
+    fn on_renegotiation_needed(&self) {
+        send_event!(self.renegotiation_needed, ());
+    }
 
     fn on_renegotiation_needed_event(&self, _event: u32) {}
 
@@ -3,7 +3,8 @@ use log::debug;
 
 use crate::{
     error::{Result, WebRTCError},
-    now, ok_or_return,
+    media_clock::{MediaClock, VIDEO_CLOCK_RATE_HZ},
+    ok_or_return,
     video_codec::VideoCodec,
     video_frame::RawVideoFrame,
 };
@@ -24,6 +25,7 @@ pub struct GStreamerRawFrameProducer {
     pub width: i32,
     pub height: i32,
     pub fps: u32,
+    clock: MediaClock,
     cancel_rx: Option<Receiver<()>>,
     cancel_tx: Sender<()>,
 }
@@ -50,8 +52,16 @@ impl GStreamerRawFrameProducer {
             pipeline,
             height,
             fps,
+            clock: MediaClock::new()?,
         })
     }
+
+    /// Timestamp frames with a shared clock so they line up with other
+    /// producers.  GStreamer still paces the frames.
+    pub fn with_clock(mut self, clock: MediaClock) -> Self {
+        self.clock = clock;
+        self
+    }
 }
 
 impl RawFrameProducer for GStreamerRawFrameProducer {
@@ -84,19 +94,20 @@ impl RawFrameProducer for GStreamerRawFrameProducer {
 
         let width = self.width;
         let height = self.height;
+        let clock = self.clock.clone();
 
         std::thread::spawn(move || {
             loop {
                 select! {
                     recv(rx) -> buf_result => {
                         let buf = ok_or_return!(buf_result);
-                        let now_ms = ok_or_return!(now::now());
+                        let timestamp = clock.capture_timestamp(VIDEO_CLOCK_RATE_HZ);
 
                         // XXX: Creating a raw frame should never fail...
-                        let raw_frame = ok_or_return!(RawVideoFrame::create(
+                        let raw_frame = ok_or_return!(RawVideoFrame::create_with_timestamp(
                             width,
                             height,
-                            now_ms,
+                            timestamp,
                             buf.into()));
 
                         ok_or_return!(result_tx.send(raw_frame));
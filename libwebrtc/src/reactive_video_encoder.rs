@@ -1,6 +1,6 @@
 use std::{
     cmp::max,
-    collections::hash_map::DefaultHasher,
+    collections::{hash_map::DefaultHasher, HashMap},
     hash::{Hash, Hasher},
 };
 
@@ -30,40 +30,57 @@ pub const DEFAULT_ENCODING: &str = "VP8";
 pub struct ReactiveVideoEncoderFactory {
     encoder_pool_request_tx: Sender<VideoEncoderPoolRequest>,
     builtin_factory: UniquePtr<ffi::ArcasVideoEncoderFactoryWrapper>,
-    // This is for the purpose of getting the encoder info only...
-    encoder: UniquePtr<ffi::ArcasVideoEncoderWrapper>,
+    // Info of the builtin encoder for each codec name, reported by the
+    // reactive encoders of that codec.
+    encoder_infos: HashMap<String, ArcasVideoEncoderInfo>,
 }
 
 impl ReactiveVideoEncoderFactory {
     pub fn create(encoder_pool_request_tx: Sender<VideoEncoderPoolRequest>) -> Result<Self> {
         let builtin_factory = create_arcas_video_encoder_factory_from_builtin();
-        let formats = builtin_factory.get_supported_formats();
-        let format = formats
-            .iter()
-            .find(|value| value.get_name() == DEFAULT_ENCODING)
-            .unwrap();
-        let wrapper = Box::new(libwebrtc_sys::EncodedImageCallbackHandler::new(
-            Box::new(move |_drop_reason| {}),
-            Box::new(move |_encoded_image, _codec_info| {}),
-        ));
-        let encoder = builtin_factory.create_encoder(format, wrapper);
+        let mut encoder_infos = HashMap::new();
+        for format in builtin_factory.get_supported_formats().iter() {
+            let name = format.get_name();
+            if encoder_infos.contains_key(&name) {
+                continue;
+            }
+            let wrapper = Box::new(libwebrtc_sys::EncodedImageCallbackHandler::new(
+                Box::new(move |_drop_reason| {}),
+                Box::new(move |_encoded_image, _codec_info| {}),
+            ));
+            let encoder = builtin_factory.create_encoder(format, wrapper);
+            if !encoder.is_null() {
+                encoder_infos.insert(name, encoder.get_encoder_info());
+            }
+        }
+        if !encoder_infos.contains_key(DEFAULT_ENCODING) {
+            return Err(WebRTCError::VideoCodecUnsupportedType(
+                DEFAULT_ENCODING.into(),
+            ));
+        }
         Ok(Self {
             encoder_pool_request_tx,
             builtin_factory,
-            encoder,
+            encoder_infos,
         })
     }
+
+    /// Info of the builtin encoder for `format`'s codec.
+    fn encoder_info(&self, format: &ffi::ArcasCxxSdpVideoFormat) -> ArcasVideoEncoderInfo {
+        let name = ffi::sdp_video_format_get_name(format).to_string_lossy();
+        self.encoder_infos
+            .get(name.as_ref())
+            .or_else(|| self.encoder_infos.get(DEFAULT_ENCODING))
+            .cloned()
+            .unwrap()
+    }
 }
 
 impl VideoEncoderFactoryImpl for ReactiveVideoEncoderFactory {
     fn get_supported_formats(&self) -> UniquePtr<cxx::CxxVector<ffi::ArcasCxxSdpVideoFormat>> {
-        let formats = self.builtin_factory.get_supported_formats();
-        let format = formats
-            .iter()
-            .find(|format| format.get_name() == DEFAULT_ENCODING)
-            .unwrap();
-
-        format.cxx_format_list()
+        // Controllers create their shared encoder for whichever codec gets
+        // negotiated so advertise everything the builtin factory supports.
+        self.builtin_factory.cxx_get_supported_formats()
     }
 
     fn get_implementations(&self) -> UniquePtr<cxx::CxxVector<ffi::ArcasCxxSdpVideoFormat>> {
@@ -84,10 +101,14 @@ impl VideoEncoderFactoryImpl for ReactiveVideoEncoderFactory {
         format: &ffi::ArcasCxxSdpVideoFormat,
         _scalability_mode: Vec<String>,
     ) -> ffi::ArcasVideoEncoderFactoryCodecSupport {
-        // for now we hardcode to VP9 support for simplicity
-        let name = ffi::sdp_video_format_get_name(format);
-        info!("query codec support = {:?}", name.to_str());
-        if name == DEFAULT_ENCODING {
+        let name = ffi::sdp_video_format_get_name(format).to_string_lossy();
+        info!("query codec support = {:?}", name);
+        let supported = self
+            .builtin_factory
+            .get_supported_formats()
+            .iter()
+            .any(|format| format.get_name() == name);
+        if supported {
             return ffi::ArcasVideoEncoderFactoryCodecSupport {
                 is_supported: true,
                 is_power_efficient: true,
@@ -100,13 +121,10 @@ impl VideoEncoderFactoryImpl for ReactiveVideoEncoderFactory {
         }
     }
 
-    fn create_video_encoder(
-        &self,
-        _format: &ffi::ArcasCxxSdpVideoFormat,
-    ) -> Box<VideoEncoderProxy> {
+    fn create_video_encoder(&self, format: &ffi::ArcasCxxSdpVideoFormat) -> Box<VideoEncoderProxy> {
         let request_tx = self.encoder_pool_request_tx.clone();
         Box::new(VideoEncoderProxy::new(Box::new(ReactiveVideoEncoder::new(
-            self.encoder.get_encoder_info(),
+            self.encoder_info(format),
             request_tx,
         ))))
     }
@@ -124,6 +142,7 @@ pub struct ReactiveVideoEncoder {
     sender: Sender<VideoEncoderPoolRequest>,
     callback: Option<UniquePtr<ArcasEncodedImageCallback>>,
     sent: bool,
+    last_bitrate_bps: u32,
     controller_id: Option<String>,
     info: ArcasVideoEncoderInfo,
 }
@@ -139,6 +158,7 @@ impl ReactiveVideoEncoder {
             number_of_cores: None,
             max_payload_size: None,
             sent: false,
+            last_bitrate_bps: 0,
             callback: None,
         }
     }
@@ -170,10 +190,10 @@ impl VideoEncoderImpl for ReactiveVideoEncoder {
     }
 
     fn release(&mut self) -> i32 {
-        if let Some(ref controller_id) = self.controller_id {
+        if let Some(controller_id) = self.controller_id.take() {
             match self.sender.send(VideoEncoderPoolRequest::Release {
                 id: self.id.clone(),
-                controller_id: controller_id.clone(),
+                controller_id,
             }) {
                 Ok(_) => {}
                 Err(e) => {
@@ -181,25 +201,68 @@ impl VideoEncoderImpl for ReactiveVideoEncoder {
                 }
             }
         };
+        // The controller may be torn down once idle, so the next rates after
+        // init_encode subscribe again rather than update a missing one.
+        self.sent = false;
+        self.last_bitrate_bps = 0;
         0
     }
 
     unsafe fn encode(
         &mut self,
         _frame: &ffi::ArcasCxxVideoFrame,
-        _frame_types: *const cxx::CxxVector<ffi::ArcasCxxVideoFrameType>,
+        frame_types: *const cxx::CxxVector<ffi::ArcasCxxVideoFrameType>,
     ) -> i32 {
+        // Frames come from the shared encoder, all we forward are keyframe
+        // requests so the controller can rate limit them across subscribers.
+        let keyframe_requested = frame_types
+            .as_ref()
+            .map(|frame_types| {
+                frame_types
+                    .iter()
+                    .any(|frame_type| *frame_type == ffi::ArcasCxxVideoFrameType::kVideoFrameKey)
+            })
+            .unwrap_or(false);
+
+        if keyframe_requested {
+            if let Some(ref controller_id) = self.controller_id {
+                if let Err(err) = self.sender.send(VideoEncoderPoolRequest::KeyFrame {
+                    controller_id: controller_id.clone(),
+                    id: self.id.clone(),
+                }) {
+                    error!("Failed to send keyframe request: {:?}", err.to_string());
+                }
+            }
+        }
         0
     }
 
     fn set_rates(&mut self, rate: UniquePtr<ArcasVideoEncoderRateControlParameters>) {
+        let bitrate_bps = rate.get_bitrate().get_sum_bps();
         if self.sent {
-            // TODO: Implement some rate updating based on individual needs of the encoders.
-            // A more realistic (but generates less load) implementation could switch between
-            // different encoders which have varying rates.
+            // Later estimates move this encoder between the bitrate tiers of
+            // its controller.
+            if self.last_bitrate_bps == bitrate_bps {
+                return;
+            }
+            self.last_bitrate_bps = bitrate_bps;
+            if let Some(ref controller_id) = self.controller_id {
+                if let Err(err) = self.sender.send(VideoEncoderPoolRequest::SetRates {
+                    controller_id: controller_id.clone(),
+                    id: self.id.clone(),
+                    bitrate_bps,
+                }) {
+                    error!("Failed to send rate update: {:?}", err.to_string());
+                }
+            }
+            return;
+        }
+        if self.codec.is_none() || self.callback.is_none() {
+            error!("set_rates called before init_encode and register_encode_complete_callback");
             return;
         }
         self.sent = true;
+        self.last_bitrate_bps = bitrate_bps;
 
         let codec = ok_or_return!(self
             .codec
@@ -248,24 +311,87 @@ mod tests {
 
     use tokio::{sync::mpsc::channel, time::sleep};
 
+    use libwebrtc_sys::{
+        ffi,
+        video_encoding::{VideoEncoderFactoryImpl, VideoEncoderImpl},
+    };
+
+    use super::{ReactiveVideoEncoder, ReactiveVideoEncoderFactory};
     use crate::{
         factory::Factory,
-        peer_connection::PeerConnectionConfig,
+        peer_connection::{PeerConnectionConfig, SDPSemantic},
         peer_connection_observer::ObserverSenders,
         raw_video_frame_producer::{GStreamerRawFrameProducer, RawFrameProducer},
         video_codec::VideoCodec,
-        video_encoder_pool::VideoEncoderPool,
+        video_encoder_pool::{self, VideoEncoderPool},
         video_track_source::VideoTrackSource,
     };
 
+    #[test]
+    fn test_encoder_info_per_codec() {
+        let (pool_tx, _pool_rx) = unbounded();
+        let factory = ReactiveVideoEncoderFactory::create(pool_tx).unwrap();
+        let formats = factory.get_supported_formats();
+        let info = |name: &str| {
+            let format = formats
+                .iter()
+                .find(|format| ffi::sdp_video_format_get_name(format).to_string_lossy() == name)
+                .unwrap();
+            factory.encoder_info(format)
+        };
+
+        // Only the VP8 encoder does simulcast itself.
+        assert!(info("VP8").supports_simulcast);
+        assert!(!info("VP9").supports_simulcast);
+    }
+
+    #[test]
+    fn test_release_resubscribes() {
+        let (pool_tx, pool_rx) = unbounded();
+        let factory = ReactiveVideoEncoderFactory::create(pool_tx.clone()).unwrap();
+        let formats = factory.get_supported_formats();
+        let info = factory.encoder_info(formats.iter().next().unwrap());
+        let mut encoder = ReactiveVideoEncoder::new(info, pool_tx);
+
+        // Subscribed to a controller, as after the first set_rates.
+        encoder.sent = true;
+        encoder.last_bitrate_bps = 500_000;
+        encoder.controller_id = Some("controller".into());
+
+        encoder.release();
+        match pool_rx.try_recv().unwrap() {
+            video_encoder_pool::VideoEncoderPoolRequest::Release { controller_id, id } => {
+                assert_eq!(controller_id, "controller");
+                assert_eq!(id, encoder.id);
+            }
+            _ => panic!("expected a release request"),
+        }
+        // The next set_rates creates or joins a controller again instead of
+        // updating the released one.
+        assert!(!encoder.sent);
+        assert_eq!(encoder.last_bitrate_bps, 0);
+        assert!(encoder.controller_id.is_none());
+
+        // Released encoders have nothing more to release.
+        encoder.release();
+        assert!(pool_rx.try_recv().is_err());
+    }
+
     #[tokio::test]
     async fn test_reactive_video_encoder_factory() {
         pretty_env_logger::init();
         let api_factory = Factory::new();
         let api_factory2 = Factory::new();
-        let config = PeerConnectionConfig::default();
-        let config2 = PeerConnectionConfig::default();
-        let (_encode_pool, pool_tx) = VideoEncoderPool::create().unwrap();
+        let config = PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]);
+        let config2 = PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]);
+        let (negotiated_tx, negotiated_rx) = unbounded::<String>();
+        let (encode_pool, pool_tx) = VideoEncoderPool::create(move |controller_id, description| {
+            negotiated_tx
+                .send(description.codec_name().to_string())
+                .unwrap();
+            video_encoder_pool::default_raw_frame_producer(controller_id, description)
+        })
+        .unwrap();
 
         let (ice_tx, mut ice_rx) = channel(100);
         let (ice_tx2, mut ice_rx2) = channel(100);
@@ -354,5 +480,11 @@ mod tests {
             }
         });
         test_done_rx.recv().await.unwrap();
+        assert_eq!(negotiated_rx.try_recv().unwrap(), "VP8");
+
+        let metrics = encode_pool.metrics();
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].subscribers, 1);
+        assert!(metrics[0].frames_encoded > 0);
     }
 }
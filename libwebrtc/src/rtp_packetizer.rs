@@ -0,0 +1,1091 @@
+//! RTP packetization of encoded frames for VP8 (RFC 7741), VP9
+//! (draft-ietf-payload-vp9), H.264 (RFC 6184, packetization mode 1) and
+//! Opus (RFC 7587), for sending media over a `TransportController`.
+
+use std::{convert::TryInto, mem, time::Duration};
+
+use crate::{
+    error::{Result, WebRTCError},
+    sctp::{secure_random_u64, XorShift},
+};
+
+pub const RTP_HEADER_SIZE: usize = 12;
+/// Leaves room for SRTP, header extensions and tunnel overhead in a 1500
+/// byte MTU.
+pub const DEFAULT_MAX_PACKET_SIZE: usize = 1200;
+
+const H264_START_CODE: [u8; 4] = [0, 0, 0, 1];
+const H264_STAP_A: u8 = 24;
+const H264_FU_A: u8 = 28;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RtpHeader {
+    pub marker: bool,
+    pub payload_type: u8,
+    pub sequence_number: u16,
+    pub timestamp: u32,
+    pub ssrc: u32,
+}
+
+impl RtpHeader {
+    /// Parse a packet, returning its header and payload.  CSRCs, header
+    /// extensions and padding are skipped.
+    pub fn parse(packet: &[u8]) -> Result<(Self, &[u8])> {
+        if packet.len() < RTP_HEADER_SIZE || packet[0] >> 6 != 2 {
+            return Err(rtp_error("not an RTP packet"));
+        }
+        let header = Self {
+            marker: packet[1] & 0x80 != 0,
+            payload_type: packet[1] & 0x7F,
+            sequence_number: u16::from_be_bytes([packet[2], packet[3]]),
+            timestamp: u32::from_be_bytes(packet[4..8].try_into().unwrap()),
+            ssrc: u32::from_be_bytes(packet[8..12].try_into().unwrap()),
+        };
+
+        let mut offset = RTP_HEADER_SIZE + 4 * (packet[0] & 0x0F) as usize;
+        if packet[0] & 0x10 != 0 {
+            let extension = packet
+                .get(offset..offset + 4)
+                .ok_or_else(|| rtp_error("truncated header extension"))?;
+            offset += 4 + 4 * u16::from_be_bytes([extension[2], extension[3]]) as usize;
+        }
+        let mut end = packet.len();
+        if packet[0] & 0x20 != 0 {
+            end = end.saturating_sub(packet[end - 1] as usize);
+        }
+        if offset > end {
+            return Err(rtp_error("truncated packet"));
+        }
+        Ok((header, &packet[offset..end]))
+    }
+
+    pub fn write(&self, payload: &[u8]) -> Vec<u8> {
+        let mut packet = Vec::with_capacity(RTP_HEADER_SIZE + payload.len());
+        packet.push(0x80);
+        packet.push(((self.marker as u8) << 7) | (self.payload_type & 0x7F));
+        packet.extend_from_slice(&self.sequence_number.to_be_bytes());
+        packet.extend_from_slice(&self.timestamp.to_be_bytes());
+        packet.extend_from_slice(&self.ssrc.to_be_bytes());
+        packet.extend_from_slice(payload);
+        packet
+    }
+}
+
+/// Splits a frame into RTP payloads of a payload format.
+pub trait Packetizer: Send {
+    /// Payloads, descriptors included, of at most `max_payload_size` bytes.
+    fn packetize(&mut self, frame: &[u8], max_payload_size: usize) -> Result<Vec<Vec<u8>>>;
+
+    /// True if the marker bit flags the last packet of a frame, as for
+    /// video.  Otherwise every packet is a whole frame.
+    fn marks_frame_end(&self) -> bool {
+        true
+    }
+}
+
+/// Reassembles a frame from the RTP payloads of a payload format.
+pub trait Depacketizer: Send {
+    /// Append what `payload` carries to the frame being assembled, failing
+    /// if the payload is malformed or doesn't continue `frame`.
+    fn depacketize(&mut self, payload: &[u8], frame: &mut Vec<u8>) -> Result<()>;
+
+    /// See `Packetizer::marks_frame_end`.
+    fn marks_frame_end(&self) -> bool {
+        true
+    }
+}
+
+/// Packetizes frames of one stream, filling in sequence numbers, timestamps
+/// and marker bits.
+pub struct RtpPacketizer {
+    packetizer: Box<dyn Packetizer>,
+    ssrc: u32,
+    payload_type: u8,
+    clock_rate: u32,
+    sequence_number: u16,
+    timestamp_offset: u32,
+    max_packet_size: usize,
+}
+
+impl RtpPacketizer {
+    /// Sequence numbers and timestamps start at unpredictable values as RFC
+    /// 3550 recommends, so SRTP known-plaintext attacks are harder.
+    pub fn new(
+        ssrc: u32,
+        payload_type: u8,
+        clock_rate: u32,
+        packetizer: Box<dyn Packetizer>,
+    ) -> Self {
+        let random = secure_random_u64();
+        Self {
+            packetizer,
+            ssrc,
+            payload_type,
+            clock_rate,
+            sequence_number: random as u16,
+            timestamp_offset: (random >> 32) as u32,
+            max_packet_size: DEFAULT_MAX_PACKET_SIZE,
+        }
+    }
+
+    pub fn set_max_packet_size(&mut self, max_packet_size: usize) {
+        self.max_packet_size = max_packet_size;
+    }
+
+    /// Sequence number of the next packet.
+    pub fn sequence_number(&self) -> u16 {
+        self.sequence_number
+    }
+
+    /// RTP packets carrying `frame`, its timestamp derived from
+    /// `capture_time` relative to the start of the stream.
+    pub fn packetize(&mut self, frame: &[u8], capture_time: Duration) -> Result<Vec<Vec<u8>>> {
+        let ticks = capture_time.as_micros() * self.clock_rate as u128 / 1_000_000;
+        let timestamp = self.timestamp_offset.wrapping_add(ticks as u32);
+        let payloads = self
+            .packetizer
+            .packetize(frame, self.max_packet_size.saturating_sub(RTP_HEADER_SIZE))?;
+
+        let marks_frame_end = self.packetizer.marks_frame_end();
+        let last = payloads.len().saturating_sub(1);
+        Ok(payloads
+            .iter()
+            .enumerate()
+            .map(|(i, payload)| {
+                let header = RtpHeader {
+                    marker: marks_frame_end && i == last,
+                    payload_type: self.payload_type,
+                    sequence_number: self.sequence_number,
+                    timestamp,
+                    ssrc: self.ssrc,
+                };
+                self.sequence_number = self.sequence_number.wrapping_add(1);
+                header.write(payload)
+            })
+            .collect())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RtpFrame {
+    pub timestamp: u32,
+    pub data: Vec<u8>,
+}
+
+/// Reassembles frames from the packets of one stream.
+///
+/// Packets must arrive in order, as from a jitter buffer.  A gap in sequence
+/// numbers drops the frame it falls in.
+pub struct RtpDepacketizer {
+    depacketizer: Box<dyn Depacketizer>,
+    frame: Vec<u8>,
+    timestamp: Option<u32>,
+    next_sequence_number: Option<u16>,
+    // The last packet received ended a frame.
+    frame_ended: bool,
+    // Skip the rest of a frame that can't be completed.
+    discarding: bool,
+}
+
+impl RtpDepacketizer {
+    pub fn new(depacketizer: Box<dyn Depacketizer>) -> Self {
+        Self {
+            depacketizer,
+            frame: vec![],
+            timestamp: None,
+            next_sequence_number: None,
+            frame_ended: true,
+            discarding: false,
+        }
+    }
+
+    /// Add a packet, returning the frame it completes.
+    pub fn push(&mut self, packet: &[u8]) -> Result<Option<RtpFrame>> {
+        let (header, payload) = RtpHeader::parse(packet)?;
+        let in_sequence = self
+            .next_sequence_number
+            .map_or(true, |next| next == header.sequence_number);
+        self.next_sequence_number = Some(header.sequence_number.wrapping_add(1));
+        if payload.is_empty() {
+            // Padding only, as sent to probe bandwidth.  It takes a sequence
+            // number but is part of no frame, so it doesn't end one.
+            if !in_sequence {
+                // The packets lost before it may have started a frame.
+                self.frame_ended = false;
+                self.discarding = true;
+            }
+            return Ok(None);
+        }
+        // A new timestamp only starts a frame if no packet was lost since the
+        // previous frame ended, otherwise the packets starting it were.
+        let frame_start = !self.depacketizer.marks_frame_end() || (in_sequence && self.frame_ended);
+        self.frame_ended = header.marker;
+
+        if self.timestamp != Some(header.timestamp) {
+            // Whatever was left of the previous frame is incomplete.
+            self.frame.clear();
+            self.timestamp = Some(header.timestamp);
+            self.discarding = !frame_start;
+        } else if !in_sequence {
+            self.discarding = true;
+        }
+        if self.discarding {
+            return Ok(None);
+        }
+
+        if let Err(err) = self.depacketizer.depacketize(payload, &mut self.frame) {
+            self.discarding = true;
+            return Err(err);
+        }
+        if self.depacketizer.marks_frame_end() && !header.marker {
+            return Ok(None);
+        }
+        // Later packets with this timestamp are duplicates.
+        self.discarding = true;
+        Ok(Some(RtpFrame {
+            timestamp: header.timestamp,
+            data: mem::take(&mut self.frame),
+        }))
+    }
+}
+
+/// VP8 with a 15 bit picture id in every payload descriptor.
+pub struct Vp8Packetizer {
+    picture_id: u16,
+}
+
+impl Vp8Packetizer {
+    pub fn new() -> Self {
+        Self {
+            picture_id: XorShift::new().next_u64() as u16 & 0x7FFF,
+        }
+    }
+}
+
+impl Default for Vp8Packetizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Packetizer for Vp8Packetizer {
+    fn packetize(&mut self, frame: &[u8], max_payload_size: usize) -> Result<Vec<Vec<u8>>> {
+        const DESCRIPTOR_SIZE: usize = 4;
+        check_sizes(frame, max_payload_size, DESCRIPTOR_SIZE)?;
+        let picture_id = self.picture_id;
+        self.picture_id = (self.picture_id + 1) & 0x7FFF;
+
+        Ok(frame
+            .chunks(max_payload_size - DESCRIPTOR_SIZE)
+            .enumerate()
+            .map(|(i, chunk)| {
+                // X, S on the first packet of the only partition, then I and
+                // M with the picture id.
+                let start = if i == 0 { 0x10 } else { 0 };
+                let mut payload = vec![
+                    0x80 | start,
+                    0x80,
+                    0x80 | (picture_id >> 8) as u8,
+                    picture_id as u8,
+                ];
+                payload.extend_from_slice(chunk);
+                payload
+            })
+            .collect())
+    }
+}
+
+#[derive(Default)]
+pub struct Vp8Depacketizer;
+
+impl Depacketizer for Vp8Depacketizer {
+    fn depacketize(&mut self, payload: &[u8], frame: &mut Vec<u8>) -> Result<()> {
+        let descriptor = *payload
+            .first()
+            .ok_or_else(|| rtp_error("empty VP8 payload"))?;
+        let mut offset = 1;
+        if descriptor & 0x80 != 0 {
+            let extension = byte_at(payload, offset)?;
+            offset += 1;
+            if extension & 0x80 != 0 {
+                offset += if byte_at(payload, offset)? & 0x80 != 0 {
+                    2
+                } else {
+                    1
+                };
+            }
+            if extension & 0x40 != 0 {
+                offset += 1;
+            }
+            if extension & 0x30 != 0 {
+                offset += 1;
+            }
+        }
+        if offset >= payload.len() {
+            return Err(rtp_error("truncated VP8 payload"));
+        }
+
+        let starts_frame = descriptor & 0x10 != 0 && descriptor & 0x07 == 0;
+        if frame.is_empty() && !starts_frame {
+            return Err(rtp_error("VP8 frame is missing its start"));
+        }
+        frame.extend_from_slice(&payload[offset..]);
+        Ok(())
+    }
+}
+
+/// VP9 in non-flexible mode for streams with a single spatial and temporal
+/// layer, with descriptors as libwebrtc's `RtpPacketizerVp9` writes them: a
+/// 15 bit picture id, layer indices and TL0PICIDX in every packet and a
+/// scalability structure in the first packet of key frames.  Receivers
+/// drop non-flexible frames without TL0PICIDX.
+pub struct Vp9Packetizer {
+    picture_id: u16,
+    tl0_pic_idx: u8,
+}
+
+impl Vp9Packetizer {
+    pub fn new() -> Self {
+        let mut random = XorShift::new();
+        Self {
+            picture_id: random.next_u64() as u16 & 0x7FFF,
+            tl0_pic_idx: random.next_u64() as u8,
+        }
+    }
+
+    /// Scalability structure of one layer and a picture group of one frame
+    /// referencing the previous one.
+    fn scalability_structure(frame: &[u8]) -> Vec<u8> {
+        // N_S = 0, G.
+        let mut structure = vec![0x08];
+        if let Some((width, height)) = vp9_keyframe_size(frame) {
+            // Y, resolutions present.
+            structure[0] |= 0x10;
+            structure.extend_from_slice(&width.to_be_bytes());
+            structure.extend_from_slice(&height.to_be_bytes());
+        }
+        // N_G = 1, TID = 0, U = 0, R = 1, P_DIFF = 1.
+        structure.extend_from_slice(&[1, 0x04, 1]);
+        structure
+    }
+}
+
+impl Default for Vp9Packetizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Packetizer for Vp9Packetizer {
+    fn packetize(&mut self, frame: &[u8], max_payload_size: usize) -> Result<Vec<Vec<u8>>> {
+        const DESCRIPTOR_SIZE: usize = 5;
+        let keyframe = vp9_is_keyframe(frame);
+        let structure = if keyframe {
+            Self::scalability_structure(frame)
+        } else {
+            vec![]
+        };
+        check_sizes(frame, max_payload_size, DESCRIPTOR_SIZE + structure.len())?;
+        let picture_id = self.picture_id;
+        self.picture_id = (self.picture_id + 1) & 0x7FFF;
+        // Every frame is in temporal layer 0.
+        let tl0_pic_idx = self.tl0_pic_idx;
+        self.tl0_pic_idx = self.tl0_pic_idx.wrapping_add(1);
+        // I, picture id present, L, layer indices present, and P,
+        // inter-picture predicted.
+        let flags = 0x80 | 0x20 | if keyframe { 0 } else { 0x40 };
+
+        let mut payloads: Vec<Vec<u8>> = vec![];
+        let mut remaining = frame;
+        while !remaining.is_empty() {
+            let first = payloads.is_empty();
+            let descriptor_size = DESCRIPTOR_SIZE + if first { structure.len() } else { 0 };
+            let (chunk, rest) =
+                remaining.split_at(remaining.len().min(max_payload_size - descriptor_size));
+            remaining = rest;
+
+            // B and V on the first packet.
+            let first_flags = if first {
+                0x08 | if keyframe { 0x02 } else { 0 }
+            } else {
+                0
+            };
+            let mut payload = vec![
+                flags | first_flags,
+                0x80 | (picture_id >> 8) as u8,
+                picture_id as u8,
+                // TID = 0, U = 0, SID = 0, D = 0.
+                0,
+                tl0_pic_idx,
+            ];
+            if first {
+                payload.extend_from_slice(&structure);
+            }
+            payload.extend_from_slice(chunk);
+            payloads.push(payload);
+        }
+        // E on the last packet.
+        payloads.last_mut().unwrap()[0] |= 0x04;
+        Ok(payloads)
+    }
+}
+
+#[derive(Default)]
+pub struct Vp9Depacketizer;
+
+impl Depacketizer for Vp9Depacketizer {
+    fn depacketize(&mut self, payload: &[u8], frame: &mut Vec<u8>) -> Result<()> {
+        let descriptor = *payload
+            .first()
+            .ok_or_else(|| rtp_error("empty VP9 payload"))?;
+        let flexible = descriptor & 0x10 != 0;
+        let mut offset = 1;
+        if descriptor & 0x80 != 0 {
+            offset += if byte_at(payload, offset)? & 0x80 != 0 {
+                2
+            } else {
+                1
+            };
+        }
+        if descriptor & 0x20 != 0 {
+            // Layer indices, followed by TL0PICIDX in non-flexible mode.
+            offset += if flexible { 1 } else { 2 };
+        }
+        if flexible && descriptor & 0x40 != 0 {
+            // Reference indices, N set on all but the last.
+            while byte_at(payload, offset)? & 0x01 != 0 {
+                offset += 1;
+            }
+            offset += 1;
+        }
+        if descriptor & 0x02 != 0 {
+            let structure = byte_at(payload, offset)?;
+            offset += 1;
+            let spatial_layers = (structure >> 5) as usize + 1;
+            if structure & 0x10 != 0 {
+                offset += 4 * spatial_layers;
+            }
+            if structure & 0x08 != 0 {
+                let pictures = byte_at(payload, offset)?;
+                offset += 1;
+                for _ in 0..pictures {
+                    let references = (byte_at(payload, offset)? >> 2) & 0x03;
+                    offset += 1 + references as usize;
+                }
+            }
+        }
+        if offset >= payload.len() {
+            return Err(rtp_error("truncated VP9 payload"));
+        }
+
+        if frame.is_empty() && descriptor & 0x08 == 0 {
+            return Err(rtp_error("VP9 frame is missing its start"));
+        }
+        frame.extend_from_slice(&payload[offset..]);
+        Ok(())
+    }
+}
+
+/// True if `frame` starts with the uncompressed header of a VP9 key frame.
+pub fn vp9_is_keyframe(frame: &[u8]) -> bool {
+    let byte = match frame.first() {
+        Some(byte) => *byte,
+        None => return false,
+    };
+    let bit = |i: u32| (byte >> (7 - i)) & 1;
+    if byte >> 6 != 2 {
+        return false;
+    }
+    let profile = bit(2) | (bit(3) << 1);
+    let show_existing_frame = if profile == 3 { 5 } else { 4 };
+    bit(show_existing_frame) == 0 && bit(show_existing_frame + 1) == 0
+}
+
+/// Width and height from the uncompressed header of a VP9 key frame.
+fn vp9_keyframe_size(frame: &[u8]) -> Option<(u16, u16)> {
+    if !vp9_is_keyframe(frame) {
+        return None;
+    }
+    let mut position = 0;
+    let mut read = |bits: usize| -> Option<u32> {
+        let mut value = 0;
+        for _ in 0..bits {
+            let byte = *frame.get(position / 8)?;
+            value = (value << 1) | ((byte >> (7 - position % 8)) & 1) as u32;
+            position += 1;
+        }
+        Some(value)
+    };
+
+    // Frame marker.
+    read(2)?;
+    let profile = read(1)? | (read(1)? << 1);
+    if profile == 3 {
+        read(1)?;
+    }
+    // show_existing_frame, frame_type, show_frame and error_resilient_mode.
+    read(4)?;
+    if read(24)? != 0x49_83_42 {
+        return None;
+    }
+    // Color config.
+    if profile >= 2 {
+        read(1)?;
+    }
+    let color_space = read(3)?;
+    let odd_profile = profile == 1 || profile == 3;
+    if color_space != 7 {
+        read(if odd_profile { 4 } else { 1 })?;
+    } else if odd_profile {
+        read(1)?;
+    }
+    let width = read(16)? + 1;
+    let height = read(16)? + 1;
+    Some((width as u16, height as u16))
+}
+
+/// H.264 in packetization mode 1 from Annex B byte streams.  Small NAL
+/// units are aggregated in STAP-A packets and large ones fragmented in FU-A
+/// packets.
+#[derive(Default)]
+pub struct H264Packetizer;
+
+impl H264Packetizer {
+    fn flush(aggregate: &mut Vec<&[u8]>, payloads: &mut Vec<Vec<u8>>) {
+        match aggregate.len() {
+            0 => {}
+            1 => payloads.push(aggregate[0].to_vec()),
+            _ => {
+                // F and the highest NRI of the aggregated units.
+                let header = aggregate.iter().fold(0, |header, nalu| {
+                    (header & 0x80) | (nalu[0] & 0x80) | (header & 0x60).max(nalu[0] & 0x60)
+                });
+                let mut payload = vec![header | H264_STAP_A];
+                for nalu in aggregate.iter() {
+                    payload.extend_from_slice(&(nalu.len() as u16).to_be_bytes());
+                    payload.extend_from_slice(nalu);
+                }
+                payloads.push(payload);
+            }
+        }
+        aggregate.clear();
+    }
+}
+
+impl Packetizer for H264Packetizer {
+    fn packetize(&mut self, frame: &[u8], max_payload_size: usize) -> Result<Vec<Vec<u8>>> {
+        const FU_A_HEADER_SIZE: usize = 2;
+        let nalus = split_annex_b(frame);
+        if nalus.is_empty() {
+            return Err(rtp_error("H.264 frame has no NAL units"));
+        }
+        if max_payload_size <= FU_A_HEADER_SIZE + 1 {
+            return Err(rtp_error("max payload size too small"));
+        }
+
+        let mut payloads = vec![];
+        let mut aggregate: Vec<&[u8]> = vec![];
+        // STAP-A header.
+        let mut aggregate_size = 1;
+        for nalu in nalus {
+            if nalu.len() <= max_payload_size {
+                if aggregate_size + 2 + nalu.len() > max_payload_size {
+                    Self::flush(&mut aggregate, &mut payloads);
+                    aggregate_size = 1;
+                }
+                aggregate.push(nalu);
+                aggregate_size += 2 + nalu.len();
+                continue;
+            }
+
+            Self::flush(&mut aggregate, &mut payloads);
+            aggregate_size = 1;
+            let indicator = (nalu[0] & 0xE0) | H264_FU_A;
+            let chunks: Vec<&[u8]> = nalu[1..]
+                .chunks(max_payload_size - FU_A_HEADER_SIZE)
+                .collect();
+            let last = chunks.len() - 1;
+            for (i, chunk) in chunks.into_iter().enumerate() {
+                let start = if i == 0 { 0x80 } else { 0 };
+                let end = if i == last { 0x40 } else { 0 };
+                let mut payload = vec![indicator, start | end | (nalu[0] & 0x1F)];
+                payload.extend_from_slice(chunk);
+                payloads.push(payload);
+            }
+        }
+        Self::flush(&mut aggregate, &mut payloads);
+        Ok(payloads)
+    }
+}
+
+/// Reassembles Annex B byte streams with 4 byte start codes.
+#[derive(Default)]
+pub struct H264Depacketizer;
+
+impl Depacketizer for H264Depacketizer {
+    fn depacketize(&mut self, payload: &[u8], frame: &mut Vec<u8>) -> Result<()> {
+        let header = *payload
+            .first()
+            .ok_or_else(|| rtp_error("empty H.264 payload"))?;
+        match header & 0x1F {
+            1..=23 => {
+                frame.extend_from_slice(&H264_START_CODE);
+                frame.extend_from_slice(payload);
+            }
+            H264_STAP_A => {
+                let mut offset = 1;
+                while offset < payload.len() {
+                    let size = payload
+                        .get(offset..offset + 2)
+                        .map(|size| u16::from_be_bytes([size[0], size[1]]) as usize)
+                        .ok_or_else(|| rtp_error("truncated STAP-A"))?;
+                    let nalu = payload
+                        .get(offset + 2..offset + 2 + size)
+                        .filter(|nalu| !nalu.is_empty())
+                        .ok_or_else(|| rtp_error("truncated STAP-A"))?;
+                    frame.extend_from_slice(&H264_START_CODE);
+                    frame.extend_from_slice(nalu);
+                    offset += 2 + size;
+                }
+            }
+            H264_FU_A => {
+                let fu_header = byte_at(payload, 1)?;
+                if fu_header & 0x80 != 0 {
+                    frame.extend_from_slice(&H264_START_CODE);
+                    frame.push((header & 0xE0) | (fu_header & 0x1F));
+                } else if frame.is_empty() {
+                    return Err(rtp_error("FU-A is missing its start"));
+                }
+                frame.extend_from_slice(&payload[2..]);
+            }
+            nal_type => {
+                return Err(rtp_error(&format!(
+                    "unsupported H.264 NAL unit type {}",
+                    nal_type
+                )))
+            }
+        }
+        Ok(())
+    }
+}
+
+/// NAL units of an Annex B byte stream, without start codes.
+fn split_annex_b(stream: &[u8]) -> Vec<&[u8]> {
+    let mut nalus = vec![];
+    let mut start = None;
+    let mut i = 0;
+    while i + 3 <= stream.len() {
+        if stream[i..i + 3] == [0, 0, 1] {
+            if let Some(start) = start {
+                // A 4 byte start code's leading zero isn't part of the unit.
+                let mut end = i;
+                if end > start && stream[end - 1] == 0 {
+                    end -= 1;
+                }
+                nalus.push(&stream[start..end]);
+            }
+            i += 3;
+            start = Some(i);
+        } else {
+            i += 1;
+        }
+    }
+    if let Some(start) = start {
+        nalus.push(&stream[start..]);
+    }
+    nalus.retain(|nalu| !nalu.is_empty());
+    nalus
+}
+
+/// Opus frames sent whole, one per packet.
+#[derive(Default)]
+pub struct OpusPacketizer;
+
+impl Packetizer for OpusPacketizer {
+    fn packetize(&mut self, frame: &[u8], max_payload_size: usize) -> Result<Vec<Vec<u8>>> {
+        check_sizes(frame, max_payload_size, 0)?;
+        if frame.len() > max_payload_size {
+            return Err(rtp_error("Opus frame larger than the max payload size"));
+        }
+        Ok(vec![frame.to_vec()])
+    }
+
+    fn marks_frame_end(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Default)]
+pub struct OpusDepacketizer;
+
+impl Depacketizer for OpusDepacketizer {
+    fn depacketize(&mut self, payload: &[u8], frame: &mut Vec<u8>) -> Result<()> {
+        frame.extend_from_slice(payload);
+        Ok(())
+    }
+
+    fn marks_frame_end(&self) -> bool {
+        false
+    }
+}
+
+fn check_sizes(frame: &[u8], max_payload_size: usize, descriptor_size: usize) -> Result<()> {
+    if frame.is_empty() {
+        return Err(rtp_error("empty frame"));
+    }
+    if max_payload_size <= descriptor_size {
+        return Err(rtp_error("max payload size too small"));
+    }
+    Ok(())
+}
+
+fn byte_at(payload: &[u8], offset: usize) -> Result<u8> {
+    payload
+        .get(offset)
+        .copied()
+        .ok_or_else(|| rtp_error("truncated payload descriptor"))
+}
+
+fn rtp_error(reason: &str) -> WebRTCError {
+    WebRTCError::RtpError(reason.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(
+        packetizer: Box<dyn Packetizer>,
+        depacketizer: Box<dyn Depacketizer>,
+        frames: &[Vec<u8>],
+    ) -> Vec<Vec<u8>> {
+        let mut rtp_packetizer = RtpPacketizer::new(1234, 96, 90000, packetizer);
+        rtp_packetizer.set_max_packet_size(100);
+        let mut rtp_depacketizer = RtpDepacketizer::new(depacketizer);
+        let mut received = vec![];
+        for (i, frame) in frames.iter().enumerate() {
+            let capture_time = Duration::from_millis(33 * i as u64);
+            for packet in rtp_packetizer.packetize(frame, capture_time).unwrap() {
+                assert!(packet.len() <= 100);
+                if let Some(frame) = rtp_depacketizer.push(&packet).unwrap() {
+                    received.push(frame.data);
+                }
+            }
+        }
+        received
+    }
+
+    #[test]
+    fn test_rtp_header() {
+        let header = RtpHeader {
+            marker: true,
+            payload_type: 111,
+            sequence_number: 65535,
+            timestamp: 0xDEAD_BEEF,
+            ssrc: 42,
+        };
+        let packet = header.write(b"payload");
+        assert_eq!(
+            RtpHeader::parse(&packet).unwrap(),
+            (header, &b"payload"[..])
+        );
+
+        // One CSRC, a one word extension and two bytes of padding.
+        let mut packet = header.write(b"");
+        packet[0] |= 0x20 | 0x10 | 0x01;
+        packet.extend_from_slice(&[0, 0, 0, 7]);
+        packet.extend_from_slice(&[0xBE, 0xDE, 0, 1, 1, 2, 3, 4]);
+        packet.extend_from_slice(b"payload");
+        packet.extend_from_slice(&[0, 2]);
+        assert_eq!(RtpHeader::parse(&packet).unwrap().1, b"payload");
+
+        assert!(RtpHeader::parse(&packet[..8]).is_err());
+    }
+
+    #[test]
+    fn test_packetizer_sequencing() {
+        let mut packetizer = RtpPacketizer::new(7, 96, 90000, Box::new(Vp8Packetizer::new()));
+        packetizer.set_max_packet_size(RTP_HEADER_SIZE + 14);
+        let first = packetizer.sequence_number();
+        let packets = packetizer
+            .packetize(&[1; 25], Duration::from_millis(0))
+            .unwrap();
+        let headers: Vec<RtpHeader> = packets
+            .iter()
+            .map(|packet| RtpHeader::parse(packet).unwrap().0)
+            .collect();
+        assert_eq!(headers.len(), 3);
+        for (i, header) in headers.iter().enumerate() {
+            assert_eq!(header.sequence_number, first.wrapping_add(i as u16));
+            assert_eq!(header.marker, i == 2);
+            assert_eq!(header.timestamp, headers[0].timestamp);
+            assert_eq!((header.ssrc, header.payload_type), (7, 96));
+        }
+
+        // 1 second at 90 kHz later.
+        let packets = packetizer
+            .packetize(&[1; 5], Duration::from_secs(1))
+            .unwrap();
+        let header = RtpHeader::parse(&packets[0]).unwrap().0;
+        assert_eq!(header.timestamp, headers[0].timestamp.wrapping_add(90000));
+        assert_eq!(header.sequence_number, first.wrapping_add(3));
+    }
+
+    #[test]
+    fn test_vp8() {
+        let frames: Vec<Vec<u8>> = (0..3u8).map(|i| vec![i; 250]).collect();
+        let received = round_trip(
+            Box::new(Vp8Packetizer::new()),
+            Box::new(Vp8Depacketizer),
+            &frames,
+        );
+        assert_eq!(received, frames);
+
+        let payloads = Vp8Packetizer::new().packetize(&[9; 10], 8).unwrap();
+        assert_eq!(payloads.len(), 3);
+        assert_eq!(payloads[0][0], 0x90);
+        assert_eq!(payloads[1][0], 0x80);
+        // Picture ids carry over to the next frame.
+        let mut packetizer = Vp8Packetizer::new();
+        let picture_id = |payload: &[u8]| u16::from_be_bytes([payload[2] & 0x7F, payload[3]]);
+        let first = picture_id(&packetizer.packetize(&[1], 100).unwrap()[0]);
+        let second = picture_id(&packetizer.packetize(&[1], 100).unwrap()[0]);
+        assert_eq!(second, (first + 1) & 0x7FFF);
+
+        // A frame missing its first packet isn't reassembled.
+        let mut frame = vec![];
+        assert!(Vp8Depacketizer
+            .depacketize(&payloads[1], &mut frame)
+            .is_err());
+        // Minimal descriptor.
+        Vp8Depacketizer.depacketize(&[0x10, 5], &mut frame).unwrap();
+        assert_eq!(frame, vec![5]);
+    }
+
+    #[test]
+    fn test_vp9() {
+        // Key frame, profile 0: frame marker, profile, show_existing_frame 0,
+        // frame_type 0.
+        let keyframe = [vec![0x80], vec![3; 200]].concat();
+        let inter_frame = [vec![0x84], vec![4; 200]].concat();
+        assert!(vp9_is_keyframe(&keyframe));
+        assert!(!vp9_is_keyframe(&inter_frame));
+
+        let frames = vec![keyframe.clone(), inter_frame.clone()];
+        let received = round_trip(
+            Box::new(Vp9Packetizer::new()),
+            Box::new(Vp9Depacketizer),
+            &frames,
+        );
+        assert_eq!(received, frames);
+
+        // Profile 0 key frame of 640x480: sync code, color space 0, color
+        // range 0, then the width and height minus 1 in 16 bits each.
+        let header = [0x82, 0x49, 0x83, 0x42, 0x00, 0x27, 0xF0, 0x1D, 0xF0];
+        let sized_keyframe = [&header[..], &[3; 200][..]].concat();
+        assert_eq!(vp9_keyframe_size(&sized_keyframe), Some((640, 480)));
+        assert_eq!(vp9_keyframe_size(&keyframe), None);
+
+        let mut packetizer = Vp9Packetizer::new();
+        let payloads = packetizer.packetize(&sized_keyframe, 150).unwrap();
+        // I, L, B and V.
+        assert_eq!(payloads[0][0], 0x80 | 0x20 | 0x08 | 0x02);
+        let picture_id = u16::from_be_bytes([payloads[0][1] & 0x7F, payloads[0][2]]);
+        // TID, U, SID and D, then TL0PICIDX.
+        assert_eq!(payloads[0][3], 0);
+        let tl0_pic_idx = payloads[0][4];
+        // N_S = 0, Y, G, 640x480, N_G = 1, TID = 0, U = 0, R = 1, P_DIFF = 1.
+        assert_eq!(
+            &payloads[0][5..13],
+            &[0x18, 0x02, 0x80, 0x01, 0xE0, 1, 0x04, 1][..]
+        );
+        assert_eq!(payloads[0].len(), 150);
+        assert_eq!(payloads[1][0], 0x80 | 0x20 | 0x04);
+        assert_eq!(&payloads[1][3..5], &[0, tl0_pic_idx][..]);
+
+        let payloads = packetizer.packetize(&inter_frame, 300).unwrap();
+        assert_eq!(payloads[0][0], 0x80 | 0x40 | 0x20 | 0x08 | 0x04);
+        assert_eq!(
+            u16::from_be_bytes([payloads[0][1] & 0x7F, payloads[0][2]]),
+            (picture_id + 1) & 0x7FFF
+        );
+        assert_eq!(&payloads[0][3..5], &[0, tl0_pic_idx.wrapping_add(1)][..]);
+        let mut frame = vec![];
+        Vp9Depacketizer
+            .depacketize(&payloads[0], &mut frame)
+            .unwrap();
+        assert_eq!(frame, inter_frame);
+        // Key frames without a readable size leave the resolutions out.
+        let payloads = packetizer.packetize(&keyframe, 300).unwrap();
+        assert_eq!(&payloads[0][5..9], &[0x08, 1, 0x04, 1][..]);
+
+        // Layer indices, TL0PICIDX and a scalability structure with one
+        // resolution and one picture group entry with one reference.
+        let payload = [
+            0x80 | 0x20 | 0x08 | 0x04 | 0x02,
+            0x05,
+            0x00,
+            0x00,
+            0x10 | 0x08,
+            0x01,
+            0x40,
+            0x02,
+            0xD0,
+            0x01,
+            // R, one reference.
+            0x04,
+            0x01,
+            0xAA,
+        ];
+        let mut frame = vec![];
+        Vp9Depacketizer.depacketize(&payload, &mut frame).unwrap();
+        assert_eq!(frame, vec![0xAA]);
+    }
+
+    #[test]
+    fn test_h264() {
+        let sps = vec![0x67, 1, 2, 3];
+        let pps = vec![0x68, 4, 5];
+        let idr = [vec![0x65], vec![6; 300]].concat();
+        let frame = [
+            &H264_START_CODE[..],
+            &sps[..],
+            &[0, 0, 1][..],
+            &pps[..],
+            &H264_START_CODE[..],
+            &idr[..],
+        ]
+        .concat();
+        assert_eq!(split_annex_b(&frame), vec![&sps[..], &pps[..], &idr[..]]);
+
+        let payloads = H264Packetizer.packetize(&frame, 100).unwrap();
+        // SPS and PPS aggregated, then the IDR slice fragmented.
+        assert_eq!(payloads[0][0], 0x60 | H264_STAP_A);
+        assert_eq!(payloads[1][..2], [0x60 | H264_FU_A, 0x80 | 0x05]);
+        assert_eq!(payloads.last().unwrap()[1], 0x40 | 0x05);
+
+        let single = [&H264_START_CODE[..], &[0x41, 7, 7][..]].concat();
+        let expected = [
+            &H264_START_CODE[..],
+            &sps[..],
+            &H264_START_CODE[..],
+            &pps[..],
+            &H264_START_CODE[..],
+            &idr[..],
+        ]
+        .concat();
+        let received = round_trip(
+            Box::new(H264Packetizer),
+            Box::new(H264Depacketizer),
+            &[frame, single.clone()],
+        );
+        assert_eq!(received, vec![expected, single]);
+
+        let mut frame = vec![];
+        assert!(H264Depacketizer
+            .depacketize(&payloads[2], &mut frame)
+            .is_err());
+        assert!(H264Packetizer.packetize(b"no start code", 100).is_err());
+    }
+
+    #[test]
+    fn test_opus() {
+        let frames: Vec<Vec<u8>> = (1..4u8).map(|i| vec![i; 60]).collect();
+        let received = round_trip(
+            Box::new(OpusPacketizer),
+            Box::new(OpusDepacketizer),
+            &frames,
+        );
+        assert_eq!(received, frames);
+
+        let mut packetizer = RtpPacketizer::new(1, 111, 48000, Box::new(OpusPacketizer));
+        let packet = &packetizer
+            .packetize(&[1; 60], Duration::from_millis(20))
+            .unwrap()[0];
+        assert!(!RtpHeader::parse(packet).unwrap().0.marker);
+        assert!(OpusPacketizer.packetize(&[1; 1200], 1000).is_err());
+    }
+
+    #[test]
+    fn test_padding_between_frames() {
+        let mut packetizer = RtpPacketizer::new(1, 96, 90000, Box::new(H264Packetizer));
+        let mut depacketizer = RtpDepacketizer::new(Box::new(H264Depacketizer));
+        let frame = [&H264_START_CODE[..], &[0x41][..], &[8; 100][..]].concat();
+
+        let first = packetizer
+            .packetize(&frame, Duration::from_millis(0))
+            .unwrap();
+        // Padding after the frame's last packet with its timestamp and no
+        // marker, as libwebrtc sends it.
+        let (last, _) = RtpHeader::parse(first.last().unwrap()).unwrap();
+        let mut padding = RtpHeader {
+            marker: false,
+            sequence_number: last.sequence_number.wrapping_add(1),
+            ..last
+        }
+        .write(&[0, 0, 0, 4]);
+        padding[0] |= 0x20;
+        // The next frame is numbered after the padding.
+        let mut second = packetizer
+            .packetize(&frame, Duration::from_millis(33))
+            .unwrap();
+        for packet in &mut second {
+            let sequence_number = u16::from_be_bytes([packet[2], packet[3]]).wrapping_add(1);
+            packet[2..4].copy_from_slice(&sequence_number.to_be_bytes());
+        }
+
+        let mut received = vec![];
+        for packet in first.iter().chain(Some(&padding)).chain(&second) {
+            received.extend(depacketizer.push(packet).unwrap());
+        }
+        assert_eq!(received.len(), 2);
+        assert!(received.iter().all(|received| received.data == frame));
+    }
+
+    #[test]
+    fn test_loss() {
+        let mut packetizer = RtpPacketizer::new(1, 96, 90000, Box::new(H264Packetizer));
+        packetizer.set_max_packet_size(50);
+        let mut depacketizer = RtpDepacketizer::new(Box::new(H264Depacketizer));
+        let frame = [&H264_START_CODE[..], &[0x41][..], &[8; 100][..]].concat();
+
+        // The frame missing its second packet is dropped, the next one is
+        // received.
+        let mut packets = packetizer
+            .packetize(&frame, Duration::from_millis(0))
+            .unwrap();
+        packets.remove(1);
+        for packet in &packets {
+            assert!(depacketizer.push(packet).unwrap().is_none());
+        }
+        // As is one missing its first packet, here the SPS and PPS, although
+        // the next packet starts a NAL unit.
+        let key_frame = [
+            &H264_START_CODE[..],
+            &[0x67, 1, 2, 3][..],
+            &H264_START_CODE[..],
+            &[0x68, 4, 5][..],
+            &H264_START_CODE[..],
+            &[0x65][..],
+            &[9; 100][..],
+        ]
+        .concat();
+        let mut packets = packetizer
+            .packetize(&key_frame, Duration::from_millis(33))
+            .unwrap();
+        assert_eq!(packets[0][RTP_HEADER_SIZE] & 0x1F, H264_STAP_A);
+        packets.remove(0);
+        for packet in &packets {
+            assert!(depacketizer.push(packet).unwrap().is_none());
+        }
+
+        let mut received = None;
+        for packet in packetizer
+            .packetize(&frame, Duration::from_millis(66))
+            .unwrap()
+        {
+            received = depacketizer.push(&packet).unwrap();
+        }
+        assert_eq!(received.unwrap().data, frame);
+    }
+}
@@ -0,0 +1,743 @@
+//! A dcSCTP association driven by tokio.  Packets are handed to and taken
+//! from the caller, so it can run over any datagram transport.
+
+use std::{
+    collections::{hash_map::RandomState, BTreeMap, HashSet},
+    hash::{BuildHasher, Hasher},
+    sync::{Arc, Weak},
+    time::Duration,
+};
+
+use cxx::UniquePtr;
+use libwebrtc_sys::sctp::socket::{
+    ffi::{
+        create_arcas_sctp_callback_wrapper, create_arcas_sctp_options,
+        create_arcas_sctp_send_options, create_arcas_sctp_socket, ArcasSctpCallbacksProxyWrapper,
+        ArcasSctpMessage, ArcasSctpSocket, ErrorKind, ResetStreamsStatus, SendStatus, SocketState,
+    },
+    ArcasRustSctpCallbacksProxy, ArcasRustSctpTimeoutProxy, SctpSocketCallbacks, SctpTimeout,
+};
+use parking_lot::Mutex;
+use tokio::{
+    sync::{
+        mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+        oneshot, watch, Notify,
+    },
+    task::JoinHandle,
+    time::{sleep_until, Instant},
+};
+
+use crate::error::{Result, WebRTCError};
+
+pub type SctpErrorKind = ErrorKind;
+
+#[derive(Debug, Clone)]
+pub struct SctpOptions {
+    pub local_port: u16,
+    pub remote_port: u16,
+    /// Largest message accepted by `send`.
+    pub max_message_size: usize,
+    /// Retransmissions of a packet before the association is aborted.
+    pub max_retransmissions: u32,
+    /// Allow messages with a lifetime or retransmission limit (RFC 3758).
+    pub enable_partial_reliability: bool,
+}
+
+impl Default for SctpOptions {
+    fn default() -> Self {
+        Self {
+            local_port: 5000,
+            remote_port: 5000,
+            max_message_size: 256 * 1024,
+            max_retransmissions: 10,
+            enable_partial_reliability: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SctpSendOptions {
+    pub ordered: bool,
+    /// Drop the message if it hasn't been sent within `lifetime`.
+    pub lifetime: Option<Duration>,
+    /// Drop the message after this many retransmissions.
+    pub max_retransmissions: Option<usize>,
+}
+
+impl Default for SctpSendOptions {
+    fn default() -> Self {
+        Self {
+            ordered: true,
+            lifetime: None,
+            max_retransmissions: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SctpMessage {
+    pub stream_id: u16,
+    /// Payload protocol identifier.
+    pub ppid: u32,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SctpState {
+    Closed,
+    Connecting,
+    Connected,
+    ShuttingDown,
+}
+
+#[derive(Debug, Clone)]
+pub enum SctpEvent {
+    Connected,
+    /// Closed by a shutdown from either side.
+    Closed,
+    /// Nothing is delivered after an abort.
+    Aborted {
+        kind: SctpErrorKind,
+        message: String,
+    },
+    /// A non-fatal error, the association is still usable.
+    Error {
+        kind: SctpErrorKind,
+        message: String,
+    },
+    ConnectionRestarted,
+    /// The peer reset these streams, empty for all streams.
+    IncomingStreamsReset(Vec<u16>),
+    /// A stream's buffered amount fell to its low threshold.
+    BufferedAmountLow(u16),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SctpMetrics {
+    pub tx_packets_count: usize,
+    pub tx_messages_count: usize,
+    /// Congestion window, `None` before the association is connected.
+    pub cwnd_bytes: Option<usize>,
+    pub srtt: Option<Duration>,
+    pub unack_data_count: usize,
+    pub rx_packets_count: usize,
+    pub rx_messages_count: usize,
+    pub peer_rwnd_bytes: Option<u32>,
+}
+
+/// Output of an association.  The channels are unbounded as dcSCTP
+/// callbacks can't block.
+pub struct SctpReceivers {
+    /// Packets to deliver to the peer over the transport.
+    pub packets: UnboundedReceiver<Vec<u8>>,
+    pub messages: UnboundedReceiver<SctpMessage>,
+    pub events: UnboundedReceiver<SctpEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Status {
+    New,
+    Connected,
+    Closed,
+    Aborted(String),
+}
+
+/// Pending dcSCTP timeouts ordered by deadline, keyed by insertion order to
+/// keep equal deadlines apart.
+#[derive(Default)]
+struct TimerWheel {
+    next_key: u64,
+    deadlines: BTreeMap<(Instant, u64), u64>,
+}
+
+impl TimerWheel {
+    fn insert(&mut self, deadline: Instant, timeout_id: u64) -> (Instant, u64) {
+        let key = (deadline, self.next_key);
+        self.next_key += 1;
+        self.deadlines.insert(key, timeout_id);
+        key
+    }
+
+    fn next_deadline(&self) -> Option<Instant> {
+        self.deadlines.keys().next().map(|(deadline, _)| *deadline)
+    }
+
+    fn expire(&mut self, now: Instant) -> Vec<u64> {
+        let later = self.deadlines.split_off(&(now, u64::MAX));
+        std::mem::replace(&mut self.deadlines, later)
+            .values()
+            .copied()
+            .collect()
+    }
+}
+
+struct PendingReset {
+    streams: HashSet<u16>,
+    done: oneshot::Sender<std::result::Result<(), String>>,
+}
+
+/// State shared between the association, its callbacks and timeouts.
+/// Callbacks run while the socket is locked, so none of this may lock it.
+struct Shared {
+    timers: Mutex<TimerWheel>,
+    timers_changed: Notify,
+    status: watch::Sender<Status>,
+    buffered_low: Notify,
+    resets: Mutex<Vec<PendingReset>>,
+}
+
+impl Shared {
+    fn set_status(&self, status: Status) {
+        let _ = self.status.send(status);
+        // Wake senders waiting for buffer space so they see the new state.
+        self.buffered_low.notify_waiters();
+        self.fail_resets(None, "association closed");
+    }
+
+    fn fail_resets(&self, streams: Option<&[u16]>, error: &str) {
+        let mut resets = self.resets.lock();
+        let (failed, pending): (Vec<_>, Vec<_>) =
+            resets.drain(..).partition(|reset| match streams {
+                Some(streams) => streams.iter().any(|stream| reset.streams.contains(stream)),
+                None => true,
+            });
+        *resets = pending;
+        for reset in failed.into_iter() {
+            let _ = reset.done.send(Err(error.to_string()));
+        }
+    }
+
+    fn complete_resets(&self, streams: &[u16]) {
+        let mut resets = self.resets.lock();
+        for reset in resets.iter_mut() {
+            streams.iter().for_each(|stream| {
+                reset.streams.remove(stream);
+            });
+        }
+        let (done, pending): (Vec<_>, Vec<_>) =
+            resets.drain(..).partition(|reset| reset.streams.is_empty());
+        *resets = pending;
+        for reset in done.into_iter() {
+            let _ = reset.done.send(Ok(()));
+        }
+    }
+}
+
+struct TokioTimeout {
+    shared: Arc<Shared>,
+    key: Option<(Instant, u64)>,
+}
+
+impl SctpTimeout for TokioTimeout {
+    fn start(&mut self, timeout_ms: i32, timeout_id: u64) {
+        let deadline = Instant::now() + Duration::from_millis(timeout_ms.max(0) as u64);
+        self.key = Some(self.shared.timers.lock().insert(deadline, timeout_id));
+        self.shared.timers_changed.notify_one();
+    }
+
+    fn stop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.shared.timers.lock().deadlines.remove(&key);
+        }
+    }
+}
+
+/// xorshift64, for loss simulation and codec picture ids which don't need a
+/// secure generator.
+pub(crate) struct XorShift(u64);
+
+impl XorShift {
+    pub(crate) fn new() -> Self {
+        Self(RandomState::new().build_hasher().finish() | 1)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+/// A random u64 from the OS generator, for values an off-path attacker
+/// mustn't guess such as SCTP verification tags and initial TSNs.
+pub(crate) fn secure_random_u64() -> u64 {
+    let mut bytes = [0; 8];
+    getrandom::getrandom(&mut bytes).expect("the OS random number generator failed");
+    u64::from_ne_bytes(bytes)
+}
+
+struct Callbacks {
+    shared: Arc<Shared>,
+    packets: UnboundedSender<Vec<u8>>,
+    messages: UnboundedSender<SctpMessage>,
+    events: UnboundedSender<SctpEvent>,
+    epoch: Instant,
+}
+
+impl SctpSocketCallbacks for Callbacks {
+    fn send_packet(&mut self, packet: &[u8]) {
+        let _ = self.packets.send(packet.to_vec());
+    }
+
+    fn create_timeout(&mut self) -> Box<ArcasRustSctpTimeoutProxy> {
+        Box::new(ArcasRustSctpTimeoutProxy::new(Box::new(TokioTimeout {
+            shared: self.shared.clone(),
+            key: None,
+        })))
+    }
+
+    fn current_time_ms(&mut self) -> i64 {
+        self.epoch.elapsed().as_millis() as i64
+    }
+
+    fn get_random_int(&mut self, low: u32, high: u32) -> u32 {
+        if high <= low {
+            return low;
+        }
+        // dcSCTP draws verification tags and initial TSNs from here.
+        low + (secure_random_u64() % (high - low) as u64) as u32
+    }
+
+    fn on_message_received(&mut self, message: ArcasSctpMessage) {
+        let payload = message
+            .payload
+            .as_ref()
+            .map(|payload| payload.as_slice().to_vec())
+            .unwrap_or_default();
+        let _ = self.messages.send(SctpMessage {
+            stream_id: message.stream_id,
+            ppid: message.ppid,
+            payload,
+        });
+    }
+
+    fn on_error(&mut self, kind: ErrorKind, message: String) {
+        let _ = self.events.send(SctpEvent::Error { kind, message });
+    }
+
+    fn on_aborted(&mut self, kind: ErrorKind, message: String) {
+        self.shared.set_status(Status::Aborted(message.clone()));
+        let _ = self.events.send(SctpEvent::Aborted { kind, message });
+    }
+
+    fn on_connected(&mut self) {
+        let _ = self.shared.status.send(Status::Connected);
+        let _ = self.events.send(SctpEvent::Connected);
+    }
+
+    fn on_closed(&mut self) {
+        self.shared.set_status(Status::Closed);
+        let _ = self.events.send(SctpEvent::Closed);
+    }
+
+    fn on_connection_restarted(&mut self) {
+        let _ = self.events.send(SctpEvent::ConnectionRestarted);
+    }
+
+    fn on_streams_reset_failed(&mut self, stream_ids: Vec<u16>, error: String) {
+        self.shared.fail_resets(Some(&stream_ids[..]), &error);
+    }
+
+    fn on_streams_reset_performed(&mut self, stream_ids: Vec<u16>) {
+        self.shared.complete_resets(&stream_ids);
+    }
+
+    fn on_incoming_streams_reset(&mut self, stream_ids: Vec<u16>) {
+        let _ = self
+            .events
+            .send(SctpEvent::IncomingStreamsReset(stream_ids));
+    }
+
+    fn on_buffered_amount_low(&mut self, stream_id: u16) {
+        let _ = self.events.send(SctpEvent::BufferedAmountLow(stream_id));
+    }
+
+    fn on_total_bufferred_amount_low(&mut self) {
+        self.shared.buffered_low.notify_waiters();
+    }
+}
+
+struct Socket {
+    // Dropped before the callbacks it references.
+    socket: UniquePtr<ArcasSctpSocket>,
+    _callbacks: UniquePtr<ArcasSctpCallbacksProxyWrapper>,
+}
+
+/// An SCTP association over a transport the caller provides: packets from
+/// the peer go into `handle_packet`, packets for the peer come out of
+/// `SctpReceivers::packets`.  Timeouts are driven by a tokio task until the
+/// association is dropped.
+pub struct SctpAssociation {
+    socket: Arc<Mutex<Socket>>,
+    shared: Arc<Shared>,
+    status: watch::Receiver<Status>,
+    timer_task: JoinHandle<()>,
+}
+
+impl SctpAssociation {
+    /// Create a closed association, must be called within a tokio runtime.
+    pub fn new(options: SctpOptions) -> (Self, SctpReceivers) {
+        let (status_tx, status) = watch::channel(Status::New);
+        let shared = Arc::new(Shared {
+            timers: Mutex::new(TimerWheel::default()),
+            timers_changed: Notify::new(),
+            status: status_tx,
+            buffered_low: Notify::new(),
+            resets: Mutex::new(vec![]),
+        });
+        let (packets_tx, packets) = unbounded_channel();
+        let (messages_tx, messages) = unbounded_channel();
+        let (events_tx, events) = unbounded_channel();
+        let callbacks = Callbacks {
+            shared: shared.clone(),
+            packets: packets_tx,
+            messages: messages_tx,
+            events: events_tx,
+            epoch: Instant::now(),
+        };
+
+        let mut sctp_options = create_arcas_sctp_options();
+        sctp_options
+            .pin_mut()
+            .set_local_port(options.local_port as i32);
+        sctp_options
+            .pin_mut()
+            .set_remote_port(options.remote_port as i32);
+        sctp_options
+            .pin_mut()
+            .set_max_message_size(options.max_message_size);
+        sctp_options
+            .pin_mut()
+            .set_max_retransmissions(options.max_retransmissions as i32);
+        sctp_options
+            .pin_mut()
+            .set_enable_partial_reliability(options.enable_partial_reliability);
+
+        let mut callbacks = create_arcas_sctp_callback_wrapper(Box::new(
+            ArcasRustSctpCallbacksProxy::new(Box::new(callbacks)),
+        ));
+        let socket = create_arcas_sctp_socket(
+            "arcas".into(),
+            callbacks.pin_mut(),
+            UniquePtr::null(),
+            sctp_options.pin_mut(),
+        );
+        let socket = Arc::new(Mutex::new(Socket {
+            socket,
+            _callbacks: callbacks,
+        }));
+        let timer_task = tokio::spawn(drive_timeouts(shared.clone(), Arc::downgrade(&socket)));
+
+        (
+            Self {
+                socket,
+                shared,
+                status,
+                timer_task,
+            },
+            SctpReceivers {
+                packets,
+                messages,
+                events,
+            },
+        )
+    }
+
+    /// A packet received from the peer.
+    pub fn handle_packet(&self, packet: &[u8]) {
+        self.socket.lock().socket.pin_mut().receive_packet(packet);
+    }
+
+    /// Start the handshake and wait for it to complete.
+    pub async fn connect(&self) -> Result<()> {
+        self.socket.lock().socket.pin_mut().connect();
+        self.wait_connected().await
+    }
+
+    /// Wait for the association to connect, either from `connect` or a
+    /// handshake started by the peer.
+    pub async fn wait_connected(&self) -> Result<()> {
+        let mut status = self.status.clone();
+        loop {
+            match &*status.borrow() {
+                Status::New => {}
+                Status::Connected => return Ok(()),
+                Status::Closed => return Err(closed_error()),
+                Status::Aborted(message) => return Err(aborted_error(message)),
+            }
+            status.changed().await.map_err(|_| closed_error())?;
+        }
+    }
+
+    /// Send a message, waiting for room in the send buffer if it's full.
+    pub async fn send(
+        &self,
+        stream_id: u16,
+        ppid: u32,
+        payload: &[u8],
+        options: &SctpSendOptions,
+    ) -> Result<()> {
+        loop {
+            let buffered_low = self.shared.buffered_low.notified();
+            let status = {
+                let mut send_options = create_arcas_sctp_send_options();
+                send_options.pin_mut().set_unordered(!options.ordered);
+                if let Some(lifetime) = options.lifetime {
+                    send_options
+                        .pin_mut()
+                        .set_lifetime(lifetime.as_millis().min(i32::MAX as u128) as i32);
+                }
+                if let Some(max_retransmissions) = options.max_retransmissions {
+                    send_options
+                        .pin_mut()
+                        .set_max_retransmissions(max_retransmissions);
+                }
+                self.socket.lock().socket.pin_mut().send(
+                    stream_id,
+                    ppid,
+                    payload,
+                    send_options.pin_mut(),
+                )
+            };
+
+            if status.repr == SendStatus::kSuccess.repr {
+                return Ok(());
+            } else if status.repr == SendStatus::kErrorResourceExhaustion.repr {
+                match &*self.status.borrow() {
+                    Status::Closed => return Err(closed_error()),
+                    Status::Aborted(message) => return Err(aborted_error(message)),
+                    _ => {}
+                }
+                buffered_low.await;
+            } else {
+                return Err(WebRTCError::SctpError(format!(
+                    "failed to send on stream {}: {:?}",
+                    stream_id, status
+                )));
+            }
+        }
+    }
+
+    /// Reset outgoing `streams` and wait for the peer to acknowledge.
+    pub async fn reset_streams(&self, streams: &[u16]) -> Result<()> {
+        let (done, result) = oneshot::channel();
+        self.shared.resets.lock().push(PendingReset {
+            streams: streams.iter().copied().collect(),
+            done,
+        });
+
+        let status = self
+            .socket
+            .lock()
+            .socket
+            .pin_mut()
+            .reset_streams(streams.to_vec());
+        if status.repr != ResetStreamsStatus::kPerformed.repr {
+            self.shared
+                .fail_resets(Some(streams), &format!("{:?}", status));
+        }
+
+        result.await.map_err(|_| closed_error())?.map_err(|error| {
+            WebRTCError::SctpError(format!("failed to reset {:?}: {}", streams, error))
+        })
+    }
+
+    /// Gracefully shut down and wait for the peer to acknowledge.
+    pub async fn shutdown(&self) -> Result<()> {
+        {
+            let mut socket = self.socket.lock();
+            if socket.socket.state().repr == SocketState::kClosed.repr {
+                return Ok(());
+            }
+            socket.socket.pin_mut().shutdown();
+        }
+
+        let mut status = self.status.clone();
+        loop {
+            if matches!(&*status.borrow(), Status::Closed | Status::Aborted(_)) {
+                return Ok(());
+            }
+            status.changed().await.map_err(|_| closed_error())?;
+        }
+    }
+
+    /// Abort the association without waiting for the peer.
+    pub fn close(&self) {
+        self.socket.lock().socket.pin_mut().close();
+        // dcSCTP doesn't call back from close.
+        self.shared.set_status(Status::Closed);
+    }
+
+    pub fn state(&self) -> SctpState {
+        let state = self.socket.lock().socket.state();
+        if state.repr == SocketState::kConnecting.repr {
+            SctpState::Connecting
+        } else if state.repr == SocketState::kConnected.repr {
+            SctpState::Connected
+        } else if state.repr == SocketState::kShuttingDown.repr {
+            SctpState::ShuttingDown
+        } else {
+            SctpState::Closed
+        }
+    }
+
+    /// Bytes queued on `stream_id` and not yet sent.
+    pub fn buffered_amount(&self, stream_id: u16) -> usize {
+        self.socket.lock().socket.buffered_amount(stream_id)
+    }
+
+    /// Emit `SctpEvent::BufferedAmountLow` when `stream_id` drains to `size`.
+    pub fn set_buffered_amount_low_threshold(&self, stream_id: u16, size: usize) {
+        self.socket
+            .lock()
+            .socket
+            .pin_mut()
+            .set_buffered_amount_low_threshold(stream_id, size);
+    }
+
+    pub fn get_metrics(&self) -> SctpMetrics {
+        let metrics = self.socket.lock().socket.get_metrics();
+        SctpMetrics {
+            tx_packets_count: metrics.tx_packets_count,
+            tx_messages_count: metrics.tx_messages_count,
+            cwnd_bytes: metrics.cwnd_bytes.first().copied(),
+            srtt: metrics
+                .srtt_ms
+                .first()
+                .map(|srtt| Duration::from_millis(*srtt as u64)),
+            unack_data_count: metrics.unack_data_count,
+            rx_packets_count: metrics.rx_packets_count,
+            rx_messages_count: metrics.rx_messages_count,
+            peer_rwnd_bytes: metrics.peer_rwnd_bytes.first().copied(),
+        }
+    }
+}
+
+impl Drop for SctpAssociation {
+    fn drop(&mut self) {
+        self.timer_task.abort();
+    }
+}
+
+/// Fire expired timeouts until the socket is dropped.
+async fn drive_timeouts(shared: Arc<Shared>, socket: Weak<Mutex<Socket>>) {
+    loop {
+        let changed = shared.timers_changed.notified();
+        let next_deadline = shared.timers.lock().next_deadline();
+        match next_deadline {
+            Some(deadline) => tokio::select! {
+                _ = sleep_until(deadline) => {}
+                _ = changed => continue,
+            },
+            None => {
+                changed.await;
+                continue;
+            }
+        }
+
+        let socket = match socket.upgrade() {
+            Some(socket) => socket,
+            None => return,
+        };
+        let expired = shared.timers.lock().expire(Instant::now());
+        for timeout_id in expired.into_iter() {
+            socket.lock().socket.pin_mut().handle_timeout(timeout_id);
+        }
+    }
+}
+
+fn closed_error() -> WebRTCError {
+    WebRTCError::SctpError("association closed".into())
+}
+
+fn aborted_error(message: &str) -> WebRTCError {
+    WebRTCError::SctpError(format!("association aborted: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn forward(mut packets: UnboundedReceiver<Vec<u8>>, to: Arc<SctpAssociation>) {
+        while let Some(packet) = packets.recv().await {
+            to.handle_packet(&packet);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_association() {
+        let (a, mut a_rx) = SctpAssociation::new(SctpOptions::default());
+        let (b, mut b_rx) = SctpAssociation::new(SctpOptions::default());
+        let (a, b) = (Arc::new(a), Arc::new(b));
+        tokio::spawn(forward(a_rx.packets, b.clone()));
+        tokio::spawn(forward(b_rx.packets, a.clone()));
+
+        assert_eq!(a.state(), SctpState::Closed);
+        a.connect().await.unwrap();
+        b.wait_connected().await.unwrap();
+        assert_eq!(b.state(), SctpState::Connected);
+
+        a.send(1, 51, b"ordered", &SctpSendOptions::default())
+            .await
+            .unwrap();
+        let unordered = SctpSendOptions {
+            ordered: false,
+            lifetime: Some(Duration::from_secs(1)),
+            max_retransmissions: Some(0),
+        };
+        a.send(2, 53, b"unordered", &unordered).await.unwrap();
+        let mut received = vec![
+            b_rx.messages.recv().await.unwrap(),
+            b_rx.messages.recv().await.unwrap(),
+        ];
+        received.sort_by_key(|message| message.stream_id);
+        assert_eq!(
+            received,
+            vec![
+                SctpMessage {
+                    stream_id: 1,
+                    ppid: 51,
+                    payload: b"ordered".to_vec(),
+                },
+                SctpMessage {
+                    stream_id: 2,
+                    ppid: 53,
+                    payload: b"unordered".to_vec(),
+                },
+            ]
+        );
+        assert!(a
+            .send(1, 51, b"", &SctpSendOptions::default())
+            .await
+            .is_err());
+
+        let metrics = a.get_metrics();
+        assert_eq!(metrics.tx_messages_count, 2);
+        assert!(metrics.cwnd_bytes.is_some());
+
+        a.reset_streams(&[1]).await.unwrap();
+        loop {
+            match b_rx.events.recv().await.unwrap() {
+                SctpEvent::IncomingStreamsReset(streams) => {
+                    assert_eq!(streams, vec![1]);
+                    break;
+                }
+                SctpEvent::Connected => {}
+                event => panic!("unexpected event {:?}", event),
+            }
+        }
+
+        b.shutdown().await.unwrap();
+        assert_eq!(b.state(), SctpState::Closed);
+        assert!(matches!(
+            a_rx.events.recv().await.unwrap(),
+            SctpEvent::Connected
+        ));
+        assert!(matches!(
+            a_rx.events.recv().await.unwrap(),
+            SctpEvent::Closed
+        ));
+        assert!(a.wait_connected().await.is_err());
+    }
+}
@@ -0,0 +1,344 @@
+//! SCTP associations carried over a UDP socket, optionally inside DTLS as
+//! data channels are, for data-only links and for testing SCTP under loss
+//! without a peer connection.
+
+use std::{net::SocketAddr, sync::Arc};
+
+use tokio::{net::UdpSocket, sync::mpsc::UnboundedReceiver, task::JoinHandle};
+
+use crate::{
+    dtls::{DtlsConfig, DtlsSession},
+    error::{Result, WebRTCError},
+    sctp::{SctpAssociation, SctpEvent, SctpMessage, SctpOptions, XorShift},
+};
+
+/// Largest datagram read from the socket.
+const MAX_DATAGRAM_SIZE: usize = 2048;
+
+#[derive(Clone, Default)]
+pub struct SctpOverUdpConfig {
+    pub sctp: SctpOptions,
+    /// Run SCTP inside DTLS.  Both ends need it, one as client and one as
+    /// server.
+    pub dtls: Option<DtlsConfig>,
+    /// Fraction of outgoing datagrams dropped, to test behavior under loss.
+    pub loss_rate: f64,
+}
+
+pub struct SctpOverUdpReceivers {
+    pub messages: UnboundedReceiver<SctpMessage>,
+    pub events: UnboundedReceiver<SctpEvent>,
+}
+
+/// An association with the peer at `remote_addr`, running until dropped.
+pub struct SctpOverUdp {
+    association: Arc<SctpAssociation>,
+    dtls: Option<Arc<DtlsSession>>,
+    local_addr: SocketAddr,
+    remote_addr: SocketAddr,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl SctpOverUdp {
+    /// Carry an association over `socket`, datagrams from anywhere but
+    /// `remote_addr` are ignored.
+    pub async fn new(
+        socket: UdpSocket,
+        remote_addr: SocketAddr,
+        config: SctpOverUdpConfig,
+    ) -> Result<(Self, SctpOverUdpReceivers)> {
+        socket.connect(remote_addr).await.map_err(io_error)?;
+        let local_addr = socket.local_addr().map_err(io_error)?;
+        let socket = Arc::new(socket);
+
+        let (association, receivers) = SctpAssociation::new(config.sctp);
+        let association = Arc::new(association);
+        let mut tasks = vec![];
+        let dtls = match config.dtls {
+            Some(dtls_config) => {
+                let (dtls, dtls_receivers) = DtlsSession::start(&dtls_config)?;
+                let dtls = Arc::new(dtls);
+                tasks.push(tokio::spawn(send_datagrams(
+                    socket.clone(),
+                    dtls_receivers.packets,
+                    config.loss_rate,
+                )));
+                tasks.push(tokio::spawn(encrypt_packets(
+                    receivers.packets,
+                    dtls.clone(),
+                )));
+                tasks.push(tokio::spawn(forward_packets(
+                    dtls_receivers.data,
+                    association.clone(),
+                )));
+                Some(dtls)
+            }
+            None => {
+                tasks.push(tokio::spawn(send_datagrams(
+                    socket.clone(),
+                    receivers.packets,
+                    config.loss_rate,
+                )));
+                None
+            }
+        };
+        tasks.push(tokio::spawn(receive_datagrams(
+            socket,
+            association.clone(),
+            dtls.clone(),
+        )));
+
+        Ok((
+            Self {
+                association,
+                dtls,
+                local_addr,
+                remote_addr,
+                tasks,
+            },
+            SctpOverUdpReceivers {
+                messages: receivers.messages,
+                events: receivers.events,
+            },
+        ))
+    }
+
+    pub fn association(&self) -> &SctpAssociation {
+        &self.association
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn remote_addr(&self) -> SocketAddr {
+        self.remote_addr
+    }
+
+    /// Complete the DTLS handshake, if any, then connect the association.
+    pub async fn connect(&self) -> Result<()> {
+        if let Some(dtls) = &self.dtls {
+            dtls.wait_connected().await?;
+        }
+        self.association.connect().await
+    }
+
+    /// Wait for the peer to connect the association.
+    pub async fn wait_connected(&self) -> Result<()> {
+        self.association.wait_connected().await
+    }
+
+    /// Gracefully shut down the association, the DTLS session is closed
+    /// when this is dropped.
+    pub async fn shutdown(&self) -> Result<()> {
+        self.association.shutdown().await
+    }
+}
+
+impl Drop for SctpOverUdp {
+    fn drop(&mut self) {
+        self.tasks.iter().for_each(|task| task.abort());
+    }
+}
+
+async fn send_datagrams(
+    socket: Arc<UdpSocket>,
+    mut packets: UnboundedReceiver<Vec<u8>>,
+    loss_rate: f64,
+) {
+    let mut random = XorShift::new();
+    while let Some(packet) = packets.recv().await {
+        if loss_rate > 0.0 && (random.next_u64() as f64 / u64::MAX as f64) < loss_rate {
+            continue;
+        }
+        // Lost like any other datagram, SCTP retransmits.
+        let _ = socket.send(&packet).await;
+    }
+}
+
+async fn encrypt_packets(mut packets: UnboundedReceiver<Vec<u8>>, dtls: Arc<DtlsSession>) {
+    while let Some(packet) = packets.recv().await {
+        // Dropped until the handshake completes.
+        let _ = dtls.send(&packet);
+    }
+}
+
+async fn forward_packets(
+    mut packets: UnboundedReceiver<Vec<u8>>,
+    association: Arc<SctpAssociation>,
+) {
+    while let Some(packet) = packets.recv().await {
+        association.handle_packet(&packet);
+    }
+}
+
+async fn receive_datagrams(
+    socket: Arc<UdpSocket>,
+    association: Arc<SctpAssociation>,
+    dtls: Option<Arc<DtlsSession>>,
+) {
+    let mut buffer = [0u8; MAX_DATAGRAM_SIZE];
+    loop {
+        let len = match socket.recv(&mut buffer).await {
+            Ok(len) => len,
+            Err(err) if is_transient(&err) => continue,
+            Err(err) => {
+                log::warn!("stopped receiving SCTP datagrams: {}", err);
+                return;
+            }
+        };
+        match &dtls {
+            Some(dtls) => dtls.handle_packet(&buffer[..len]),
+            None => association.handle_packet(&buffer[..len]),
+        }
+    }
+}
+
+// ICMP port unreachable while the peer isn't bound yet surfaces as a refused
+// connection, anything else won't go away by receiving again.
+fn is_transient(err: &std::io::Error) -> bool {
+    err.kind() == std::io::ErrorKind::ConnectionRefused
+}
+
+fn io_error(err: std::io::Error) -> WebRTCError {
+    WebRTCError::SctpError(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        certificate::{generate_certificate, KeyParams, KeyType, SSLCertificate},
+        dtls::DtlsRole,
+        sctp::{SctpSendOptions, SctpState},
+    };
+
+    async fn pair(
+        config_a: SctpOverUdpConfig,
+        config_b: SctpOverUdpConfig,
+    ) -> (
+        SctpOverUdp,
+        SctpOverUdpReceivers,
+        SctpOverUdp,
+        SctpOverUdpReceivers,
+    ) {
+        let socket_a = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let socket_b = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let (addr_a, addr_b) = (
+            socket_a.local_addr().unwrap(),
+            socket_b.local_addr().unwrap(),
+        );
+        let (a, a_rx) = SctpOverUdp::new(socket_a, addr_b, config_a).await.unwrap();
+        let (b, b_rx) = SctpOverUdp::new(socket_b, addr_a, config_b).await.unwrap();
+        (a, a_rx, b, b_rx)
+    }
+
+    #[test]
+    fn test_is_transient() {
+        use std::io::{Error, ErrorKind};
+
+        assert!(is_transient(&Error::from(ErrorKind::ConnectionRefused)));
+        assert!(!is_transient(&Error::from(ErrorKind::NotConnected)));
+        assert!(!is_transient(&Error::from(ErrorKind::PermissionDenied)));
+    }
+
+    #[tokio::test]
+    async fn test_sctp_over_udp() {
+        let (a, mut a_rx, b, mut b_rx) =
+            pair(SctpOverUdpConfig::default(), SctpOverUdpConfig::default()).await;
+        assert_eq!(a.remote_addr(), b.local_addr());
+        a.connect().await.unwrap();
+        b.wait_connected().await.unwrap();
+
+        let options = SctpSendOptions::default();
+        a.association()
+            .send(1, 51, b"ping", &options)
+            .await
+            .unwrap();
+        let message = b_rx.messages.recv().await.unwrap();
+        assert_eq!((message.stream_id, message.payload), (1, b"ping".to_vec()));
+        b.association()
+            .send(1, 51, b"pong", &options)
+            .await
+            .unwrap();
+        assert_eq!(
+            a_rx.messages.recv().await.unwrap().payload,
+            b"pong".to_vec()
+        );
+
+        a.association().reset_streams(&[1]).await.unwrap();
+        loop {
+            if let SctpEvent::IncomingStreamsReset(streams) = b_rx.events.recv().await.unwrap() {
+                assert_eq!(streams, vec![1]);
+                break;
+            }
+        }
+
+        a.shutdown().await.unwrap();
+        assert_eq!(a.association().state(), SctpState::Closed);
+        loop {
+            if let SctpEvent::Closed = b_rx.events.recv().await.unwrap() {
+                break;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sctp_over_udp_with_loss() {
+        let lossy = SctpOverUdpConfig {
+            loss_rate: 0.2,
+            ..SctpOverUdpConfig::default()
+        };
+        let (a, _a_rx, _b, mut b_rx) = pair(lossy.clone(), lossy).await;
+        a.connect().await.unwrap();
+        for i in 0..20u8 {
+            a.association()
+                .send(1, 51, &[i], &SctpSendOptions::default())
+                .await
+                .unwrap();
+        }
+        for i in 0..20u8 {
+            assert_eq!(b_rx.messages.recv().await.unwrap().payload, vec![i]);
+        }
+        assert!(a.association().get_metrics().tx_packets_count >= 20);
+    }
+
+    #[tokio::test]
+    async fn test_sctp_over_dtls() {
+        let certificate = || async {
+            generate_certificate(KeyParams::new(KeyType::KT_ECDSA), Duration::from_secs(60))
+                .await
+                .unwrap()
+        };
+        let (cert_a, cert_b): (SSLCertificate, SSLCertificate) =
+            (certificate().await, certificate().await);
+        let config =
+            |certificate: &SSLCertificate, role, peer: &SSLCertificate| SctpOverUdpConfig {
+                dtls: Some(DtlsConfig {
+                    certificate: certificate.clone(),
+                    role,
+                    peer_fingerprint: peer.get_fingerprint(),
+                }),
+                ..SctpOverUdpConfig::default()
+            };
+        let (a, _a_rx, b, mut b_rx) = pair(
+            config(&cert_a, DtlsRole::Client, &cert_b),
+            config(&cert_b, DtlsRole::Server, &cert_a),
+        )
+        .await;
+
+        a.connect().await.unwrap();
+        b.wait_connected().await.unwrap();
+        a.association()
+            .send(2, 53, b"encrypted", &SctpSendOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            b_rx.messages.recv().await.unwrap().payload,
+            b"encrypted".to_vec()
+        );
+        b.shutdown().await.unwrap();
+    }
+}
@@ -4,6 +4,11 @@ use libwebrtc_sys::ffi::{create_arcas_session_description, ArcasSDPType, ArcasSe
 use libwebrtc_sys::pc::jsep_api::ffi::SdpType;
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum SDPType {
     Offer,
     PrAnswer,
@@ -0,0 +1,1220 @@
+//! Typed view of an SDP for inspecting and munging descriptions.
+//!
+//! Lines are kept in their original order and anything not modelled is kept
+//! verbatim.  Modelled attributes are written back in their canonical form,
+//! so an untouched description serializes to semantically equivalent text
+//! (with CRLF line endings), and to the same text when it was already
+//! canonical, as the descriptions libwebrtc generates are.
+
+use std::{convert::TryFrom, fmt, str::FromStr};
+
+use crate::{
+    error::{Result, WebRTCError},
+    sdp::{SDPType, SessionDescription},
+    transceiver::TransceiverDirection,
+};
+
+const CRLF: &str = "\r\n";
+
+fn parse_error(description: &str, line: &str) -> WebRTCError {
+    WebRTCError::SdpParseError(description.to_string(), line.to_string())
+}
+
+/// `o=<username> <sess-id> <sess-version> <nettype> <addrtype> <address>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Origin {
+    pub username: String,
+    pub session_id: u64,
+    pub session_version: u64,
+    pub net_type: String,
+    pub addr_type: String,
+    pub address: String,
+}
+
+impl FromStr for Origin {
+    type Err = WebRTCError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let fields: Vec<_> = value.split_whitespace().collect();
+        let number = |field: &str| {
+            field
+                .parse()
+                .map_err(|_| parse_error("invalid origin", value))
+        };
+        match fields.as_slice() {
+            [username, session_id, session_version, net_type, addr_type, address] => Ok(Self {
+                username: username.to_string(),
+                session_id: number(session_id)?,
+                session_version: number(session_version)?,
+                net_type: net_type.to_string(),
+                addr_type: addr_type.to_string(),
+                address: address.to_string(),
+            }),
+            _ => Err(parse_error("invalid origin", value)),
+        }
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {} {}",
+            self.username,
+            self.session_id,
+            self.session_version,
+            self.net_type,
+            self.addr_type,
+            self.address
+        )
+    }
+}
+
+/// `a=group:<semantics> <mid> ...`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Group {
+    pub semantics: String,
+    pub mids: Vec<String>,
+}
+
+/// `a=rtpmap:<pt> <name>/<clock rate>[/<channels>]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rtpmap {
+    pub payload_type: u8,
+    pub name: String,
+    pub clock_rate: u32,
+    pub channels: Option<u16>,
+}
+
+/// `a=fmtp:<pt> <parameters>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fmtp {
+    pub payload_type: u8,
+    pub parameters: String,
+}
+
+/// `a=rtcp-fb:<pt|*> <feedback>`, `payload_type` is `None` for `*`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RtcpFb {
+    pub payload_type: Option<u8>,
+    pub feedback: String,
+}
+
+/// `a=extmap:<id>[/<direction>] <uri> [<attributes>]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extmap {
+    pub id: u16,
+    pub direction: Option<TransceiverDirection>,
+    pub uri: String,
+    pub attributes: Option<String>,
+}
+
+/// `a=ssrc:<ssrc> <attribute>[:<value>]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ssrc {
+    pub ssrc: u32,
+    pub attribute: String,
+    pub value: Option<String>,
+}
+
+/// `a=ssrc-group:<semantics> <ssrc> ...`, e.g. `FID` for RTX.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SsrcGroup {
+    pub semantics: String,
+    pub ssrcs: Vec<u32>,
+}
+
+/// `a=fingerprint:<algorithm> <value>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fingerprint {
+    pub algorithm: String,
+    pub value: String,
+}
+
+/// `a=msid:<stream id> [<track id>]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Msid {
+    pub stream_id: String,
+    pub track_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RidDirection {
+    Send,
+    Recv,
+}
+
+/// `a=rid:<id> <send|recv> [<restrictions>]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rid {
+    pub id: String,
+    pub direction: RidDirection,
+    pub restrictions: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulcastRid {
+    pub rid: String,
+    pub paused: bool,
+}
+
+/// `a=simulcast:[send <streams>] [recv <streams>]`.  Streams are separated
+/// by `;`, each with `,` separated alternatives.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Simulcast {
+    pub send: Vec<Vec<SimulcastRid>>,
+    pub recv: Vec<Vec<SimulcastRid>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Attribute {
+    Group(Group),
+    Mid(String),
+    Msid(Msid),
+    Direction(TransceiverDirection),
+    IceUfrag(String),
+    IcePwd(String),
+    IceOptions(Vec<String>),
+    IceLite,
+    /// The candidate without the `candidate:` prefix.
+    Candidate(String),
+    EndOfCandidates,
+    Fingerprint(Fingerprint),
+    Setup(String),
+    RtcpMux,
+    RtcpRsize,
+    Rtpmap(Rtpmap),
+    Fmtp(Fmtp),
+    RtcpFb(RtcpFb),
+    Extmap(Extmap),
+    ExtmapAllowMixed,
+    Ssrc(Ssrc),
+    SsrcGroup(SsrcGroup),
+    Rid(Rid),
+    Simulcast(Simulcast),
+    /// Anything else, or a known attribute that did not parse.
+    Other {
+        name: String,
+        value: Option<String>,
+    },
+}
+
+impl Attribute {
+    /// Parse the value of an `a=` line.  Never fails, unknown or malformed
+    /// attributes are kept as `Other`.
+    pub fn parse(line: &str) -> Self {
+        let (name, value) = match line.split_once(':') {
+            Some((name, value)) => (name, Some(value)),
+            None => (line, None),
+        };
+        Self::parse_known(name, value).unwrap_or_else(|| Attribute::Other {
+            name: name.to_string(),
+            value: value.map(str::to_string),
+        })
+    }
+
+    fn parse_known(name: &str, value: Option<&str>) -> Option<Self> {
+        let attribute = match (name, value) {
+            ("group", Some(value)) => {
+                let mut fields = value.split_whitespace();
+                Attribute::Group(Group {
+                    semantics: fields.next()?.to_string(),
+                    mids: fields.map(str::to_string).collect(),
+                })
+            }
+            ("mid", Some(value)) => Attribute::Mid(value.to_string()),
+            ("msid", Some(value)) => {
+                let mut fields = value.split_whitespace();
+                let msid = Msid {
+                    stream_id: fields.next()?.to_string(),
+                    track_id: fields.next().map(str::to_string),
+                };
+                // Keep anything with more tokens than modelled as it is.
+                if fields.next().is_some() {
+                    return None;
+                }
+                Attribute::Msid(msid)
+            }
+            (direction, None) if parse_direction(direction).is_some() => {
+                Attribute::Direction(parse_direction(direction)?)
+            }
+            ("ice-ufrag", Some(value)) => Attribute::IceUfrag(value.to_string()),
+            ("ice-pwd", Some(value)) => Attribute::IcePwd(value.to_string()),
+            ("ice-options", Some(value)) => {
+                Attribute::IceOptions(value.split_whitespace().map(str::to_string).collect())
+            }
+            ("ice-lite", None) => Attribute::IceLite,
+            ("candidate", Some(value)) => Attribute::Candidate(value.to_string()),
+            ("end-of-candidates", None) => Attribute::EndOfCandidates,
+            ("fingerprint", Some(value)) => {
+                let (algorithm, value) = value.split_once(' ')?;
+                Attribute::Fingerprint(Fingerprint {
+                    algorithm: algorithm.to_string(),
+                    value: value.to_string(),
+                })
+            }
+            ("setup", Some(value)) => Attribute::Setup(value.to_string()),
+            ("rtcp-mux", None) => Attribute::RtcpMux,
+            ("rtcp-rsize", None) => Attribute::RtcpRsize,
+            ("rtpmap", Some(value)) => {
+                let (payload_type, encoding) = value.split_once(' ')?;
+                let mut encoding = encoding.split('/');
+                Attribute::Rtpmap(Rtpmap {
+                    payload_type: payload_type.parse().ok()?,
+                    name: encoding.next()?.to_string(),
+                    clock_rate: encoding.next()?.parse().ok()?,
+                    channels: match encoding.next() {
+                        Some(channels) => Some(channels.parse().ok()?),
+                        None => None,
+                    },
+                })
+            }
+            ("fmtp", Some(value)) => {
+                let (payload_type, parameters) = value.split_once(' ')?;
+                Attribute::Fmtp(Fmtp {
+                    payload_type: payload_type.parse().ok()?,
+                    parameters: parameters.to_string(),
+                })
+            }
+            ("rtcp-fb", Some(value)) => {
+                let (payload_type, feedback) = value.split_once(' ')?;
+                Attribute::RtcpFb(RtcpFb {
+                    payload_type: match payload_type {
+                        "*" => None,
+                        payload_type => Some(payload_type.parse().ok()?),
+                    },
+                    feedback: feedback.to_string(),
+                })
+            }
+            ("extmap", Some(value)) => {
+                let mut fields = value.splitn(3, ' ');
+                let id = fields.next()?;
+                let (id, direction) = match id.split_once('/') {
+                    Some((id, direction)) => (id, Some(parse_direction(direction)?)),
+                    None => (id, None),
+                };
+                Attribute::Extmap(Extmap {
+                    id: id.parse().ok()?,
+                    direction,
+                    uri: fields.next()?.to_string(),
+                    attributes: fields.next().map(str::to_string),
+                })
+            }
+            ("extmap-allow-mixed", None) => Attribute::ExtmapAllowMixed,
+            ("ssrc", Some(value)) => {
+                let (ssrc, attribute) = value.split_once(' ')?;
+                let (attribute, value) = match attribute.split_once(':') {
+                    Some((attribute, value)) => (attribute, Some(value.to_string())),
+                    None => (attribute, None),
+                };
+                Attribute::Ssrc(Ssrc {
+                    ssrc: ssrc.parse().ok()?,
+                    attribute: attribute.to_string(),
+                    value,
+                })
+            }
+            ("ssrc-group", Some(value)) => {
+                let mut fields = value.split_whitespace();
+                Attribute::SsrcGroup(SsrcGroup {
+                    semantics: fields.next()?.to_string(),
+                    ssrcs: fields
+                        .map(|ssrc| ssrc.parse().ok())
+                        .collect::<Option<_>>()?,
+                })
+            }
+            ("rid", Some(value)) => {
+                let mut fields = value.splitn(3, ' ');
+                Attribute::Rid(Rid {
+                    id: fields.next()?.to_string(),
+                    direction: match fields.next()? {
+                        "send" => RidDirection::Send,
+                        "recv" => RidDirection::Recv,
+                        _ => return None,
+                    },
+                    restrictions: fields.next().map(str::to_string),
+                })
+            }
+            ("simulcast", Some(value)) => Attribute::Simulcast(parse_simulcast(value)?),
+            _ => return None,
+        };
+        Some(attribute)
+    }
+}
+
+fn parse_direction(value: &str) -> Option<TransceiverDirection> {
+    match value {
+        "sendrecv" => Some(TransceiverDirection::SendRecv),
+        "sendonly" => Some(TransceiverDirection::SendOnly),
+        "recvonly" => Some(TransceiverDirection::RecvOnly),
+        "inactive" => Some(TransceiverDirection::Inactive),
+        _ => None,
+    }
+}
+
+fn parse_simulcast(value: &str) -> Option<Simulcast> {
+    let mut simulcast = Simulcast::default();
+    let mut fields = value.split_whitespace();
+    while let Some(direction) = fields.next() {
+        let streams = fields
+            .next()?
+            .split(';')
+            .map(|alternatives| {
+                alternatives
+                    .split(',')
+                    .map(|rid| match rid.strip_prefix('~') {
+                        Some(rid) => SimulcastRid {
+                            rid: rid.to_string(),
+                            paused: true,
+                        },
+                        None => SimulcastRid {
+                            rid: rid.to_string(),
+                            paused: false,
+                        },
+                    })
+                    .collect()
+            })
+            .collect();
+        match direction {
+            "send" => simulcast.send = streams,
+            "recv" => simulcast.recv = streams,
+            _ => return None,
+        }
+    }
+    Some(simulcast)
+}
+
+fn write_simulcast_streams(
+    f: &mut fmt::Formatter<'_>,
+    direction: &str,
+    streams: &[Vec<SimulcastRid>],
+) -> fmt::Result {
+    let streams: Vec<String> = streams
+        .iter()
+        .map(|alternatives| {
+            alternatives
+                .iter()
+                .map(|rid| {
+                    if rid.paused {
+                        format!("~{}", rid.rid)
+                    } else {
+                        rid.rid.clone()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .collect();
+    write!(f, "{} {}", direction, streams.join(";"))
+}
+
+impl fmt::Display for Attribute {
+    /// The value of the `a=` line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Attribute::Group(group) => {
+                write!(f, "group:{}", group.semantics)?;
+                for mid in &group.mids {
+                    write!(f, " {}", mid)?;
+                }
+                Ok(())
+            }
+            Attribute::Mid(mid) => write!(f, "mid:{}", mid),
+            Attribute::Msid(msid) => match &msid.track_id {
+                Some(track_id) => write!(f, "msid:{} {}", msid.stream_id, track_id),
+                None => write!(f, "msid:{}", msid.stream_id),
+            },
+            Attribute::Direction(direction) => f.write_str(&direction.to_string()),
+            Attribute::IceUfrag(ufrag) => write!(f, "ice-ufrag:{}", ufrag),
+            Attribute::IcePwd(pwd) => write!(f, "ice-pwd:{}", pwd),
+            Attribute::IceOptions(options) => write!(f, "ice-options:{}", options.join(" ")),
+            Attribute::IceLite => write!(f, "ice-lite"),
+            Attribute::Candidate(candidate) => write!(f, "candidate:{}", candidate),
+            Attribute::EndOfCandidates => write!(f, "end-of-candidates"),
+            Attribute::Fingerprint(fingerprint) => write!(
+                f,
+                "fingerprint:{} {}",
+                fingerprint.algorithm, fingerprint.value
+            ),
+            Attribute::Setup(setup) => write!(f, "setup:{}", setup),
+            Attribute::RtcpMux => write!(f, "rtcp-mux"),
+            Attribute::RtcpRsize => write!(f, "rtcp-rsize"),
+            Attribute::Rtpmap(rtpmap) => {
+                write!(
+                    f,
+                    "rtpmap:{} {}/{}",
+                    rtpmap.payload_type, rtpmap.name, rtpmap.clock_rate
+                )?;
+                match rtpmap.channels {
+                    Some(channels) => write!(f, "/{}", channels),
+                    None => Ok(()),
+                }
+            }
+            Attribute::Fmtp(fmtp) => write!(f, "fmtp:{} {}", fmtp.payload_type, fmtp.parameters),
+            Attribute::RtcpFb(rtcp_fb) => match rtcp_fb.payload_type {
+                Some(payload_type) => write!(f, "rtcp-fb:{} {}", payload_type, rtcp_fb.feedback),
+                None => write!(f, "rtcp-fb:* {}", rtcp_fb.feedback),
+            },
+            Attribute::Extmap(extmap) => {
+                write!(f, "extmap:{}", extmap.id)?;
+                if let Some(direction) = &extmap.direction {
+                    write!(f, "/{}", direction.to_string())?;
+                }
+                write!(f, " {}", extmap.uri)?;
+                match &extmap.attributes {
+                    Some(attributes) => write!(f, " {}", attributes),
+                    None => Ok(()),
+                }
+            }
+            Attribute::ExtmapAllowMixed => write!(f, "extmap-allow-mixed"),
+            Attribute::Ssrc(ssrc) => match &ssrc.value {
+                Some(value) => write!(f, "ssrc:{} {}:{}", ssrc.ssrc, ssrc.attribute, value),
+                None => write!(f, "ssrc:{} {}", ssrc.ssrc, ssrc.attribute),
+            },
+            Attribute::SsrcGroup(group) => {
+                write!(f, "ssrc-group:{}", group.semantics)?;
+                for ssrc in &group.ssrcs {
+                    write!(f, " {}", ssrc)?;
+                }
+                Ok(())
+            }
+            Attribute::Rid(rid) => {
+                let direction = match rid.direction {
+                    RidDirection::Send => "send",
+                    RidDirection::Recv => "recv",
+                };
+                write!(f, "rid:{} {}", rid.id, direction)?;
+                match &rid.restrictions {
+                    Some(restrictions) => write!(f, " {}", restrictions),
+                    None => Ok(()),
+                }
+            }
+            Attribute::Simulcast(simulcast) => {
+                write!(f, "simulcast:")?;
+                if !simulcast.send.is_empty() {
+                    write_simulcast_streams(f, "send", &simulcast.send)?;
+                    if !simulcast.recv.is_empty() {
+                        write!(f, " ")?;
+                    }
+                }
+                if !simulcast.recv.is_empty() {
+                    write_simulcast_streams(f, "recv", &simulcast.recv)?;
+                }
+                Ok(())
+            }
+            Attribute::Other { name, value } => match value {
+                Some(value) => write!(f, "{}:{}", name, value),
+                None => write!(f, "{}", name),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Line {
+    Attribute(Attribute),
+    /// Any other `<type>=<value>` line, e.g. `c=`, `b=` or `t=`.
+    Field(char, String),
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Line::Attribute(attribute) => write!(f, "a={}", attribute),
+            Line::Field(kind, value) => write!(f, "{}={}", kind, value),
+        }
+    }
+}
+
+/// Attribute helpers shared by the session and media levels, ICE and DTLS
+/// attributes may be at either.
+pub trait SdpAttributes {
+    fn lines(&self) -> &[Line];
+    fn lines_mut(&mut self) -> &mut Vec<Line>;
+
+    fn attributes(&self) -> Box<dyn Iterator<Item = &Attribute> + '_> {
+        Box::new(self.lines().iter().filter_map(|line| match line {
+            Line::Attribute(attribute) => Some(attribute),
+            Line::Field(..) => None,
+        }))
+    }
+
+    fn push_attribute(&mut self, attribute: Attribute) {
+        self.lines_mut().push(Line::Attribute(attribute));
+    }
+
+    /// Remove every attribute matching `f`.
+    fn remove_attributes<F: FnMut(&Attribute) -> bool>(&mut self, mut f: F)
+    where
+        Self: Sized,
+    {
+        self.lines_mut().retain(|line| match line {
+            Line::Attribute(attribute) => !f(attribute),
+            Line::Field(..) => true,
+        });
+    }
+
+    fn ice_ufrag(&self) -> Option<&str> {
+        self.attributes().find_map(|attribute| match attribute {
+            Attribute::IceUfrag(ufrag) => Some(ufrag.as_str()),
+            _ => None,
+        })
+    }
+
+    fn ice_pwd(&self) -> Option<&str> {
+        self.attributes().find_map(|attribute| match attribute {
+            Attribute::IcePwd(pwd) => Some(pwd.as_str()),
+            _ => None,
+        })
+    }
+
+    fn fingerprint(&self) -> Option<&Fingerprint> {
+        self.attributes().find_map(|attribute| match attribute {
+            Attribute::Fingerprint(fingerprint) => Some(fingerprint),
+            _ => None,
+        })
+    }
+
+    fn setup(&self) -> Option<&str> {
+        self.attributes().find_map(|attribute| match attribute {
+            Attribute::Setup(setup) => Some(setup.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Candidates without the `candidate:` prefix.
+    fn candidates(&self) -> Vec<&str> {
+        self.attributes()
+            .filter_map(|attribute| match attribute {
+                Attribute::Candidate(candidate) => Some(candidate.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// A codec assembled from the `rtpmap`, `fmtp` and `rtcp-fb` lines of its
+/// payload type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Codec {
+    pub payload_type: u8,
+    pub name: String,
+    pub clock_rate: u32,
+    pub channels: Option<u16>,
+    pub parameters: Option<String>,
+    /// Including the `rtcp-fb:*` lines.
+    pub feedback: Vec<String>,
+}
+
+impl Codec {
+    /// Value of a `key=value` format parameter.
+    pub fn parameter(&self, key: &str) -> Option<&str> {
+        self.parameters.as_ref()?.split(';').find_map(|parameter| {
+            let (name, value) = parameter.trim().split_once('=')?;
+            if name == key {
+                Some(value)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Payload type an RTX codec retransmits.
+    pub fn associated_payload_type(&self) -> Option<u8> {
+        self.parameter("apt")?.parse().ok()
+    }
+}
+
+/// An `m=` line and the lines that follow it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaSection {
+    pub kind: String,
+    pub port: u16,
+    pub port_count: Option<u16>,
+    pub protocol: String,
+    pub formats: Vec<String>,
+    pub lines: Vec<Line>,
+}
+
+impl FromStr for MediaSection {
+    type Err = WebRTCError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let mut fields = value.split_whitespace();
+        let (kind, port, protocol) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(kind), Some(port), Some(protocol)) => (kind, port, protocol),
+            _ => return Err(parse_error("invalid media line", value)),
+        };
+        let number = |field: &str| {
+            field
+                .parse()
+                .map_err(|_| parse_error("invalid media port", value))
+        };
+        let (port, port_count) = match port.split_once('/') {
+            Some((port, count)) => (number(port)?, Some(number(count)?)),
+            None => (number(port)?, None),
+        };
+        Ok(Self {
+            kind: kind.to_string(),
+            port,
+            port_count,
+            protocol: protocol.to_string(),
+            formats: fields.map(str::to_string).collect(),
+            lines: vec![],
+        })
+    }
+}
+
+impl SdpAttributes for MediaSection {
+    fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    fn lines_mut(&mut self) -> &mut Vec<Line> {
+        &mut self.lines
+    }
+}
+
+impl MediaSection {
+    pub fn mid(&self) -> Option<&str> {
+        self.attributes().find_map(|attribute| match attribute {
+            Attribute::Mid(mid) => Some(mid.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn direction(&self) -> Option<TransceiverDirection> {
+        self.attributes().find_map(|attribute| match attribute {
+            Attribute::Direction(direction) => Some(direction.clone()),
+            _ => None,
+        })
+    }
+
+    pub fn set_direction(&mut self, direction: TransceiverDirection) {
+        let existing = self.lines.iter_mut().find_map(|line| match line {
+            Line::Attribute(Attribute::Direction(existing)) => Some(existing),
+            _ => None,
+        });
+        match existing {
+            Some(existing) => *existing = direction,
+            None => self.push_attribute(Attribute::Direction(direction)),
+        }
+    }
+
+    /// Codecs with an `rtpmap`, in the order of the `m=` line.
+    pub fn codecs(&self) -> Vec<Codec> {
+        let mut codecs: Vec<Codec> = self
+            .formats
+            .iter()
+            .filter_map(|format| {
+                let payload_type = format.parse().ok()?;
+                self.attributes().find_map(|attribute| match attribute {
+                    Attribute::Rtpmap(rtpmap) if rtpmap.payload_type == payload_type => {
+                        Some(Codec {
+                            payload_type,
+                            name: rtpmap.name.clone(),
+                            clock_rate: rtpmap.clock_rate,
+                            channels: rtpmap.channels,
+                            parameters: None,
+                            feedback: vec![],
+                        })
+                    }
+                    _ => None,
+                })
+            })
+            .collect();
+
+        for attribute in self.attributes() {
+            for codec in codecs.iter_mut() {
+                match attribute {
+                    Attribute::Fmtp(fmtp) if fmtp.payload_type == codec.payload_type => {
+                        codec.parameters = Some(fmtp.parameters.clone());
+                    }
+                    Attribute::RtcpFb(rtcp_fb)
+                        if rtcp_fb.payload_type.is_none()
+                            || rtcp_fb.payload_type == Some(codec.payload_type) =>
+                    {
+                        codec.feedback.push(rtcp_fb.feedback.clone());
+                    }
+                    _ => {}
+                }
+            }
+        }
+        codecs
+    }
+
+    /// Keep the codecs matching `f`.  RTX codecs of removed codecs are
+    /// removed with them.
+    pub fn retain_codecs<F: FnMut(&Codec) -> bool>(&mut self, mut f: F) {
+        let codecs = self.codecs();
+        let mut removed: Vec<u8> = codecs
+            .iter()
+            .filter(|codec| !f(codec))
+            .map(|codec| codec.payload_type)
+            .collect();
+        for codec in &codecs {
+            if let Some(associated) = codec.associated_payload_type() {
+                if removed.contains(&associated) && !removed.contains(&codec.payload_type) {
+                    removed.push(codec.payload_type);
+                }
+            }
+        }
+
+        self.formats.retain(|format| match format.parse::<u8>() {
+            Ok(payload_type) => !removed.contains(&payload_type),
+            Err(_) => true,
+        });
+        self.remove_attributes(|attribute| match attribute {
+            Attribute::Rtpmap(rtpmap) => removed.contains(&rtpmap.payload_type),
+            Attribute::Fmtp(fmtp) => removed.contains(&fmtp.payload_type),
+            Attribute::RtcpFb(RtcpFb {
+                payload_type: Some(payload_type),
+                ..
+            }) => removed.contains(payload_type),
+            _ => false,
+        });
+    }
+
+    /// Replace the format parameters of `payload_type`, adding an `fmtp` line
+    /// after its `rtpmap` if there is none.
+    pub fn set_fmtp(&mut self, payload_type: u8, parameters: impl Into<String>) {
+        let parameters = parameters.into();
+        if let Some(fmtp) = self.lines.iter_mut().find_map(|line| match line {
+            Line::Attribute(Attribute::Fmtp(fmtp)) if fmtp.payload_type == payload_type => {
+                Some(fmtp)
+            }
+            _ => None,
+        }) {
+            fmtp.parameters = parameters;
+            return;
+        }
+        let position = self
+            .lines
+            .iter()
+            .position(|line| {
+                matches!(line, Line::Attribute(Attribute::Rtpmap(rtpmap)) if rtpmap.payload_type == payload_type)
+            })
+            .map(|position| position + 1)
+            .unwrap_or_else(|| self.lines.len());
+        self.lines.insert(
+            position,
+            Line::Attribute(Attribute::Fmtp(Fmtp {
+                payload_type,
+                parameters,
+            })),
+        );
+    }
+
+    pub fn extmaps(&self) -> Vec<&Extmap> {
+        self.attributes()
+            .filter_map(|attribute| match attribute {
+                Attribute::Extmap(extmap) => Some(extmap),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn ssrcs(&self) -> Vec<&Ssrc> {
+        self.attributes()
+            .filter_map(|attribute| match attribute {
+                Attribute::Ssrc(ssrc) => Some(ssrc),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn ssrc_groups(&self) -> Vec<&SsrcGroup> {
+        self.attributes()
+            .filter_map(|attribute| match attribute {
+                Attribute::SsrcGroup(group) => Some(group),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn rids(&self) -> Vec<&Rid> {
+        self.attributes()
+            .filter_map(|attribute| match attribute {
+                Attribute::Rid(rid) => Some(rid),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn simulcast(&self) -> Option<&Simulcast> {
+        self.attributes().find_map(|attribute| match attribute {
+            Attribute::Simulcast(simulcast) => Some(simulcast),
+            _ => None,
+        })
+    }
+}
+
+impl fmt::Display for MediaSection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "m={} {}", self.kind, self.port)?;
+        if let Some(port_count) = self.port_count {
+            write!(f, "/{}", port_count)?;
+        }
+        write!(f, " {}", self.protocol)?;
+        for format in &self.formats {
+            write!(f, " {}", format)?;
+        }
+        f.write_str(CRLF)?;
+        for line in &self.lines {
+            write!(f, "{}{}", line, CRLF)?;
+        }
+        Ok(())
+    }
+}
+
+/// A parsed session description.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sdp {
+    pub origin: Origin,
+    pub session_name: String,
+    /// Session level lines after `s=`, e.g. `t=` and the bundle group.
+    pub lines: Vec<Line>,
+    pub media: Vec<MediaSection>,
+}
+
+impl SdpAttributes for Sdp {
+    fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    fn lines_mut(&mut self) -> &mut Vec<Line> {
+        &mut self.lines
+    }
+}
+
+impl Sdp {
+    pub fn parse(sdp: &str) -> Result<Self> {
+        let mut origin = None;
+        let mut session_name = None;
+        let mut lines = vec![];
+        let mut media: Vec<MediaSection> = vec![];
+
+        for line in sdp.lines() {
+            if line.is_empty() {
+                continue;
+            }
+            let (kind, value) = match line.split_once('=') {
+                Some((kind, value)) if kind.len() == 1 => (kind.chars().next().unwrap(), value),
+                _ => return Err(parse_error("expected <type>=<value>", line)),
+            };
+            match kind {
+                'v' if media.is_empty() => {
+                    if value != "0" {
+                        return Err(parse_error("unsupported version", line));
+                    }
+                }
+                'o' if media.is_empty() => origin = Some(value.parse()?),
+                's' if media.is_empty() => session_name = Some(value.to_string()),
+                'm' => media.push(value.parse()?),
+                _ => {
+                    let line = match kind {
+                        'a' => Line::Attribute(Attribute::parse(value)),
+                        kind => Line::Field(kind, value.to_string()),
+                    };
+                    match media.last_mut() {
+                        Some(section) => section.lines.push(line),
+                        None => lines.push(line),
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            origin: origin.ok_or_else(|| parse_error("missing origin", ""))?,
+            session_name: session_name.ok_or_else(|| parse_error("missing session name", ""))?,
+            lines,
+            media,
+        })
+    }
+
+    /// Parse and validate with libwebrtc.
+    pub fn to_session_description(&self, kind: SDPType) -> Result<SessionDescription> {
+        SessionDescription::new(kind, self.to_string())
+    }
+
+    pub fn groups(&self) -> Vec<&Group> {
+        self.attributes()
+            .filter_map(|attribute| match attribute {
+                Attribute::Group(group) => Some(group),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn bundle_groups(&self) -> Vec<&Group> {
+        self.groups()
+            .into_iter()
+            .filter(|group| group.semantics == "BUNDLE")
+            .collect()
+    }
+
+    pub fn media_by_mid(&self, mid: &str) -> Option<&MediaSection> {
+        self.media.iter().find(|section| section.mid() == Some(mid))
+    }
+
+    pub fn media_by_mid_mut(&mut self, mid: &str) -> Option<&mut MediaSection> {
+        self.media
+            .iter_mut()
+            .find(|section| section.mid() == Some(mid))
+    }
+
+    /// Remove a media section and its mid from the groups.
+    pub fn remove_media(&mut self, mid: &str) -> Option<MediaSection> {
+        let index = self
+            .media
+            .iter()
+            .position(|section| section.mid() == Some(mid))?;
+        for line in self.lines.iter_mut() {
+            if let Line::Attribute(Attribute::Group(group)) = line {
+                group.mids.retain(|group_mid| group_mid != mid);
+            }
+        }
+        Some(self.media.remove(index))
+    }
+}
+
+impl FromStr for Sdp {
+    type Err = WebRTCError;
+
+    fn from_str(sdp: &str) -> Result<Self> {
+        Self::parse(sdp)
+    }
+}
+
+impl TryFrom<&SessionDescription> for Sdp {
+    type Error = WebRTCError;
+
+    fn try_from(description: &SessionDescription) -> Result<Self> {
+        Self::parse(&description.to_string())
+    }
+}
+
+impl fmt::Display for Sdp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v=0{}", CRLF)?;
+        write!(f, "o={}{}", self.origin, CRLF)?;
+        write!(f, "s={}{}", self.session_name, CRLF)?;
+        for line in &self.lines {
+            write!(f, "{}{}", line, CRLF)?;
+        }
+        for section in &self.media {
+            write!(f, "{}", section)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OFFER: &str = "v=0\r\n\
+        o=- 4611731400430051336 2 IN IP4 127.0.0.1\r\n\
+        s=-\r\n\
+        t=0 0\r\n\
+        a=group:BUNDLE 0 1\r\n\
+        a=extmap-allow-mixed\r\n\
+        a=msid-semantic: WMS stream\r\n\
+        m=audio 9 UDP/TLS/RTP/SAVPF 111 0\r\n\
+        c=IN IP4 0.0.0.0\r\n\
+        a=rtcp:9 IN IP4 0.0.0.0\r\n\
+        a=ice-ufrag:EsAw\r\n\
+        a=ice-pwd:P2uYro0UCOQ4zxjKXaWCBui1\r\n\
+        a=ice-options:trickle\r\n\
+        a=fingerprint:sha-256 D2:FA:0E:C3:22:59:5E:14:D2:FA:0E:C3:22:59:5E:14:D2:FA:0E:C3:22:59:5E:14:D2:FA:0E:C3:22:59:5E:14\r\n\
+        a=setup:actpass\r\n\
+        a=mid:0\r\n\
+        a=extmap:1 urn:ietf:params:rtp-hdrext:ssrc-audio-level\r\n\
+        a=sendrecv\r\n\
+        a=msid:stream audio\r\n\
+        a=rtcp-mux\r\n\
+        a=rtpmap:111 opus/48000/2\r\n\
+        a=rtcp-fb:111 transport-cc\r\n\
+        a=fmtp:111 minptime=10;useinbandfec=1\r\n\
+        a=rtpmap:0 PCMU/8000\r\n\
+        a=ssrc:1001 cname:abc\r\n\
+        m=video 9 UDP/TLS/RTP/SAVPF 96 97 98 99\r\n\
+        c=IN IP4 0.0.0.0\r\n\
+        a=ice-ufrag:EsAw\r\n\
+        a=ice-pwd:P2uYro0UCOQ4zxjKXaWCBui1\r\n\
+        a=fingerprint:sha-256 D2:FA:0E:C3:22:59:5E:14:D2:FA:0E:C3:22:59:5E:14:D2:FA:0E:C3:22:59:5E:14:D2:FA:0E:C3:22:59:5E:14\r\n\
+        a=setup:actpass\r\n\
+        a=mid:1\r\n\
+        a=extmap:3/sendonly urn:ietf:params:rtp-hdrext:sdes:rtp-stream-id\r\n\
+        a=sendonly\r\n\
+        a=rtcp-mux\r\n\
+        a=rtcp-rsize\r\n\
+        a=rtpmap:96 VP8/90000\r\n\
+        a=rtcp-fb:96 nack pli\r\n\
+        a=rtpmap:97 rtx/90000\r\n\
+        a=fmtp:97 apt=96\r\n\
+        a=rtpmap:98 VP9/90000\r\n\
+        a=fmtp:98 profile-id=0\r\n\
+        a=rtpmap:99 rtx/90000\r\n\
+        a=fmtp:99 apt=98\r\n\
+        a=rtcp-fb:* nack\r\n\
+        a=rid:h send max-width=1280\r\n\
+        a=rid:l send\r\n\
+        a=simulcast:send h;~l\r\n\
+        a=ssrc-group:FID 2001 2002\r\n\
+        a=ssrc:2001 cname:abc\r\n\
+        a=ssrc:2002 cname:abc\r\n";
+
+    #[test]
+    fn test_round_trip() {
+        let sdp = Sdp::parse(OFFER).unwrap();
+        assert_eq!(sdp.to_string(), OFFER);
+        assert_eq!(Sdp::parse(&OFFER.replace("\r\n", "\n")).unwrap(), sdp);
+
+        assert_eq!(sdp.origin.session_id, 4611731400430051336);
+        assert_eq!(sdp.origin.session_version, 2);
+        assert_eq!(
+            sdp.bundle_groups(),
+            vec![&Group {
+                semantics: "BUNDLE".into(),
+                mids: vec!["0".into(), "1".into()],
+            }]
+        );
+        assert!(Sdp::parse("v=0\r\ns=-\r\n").is_err());
+        assert!(Sdp::parse("v=0\r\no=- 1 2 IN IP4 127.0.0.1\r\ns=-\r\nm=audio\r\n").is_err());
+    }
+
+    #[test]
+    fn test_typed_attributes() {
+        let sdp = Sdp::parse(OFFER).unwrap();
+        let audio = sdp.media_by_mid("0").unwrap();
+        assert_eq!(audio.kind, "audio");
+        assert_eq!(audio.ice_ufrag(), Some("EsAw"));
+        assert_eq!(audio.fingerprint().unwrap().algorithm, "sha-256");
+        assert_eq!(audio.setup(), Some("actpass"));
+        assert_eq!(audio.direction(), Some(TransceiverDirection::SendRecv));
+        let opus = &audio.codecs()[0];
+        assert_eq!(opus.name, "opus");
+        assert_eq!(opus.channels, Some(2));
+        assert_eq!(opus.parameter("useinbandfec"), Some("1"));
+        assert_eq!(opus.feedback, vec!["transport-cc".to_string()]);
+
+        let video = sdp.media_by_mid("1").unwrap();
+        assert_eq!(
+            video.extmaps()[0].direction,
+            Some(TransceiverDirection::SendOnly)
+        );
+        assert_eq!(video.ssrc_groups()[0].ssrcs, vec![2001, 2002]);
+        assert_eq!(
+            video.rids()[0].restrictions.as_deref(),
+            Some("max-width=1280")
+        );
+        assert_eq!(
+            video.simulcast().unwrap().send,
+            vec![
+                vec![SimulcastRid {
+                    rid: "h".into(),
+                    paused: false
+                }],
+                vec![SimulcastRid {
+                    rid: "l".into(),
+                    paused: true
+                }],
+            ]
+        );
+        let vp8 = &video.codecs()[0];
+        assert_eq!(
+            vp8.feedback,
+            vec!["nack pli".to_string(), "nack".to_string()]
+        );
+        assert_eq!(video.codecs()[1].associated_payload_type(), Some(96));
+
+        assert_eq!(
+            Attribute::parse("msid-semantic: WMS stream"),
+            Attribute::Other {
+                name: "msid-semantic".into(),
+                value: Some(" WMS stream".into())
+            }
+        );
+        // Malformed known attributes are kept as they are.
+        assert_eq!(Attribute::parse("rtpmap:x VP8").to_string(), "rtpmap:x VP8");
+    }
+
+    #[test]
+    fn test_irregular_whitespace() {
+        let simulcast = Attribute::parse("simulcast: send  h;~l");
+        assert_eq!(simulcast, Attribute::parse("simulcast:send h;~l"));
+        assert_eq!(simulcast.to_string(), "simulcast:send h;~l");
+
+        let group = Attribute::parse("group:BUNDLE  0   1 ");
+        assert_eq!(
+            group,
+            Attribute::Group(Group {
+                semantics: "BUNDLE".into(),
+                mids: vec!["0".into(), "1".into()],
+            })
+        );
+        assert_eq!(group.to_string(), "group:BUNDLE 0 1");
+
+        assert_eq!(
+            Attribute::parse("msid:stream  audio").to_string(),
+            "msid:stream audio"
+        );
+        // A token the model has no place for is not dropped.
+        let msid = Attribute::parse("msid:stream audio extra");
+        assert!(matches!(msid, Attribute::Other { .. }));
+        assert_eq!(msid.to_string(), "msid:stream audio extra");
+
+        let irregular = OFFER
+            .replace("a=group:BUNDLE 0 1", "a=group:BUNDLE  0 1")
+            .replace("a=simulcast:send h;~l", "a=simulcast: send h;~l");
+        let sdp = Sdp::parse(&irregular).unwrap();
+        assert_eq!(sdp, Sdp::parse(OFFER).unwrap());
+        assert_eq!(sdp.to_string(), OFFER);
+    }
+
+    #[test]
+    fn test_munging() {
+        let mut sdp = Sdp::parse(OFFER).unwrap();
+        let video = sdp.media_by_mid_mut("1").unwrap();
+        video.retain_codecs(|codec| codec.name != "VP8");
+        assert_eq!(video.formats, vec!["98", "99"]);
+        assert_eq!(
+            video
+                .codecs()
+                .iter()
+                .map(|codec| codec.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["VP9", "rtx"]
+        );
+        video.set_fmtp(98, "profile-id=2");
+        video.set_direction(TransceiverDirection::Inactive);
+
+        let audio = sdp.media_by_mid_mut("0").unwrap();
+        audio.set_fmtp(0, "x=1");
+        audio.remove_attributes(|attribute| matches!(attribute, Attribute::Ssrc(_)));
+
+        let munged = sdp.to_string();
+        assert!(!munged.contains("VP8"));
+        assert!(!munged.contains("a=fmtp:97"));
+        assert!(munged.contains("a=rtpmap:0 PCMU/8000\r\na=fmtp:0 x=1\r\n"));
+        assert!(munged.contains("a=fmtp:98 profile-id=2\r\n"));
+        assert!(munged.contains("a=inactive\r\n"));
+        assert!(!munged.contains("a=ssrc:1001"));
+        assert_eq!(Sdp::parse(&munged).unwrap(), sdp);
+
+        let removed = sdp.remove_media("0").unwrap();
+        assert_eq!(removed.kind, "audio");
+        assert_eq!(sdp.bundle_groups()[0].mids, vec!["1".to_string()]);
+    }
+
+    #[test]
+    fn test_session_description() {
+        let mut sdp = Sdp::parse(OFFER).unwrap();
+        sdp.remove_media("1").unwrap();
+        let description = sdp.to_session_description(SDPType::Offer).unwrap();
+        let parsed = Sdp::try_from(&description).unwrap();
+        assert_eq!(parsed.media.len(), 1);
+        assert_eq!(parsed.media[0].codecs()[0].name, "opus");
+        assert_eq!(parsed.bundle_groups()[0].mids, vec!["0".to_string()]);
+    }
+}
@@ -0,0 +1,149 @@
+//! Serialize and Deserialize in the shapes browsers use for signaling,
+//! `RTCSessionDescriptionInit` and `RTCIceCandidateInit`.
+
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    ice_candidate::ICECandidate,
+    sdp::{SDPType, SessionDescription},
+};
+
+#[derive(Serialize, Deserialize)]
+struct SessionDescriptionInit {
+    #[serde(rename = "type")]
+    kind: SDPType,
+    sdp: String,
+}
+
+impl Serialize for SessionDescription {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SessionDescriptionInit {
+            kind: self.get_type(),
+            sdp: self.to_string(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SessionDescription {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let init = SessionDescriptionInit::deserialize(deserializer)?;
+        SessionDescription::new(init.kind, init.sdp).map_err(D::Error::custom)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct IceCandidateInit {
+    candidate: String,
+    #[serde(rename = "sdpMid")]
+    sdp_mid: Option<String>,
+    #[serde(rename = "sdpMLineIndex")]
+    sdp_mline_index: Option<u32>,
+}
+
+impl Serialize for ICECandidate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        IceCandidateInit {
+            candidate: self.to_string(),
+            sdp_mid: Some(self.sdp_mid()),
+            sdp_mline_index: Some(self.sdp_mline_index()),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ICECandidate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let init = IceCandidateInit::deserialize(deserializer)?;
+        ICECandidate::from_sdp(
+            init.sdp_mid.unwrap_or_default(),
+            init.sdp_mline_index.unwrap_or_default(),
+            init.candidate,
+        )
+        .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::peer_connection::{AudioSenderStats, PeerConnectionStats};
+
+    const OFFER: &str = "v=0\r\n\
+        o=- 1 2 IN IP4 127.0.0.1\r\n\
+        s=-\r\n\
+        t=0 0\r\n\
+        m=audio 9 UDP/TLS/RTP/SAVPF 111\r\n\
+        c=IN IP4 0.0.0.0\r\n\
+        a=ice-ufrag:EsAw\r\n\
+        a=ice-pwd:P2uYro0UCOQ4zxjKXaWCBui1\r\n\
+        a=fingerprint:sha-256 D2:FA:0E:C3:22:59:5E:14:D2:FA:0E:C3:22:59:5E:14:D2:FA:0E:C3:22:59:5E:14:D2:FA:0E:C3:22:59:5E:14\r\n\
+        a=setup:actpass\r\n\
+        a=mid:0\r\n\
+        a=sendrecv\r\n\
+        a=rtcp-mux\r\n\
+        a=rtpmap:111 opus/48000/2\r\n";
+
+    #[test]
+    fn test_session_description() {
+        let description: SessionDescription =
+            serde_json::from_value(json!({ "type": "offer", "sdp": OFFER })).unwrap();
+        let value = serde_json::to_value(&description).unwrap();
+        assert_eq!(value["type"], "offer");
+        assert!(value["sdp"].as_str().unwrap().contains("a=mid:0"));
+
+        assert_eq!(serde_json::to_value(SDPType::PrAnswer).unwrap(), "pranswer");
+        assert!(serde_json::from_value::<SessionDescription>(
+            json!({ "type": "offer", "sdp": "" })
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_ice_candidate() {
+        let candidate = "candidate:1 1 udp 2122260223 192.0.2.1 61764 typ host generation 0";
+        let parsed: ICECandidate = serde_json::from_value(json!({
+            "candidate": candidate,
+            "sdpMid": "0",
+            "sdpMLineIndex": 0,
+            "usernameFragment": "EsAw",
+        }))
+        .unwrap();
+        assert_eq!(parsed.sdp_mid(), "0");
+
+        let value = serde_json::to_value(&parsed).unwrap();
+        assert_eq!(value["sdpMid"], "0");
+        assert_eq!(value["sdpMLineIndex"], 0);
+        assert!(value["candidate"]
+            .as_str()
+            .unwrap()
+            .starts_with("candidate:1 1 udp"));
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = PeerConnectionStats {
+            video_sender_stats: vec![],
+            video_receiver_stats: vec![],
+            audio_sender_stats: vec![AudioSenderStats {
+                ssrc: 1,
+                packets_sent: 2,
+                bytes_sent: 3,
+                remote_packets_lost: -1,
+                remote_jitter: 0.5,
+                remote_round_trip_time: 0.25,
+                audio_level: 0.125,
+                total_audio_energy: 1.0,
+            }],
+            audio_receiver_stats: vec![],
+        };
+        let value = serde_json::to_value(&stats).unwrap();
+        assert_eq!(value["audio_sender_stats"][0]["remote_packets_lost"], -1);
+
+        let parsed: PeerConnectionStats = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed.audio_sender_stats[0].bytes_sent, 3);
+        assert_eq!(parsed.audio_sender_stats[0].remote_round_trip_time, 0.25);
+    }
+}
@@ -90,8 +90,8 @@ impl AudioEncoderFactoryImpl for SharedAudioEncoderFactory {
             thread::spawn(move || loop {
                 select! {
                     recv(encoded_rx) -> encoded_buf_res => {
-                        if let Ok(buf) = encoded_buf_res {
-                            pool.push_encoded_frame(buf);
+                        if let Ok(frame) = encoded_buf_res {
+                            pool.push_encoded_frame(frame.data);
                         }
                     },
                     recv(cancel_rx) -> _ => {
@@ -126,7 +126,7 @@ mod tests {
         audio_track_source::AudioTrackSource,
         encoded_audio_frame_producer::GStreamerOpusAudioFrameProducer,
         factory::{Factory, FactoryConfig},
-        peer_connection::{PeerConnectionConfig, SDPSemantic},
+        peer_connection::{ICETransportPolicy, PeerConnectionConfig, SDPSemantic},
         peer_connection_observer::ObserverSenders,
         transceiver::{TransceiverDirection, TransceiverInit},
     };
@@ -138,7 +138,9 @@ mod tests {
         set_arcas_log_level(LoggingSeverity::LS_ERROR);
         let arcas_factory = Factory::new();
         let opus_enc_factory = Box::from(SharedAudioEncoderFactory::new(
-            Some(Box::from(GStreamerOpusAudioFrameProducer::new(2, 8000, 0))),
+            Some(Box::from(
+                GStreamerOpusAudioFrameProducer::new(2, 8000, 0).unwrap(),
+            )),
             vec![ArcasAudioCodecSpec {
                 format: ArcasSdpAudioFormat {
                     name: "opus".to_owned(),
@@ -196,6 +198,8 @@ mod tests {
                 PeerConnectionConfig {
                     sdp_semantics: SDPSemantic::UnifiedPlan,
                     ice_servers: vec![],
+                    ice_transport_policy: ICETransportPolicy::All,
+                    certificates: vec![],
                 },
                 ObserverSenders {
                     ice_candidate: Some(ice_tx),
@@ -220,6 +224,8 @@ mod tests {
                 PeerConnectionConfig {
                     sdp_semantics: SDPSemantic::UnifiedPlan,
                     ice_servers: vec![],
+                    ice_transport_policy: ICETransportPolicy::All,
+                    certificates: vec![],
                 },
                 ObserverSenders {
                     ice_candidate: Some(ice_tx2),
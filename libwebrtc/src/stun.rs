@@ -0,0 +1,465 @@
+//! STUN messages with typed attributes, encoded and decoded by libwebrtc's
+//! `cricket::IceMessage`.
+
+use std::{
+    convert::TryFrom,
+    net::{IpAddr, SocketAddr},
+};
+
+use cxx::{let_cxx_string, UniquePtr};
+use libwebrtc_sys::stun::ffi::{
+    create_arcas_ice_message, validate_stun_fingerprint, ArcasCxxIntegrityStatus, ArcasICEMessage,
+    ArcasStunAttributeValue, IceAttributeType, StunAttributeValueType,
+};
+pub use libwebrtc_sys::stun::ffi::{StunAttributeType, StunMessageType};
+
+use crate::error::{Result, WebRTCError};
+
+pub const STUN_TRANSACTION_ID_LENGTH: usize = 12;
+/// RFC 5780 attributes, libwebrtc has no constants for them.
+pub const STUN_ATTR_RESPONSE_ORIGIN: u16 = 0x802B;
+pub const STUN_ATTR_OTHER_ADDRESS: u16 = 0x802C;
+
+const ATTR_MAPPED_ADDRESS: u16 = StunAttributeType::STUN_ATTR_MAPPED_ADDRESS.repr as u16;
+const ATTR_USERNAME: u16 = StunAttributeType::STUN_ATTR_USERNAME.repr as u16;
+const ATTR_MESSAGE_INTEGRITY: u16 = StunAttributeType::STUN_ATTR_MESSAGE_INTEGRITY.repr as u16;
+const ATTR_ERROR_CODE: u16 = StunAttributeType::STUN_ATTR_ERROR_CODE.repr as u16;
+const ATTR_UNKNOWN_ATTRIBUTES: u16 = StunAttributeType::STUN_ATTR_UNKNOWN_ATTRIBUTES.repr as u16;
+const ATTR_REALM: u16 = StunAttributeType::STUN_ATTR_REALM.repr as u16;
+const ATTR_NONCE: u16 = StunAttributeType::STUN_ATTR_NONCE.repr as u16;
+const ATTR_XOR_MAPPED_ADDRESS: u16 = StunAttributeType::STUN_ATTR_XOR_MAPPED_ADDRESS.repr as u16;
+const ATTR_SOFTWARE: u16 = StunAttributeType::STUN_ATTR_SOFTWARE.repr as u16;
+const ATTR_ALTERNATE_SERVER: u16 = StunAttributeType::STUN_ATTR_ALTERNATE_SERVER.repr as u16;
+const ATTR_FINGERPRINT: u16 = StunAttributeType::STUN_ATTR_FINGERPRINT.repr as u16;
+const ATTR_PRIORITY: u16 = IceAttributeType::STUN_ATTR_PRIORITY.repr as u16;
+const ATTR_USE_CANDIDATE: u16 = IceAttributeType::STUN_ATTR_USE_CANDIDATE.repr as u16;
+const ATTR_ICE_CONTROLLED: u16 = IceAttributeType::STUN_ATTR_ICE_CONTROLLED.repr as u16;
+const ATTR_ICE_CONTROLLING: u16 = IceAttributeType::STUN_ATTR_ICE_CONTROLLING.repr as u16;
+const ATTR_NOMINATION: u16 = IceAttributeType::STUN_ATTR_NOMINATION.repr as u16;
+const ATTR_GOOG_NETWORK_INFO: u16 = IceAttributeType::STUN_ATTR_GOOG_NETWORK_INFO.repr as u16;
+
+const VALUE_ADDRESS: u32 = StunAttributeValueType::STUN_VALUE_ADDRESS.repr;
+const VALUE_XOR_ADDRESS: u32 = StunAttributeValueType::STUN_VALUE_XOR_ADDRESS.repr;
+const VALUE_UINT32: u32 = StunAttributeValueType::STUN_VALUE_UINT32.repr;
+const VALUE_UINT64: u32 = StunAttributeValueType::STUN_VALUE_UINT64.repr;
+const VALUE_BYTE_STRING: u32 = StunAttributeValueType::STUN_VALUE_BYTE_STRING.repr;
+const VALUE_ERROR_CODE: u32 = StunAttributeValueType::STUN_VALUE_ERROR_CODE.repr;
+const VALUE_UINT16_LIST: u32 = StunAttributeValueType::STUN_VALUE_UINT16_LIST.repr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StunAttribute {
+    MappedAddress(SocketAddr),
+    XorMappedAddress(SocketAddr),
+    AlternateServer(SocketAddr),
+    /// Address the response was sent from (RFC 5780).
+    ResponseOrigin(SocketAddr),
+    /// The server's alternate address and port (RFC 5780).
+    OtherAddress(SocketAddr),
+    Username(String),
+    Realm(String),
+    Nonce(String),
+    Software(String),
+    ErrorCode {
+        code: u16,
+        reason: String,
+    },
+    UnknownAttributes(Vec<u16>),
+    /// Only decoded, use `StunMessage::add_message_integrity`.
+    MessageIntegrity(Vec<u8>),
+    /// Only decoded, use `StunMessage::add_fingerprint`.
+    Fingerprint(u32),
+    Priority(u32),
+    UseCandidate,
+    IceControlled(u64),
+    IceControlling(u64),
+    Nomination(u32),
+    /// Network id in the upper 16 bits, network cost in the lower.
+    GoogNetworkInfo(u32),
+    /// Any other attribute, numbers are in network byte order.
+    Other {
+        attr_type: u16,
+        value: Vec<u8>,
+    },
+}
+
+impl StunAttribute {
+    pub fn attr_type(&self) -> u16 {
+        match self {
+            StunAttribute::MappedAddress(_) => ATTR_MAPPED_ADDRESS,
+            StunAttribute::XorMappedAddress(_) => ATTR_XOR_MAPPED_ADDRESS,
+            StunAttribute::AlternateServer(_) => ATTR_ALTERNATE_SERVER,
+            StunAttribute::ResponseOrigin(_) => STUN_ATTR_RESPONSE_ORIGIN,
+            StunAttribute::OtherAddress(_) => STUN_ATTR_OTHER_ADDRESS,
+            StunAttribute::Username(_) => ATTR_USERNAME,
+            StunAttribute::Realm(_) => ATTR_REALM,
+            StunAttribute::Nonce(_) => ATTR_NONCE,
+            StunAttribute::Software(_) => ATTR_SOFTWARE,
+            StunAttribute::ErrorCode { .. } => ATTR_ERROR_CODE,
+            StunAttribute::UnknownAttributes(_) => ATTR_UNKNOWN_ATTRIBUTES,
+            StunAttribute::MessageIntegrity(_) => ATTR_MESSAGE_INTEGRITY,
+            StunAttribute::Fingerprint(_) => ATTR_FINGERPRINT,
+            StunAttribute::Priority(_) => ATTR_PRIORITY,
+            StunAttribute::UseCandidate => ATTR_USE_CANDIDATE,
+            StunAttribute::IceControlled(_) => ATTR_ICE_CONTROLLED,
+            StunAttribute::IceControlling(_) => ATTR_ICE_CONTROLLING,
+            StunAttribute::Nomination(_) => ATTR_NOMINATION,
+            StunAttribute::GoogNetworkInfo(_) => ATTR_GOOG_NETWORK_INFO,
+            StunAttribute::Other { attr_type, .. } => *attr_type,
+        }
+    }
+
+    fn from_cxx(value: ArcasStunAttributeValue) -> Result<Self> {
+        let text = |bytes: Vec<u8>| String::from_utf8_lossy(&bytes).into_owned();
+        let address = |value: &ArcasStunAttributeValue| -> Result<SocketAddr> {
+            let ip: IpAddr = value.address.parse().map_err(|_| {
+                WebRTCError::StunError(format!("invalid address {}", value.address))
+            })?;
+            Ok(SocketAddr::new(ip, value.port))
+        };
+
+        let attribute = match (value.attr_type, value.value_type.repr) {
+            (ATTR_MAPPED_ADDRESS, VALUE_ADDRESS) => StunAttribute::MappedAddress(address(&value)?),
+            (ATTR_XOR_MAPPED_ADDRESS, VALUE_XOR_ADDRESS) => {
+                StunAttribute::XorMappedAddress(address(&value)?)
+            }
+            (ATTR_ALTERNATE_SERVER, VALUE_ADDRESS) => {
+                StunAttribute::AlternateServer(address(&value)?)
+            }
+            (STUN_ATTR_RESPONSE_ORIGIN, VALUE_ADDRESS) => {
+                StunAttribute::ResponseOrigin(address(&value)?)
+            }
+            (STUN_ATTR_OTHER_ADDRESS, VALUE_ADDRESS) => {
+                StunAttribute::OtherAddress(address(&value)?)
+            }
+            (ATTR_USERNAME, VALUE_BYTE_STRING) => StunAttribute::Username(text(value.bytes)),
+            (ATTR_REALM, VALUE_BYTE_STRING) => StunAttribute::Realm(text(value.bytes)),
+            (ATTR_NONCE, VALUE_BYTE_STRING) => StunAttribute::Nonce(text(value.bytes)),
+            (ATTR_SOFTWARE, VALUE_BYTE_STRING) => StunAttribute::Software(text(value.bytes)),
+            (ATTR_ERROR_CODE, VALUE_ERROR_CODE) => StunAttribute::ErrorCode {
+                code: value.error_code as u16,
+                reason: value.reason,
+            },
+            (ATTR_UNKNOWN_ATTRIBUTES, VALUE_UINT16_LIST) => {
+                StunAttribute::UnknownAttributes(value.list)
+            }
+            (ATTR_MESSAGE_INTEGRITY, VALUE_BYTE_STRING) => {
+                StunAttribute::MessageIntegrity(value.bytes)
+            }
+            (ATTR_FINGERPRINT, VALUE_UINT32) => StunAttribute::Fingerprint(value.number as u32),
+            (ATTR_PRIORITY, VALUE_UINT32) => StunAttribute::Priority(value.number as u32),
+            (ATTR_USE_CANDIDATE, VALUE_BYTE_STRING) => StunAttribute::UseCandidate,
+            (ATTR_ICE_CONTROLLED, VALUE_UINT64) => StunAttribute::IceControlled(value.number),
+            (ATTR_ICE_CONTROLLING, VALUE_UINT64) => StunAttribute::IceControlling(value.number),
+            (ATTR_NOMINATION, VALUE_UINT32) => StunAttribute::Nomination(value.number as u32),
+            (ATTR_GOOG_NETWORK_INFO, VALUE_UINT32) => {
+                StunAttribute::GoogNetworkInfo(value.number as u32)
+            }
+            (attr_type, _) => StunAttribute::Other {
+                attr_type,
+                value: value.raw,
+            },
+        };
+        Ok(attribute)
+    }
+}
+
+/// A STUN message, including the ICE attributes of RFC 8445.
+pub struct StunMessage {
+    inner: UniquePtr<ArcasICEMessage>,
+}
+
+// Owned exclusively, the message isn't shared with libwebrtc.
+unsafe impl Send for StunMessage {}
+
+impl StunMessage {
+    /// A message with a random transaction id.
+    pub fn new(message_type: StunMessageType) -> Self {
+        let transaction_id = nanoid::nanoid!(STUN_TRANSACTION_ID_LENGTH);
+        Self::with_transaction_id(message_type, transaction_id.as_bytes())
+            .expect("generated transaction id has the STUN length")
+    }
+
+    /// Responses reuse the transaction id of their request.
+    pub fn with_transaction_id(
+        message_type: StunMessageType,
+        transaction_id: &[u8],
+    ) -> Result<Self> {
+        if transaction_id.len() != STUN_TRANSACTION_ID_LENGTH {
+            return Err(WebRTCError::StunError(format!(
+                "transaction id must be {} bytes, got {}",
+                STUN_TRANSACTION_ID_LENGTH,
+                transaction_id.len()
+            )));
+        }
+        let mut inner = create_arcas_ice_message();
+        inner.pin_mut().set_type(message_type.repr);
+        let_cxx_string!(id = transaction_id);
+        inner.pin_mut().set_transaction_id(&id);
+        Ok(Self { inner })
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let mut inner = create_arcas_ice_message();
+        if !inner.pin_mut().read_slice(data) {
+            return Err(WebRTCError::StunError("malformed STUN message".into()));
+        }
+        Ok(Self { inner })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        self.inner.write()
+    }
+
+    pub fn message_type(&self) -> StunMessageType {
+        StunMessageType {
+            repr: self.inner.get_type(),
+        }
+    }
+
+    pub fn transaction_id(&self) -> Vec<u8> {
+        self.inner.transaction_id().as_bytes().to_vec()
+    }
+
+    /// Attributes in message order, repeated ones included.
+    pub fn attributes(&self) -> Result<Vec<StunAttribute>> {
+        self.inner
+            .attributes()
+            .into_iter()
+            .map(StunAttribute::from_cxx)
+            .collect()
+    }
+
+    /// The first attribute of `attr_type`.
+    pub fn attribute(&self, attr_type: u16) -> Result<Option<StunAttribute>> {
+        let value = self.inner.attribute(attr_type);
+        if value.value_type == StunAttributeValueType::STUN_VALUE_UNKNOWN {
+            return Ok(None);
+        }
+        StunAttribute::from_cxx(value).map(Some)
+    }
+
+    pub fn username(&self) -> Option<String> {
+        match self.attribute(ATTR_USERNAME) {
+            Ok(Some(StunAttribute::Username(username))) => Some(username),
+            _ => None,
+        }
+    }
+
+    pub fn xor_mapped_address(&self) -> Option<SocketAddr> {
+        match self.attribute(ATTR_XOR_MAPPED_ADDRESS) {
+            Ok(Some(StunAttribute::XorMappedAddress(address))) => Some(address),
+            _ => None,
+        }
+    }
+
+    /// The error code of an error response.
+    pub fn error_code(&self) -> Option<u16> {
+        match self.attribute(ATTR_ERROR_CODE) {
+            Ok(Some(StunAttribute::ErrorCode { code, .. })) => Some(code),
+            _ => None,
+        }
+    }
+
+    /// Append an attribute.  MESSAGE-INTEGRITY and FINGERPRINT are computed
+    /// over the attributes before them, so add them last.
+    pub fn add_attribute(&mut self, attribute: StunAttribute) -> Result<()> {
+        let attr_type = attribute.attr_type();
+        let inner = self.inner.pin_mut();
+        match attribute {
+            StunAttribute::MappedAddress(address)
+            | StunAttribute::AlternateServer(address)
+            | StunAttribute::ResponseOrigin(address)
+            | StunAttribute::OtherAddress(address) => {
+                inner.add_address(attr_type, false, address.ip().to_string(), address.port())
+            }
+            StunAttribute::XorMappedAddress(address) => {
+                inner.add_address(attr_type, true, address.ip().to_string(), address.port())
+            }
+            StunAttribute::Username(text)
+            | StunAttribute::Realm(text)
+            | StunAttribute::Nonce(text)
+            | StunAttribute::Software(text) => inner.add_byte_string(attr_type, text.as_bytes()),
+            StunAttribute::ErrorCode { code, reason } => inner.add_error_code(code.into(), reason),
+            StunAttribute::UnknownAttributes(types) => inner.add_uint16_list(attr_type, &types),
+            StunAttribute::Priority(value)
+            | StunAttribute::Nomination(value)
+            | StunAttribute::GoogNetworkInfo(value) => inner.add_uint32(attr_type, value),
+            StunAttribute::UseCandidate => inner.add_byte_string(attr_type, &[]),
+            StunAttribute::IceControlled(value) | StunAttribute::IceControlling(value) => {
+                inner.add_uint64(attr_type, value)
+            }
+            StunAttribute::Other { value, .. } => inner.add_byte_string(attr_type, &value),
+            StunAttribute::MessageIntegrity(_) | StunAttribute::Fingerprint(_) => {
+                return Err(WebRTCError::StunError(format!(
+                    "attribute {:#06x} is computed, not added",
+                    attr_type
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    /// Add MESSAGE-INTEGRITY keyed with `password`, the ICE password for
+    /// short-term credentials.
+    pub fn add_message_integrity(&mut self, password: &str) -> Result<()> {
+        if !self.inner.pin_mut().add_message_integrity(password.into()) {
+            return Err(WebRTCError::StunError(
+                "failed to add MESSAGE-INTEGRITY".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn add_fingerprint(&mut self) -> Result<()> {
+        if !self.inner.pin_mut().add_fingerprint() {
+            return Err(WebRTCError::StunError("failed to add FINGERPRINT".into()));
+        }
+        Ok(())
+    }
+
+    /// Check MESSAGE-INTEGRITY of a decoded message against `password`.
+    pub fn validate_message_integrity(&mut self, password: &str) -> bool {
+        let_cxx_string!(password = password);
+        self.inner
+            .pin_mut()
+            .validate_message_integrity(&password)
+            .repr
+            == ArcasCxxIntegrityStatus::kIntegrityOk.repr
+    }
+}
+
+/// Whether `data` is a STUN message with a valid FINGERPRINT, the usual way
+/// to tell STUN apart from other packets on the same socket.
+pub fn validate_fingerprint(data: &[u8]) -> bool {
+    validate_stun_fingerprint(data)
+}
+
+impl TryFrom<&[u8]> for StunMessage {
+    type Error = WebRTCError;
+
+    fn try_from(value: &[u8]) -> Result<Self> {
+        StunMessage::decode(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binding_request_round_trip() {
+        let mut request = StunMessage::new(StunMessageType::STUN_BINDING_REQUEST);
+        request
+            .add_attribute(StunAttribute::Username("remote:local".into()))
+            .unwrap();
+        request
+            .add_attribute(StunAttribute::Priority(0x6e00_1eff))
+            .unwrap();
+        request
+            .add_attribute(StunAttribute::IceControlling(0x1234_5678_9abc_def0))
+            .unwrap();
+        request.add_attribute(StunAttribute::UseCandidate).unwrap();
+        request.add_message_integrity("password").unwrap();
+        request.add_fingerprint().unwrap();
+        assert!(request
+            .add_attribute(StunAttribute::Fingerprint(0))
+            .is_err());
+
+        let data = request.encode();
+        assert!(validate_fingerprint(&data));
+        let mut corrupted = data.clone();
+        corrupted[24] ^= 0xff;
+        assert!(!validate_fingerprint(&corrupted));
+
+        let mut decoded = StunMessage::try_from(data.as_slice()).unwrap();
+        assert_eq!(
+            decoded.message_type(),
+            StunMessageType::STUN_BINDING_REQUEST
+        );
+        assert_eq!(decoded.transaction_id(), request.transaction_id());
+        assert_eq!(decoded.username().unwrap(), "remote:local");
+
+        let attributes = decoded.attributes().unwrap();
+        assert_eq!(attributes.len(), 6);
+        assert_eq!(
+            &attributes[1..4],
+            &[
+                StunAttribute::Priority(0x6e00_1eff),
+                StunAttribute::IceControlling(0x1234_5678_9abc_def0),
+                StunAttribute::UseCandidate,
+            ]
+        );
+        assert!(matches!(attributes[5], StunAttribute::Fingerprint(_)));
+
+        assert!(decoded.validate_message_integrity("password"));
+        assert!(!decoded.validate_message_integrity("wrong"));
+    }
+
+    #[test]
+    fn test_other_and_repeated_attributes() {
+        let mut message = StunMessage::new(StunMessageType::STUN_BINDING_REQUEST);
+        message
+            .add_attribute(StunAttribute::Software("first".into()))
+            .unwrap();
+        message
+            .add_attribute(StunAttribute::Software("second".into()))
+            .unwrap();
+        // Decoded by libwebrtc as a list of u16, which has no variant here.
+        let misc_info = StunAttribute::Other {
+            attr_type: IceAttributeType::STUN_ATTR_GOOG_MISC_INFO.repr as u16,
+            value: vec![0, 1, 0, 2],
+        };
+        message.add_attribute(misc_info.clone()).unwrap();
+
+        let decoded = StunMessage::decode(&message.encode()).unwrap();
+        let attributes = decoded.attributes().unwrap();
+        assert_eq!(
+            attributes,
+            vec![
+                StunAttribute::Software("first".into()),
+                StunAttribute::Software("second".into()),
+                misc_info,
+            ]
+        );
+        assert_eq!(
+            decoded.attribute(ATTR_SOFTWARE).unwrap(),
+            Some(StunAttribute::Software("first".into()))
+        );
+    }
+
+    #[test]
+    fn test_error_response() {
+        let transaction_id = b"0123456789ab";
+        let mut response = StunMessage::with_transaction_id(
+            StunMessageType::STUN_BINDING_ERROR_RESPONSE,
+            transaction_id,
+        )
+        .unwrap();
+        response
+            .add_attribute(StunAttribute::ErrorCode {
+                code: 487,
+                reason: "Role Conflict".into(),
+            })
+            .unwrap();
+        response
+            .add_attribute(StunAttribute::XorMappedAddress(
+                "192.0.2.1:32853".parse().unwrap(),
+            ))
+            .unwrap();
+
+        let decoded = StunMessage::decode(&response.encode()).unwrap();
+        assert_eq!(decoded.transaction_id(), transaction_id);
+        assert_eq!(decoded.error_code(), Some(487));
+        assert_eq!(
+            decoded.xor_mapped_address(),
+            Some("192.0.2.1:32853".parse().unwrap())
+        );
+        assert!(decoded.attribute(ATTR_NONCE).unwrap().is_none());
+
+        assert!(
+            StunMessage::with_transaction_id(StunMessageType::STUN_BINDING_RESPONSE, b"short")
+                .is_err()
+        );
+        assert!(StunMessage::decode(&[0, 1, 2]).is_err());
+    }
+}
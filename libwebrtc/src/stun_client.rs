@@ -0,0 +1,472 @@
+//! STUN binding requests (RFC 5389) and NAT mapping behavior discovery
+//! (RFC 5780) without a peer connection.
+
+use std::{net::SocketAddr, time::Duration};
+
+use tokio::{
+    net::UdpSocket,
+    time::{timeout_at, Instant},
+};
+
+use crate::{
+    error::{Result, WebRTCError},
+    stun::{StunAttribute, StunMessage, StunMessageType},
+};
+
+/// Largest datagram a STUN response is read into.
+const MAX_STUN_MESSAGE_SIZE: usize = 1500;
+/// RFC 5389 waits Rm times the initial RTO after the last request.
+const FINAL_WAIT_FACTOR: u32 = 16;
+const TRY_ALTERNATE: u16 = 300;
+
+#[derive(Debug, Clone)]
+pub struct StunClientOptions {
+    /// Retransmission timeout of the first request, doubled on each retry.
+    pub initial_rto: Duration,
+    /// Requests sent before giving up, Rc in RFC 5389.
+    pub max_requests: u32,
+    /// 300 Try Alternate redirects followed before giving up.
+    pub max_alternates: usize,
+}
+
+impl Default for StunClientOptions {
+    fn default() -> Self {
+        Self {
+            initial_rto: Duration::from_millis(500),
+            max_requests: 7,
+            max_alternates: 3,
+        }
+    }
+}
+
+/// The outcome of a successful binding request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StunBinding {
+    pub local_addr: SocketAddr,
+    /// Server that answered, after any redirects.
+    pub server_addr: SocketAddr,
+    /// Server reflexive address, XOR-MAPPED-ADDRESS or MAPPED-ADDRESS for
+    /// RFC 3489 servers.
+    pub mapped_addr: SocketAddr,
+    /// Time from the last request sent to the response.
+    pub rtt: Duration,
+    /// Servers that redirected with 300 Try Alternate, in order.
+    pub alternate_servers: Vec<SocketAddr>,
+    /// RFC 5780 OTHER-ADDRESS, required for NAT behavior discovery.
+    pub other_addr: Option<SocketAddr>,
+    pub response_origin: Option<SocketAddr>,
+}
+
+/// NAT mapping behavior of RFC 5780 section 4.3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NatMapping {
+    /// The mapped address is the local address, or for a wildcard bind the
+    /// address of the interface the server is reached through.
+    NoNat,
+    EndpointIndependent,
+    AddressDependent,
+    AddressAndPortDependent,
+}
+
+/// A UDP socket sending STUN binding requests.
+pub struct StunClient {
+    socket: UdpSocket,
+    options: StunClientOptions,
+}
+
+impl StunClient {
+    pub async fn bind(local_bind: SocketAddr) -> Result<Self> {
+        let socket = UdpSocket::bind(local_bind).await.map_err(io_error)?;
+        Ok(Self {
+            socket,
+            options: StunClientOptions::default(),
+        })
+    }
+
+    pub fn with_options(mut self, options: StunClientOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.socket.local_addr().map_err(io_error)
+    }
+
+    /// Send a binding request to `server_addr`, following 300 Try Alternate
+    /// redirects.
+    pub async fn binding(&self, server_addr: SocketAddr) -> Result<StunBinding> {
+        let mut server_addr = server_addr;
+        let mut alternate_servers = vec![];
+        loop {
+            let (response, rtt) = self.transaction(server_addr).await?;
+            if response.message_type() == StunMessageType::STUN_BINDING_RESPONSE {
+                return binding_from_response(
+                    &response,
+                    self.local_addr()?,
+                    server_addr,
+                    rtt,
+                    alternate_servers,
+                );
+            }
+
+            let attributes = response.attributes()?;
+            let (code, reason) = attributes
+                .iter()
+                .find_map(|attribute| match attribute {
+                    StunAttribute::ErrorCode { code, reason } => Some((*code, reason.clone())),
+                    _ => None,
+                })
+                .ok_or_else(|| {
+                    WebRTCError::StunError(format!(
+                        "unexpected message type {:#06x} from {}",
+                        response.message_type().repr,
+                        server_addr
+                    ))
+                })?;
+            let alternate = attributes.iter().find_map(|attribute| match attribute {
+                StunAttribute::AlternateServer(address) => Some(*address),
+                _ => None,
+            });
+            match alternate {
+                Some(alternate)
+                    if code == TRY_ALTERNATE
+                        && alternate_servers.len() < self.options.max_alternates =>
+                {
+                    alternate_servers.push(server_addr);
+                    server_addr = alternate;
+                }
+                _ => {
+                    return Err(WebRTCError::StunError(format!(
+                        "binding error {} {} from {}",
+                        code, reason, server_addr
+                    )))
+                }
+            }
+        }
+    }
+
+    /// Classify the NAT mapping behavior with the RFC 5780 mapping tests.
+    /// `None` if the server doesn't send OTHER-ADDRESS.
+    pub async fn nat_mapping(&self, server_addr: SocketAddr) -> Result<Option<NatMapping>> {
+        // Test I: the primary address.
+        let first = self.binding(server_addr).await?;
+        let other_addr = match first.other_addr {
+            Some(other_addr) => other_addr,
+            None => return Ok(None),
+        };
+        if first.mapped_addr == self.route_local_addr(first.server_addr).await? {
+            return Ok(Some(NatMapping::NoNat));
+        }
+
+        // Test II: the alternate IP with the primary port.
+        let second = self
+            .binding(SocketAddr::new(other_addr.ip(), first.server_addr.port()))
+            .await?;
+        if second.mapped_addr == first.mapped_addr {
+            return Ok(Some(NatMapping::EndpointIndependent));
+        }
+
+        // Test III: the alternate IP and port.
+        let third = self.binding(other_addr).await?;
+        if third.mapped_addr == second.mapped_addr {
+            Ok(Some(NatMapping::AddressDependent))
+        } else {
+            Ok(Some(NatMapping::AddressAndPortDependent))
+        }
+    }
+
+    /// The local address requests to `server_addr` are sent from, with a
+    /// wildcard bind resolved to the address of the interface routing to it.
+    async fn route_local_addr(&self, server_addr: SocketAddr) -> Result<SocketAddr> {
+        let local_addr = self.local_addr()?;
+        if !local_addr.ip().is_unspecified() {
+            return Ok(local_addr);
+        }
+        // Connecting a UDP socket picks the route without sending anything.
+        let probe = UdpSocket::bind(SocketAddr::new(local_addr.ip(), 0))
+            .await
+            .map_err(io_error)?;
+        probe.connect(server_addr).await.map_err(io_error)?;
+        let route_ip = probe.local_addr().map_err(io_error)?.ip();
+        Ok(SocketAddr::new(route_ip, local_addr.port()))
+    }
+
+    /// Send one request, retransmitted with exponential backoff, and wait
+    /// for the response with the same transaction id.
+    async fn transaction(&self, server_addr: SocketAddr) -> Result<(StunMessage, Duration)> {
+        let request = StunMessage::new(StunMessageType::STUN_BINDING_REQUEST);
+        let transaction_id = request.transaction_id();
+        let data = request.encode();
+
+        let mut buffer = [0u8; MAX_STUN_MESSAGE_SIZE];
+        let mut rto = self.options.initial_rto;
+        for attempt in 1..=self.options.max_requests {
+            self.socket
+                .send_to(&data, server_addr)
+                .await
+                .map_err(io_error)?;
+            let sent = Instant::now();
+            let wait = if attempt == self.options.max_requests {
+                self.options.initial_rto * FINAL_WAIT_FACTOR
+            } else {
+                rto
+            };
+
+            while let Ok(received) =
+                timeout_at(sent + wait, self.socket.recv_from(&mut buffer)).await
+            {
+                let (len, _) = received.map_err(io_error)?;
+                // Anything else on the socket, or a late response to an
+                // earlier transaction, is ignored.
+                match StunMessage::decode(&buffer[..len]) {
+                    Ok(response) if response.transaction_id() == transaction_id => {
+                        return Ok((response, sent.elapsed()));
+                    }
+                    _ => {}
+                }
+            }
+            rto *= 2;
+        }
+
+        Err(WebRTCError::StunError(format!(
+            "no response from {} after {} requests",
+            server_addr, self.options.max_requests
+        )))
+    }
+}
+
+/// Binding request from an ephemeral socket bound to `local_bind`.
+pub async fn stun_binding(server_addr: SocketAddr, local_bind: SocketAddr) -> Result<StunBinding> {
+    StunClient::bind(local_bind)
+        .await?
+        .binding(server_addr)
+        .await
+}
+
+fn binding_from_response(
+    response: &StunMessage,
+    local_addr: SocketAddr,
+    server_addr: SocketAddr,
+    rtt: Duration,
+    alternate_servers: Vec<SocketAddr>,
+) -> Result<StunBinding> {
+    let mut xor_mapped_addr = None;
+    let mut mapped_addr = None;
+    let mut other_addr = None;
+    let mut response_origin = None;
+    for attribute in response.attributes()? {
+        match attribute {
+            StunAttribute::XorMappedAddress(address) => xor_mapped_addr = Some(address),
+            StunAttribute::MappedAddress(address) => mapped_addr = Some(address),
+            StunAttribute::OtherAddress(address) => other_addr = Some(address),
+            StunAttribute::ResponseOrigin(address) => response_origin = Some(address),
+            _ => {}
+        }
+    }
+
+    let mapped_addr = xor_mapped_addr
+        .or(mapped_addr)
+        .ok_or_else(|| WebRTCError::StunError(format!("no mapped address from {}", server_addr)))?;
+    Ok(StunBinding {
+        local_addr,
+        server_addr,
+        mapped_addr,
+        rtt,
+        alternate_servers,
+        other_addr,
+        response_origin,
+    })
+}
+
+fn io_error(err: std::io::Error) -> WebRTCError {
+    WebRTCError::StunError(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::IpAddr,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use super::*;
+    use crate::turn_server::StunServer;
+
+    /// Answers binding requests on a socket bound to `bind`, `respond`
+    /// gets the server and client addresses and returns the attributes of
+    /// the response, or `None` to drop the request.
+    async fn stand_in_server<F>(bind: SocketAddr, respond: F) -> SocketAddr
+    where
+        F: Fn(SocketAddr, SocketAddr) -> Option<Vec<StunAttribute>> + Send + 'static,
+    {
+        let socket = UdpSocket::bind(bind).await.unwrap();
+        let local_addr = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buffer = [0u8; MAX_STUN_MESSAGE_SIZE];
+            while let Ok((len, from)) = socket.recv_from(&mut buffer).await {
+                let request = StunMessage::decode(&buffer[..len]).unwrap();
+                let attributes = match respond(local_addr, from) {
+                    Some(attributes) => attributes,
+                    None => continue,
+                };
+                let is_error = attributes
+                    .iter()
+                    .any(|attribute| matches!(attribute, StunAttribute::ErrorCode { .. }));
+                let message_type = if is_error {
+                    StunMessageType::STUN_BINDING_ERROR_RESPONSE
+                } else {
+                    StunMessageType::STUN_BINDING_RESPONSE
+                };
+                let data = {
+                    let mut response =
+                        StunMessage::with_transaction_id(message_type, &request.transaction_id())
+                            .unwrap();
+                    for attribute in attributes {
+                        response.add_attribute(attribute).unwrap();
+                    }
+                    response.encode()
+                };
+                socket.send_to(&data, from).await.unwrap();
+            }
+        });
+        local_addr
+    }
+
+    fn any_port(ip: &str) -> SocketAddr {
+        SocketAddr::new(ip.parse().unwrap(), 0)
+    }
+
+    fn fast_options() -> StunClientOptions {
+        StunClientOptions {
+            initial_rto: Duration::from_millis(20),
+            max_requests: 3,
+            max_alternates: 3,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_binding_against_libwebrtc_server() {
+        let server = StunServer::start(any_port("127.0.0.1")).unwrap();
+        let binding = stun_binding(server.local_addr(), any_port("127.0.0.1"))
+            .await
+            .unwrap();
+        assert_eq!(binding.mapped_addr, binding.local_addr);
+        assert_eq!(binding.server_addr, server.local_addr());
+        assert!(binding.alternate_servers.is_empty());
+        assert!(binding.other_addr.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_retransmits_and_follows_alternate() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counted = requests.clone();
+        let primary = stand_in_server(any_port("127.0.0.1"), move |_, from| {
+            // Drop the first request to force a retransmission.
+            if counted.fetch_add(1, Ordering::SeqCst) == 0 {
+                return None;
+            }
+            Some(vec![StunAttribute::XorMappedAddress(from)])
+        })
+        .await;
+        let redirect = stand_in_server(any_port("127.0.0.1"), move |_, _| {
+            Some(vec![
+                StunAttribute::ErrorCode {
+                    code: TRY_ALTERNATE,
+                    reason: "Try Alternate".into(),
+                },
+                StunAttribute::AlternateServer(primary),
+            ])
+        })
+        .await;
+
+        let client = StunClient::bind(any_port("127.0.0.1"))
+            .await
+            .unwrap()
+            .with_options(fast_options());
+        let binding = client.binding(redirect).await.unwrap();
+        assert_eq!(binding.server_addr, primary);
+        assert_eq!(binding.alternate_servers, vec![redirect]);
+        assert_eq!(binding.mapped_addr, client.local_addr().unwrap());
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        let silent = stand_in_server(any_port("127.0.0.1"), |_, _| None).await;
+        assert!(client.binding(silent).await.is_err());
+    }
+
+    /// An RFC 5780 server on 127.0.0.1 and 127.0.0.2 in front of a NAT
+    /// that maps the client with `nat`, given the server address.
+    async fn classify(nat: fn(SocketAddr) -> SocketAddr) -> Option<NatMapping> {
+        let respond = move |server, _| Some(vec![StunAttribute::XorMappedAddress(nat(server))]);
+        let other_addr = stand_in_server(any_port("127.0.0.2"), respond).await;
+        let primary = stand_in_server(any_port("127.0.0.1"), move |server, _| {
+            Some(vec![
+                StunAttribute::XorMappedAddress(nat(server)),
+                StunAttribute::OtherAddress(other_addr),
+            ])
+        })
+        .await;
+        stand_in_server(SocketAddr::new(other_addr.ip(), primary.port()), respond).await;
+
+        let client = StunClient::bind(any_port("127.0.0.1"))
+            .await
+            .unwrap()
+            .with_options(fast_options());
+        client.nat_mapping(primary).await.unwrap()
+    }
+
+    fn mapped_ip(server: SocketAddr) -> IpAddr {
+        match server.ip() {
+            IpAddr::V4(ip) => [198, 51, 100, ip.octets()[3]].into(),
+            IpAddr::V6(_) => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_nat_mapping() {
+        assert_eq!(
+            classify(|_| "198.51.100.1:40000".parse().unwrap()).await,
+            Some(NatMapping::EndpointIndependent)
+        );
+        assert_eq!(
+            classify(|server| SocketAddr::new(mapped_ip(server), 40000)).await,
+            Some(NatMapping::AddressDependent)
+        );
+        assert_eq!(
+            classify(|server| SocketAddr::new(mapped_ip(server), server.port())).await,
+            Some(NatMapping::AddressAndPortDependent)
+        );
+
+        let client = StunClient::bind(any_port("127.0.0.1")).await.unwrap();
+        let plain = stand_in_server(any_port("127.0.0.1"), |_, from| {
+            Some(vec![StunAttribute::XorMappedAddress(from)])
+        })
+        .await;
+        assert_eq!(client.nat_mapping(plain).await.unwrap(), None);
+
+        let server = StunServer::start(any_port("127.0.0.1")).unwrap();
+        assert_eq!(client.nat_mapping(server.local_addr()).await.unwrap(), None);
+
+        // Mapped to the address of the interface a wildcard bind sends from.
+        let unmapped = stand_in_server(any_port("127.0.0.1"), |server, from| {
+            Some(vec![
+                StunAttribute::XorMappedAddress(from),
+                StunAttribute::OtherAddress(SocketAddr::new(
+                    "127.0.0.2".parse().unwrap(),
+                    server.port(),
+                )),
+            ])
+        })
+        .await;
+        let client = StunClient::bind(any_port("0.0.0.0"))
+            .await
+            .unwrap()
+            .with_options(fast_options());
+        assert_eq!(
+            client.nat_mapping(unmapped).await.unwrap(),
+            Some(NatMapping::NoNat)
+        );
+    }
+}
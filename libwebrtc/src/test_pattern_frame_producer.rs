@@ -0,0 +1,439 @@
+use bytes::{Bytes, BytesMut};
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::{
+    error::{Result, WebRTCError},
+    media_clock::{MediaClock, VIDEO_CLOCK_RATE_HZ},
+    ok_or_return,
+    raw_video_frame_producer::RawFrameProducer,
+    video_codec::VideoCodec,
+    video_frame::RawVideoFrame,
+};
+
+// Marks frames carrying a burn in so receivers can tell them apart from other
+// content.
+const BURN_IN_MAGIC: u64 = 0xA5C3;
+const BURN_IN_MAGIC_BITS: usize = 16;
+const BURN_IN_FRAME_NUMBER_BITS: usize = 32;
+const BURN_IN_TIMESTAMP_BITS: usize = 64;
+// Bits per row of the burn in, the widest row decides the block size.
+const BURN_IN_ROW_BITS: usize = BURN_IN_TIMESTAMP_BITS;
+
+const LUMA_BLACK: u8 = 16;
+const LUMA_WHITE: u8 = 235;
+const CHROMA_NEUTRAL: u8 = 128;
+
+/// A color in limited range BT.601 YUV.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Yuv {
+    pub y: u8,
+    pub u: u8,
+    pub v: u8,
+}
+
+impl Yuv {
+    pub const fn new(y: u8, u: u8, v: u8) -> Self {
+        Self { y, u, v }
+    }
+
+    pub const BLACK: Yuv = Yuv::new(LUMA_BLACK, CHROMA_NEUTRAL, CHROMA_NEUTRAL);
+    pub const WHITE: Yuv = Yuv::new(LUMA_WHITE, CHROMA_NEUTRAL, CHROMA_NEUTRAL);
+}
+
+// 75% SMPTE color bars, left to right.
+const SMPTE_BARS: [Yuv; 7] = [
+    Yuv::new(180, 128, 128),
+    Yuv::new(162, 44, 142),
+    Yuv::new(131, 156, 44),
+    Yuv::new(112, 72, 58),
+    Yuv::new(84, 184, 198),
+    Yuv::new(65, 100, 212),
+    Yuv::new(35, 212, 114),
+];
+
+// Reverse blue bars under the main bars.
+const SMPTE_CASTELLATIONS: [Yuv; 7] = [
+    Yuv::new(35, 212, 114),
+    Yuv::BLACK,
+    Yuv::new(84, 184, 198),
+    Yuv::BLACK,
+    Yuv::new(131, 156, 44),
+    Yuv::BLACK,
+    Yuv::new(180, 128, 128),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestPattern {
+    SmpteBars,
+    /// A white box bouncing horizontally over a black background.
+    MovingBox,
+    SolidColor(Yuv),
+    /// Random luma, different for every frame.
+    Noise,
+}
+
+/// Frame number and capture time recovered from a frame's burn in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BurnIn {
+    pub frame_number: u32,
+    pub capture_time_ms: u64,
+}
+
+/// Draws I420 test patterns.  Every frame has a burn in of its frame number
+/// and capture time in the top left corner as two rows of black/white blocks
+/// which survive lossy encoding and can be read back with `BurnIn::decode`.
+#[derive(Debug, Clone)]
+pub struct TestPatternGenerator {
+    pub width: i32,
+    pub height: i32,
+    pub pattern: TestPattern,
+}
+
+impl TestPatternGenerator {
+    pub fn new(width: i32, height: i32, pattern: TestPattern) -> Self {
+        Self {
+            width,
+            height,
+            pattern,
+        }
+    }
+
+    /// Render a full I420 frame.
+    pub fn render(&self, frame_number: u32, capture_time_ms: u64) -> BytesMut {
+        let mut frame = I420Frame::new(self.width as usize, self.height as usize);
+        match self.pattern {
+            TestPattern::SmpteBars => frame.draw_smpte_bars(),
+            TestPattern::MovingBox => frame.draw_moving_box(frame_number),
+            TestPattern::SolidColor(color) => {
+                frame.fill_rect(0, 0, frame.width, frame.height, color)
+            }
+            TestPattern::Noise => frame.draw_noise(frame_number),
+        }
+        frame.draw_burn_in(frame_number, capture_time_ms);
+        frame.data
+    }
+}
+
+impl BurnIn {
+    /// Number of luma rows at the top of a frame the burn in occupies, `None`
+    /// when the frame is too small to carry one.
+    pub fn luma_rows(width: i32, height: i32) -> Option<usize> {
+        burn_in_block_size(width as usize, height as usize).map(|block| block * 2)
+    }
+
+    /// Read the burn in from an I420 frame of the given dimensions.  Only the
+    /// top `luma_rows` rows of the Y plane are required.  Returns `None` when
+    /// the frame does not carry a burn in.
+    pub fn decode(width: i32, height: i32, i420: &[u8]) -> Option<Self> {
+        let (width, height) = (width as usize, height as usize);
+        let block = burn_in_block_size(width, height)?;
+        if i420.len() < block * 2 * width {
+            return None;
+        }
+        let read_bits = |row: usize, offset: usize, bits: usize| -> u64 {
+            (0..bits).fold(0u64, |value, bit| {
+                let x = (offset + bit) * block + block / 2;
+                let y = row * block + block / 2;
+                let set = i420[y * width + x] > 128;
+                (value << 1) | set as u64
+            })
+        };
+
+        if read_bits(0, 0, BURN_IN_MAGIC_BITS) != BURN_IN_MAGIC {
+            return None;
+        }
+
+        Some(Self {
+            frame_number: read_bits(0, BURN_IN_MAGIC_BITS, BURN_IN_FRAME_NUMBER_BITS) as u32,
+            capture_time_ms: read_bits(1, 0, BURN_IN_TIMESTAMP_BITS),
+        })
+    }
+}
+
+// Blocks are even sized so they line up with the chroma planes.
+fn burn_in_block_size(width: usize, height: usize) -> Option<usize> {
+    let block = (width / BURN_IN_ROW_BITS) & !1;
+    if block < 2 || block * 2 > height {
+        return None;
+    }
+    Some(block)
+}
+
+struct I420Frame {
+    width: usize,
+    height: usize,
+    data: BytesMut,
+}
+
+impl I420Frame {
+    fn new(width: usize, height: usize) -> Self {
+        let chroma = width.div_ceil(2) * height.div_ceil(2);
+        let mut data = BytesMut::with_capacity(width * height + chroma * 2);
+        data.resize(width * height + chroma * 2, 0);
+        Self {
+            width,
+            height,
+            data,
+        }
+    }
+
+    fn chroma_width(&self) -> usize {
+        self.width.div_ceil(2)
+    }
+
+    fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Yuv) {
+        let x_end = (x + width).min(self.width);
+        let y_end = (y + height).min(self.height);
+        for row in y..y_end {
+            let start = row * self.width;
+            self.data[start + x..start + x_end].fill(color.y);
+        }
+
+        let chroma_width = self.chroma_width();
+        let chroma_height = self.height.div_ceil(2);
+        let u_offset = self.width * self.height;
+        let v_offset = u_offset + chroma_width * chroma_height;
+        let (cx, cx_end) = (x / 2, x_end.div_ceil(2));
+        for row in y / 2..y_end.div_ceil(2) {
+            let start = row * chroma_width;
+            self.data[u_offset + start + cx..u_offset + start + cx_end].fill(color.u);
+            self.data[v_offset + start + cx..v_offset + start + cx_end].fill(color.v);
+        }
+    }
+
+    fn draw_smpte_bars(&mut self) {
+        let bars_height = self.height * 2 / 3;
+        let castellations_height = self.height / 12;
+        for (index, (bar, castellation)) in SMPTE_BARS
+            .iter()
+            .zip(SMPTE_CASTELLATIONS.iter())
+            .enumerate()
+        {
+            let x = self.width * index / SMPTE_BARS.len();
+            let bar_width = self.width * (index + 1) / SMPTE_BARS.len() - x;
+            self.fill_rect(x, 0, bar_width, bars_height, *bar);
+            self.fill_rect(
+                x,
+                bars_height,
+                bar_width,
+                castellations_height,
+                *castellation,
+            );
+        }
+        let bottom = bars_height + castellations_height;
+        self.fill_rect(0, bottom, self.width, self.height - bottom, Yuv::BLACK);
+    }
+
+    fn draw_moving_box(&mut self, frame_number: u32) {
+        self.fill_rect(0, 0, self.width, self.height, Yuv::BLACK);
+        let size = (self.height / 4).max(2) & !1;
+        let travel = self.width.saturating_sub(size).max(1);
+        // Four pixels a frame, bouncing off both edges.
+        let position = (frame_number as usize * 4) % (travel * 2);
+        let x = if position < travel {
+            position
+        } else {
+            travel * 2 - position
+        };
+        self.fill_rect(x & !1, (self.height - size) / 2, size, size, Yuv::WHITE);
+    }
+
+    fn draw_noise(&mut self, frame_number: u32) {
+        // xorshift64*, seeded per frame so every frame is different but
+        // reproducible.
+        let mut state = 0x9E37_79B9_7F4A_7C15u64 ^ (frame_number as u64 + 1);
+        let luma = self.width * self.height;
+        for pixel in self.data[..luma].iter_mut() {
+            state ^= state >> 12;
+            state ^= state << 25;
+            state ^= state >> 27;
+            let value = (state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8;
+            *pixel = LUMA_BLACK + (value as u16 * (LUMA_WHITE - LUMA_BLACK) as u16 / 255) as u8;
+        }
+        self.data[luma..].fill(CHROMA_NEUTRAL);
+    }
+
+    fn draw_burn_in(&mut self, frame_number: u32, capture_time_ms: u64) {
+        let block = match burn_in_block_size(self.width, self.height) {
+            Some(block) => block,
+            None => return,
+        };
+        let header = (BURN_IN_MAGIC << BURN_IN_FRAME_NUMBER_BITS) | frame_number as u64;
+        let rows = [
+            (header, BURN_IN_MAGIC_BITS + BURN_IN_FRAME_NUMBER_BITS),
+            (capture_time_ms, BURN_IN_TIMESTAMP_BITS),
+        ];
+        for (row, (value, bits)) in rows.iter().enumerate() {
+            for bit in 0..*bits {
+                let set = (value >> (bits - 1 - bit)) & 1 == 1;
+                let color = if set { Yuv::WHITE } else { Yuv::BLACK };
+                self.fill_rect(bit * block, row * block, block, block, color);
+            }
+        }
+    }
+}
+
+/// `RawFrameProducer` drawing test patterns in Rust, no GStreamer required.
+pub struct TestPatternFrameProducer {
+    generator: TestPatternGenerator,
+    fps: u32,
+    clock: MediaClock,
+    cancel_rx: Option<Receiver<()>>,
+    cancel_tx: Sender<()>,
+}
+
+impl TestPatternFrameProducer {
+    pub fn new(codec: &VideoCodec, pattern: TestPattern) -> Result<Self> {
+        Self::with_clock(codec, pattern, MediaClock::new()?)
+    }
+
+    pub fn with_clock(codec: &VideoCodec, pattern: TestPattern, clock: MediaClock) -> Result<Self> {
+        let (cancel_tx, cancel_rx) = crossbeam_channel::bounded::<()>(1);
+        Ok(Self {
+            generator: TestPatternGenerator::new(
+                codec.primary.width,
+                codec.primary.height,
+                pattern,
+            ),
+            fps: codec.primary.max_frame_rate,
+            clock,
+            cancel_rx: Some(cancel_rx),
+            cancel_tx,
+        })
+    }
+}
+
+impl RawFrameProducer for TestPatternFrameProducer {
+    fn width(&self) -> i32 {
+        self.generator.width
+    }
+
+    fn height(&self) -> i32 {
+        self.generator.height
+    }
+
+    fn fps(&self) -> u32 {
+        self.fps
+    }
+
+    fn start(&mut self) -> Result<Receiver<RawVideoFrame>> {
+        let cancel_rx = self
+            .cancel_rx
+            .take()
+            .ok_or_else(|| WebRTCError::UnexpectedError("cannot call start twice".into()))?;
+        let (result_tx, result_rx) = crossbeam_channel::bounded::<RawVideoFrame>(100);
+        let mut pacer = self.clock.pacer(self.fps, cancel_rx);
+        let generator = self.generator.clone();
+
+        std::thread::spawn(move || {
+            let mut frame_number = 0u32;
+            while let Some(timestamp) = pacer.wait(VIDEO_CLOCK_RATE_HZ) {
+                let data: Bytes = generator
+                    .render(frame_number, timestamp.capture_time_ms)
+                    .freeze();
+                let raw_frame = ok_or_return!(RawVideoFrame::create_with_timestamp(
+                    generator.width,
+                    generator.height,
+                    timestamp,
+                    data,
+                ));
+                ok_or_return!(result_tx.send(raw_frame));
+                frame_number = frame_number.wrapping_add(1);
+            }
+        });
+
+        Ok(result_rx)
+    }
+
+    fn cancel(&self) {
+        let _ = self.cancel_tx.try_send(());
+    }
+}
+
+impl Drop for TestPatternFrameProducer {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: i32 = 640;
+    const HEIGHT: i32 = 480;
+
+    #[test]
+    fn test_burn_in_round_trip() {
+        for pattern in &[
+            TestPattern::SmpteBars,
+            TestPattern::MovingBox,
+            TestPattern::SolidColor(Yuv::new(81, 90, 240)),
+            TestPattern::Noise,
+        ] {
+            let generator = TestPatternGenerator::new(WIDTH, HEIGHT, *pattern);
+            let frame = generator.render(123_456, 1_650_000_000_123);
+            assert_eq!(frame.len(), (WIDTH * HEIGHT * 3 / 2) as usize);
+            assert_eq!(
+                BurnIn::decode(WIDTH, HEIGHT, &frame),
+                Some(BurnIn {
+                    frame_number: 123_456,
+                    capture_time_ms: 1_650_000_000_123,
+                })
+            );
+            // The top rows alone are enough.
+            let rows = BurnIn::luma_rows(WIDTH, HEIGHT).unwrap();
+            assert_eq!(
+                BurnIn::decode(WIDTH, HEIGHT, &frame[..rows * WIDTH as usize]),
+                BurnIn::decode(WIDTH, HEIGHT, &frame)
+            );
+        }
+    }
+
+    #[test]
+    fn test_frames_without_burn_in() {
+        let frame = vec![LUMA_BLACK; (WIDTH * HEIGHT * 3 / 2) as usize];
+        assert_eq!(BurnIn::decode(WIDTH, HEIGHT, &frame), None);
+        // Too small to carry a burn in.
+        let generator = TestPatternGenerator::new(64, 64, TestPattern::SmpteBars);
+        assert_eq!(BurnIn::decode(64, 64, &generator.render(1, 1)), None);
+        assert_eq!(BurnIn::luma_rows(64, 64), None);
+    }
+
+    #[test]
+    fn test_smpte_bars() {
+        let generator = TestPatternGenerator::new(WIDTH, HEIGHT, TestPattern::SmpteBars);
+        let frame = generator.render(0, 0);
+        let width = WIDTH as usize;
+        // Sample the middle of each bar below the burn in.
+        let y = HEIGHT as usize / 3;
+        for (index, bar) in SMPTE_BARS.iter().enumerate() {
+            let x = width * index / SMPTE_BARS.len() + width / SMPTE_BARS.len() / 2;
+            assert_eq!(frame[y * width + x], bar.y);
+        }
+    }
+
+    #[test]
+    fn test_moving_box_and_noise_change_between_frames() {
+        for pattern in &[TestPattern::MovingBox, TestPattern::Noise] {
+            let generator = TestPatternGenerator::new(WIDTH, HEIGHT, *pattern);
+            // Compare below the burn in.
+            let offset = (WIDTH * HEIGHT / 2) as usize;
+            let luma = (WIDTH * HEIGHT) as usize;
+            assert_ne!(
+                generator.render(1, 0)[offset..luma],
+                generator.render(2, 0)[offset..luma]
+            );
+        }
+    }
+
+    #[test]
+    fn test_pattern_frame_producer() {
+        let codec = VideoCodec::vp8(WIDTH, HEIGHT, 30);
+        let mut producer = TestPatternFrameProducer::new(&codec, TestPattern::SmpteBars).unwrap();
+        let rx = producer.start().unwrap();
+        let _frame = rx.recv().unwrap();
+        let _frame = rx.recv().unwrap();
+        producer.cancel();
+    }
+}
@@ -1,4 +1,5 @@
 use crate::peer_connection::{PeerConnectionStats, STATS_BUFFER_SIZE};
+use crate::{audio_track::AudioTrack, latency::ABSOLUTE_CAPTURE_TIME_URI, video_track::VideoTrack};
 use crate::{error::WebRTCError, media_type::MediaType, ok_or_return, rx_recv_async_or_err};
 use cxx::UniquePtr;
 use libwebrtc_sys::ffi::{
@@ -9,7 +10,7 @@ use libwebrtc_sys::ArcasRustRTCStatsCollectorCallback;
 use std::ptr::null;
 use tokio::sync::mpsc::{channel, Receiver};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TransceiverDirection {
     SendRecv,
     SendOnly,
@@ -128,6 +129,25 @@ impl VideoTransceiver {
         }
     }
 
+    /// The track receiving media from the remote peer.
+    pub fn receiver_track(&self) -> VideoTrack {
+        VideoTrack::new(self.cxx_transceiver.get_receiver().get_track())
+    }
+
+    /// Offer the abs-capture-time header extension so the receiver can
+    /// measure glass to glass latency, see `LatencyMonitor`.  Must be called
+    /// before creating the offer.
+    pub fn enable_absolute_capture_time(&mut self) -> Result<(), WebRTCError> {
+        let err = self
+            .cxx_transceiver
+            .enable_header_extension(ABSOLUTE_CAPTURE_TIME_URI.into());
+        if err.ok() {
+            Ok(())
+        } else {
+            Err(WebRTCError::UnexpectedError(err.message()))
+        }
+    }
+
     pub async fn get_stats(&self) -> Result<PeerConnectionStats, WebRTCError> {
         let (tx, mut rx) = channel(STATS_BUFFER_SIZE);
         self.cxx_transceiver
@@ -190,4 +210,9 @@ impl AudioTransceiver {
             Err(WebRTCError::FailedToSetDirection)
         }
     }
+
+    /// The track receiving media from the remote peer.
+    pub fn receiver_track(&self) -> AudioTrack {
+        AudioTrack::new(self.cxx_transceiver.get_receiver().get_track())
+    }
 }
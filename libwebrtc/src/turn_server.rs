@@ -0,0 +1,302 @@
+//! libwebrtc's STUN and TURN servers on local ports, so tests can gather
+//! server reflexive and relay candidates without touching the network.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use cxx::UniquePtr;
+use libwebrtc_sys::p2p::turn_server::ffi::{
+    create_arcas_stun_server, create_arcas_turn_server, ArcasStunServer, ArcasTurnAllocation,
+    ArcasTurnServer, ArcasTurnServerConfig, ArcasTurnUser,
+};
+
+use crate::{
+    error::{Result, WebRTCError},
+    peer_connection::ICEServer,
+};
+
+pub type TurnUser = ArcasTurnUser;
+
+fn local_addr(address: String, port: u16) -> Result<SocketAddr> {
+    let ip: IpAddr = address
+        .parse()
+        .map_err(|_| WebRTCError::UnexpectedError(format!("invalid address {}", address)))?;
+    Ok(SocketAddr::new(ip, port))
+}
+
+/// A STUN server running on its own thread until dropped.
+pub struct StunServer {
+    inner: UniquePtr<ArcasStunServer>,
+    local_addr: SocketAddr,
+}
+
+// The server only runs on its own thread, calls are marshalled onto it.
+unsafe impl Send for StunServer {}
+unsafe impl Sync for StunServer {}
+
+impl StunServer {
+    /// Listen for UDP binding requests on `address`, port 0 picks a free
+    /// port.
+    pub fn start(address: SocketAddr) -> Result<Self> {
+        Self::create(address, None)
+    }
+
+    /// Like `start`, answering as a NAT with the public address
+    /// `mapped_ip` would.  Clients on this host then gather server
+    /// reflexive candidates, which otherwise equal their host candidates
+    /// and are discarded.
+    pub fn start_with_mapped_ip(address: SocketAddr, mapped_ip: IpAddr) -> Result<Self> {
+        Self::create(address, Some(mapped_ip))
+    }
+
+    fn create(address: SocketAddr, mapped_ip: Option<IpAddr>) -> Result<Self> {
+        let inner = create_arcas_stun_server(
+            address.ip().to_string(),
+            address.port(),
+            mapped_ip.map(|ip| ip.to_string()).unwrap_or_default(),
+        );
+        if inner.is_null() {
+            return Err(WebRTCError::UnexpectedError(format!(
+                "failed to start STUN server on {}",
+                address
+            )));
+        }
+        let local_addr = local_addr(inner.local_address(), inner.local_port())?;
+        Ok(Self { inner, local_addr })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn url(&self) -> String {
+        format!("stun:{}", self.local_addr)
+    }
+
+    /// Binding requests answered so far.
+    pub fn binding_requests(&self) -> u32 {
+        self.inner.binding_requests()
+    }
+
+    pub fn ice_server(&self) -> ICEServer {
+        ICEServer {
+            urls: vec![self.url()],
+            username: "".into(),
+            password: "".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TurnServerConfig {
+    /// UDP address clients connect to, port 0 picks a free port.
+    pub listen_address: SocketAddr,
+    /// Local address relayed candidates are allocated on.
+    pub relay_address: IpAddr,
+    pub realm: String,
+    /// Long-term credentials accepted by the server.
+    pub users: Vec<TurnUser>,
+}
+
+impl TurnServerConfig {
+    pub fn with_user(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.users.push(TurnUser {
+            username: username.into(),
+            password: password.into(),
+        });
+        self
+    }
+}
+
+impl Default for TurnServerConfig {
+    fn default() -> Self {
+        Self {
+            listen_address: SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0),
+            relay_address: Ipv4Addr::LOCALHOST.into(),
+            realm: "arcas".into(),
+            users: vec![],
+        }
+    }
+}
+
+/// An allocation a client holds on the TURN server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TurnAllocation {
+    pub username: String,
+    pub client_addr: SocketAddr,
+    /// Transport between the client and the server, e.g. `udp`.
+    pub protocol: String,
+}
+
+impl TurnAllocation {
+    fn from_cxx(value: ArcasTurnAllocation) -> Result<Self> {
+        Ok(Self {
+            username: value.username,
+            client_addr: local_addr(value.client_address, value.client_port)?,
+            protocol: value.protocol,
+        })
+    }
+}
+
+/// A UDP TURN server running on its own thread until dropped.
+pub struct TurnServer {
+    inner: UniquePtr<ArcasTurnServer>,
+    local_addr: SocketAddr,
+    users: Vec<TurnUser>,
+}
+
+// The server only runs on its own thread, calls are marshalled onto it.
+unsafe impl Send for TurnServer {}
+unsafe impl Sync for TurnServer {}
+
+impl TurnServer {
+    pub fn start(config: TurnServerConfig) -> Result<Self> {
+        let users = config.users.clone();
+        let inner = create_arcas_turn_server(ArcasTurnServerConfig {
+            listen_address: config.listen_address.ip().to_string(),
+            listen_port: config.listen_address.port(),
+            relay_address: config.relay_address.to_string(),
+            realm: config.realm,
+            users: config.users,
+        });
+        if inner.is_null() {
+            return Err(WebRTCError::UnexpectedError(format!(
+                "failed to start TURN server on {}",
+                config.listen_address
+            )));
+        }
+        let local_addr = local_addr(inner.local_address(), inner.local_port())?;
+        Ok(Self {
+            inner,
+            local_addr,
+            users,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn url(&self) -> String {
+        format!("turn:{}?transport=udp", self.local_addr)
+    }
+
+    /// ICE server entry with the credentials of `username`, `None` if the
+    /// server wasn't configured with that user.
+    pub fn ice_server(&self, username: &str) -> Option<ICEServer> {
+        self.users
+            .iter()
+            .find(|user| user.username == username)
+            .map(|user| ICEServer {
+                urls: vec![self.url()],
+                username: user.username.clone(),
+                password: user.password.clone(),
+            })
+    }
+
+    /// Allocations currently held by clients.
+    pub fn allocations(&self) -> Result<Vec<TurnAllocation>> {
+        self.inner
+            .allocations()
+            .into_iter()
+            .map(TurnAllocation::from_cxx)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::{sync::mpsc::channel, time::timeout};
+
+    use super::*;
+    use crate::{
+        factory::Factory,
+        loopback::{connect_pair, LoopbackReceivers},
+        peer_connection::{ICETransportPolicy, PeerConnectionConfig, SDPSemantic},
+        peer_connection_observer::ObserverSenders,
+    };
+
+    #[tokio::test]
+    async fn test_server_reflexive_candidates() {
+        let mapped_ip: IpAddr = "203.0.113.1".parse().unwrap();
+        let stun =
+            StunServer::start_with_mapped_ip("127.0.0.1:0".parse().unwrap(), mapped_ip).unwrap();
+
+        let factory = Factory::new();
+        let pc_factory = factory.create_peer_connection_factory().unwrap();
+        let (candidate_tx, mut candidate_rx) = channel(100);
+        let pc = pc_factory
+            .create_peer_connection(
+                PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![stun.ice_server()]),
+                ObserverSenders {
+                    ice_candidate: Some(candidate_tx),
+                    ..ObserverSenders::default()
+                },
+            )
+            .unwrap();
+        let _dc = pc
+            .create_data_channel("test".into(), Default::default())
+            .unwrap();
+        let offer = pc.create_offer().await.unwrap();
+        pc.set_local_description(offer).await.unwrap();
+
+        let srflx = timeout(Duration::from_secs(10), async {
+            loop {
+                let candidate = candidate_rx.recv().await.unwrap().sdp();
+                if candidate.contains("typ srflx") {
+                    break candidate;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert!(srflx.contains(&format!(" {} ", mapped_ip)));
+        assert!(stun.binding_requests() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_relay_only_connection() {
+        let stun = StunServer::start("127.0.0.1:0".parse().unwrap()).unwrap();
+        assert_eq!(
+            stun.url(),
+            format!("stun:127.0.0.1:{}", stun.local_addr().port())
+        );
+
+        let turn =
+            TurnServer::start(TurnServerConfig::default().with_user("user", "secret")).unwrap();
+        assert!(turn.ice_server("nobody").is_none());
+        assert!(turn.allocations().unwrap().is_empty());
+
+        let config = || {
+            PeerConnectionConfig::new(
+                SDPSemantic::UnifiedPlan,
+                vec![stun.ice_server(), turn.ice_server("user").unwrap()],
+            )
+            .with_ice_transport_policy(ICETransportPolicy::Relay)
+        };
+        let factory1 = Factory::new();
+        let factory2 = Factory::new();
+        let pc_factory1 = factory1.create_peer_connection_factory().unwrap();
+        let pc_factory2 = factory2.create_peer_connection_factory().unwrap();
+        let (senders1, rx1) = LoopbackReceivers::create();
+        let pc1 = pc_factory1
+            .create_peer_connection(config(), senders1)
+            .unwrap();
+        let (senders2, rx2) = LoopbackReceivers::create();
+        let pc2 = pc_factory2
+            .create_peer_connection(config(), senders2)
+            .unwrap();
+
+        let _dc = pc1
+            .create_data_channel("test".into(), Default::default())
+            .unwrap();
+        let _pair = connect_pair(&pc1, &pc2, rx1, rx2).await.unwrap();
+
+        let allocations = turn.allocations().unwrap();
+        assert!(allocations.len() >= 2);
+        assert!(allocations
+            .iter()
+            .all(|allocation| allocation.username == "user" && allocation.protocol == "udp"));
+    }
+}
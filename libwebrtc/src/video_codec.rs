@@ -1,5 +1,7 @@
 use cxx::{SharedPtr, UniquePtr};
-use libwebrtc_sys::ffi::{ArcasCxxVideoCodecType, ArcasSpatialLayer, ArcasVideoCodec};
+use libwebrtc_sys::ffi::{
+    ArcasCxxInterLayerPredMode, ArcasCxxVideoCodecType, ArcasSpatialLayer, ArcasVideoCodec,
+};
 
 use crate::{
     encoded_video_frame_producer::{DEFAULT_FPS, DEFAULT_HEIGHT, DEFAULT_WIDTH},
@@ -14,6 +16,10 @@ const DEFAULT_MIN_BITRATE_KBS: u32 = 500;
 // QP max taken from some test in libwebrtc...
 const DEFAULT_QP_MAX: u32 = 56;
 
+// Limits mirror kMaxSpatialLayers and kMaxTemporalStreams in libwebrtc.
+pub const MAX_SPATIAL_LAYERS: u8 = 5;
+pub const MAX_TEMPORAL_LAYERS: u8 = 4;
+
 /**
  * NOTE: All magic values in this file come from video_encoder.cc in libwebrtc.
  */
@@ -108,6 +114,7 @@ impl From<&ArcasSpatialLayer> for VideoCodecDescriptionSpatialLayer {
 /// configuration type.
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct VideoCodecDescription {
+    codec_name: String,
     scalability_mode: String,
     width: i32,
     height: i32,
@@ -124,6 +131,7 @@ pub struct VideoCodecDescription {
 impl VideoCodecDescription {
     pub fn create_from_codec(codec: &UniquePtr<ArcasVideoCodec>) -> Self {
         Self {
+            codec_name: video_codec_type_name(codec.get_codec_type()),
             scalability_mode: codec.get_scalability_mode(),
             width: codec.get_width(),
             height: codec.get_height(),
@@ -145,6 +153,208 @@ impl VideoCodecDescription {
                 .collect(),
         }
     }
+
+    /// SDP name of the negotiated codec (e.g. "VP8").
+    pub fn codec_name(&self) -> &str {
+        &self.codec_name
+    }
+
+    pub fn codec_type(&self) -> ArcasCxxVideoCodecType {
+        video_codec_type_from_name(&self.codec_name)
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn max_framerate(&self) -> u32 {
+        self.max_framerate
+    }
+
+    /// Copy of this description with the resolution divided by
+    /// `scale_resolution_down_by` (rounded down to even dimensions for I420).
+    pub fn scaled(&self, scale_resolution_down_by: f64) -> Self {
+        let scale = scale_resolution_down_by.max(1.0);
+        Self {
+            width: (self.width as f64 / scale) as i32 & !1,
+            height: (self.height as f64 / scale) as i32 & !1,
+            ..self.clone()
+        }
+    }
+
+    /// Build a `VideoCodec` matching the primary settings of this description.
+    pub fn to_video_codec(&self) -> VideoCodec {
+        VideoCodec::new(
+            self.codec_type(),
+            VideoCodecConfig {
+                width: self.width,
+                height: self.height,
+                max_bitrate_kbs: self.max_bitrate,
+                target_bitrate_kbs: self.start_bitrate,
+                min_bitrate_kbs: self.min_bitrate,
+                max_frame_rate: self.max_framerate,
+                qp_max: self.qp_max,
+            },
+            vec![],
+            vec![],
+        )
+    }
+}
+
+pub(crate) fn video_codec_type_name(codec_type: ArcasCxxVideoCodecType) -> String {
+    match codec_type {
+        ArcasCxxVideoCodecType::kVideoCodecVP8 => "VP8".to_string(),
+        ArcasCxxVideoCodecType::kVideoCodecVP9 => "VP9".to_string(),
+        ArcasCxxVideoCodecType::kVideoCodecAV1 => "AV1".to_string(),
+        ArcasCxxVideoCodecType::kVideoCodecH264 => "H264".to_string(),
+        _ => "Unknown".to_string(),
+    }
+}
+
+pub(crate) fn video_codec_type_from_name(name: &str) -> ArcasCxxVideoCodecType {
+    match name {
+        "VP8" => ArcasCxxVideoCodecType::kVideoCodecVP8,
+        "VP9" => ArcasCxxVideoCodecType::kVideoCodecVP9,
+        "AV1" => ArcasCxxVideoCodecType::kVideoCodecAV1,
+        "H264" => ArcasCxxVideoCodecType::kVideoCodecH264,
+        _ => ArcasCxxVideoCodecType::kVideoCodecGeneric,
+    }
+}
+
+/// Inter-layer prediction used between spatial layers of a scalable stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InterLayerPrediction {
+    /// Every spatial layer is encoded independently (simulcast style, "S" modes).
+    Off,
+    /// Upper spatial layers always predict from the layer below ("L" modes).
+    On,
+    /// Upper spatial layers only predict from the layer below on key pictures.
+    OnKeyPicture,
+}
+
+impl From<InterLayerPrediction> for ArcasCxxInterLayerPredMode {
+    fn from(value: InterLayerPrediction) -> Self {
+        match value {
+            InterLayerPrediction::Off => ArcasCxxInterLayerPredMode::kOff,
+            InterLayerPrediction::On => ArcasCxxInterLayerPredMode::kOn,
+            InterLayerPrediction::OnKeyPicture => ArcasCxxInterLayerPredMode::kOnKeyPic,
+        }
+    }
+}
+
+/// Bitrate (in bps) assigned to a single spatial/temporal layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayerBitrate {
+    pub spatial_index: usize,
+    pub temporal_index: usize,
+    pub bitrate_bps: u32,
+}
+
+/// Spatial and temporal layering of a scalable (SVC) stream.
+///
+/// Spatial layers are ordered from the lowest resolution to the highest which
+/// matches the layout libwebrtc expects in `VideoCodec::spatialLayers`.
+#[derive(Debug, Clone)]
+pub struct SvcConfig {
+    pub spatial_layers: Vec<VideoCodecConfig>,
+    pub number_of_temporal_layers: u8,
+    pub inter_layer_pred: InterLayerPrediction,
+}
+
+impl SvcConfig {
+    /// Scalability mode name as defined by https://w3c.github.io/webrtc-svc/
+    pub fn scalability_mode(&self) -> String {
+        let spatial = self.spatial_layers.len();
+        let temporal = self.number_of_temporal_layers;
+        match self.inter_layer_pred {
+            InterLayerPrediction::Off if spatial > 1 => format!("S{}T{}", spatial, temporal),
+            InterLayerPrediction::OnKeyPicture if spatial > 1 => {
+                format!("L{}T{}_KEY", spatial, temporal)
+            }
+            _ => format!("L{}T{}", spatial, temporal),
+        }
+    }
+
+    /// Split the target bitrate of every spatial layer across its temporal
+    /// layers.  The ratios follow SvcRateAllocator in libwebrtc, which stops
+    /// at three layers, and SimulcastRateAllocator for four.
+    pub fn bitrate_allocation(&self) -> Vec<LayerBitrate> {
+        let mut allocation = vec![];
+        for (spatial_index, layer) in self.spatial_layers.iter().enumerate() {
+            let layer_bps = layer.target_bitrate_kbs * 1000;
+            let temporal_bps = match self.number_of_temporal_layers.min(MAX_TEMPORAL_LAYERS) {
+                0 | 1 => vec![layer_bps],
+                2 => vec![layer_bps * 2 / 3, layer_bps - layer_bps * 2 / 3],
+                3 => {
+                    let base = layer_bps / 2;
+                    let middle = layer_bps / 4;
+                    vec![base, middle, layer_bps - base - middle]
+                }
+                _ => {
+                    // 25%, 40% and 60% of the rate up to each layer.
+                    let rates_up_to = [layer_bps / 4, layer_bps / 5 * 2, layer_bps / 5 * 3];
+                    vec![
+                        rates_up_to[0],
+                        rates_up_to[1] - rates_up_to[0],
+                        rates_up_to[2] - rates_up_to[1],
+                        layer_bps - rates_up_to[2],
+                    ]
+                }
+            };
+
+            for (temporal_index, bitrate_bps) in temporal_bps.into_iter().enumerate() {
+                allocation.push(LayerBitrate {
+                    spatial_index,
+                    temporal_index,
+                    bitrate_bps,
+                });
+            }
+        }
+        allocation
+    }
+
+    fn set_on_video_codec(
+        &self,
+        codec_type: ArcasCxxVideoCodecType,
+        video_codec: &ArcasVideoCodec,
+    ) -> Result<()> {
+        let number_of_spatial_layers = self.spatial_layers.len() as u8;
+        match codec_type {
+            ArcasCxxVideoCodecType::kVideoCodecVP8 => {
+                video_codec.vp8_set_number_of_temporal_layers(self.number_of_temporal_layers);
+            }
+            ArcasCxxVideoCodecType::kVideoCodecVP9 => {
+                video_codec.vp9_set_number_of_spatial_layers(number_of_spatial_layers);
+                video_codec.vp9_set_number_of_temporal_layers(self.number_of_temporal_layers);
+                video_codec.vp9_set_inter_layer_pred(self.inter_layer_pred.into());
+            }
+            ArcasCxxVideoCodecType::kVideoCodecH264 => {
+                video_codec.h264_set_number_of_temporal_layers(self.number_of_temporal_layers);
+            }
+            _ => {
+                return Err(WebRTCError::VideoCodecUnsupportedType(format!(
+                    "{:?}",
+                    codec_type
+                )));
+            }
+        }
+
+        for (index, layer) in self.spatial_layers.iter().enumerate() {
+            let spatial_layer = layer.to_spatial_layer();
+            let spatial_layer_ref = spatial_layer
+                .as_ref()
+                .ok_or_else(|| WebRTCError::CXXUnwrapError("Arcas Spatial Layer".into()))?;
+            spatial_layer_ref.set_number_of_temporal_layers(self.number_of_temporal_layers);
+            video_codec.set_spatial_layer_at(index as u8, spatial_layer_ref);
+        }
+
+        video_codec.set_scalability_mode(self.scalability_mode());
+        Ok(())
+    }
 }
 
 pub struct VideoCodec {
@@ -152,6 +362,9 @@ pub struct VideoCodec {
     pub primary: VideoCodecConfig,
     pub spatial_layers: Vec<VideoCodecConfig>,
     pub simulcast_streams: Vec<VideoCodecConfig>,
+    /// Scalable coding settings, when set these replace the spatial layers
+    /// above.  See `VideoCodecBuilder`.
+    pub svc: Option<SvcConfig>,
 }
 
 impl VideoCodec {
@@ -182,6 +395,7 @@ impl VideoCodec {
                 max_frame_rate: fps,
                 ..VideoCodecConfig::default()
             }],
+            svc: None,
         }
     }
 
@@ -206,6 +420,7 @@ impl VideoCodec {
                 max_frame_rate: fps,
                 ..VideoCodecConfig::default()
             }],
+            svc: None,
         }
     }
 
@@ -220,17 +435,12 @@ impl VideoCodec {
             primary,
             spatial_layers,
             simulcast_streams,
+            svc: None,
         }
     }
 
     pub fn codec_name(&self) -> String {
-        match self.codec_type {
-            ArcasCxxVideoCodecType::kVideoCodecVP8 => "VP8".to_string(),
-            ArcasCxxVideoCodecType::kVideoCodecVP9 => "VP9".to_string(),
-            ArcasCxxVideoCodecType::kVideoCodecAV1 => "AV1".to_string(),
-            ArcasCxxVideoCodecType::kVideoCodecH264 => "H264".to_string(),
-            _ => "Unknown".to_string(),
-        }
+        video_codec_type_name(self.codec_type)
     }
 
     pub(crate) fn to_arcas_video_codec(&self) -> Result<SharedPtr<ArcasVideoCodec>> {
@@ -282,8 +492,198 @@ impl VideoCodec {
             simulcast_idx += 1;
         }
 
+        if let Some(ref svc) = self.svc {
+            svc.set_on_video_codec(self.codec_type, codec_ref)?;
+        }
+
         Ok(codec)
     }
+
+    /// Per layer bitrates used to prime the encoder rates.
+    pub fn bitrate_allocation(&self) -> Vec<LayerBitrate> {
+        match self.svc {
+            Some(ref svc) => svc.bitrate_allocation(),
+            None => vec![LayerBitrate {
+                spatial_index: 0,
+                temporal_index: 0,
+                bitrate_bps: self.primary.target_bitrate_kbs * 1000,
+            }],
+        }
+    }
+}
+
+/// Builder for scalable (SVC) and temporally layered codec configurations.
+#[derive(Debug, Clone)]
+pub struct VideoCodecBuilder {
+    codec_type: ArcasCxxVideoCodecType,
+    width: i32,
+    height: i32,
+    fps: u32,
+    number_of_spatial_layers: u8,
+    number_of_temporal_layers: u8,
+    inter_layer_pred: InterLayerPrediction,
+    // (min, target, max) in kbps indexed by spatial layer.
+    layer_bitrates: Vec<Option<(u32, u32, u32)>>,
+}
+
+impl VideoCodecBuilder {
+    pub fn new(codec_type: ArcasCxxVideoCodecType, width: i32, height: i32, fps: u32) -> Self {
+        Self {
+            codec_type,
+            width,
+            height,
+            fps,
+            number_of_spatial_layers: 1,
+            number_of_temporal_layers: 1,
+            inter_layer_pred: InterLayerPrediction::On,
+            layer_bitrates: vec![],
+        }
+    }
+
+    pub fn vp8(width: i32, height: i32, fps: u32) -> Self {
+        Self::new(ArcasCxxVideoCodecType::kVideoCodecVP8, width, height, fps)
+    }
+
+    pub fn vp9(width: i32, height: i32, fps: u32) -> Self {
+        Self::new(ArcasCxxVideoCodecType::kVideoCodecVP9, width, height, fps)
+    }
+
+    pub fn h264(width: i32, height: i32, fps: u32) -> Self {
+        Self::new(ArcasCxxVideoCodecType::kVideoCodecH264, width, height, fps)
+    }
+
+    /// Number of spatial layers, each lower layer halves the resolution of the
+    /// one above it.  Only VP9 supports more than one.
+    pub fn spatial_layers(mut self, number_of_spatial_layers: u8) -> Self {
+        self.number_of_spatial_layers = number_of_spatial_layers;
+        self
+    }
+
+    pub fn temporal_layers(mut self, number_of_temporal_layers: u8) -> Self {
+        self.number_of_temporal_layers = number_of_temporal_layers;
+        self
+    }
+
+    pub fn inter_layer_pred(mut self, inter_layer_pred: InterLayerPrediction) -> Self {
+        self.inter_layer_pred = inter_layer_pred;
+        self
+    }
+
+    /// Override the bitrates (kbps) of a spatial layer, index 0 is the lowest
+    /// resolution layer.
+    pub fn spatial_layer_bitrate(
+        mut self,
+        spatial_index: u8,
+        min_bitrate_kbs: u32,
+        target_bitrate_kbs: u32,
+        max_bitrate_kbs: u32,
+    ) -> Self {
+        let index = spatial_index as usize;
+        if self.layer_bitrates.len() <= index {
+            self.layer_bitrates.resize(index + 1, None);
+        }
+        self.layer_bitrates[index] = Some((min_bitrate_kbs, target_bitrate_kbs, max_bitrate_kbs));
+        self
+    }
+
+    pub fn build(self) -> Result<VideoCodec> {
+        if self.number_of_spatial_layers == 0 || self.number_of_spatial_layers > MAX_SPATIAL_LAYERS
+        {
+            return Err(WebRTCError::InvalidSvcConfig(format!(
+                "spatial layers must be between 1 and {}",
+                MAX_SPATIAL_LAYERS
+            )));
+        }
+
+        if self.number_of_temporal_layers == 0
+            || self.number_of_temporal_layers > MAX_TEMPORAL_LAYERS
+        {
+            return Err(WebRTCError::InvalidSvcConfig(format!(
+                "temporal layers must be between 1 and {}",
+                MAX_TEMPORAL_LAYERS
+            )));
+        }
+
+        if self.number_of_spatial_layers > 1
+            && self.codec_type != ArcasCxxVideoCodecType::kVideoCodecVP9
+        {
+            return Err(WebRTCError::InvalidSvcConfig(format!(
+                "{:?} does not support spatial layers",
+                self.codec_type
+            )));
+        }
+
+        if self.layer_bitrates.len() > self.number_of_spatial_layers as usize {
+            return Err(WebRTCError::InvalidSvcConfig(
+                "bitrate configured for a spatial layer that does not exist".into(),
+            ));
+        }
+
+        let mut spatial_layers = vec![];
+        for index in 0..self.number_of_spatial_layers {
+            let scale = 1 << (self.number_of_spatial_layers - 1 - index);
+            let width = self.width / scale;
+            let height = self.height / scale;
+            if width == 0 || height == 0 {
+                return Err(WebRTCError::InvalidSvcConfig(format!(
+                    "spatial layer {} has an empty resolution",
+                    index
+                )));
+            }
+
+            // Default bitrates scale with the number of pixels of the layer.
+            let pixel_scale = (scale * scale) as u32;
+            let (min_bitrate_kbs, target_bitrate_kbs, max_bitrate_kbs) = self
+                .layer_bitrates
+                .get(index as usize)
+                .copied()
+                .flatten()
+                .unwrap_or((
+                    DEFAULT_MIN_BITRATE_KBS / pixel_scale,
+                    DEFAULT_TARGET_BITRATE_KBS / pixel_scale,
+                    DEFAULT_MAX_BITRATE_KBS / pixel_scale,
+                ));
+
+            if min_bitrate_kbs > target_bitrate_kbs || target_bitrate_kbs > max_bitrate_kbs {
+                return Err(WebRTCError::InvalidSvcConfig(format!(
+                    "spatial layer {} bitrates must satisfy min <= target <= max",
+                    index
+                )));
+            }
+
+            spatial_layers.push(VideoCodecConfig {
+                width,
+                height,
+                max_frame_rate: self.fps,
+                min_bitrate_kbs,
+                target_bitrate_kbs,
+                max_bitrate_kbs,
+                ..VideoCodecConfig::default()
+            });
+        }
+
+        let primary = VideoCodecConfig {
+            width: self.width,
+            height: self.height,
+            max_frame_rate: self.fps,
+            min_bitrate_kbs: spatial_layers[0].min_bitrate_kbs,
+            target_bitrate_kbs: spatial_layers.iter().map(|l| l.target_bitrate_kbs).sum(),
+            max_bitrate_kbs: spatial_layers.iter().map(|l| l.max_bitrate_kbs).sum(),
+            ..VideoCodecConfig::default()
+        };
+
+        Ok(VideoCodec {
+            codec_type: self.codec_type,
+            primary,
+            spatial_layers: vec![],
+            simulcast_streams: vec![],
+            svc: Some(SvcConfig {
+                spatial_layers,
+                number_of_temporal_layers: self.number_of_temporal_layers,
+                inter_layer_pred: self.inter_layer_pred,
+            }),
+        })
+    }
 }
 
 impl Default for VideoCodec {
@@ -293,6 +693,7 @@ impl Default for VideoCodec {
             spatial_layers: vec![VideoCodecConfig::default()],
             simulcast_streams: vec![],
             codec_type: ArcasCxxVideoCodecType::kVideoCodecVP8,
+            svc: None,
         }
     }
 }
@@ -335,4 +736,92 @@ mod tests {
         let vp9 = VideoCodec::vp9(720, 480, 60u32);
         let _codec = vp9.to_arcas_video_codec().unwrap();
     }
+
+    #[test]
+    fn test_video_codec_type_name_round_trip() {
+        for codec_type in &[
+            ArcasCxxVideoCodecType::kVideoCodecVP8,
+            ArcasCxxVideoCodecType::kVideoCodecVP9,
+            ArcasCxxVideoCodecType::kVideoCodecH264,
+        ] {
+            let name = video_codec_type_name(*codec_type);
+            assert_eq!(video_codec_type_from_name(&name), *codec_type);
+        }
+    }
+
+    #[test]
+    fn test_svc_builder() {
+        let vp9 = VideoCodecBuilder::vp9(1280, 720, 30)
+            .spatial_layers(3)
+            .temporal_layers(3)
+            .inter_layer_pred(InterLayerPrediction::OnKeyPicture)
+            .spatial_layer_bitrate(2, 800, 1200, 2000)
+            .build()
+            .unwrap();
+
+        let svc = vp9.svc.as_ref().unwrap();
+        assert_eq!(svc.scalability_mode(), "L3T3_KEY");
+        assert_eq!(svc.spatial_layers[0].width, 320);
+        assert_eq!(svc.spatial_layers[2].height, 720);
+
+        let allocation = vp9.bitrate_allocation();
+        assert_eq!(allocation.len(), 9);
+        let top: u32 = allocation
+            .iter()
+            .filter(|layer| layer.spatial_index == 2)
+            .map(|layer| layer.bitrate_bps)
+            .sum();
+        assert_eq!(top, 1_200_000);
+
+        let codec = vp9.to_arcas_video_codec().unwrap();
+        assert_eq!(codec.get_scalability_mode(), "L3T3_KEY");
+        let layers = codec.spatial_layers();
+        let layers: Vec<&ArcasSpatialLayer> = layers.iter().collect();
+        assert_eq!(layers[0].get_width(), 320);
+        assert_eq!(layers[2].get_number_of_temporal_layers(), 3);
+    }
+
+    #[test]
+    fn test_every_temporal_layer_gets_bitrate() {
+        for temporal_layers in 1..=MAX_TEMPORAL_LAYERS {
+            let vp9 = VideoCodecBuilder::vp9(1280, 720, 30)
+                .spatial_layers(2)
+                .temporal_layers(temporal_layers)
+                .build()
+                .unwrap();
+            let svc = vp9.svc.as_ref().unwrap();
+            let allocation = vp9.bitrate_allocation();
+            assert_eq!(allocation.len(), 2 * temporal_layers as usize);
+            assert!(
+                allocation.iter().all(|layer| layer.bitrate_bps > 0),
+                "{:?}",
+                allocation
+            );
+            for (spatial_index, layer) in svc.spatial_layers.iter().enumerate() {
+                let total: u32 = allocation
+                    .iter()
+                    .filter(|layer| layer.spatial_index == spatial_index)
+                    .map(|layer| layer.bitrate_bps)
+                    .sum();
+                assert_eq!(total, layer.target_bitrate_kbs * 1000);
+            }
+        }
+    }
+
+    #[test]
+    fn test_svc_builder_rejects_invalid_layers() {
+        assert!(VideoCodecBuilder::vp8(640, 480, 30)
+            .spatial_layers(2)
+            .build()
+            .is_err());
+        assert!(VideoCodecBuilder::vp9(640, 480, 30)
+            .temporal_layers(MAX_TEMPORAL_LAYERS + 1)
+            .build()
+            .is_err());
+        assert!(VideoCodecBuilder::vp9(640, 480, 30)
+            .spatial_layers(2)
+            .spatial_layer_bitrate(3, 100, 200, 300)
+            .build()
+            .is_err());
+    }
 }
@@ -1,12 +1,14 @@
+use std::convert::TryFrom;
+
 use crossbeam_channel::{unbounded, Receiver};
 
 use cxx::{SharedPtr, UniquePtr};
 use libwebrtc_sys::{
     ffi::{
         create_arcas_video_encoder_factory_from_builtin, create_arcas_video_encoder_settings,
-        create_arcas_video_frame_types_collection, ArcasCodecSpecificInfo, ArcasCxxEncodedImage,
-        ArcasCxxVideoFrameType, ArcasVideoCodec, ArcasVideoEncoderDropReason,
-        ArcasVideoEncoderFactoryWrapper, ArcasVideoEncoderInfo,
+        create_arcas_video_frame_types_collection, get_encoded_image_spatial_index,
+        ArcasCodecSpecificInfo, ArcasCxxEncodedImage, ArcasCxxVideoFrameType, ArcasVideoCodec,
+        ArcasVideoEncoderDropReason, ArcasVideoEncoderFactoryWrapper, ArcasVideoEncoderInfo,
         ArcasVideoEncoderRateControlParameters, ArcasVideoEncoderSettings,
         ArcasVideoEncoderWrapper,
     },
@@ -29,6 +31,33 @@ pub type EncodedImageOutput = (
     UniquePtr<ArcasCodecSpecificInfo>,
 );
 
+/// Spatial and temporal layer an encoded image belongs to.  Non-scalable
+/// streams report `None` for both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodedLayerIndex {
+    pub spatial_index: Option<u8>,
+    pub temporal_index: Option<u8>,
+}
+
+impl EncodedLayerIndex {
+    pub fn from_output(output: &EncodedImageOutput) -> Self {
+        let (encoded_image, codec_specific_info) = output;
+        let spatial_index = encoded_image
+            .as_ref()
+            .map(get_encoded_image_spatial_index)
+            .and_then(|index| u8::try_from(index).ok());
+        let temporal_index = codec_specific_info
+            .as_ref()
+            .map(|info| info.get_temporal_index())
+            .and_then(|index| u8::try_from(index).ok());
+
+        Self {
+            spatial_index,
+            temporal_index,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum EncodeResult {
     Ok,
@@ -204,16 +233,18 @@ impl VideoEncoder {
         }
 
         let mut bitrate_allocation = libwebrtc_sys::ffi::create_video_bitrate_allocation();
-        let bitrate_allocation_write_ref = bitrate_allocation.as_mut().ok_or_else(|| {
+        let mut bitrate_allocation_write_ref = bitrate_allocation.as_mut().ok_or_else(|| {
             error::WebRTCError::CXXUnwrapError("failed to get ArcasVideoBitrateAllocation".into())
         })?;
 
         // XXX: Set the bitrate allocation based on the parameters in the codec for now.
-        bitrate_allocation_write_ref.set_bitrate(
-            0,
-            0,
-            video_codec.primary.target_bitrate_kbs * 1000,
-        );
+        for layer in video_codec.bitrate_allocation() {
+            bitrate_allocation_write_ref.as_mut().set_bitrate(
+                layer.spatial_index,
+                layer.temporal_index,
+                layer.bitrate_bps,
+            );
+        }
 
         let bitrate_allocation_read_ref = bitrate_allocation.as_ref().ok_or_else(|| {
             error::WebRTCError::CXXUnwrapError("failed to get ArcasVideoBitrateAllocation".into())
@@ -338,7 +369,7 @@ mod tests {
 
     use bytes::{Bytes, BytesMut};
 
-    use crate::reactive_video_encoder::DEFAULT_ENCODING;
+    use crate::{reactive_video_encoder::DEFAULT_ENCODING, video_codec::VideoCodecBuilder};
 
     use super::*;
 
@@ -410,4 +441,51 @@ mod tests {
         });
         video_done_rx.recv().unwrap();
     }
+
+    #[test]
+    fn test_svc_encoder_reports_layer_indices() {
+        let width = 640;
+        let height = 480;
+        let launch = format!("videotestsrc pattern=snow ! videoconvert ! videoscale ! video/x-raw,format=I420,width={},height={}",  width, height);
+        let rx: crossbeam_channel::Receiver<bytes::BytesMut> =
+            media_pipeline::create_and_start_appsink_pipeline(launch.as_str()).unwrap();
+
+        let encoder_factory = VideoEncoderFactory::new();
+        let formats = encoder_factory.get_supported_formats();
+        let vp9 = formats.iter().find(|f| f.get_name() == "VP9").unwrap();
+        let codec = VideoCodecBuilder::vp9(width, height, 30)
+            .spatial_layers(2)
+            .temporal_layers(2)
+            .build()
+            .unwrap();
+        let mut encoder = encoder_factory
+            .create_encoder(vp9, codec, VideoEncoderSettings::default())
+            .unwrap();
+        let encode_rx = encoder.take_encoded_image_rx().unwrap();
+
+        for (frame_number, buf) in rx.iter().take(10).enumerate() {
+            let frame = crate::video_frame::RawVideoFrame::create(
+                width,
+                height,
+                frame_number as u64 * 33,
+                buf.into(),
+            )
+            .unwrap();
+            let frame_types = if frame_number == 0 {
+                vec![FrameTypes::KeyFrame]
+            } else {
+                vec![FrameTypes::DeltaFrame]
+            };
+            encoder.encode(frame, frame_types).unwrap();
+        }
+
+        let mut spatial_indexes = vec![];
+        while let Ok(output) = encode_rx.recv_timeout(std::time::Duration::from_secs(1)) {
+            let layer = EncodedLayerIndex::from_output(&output);
+            spatial_indexes.push(layer.spatial_index);
+        }
+
+        assert!(spatial_indexes.contains(&Some(0)));
+        assert!(spatial_indexes.contains(&Some(1)));
+    }
 }
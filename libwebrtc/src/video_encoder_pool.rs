@@ -1,22 +1,54 @@
-use std::{sync::Arc, time::SystemTime};
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant, SystemTime},
+};
 
-use crossbeam_channel::{select, Sender};
+use crossbeam_channel::{select, Receiver, Sender};
 use cxx::UniquePtr;
 use dashmap::DashMap;
 use libwebrtc_sys::ffi::{
-    ArcasEncodedImageCallback, ArcasVideoCodec, ArcasVideoEncoderRateControlParameters,
+    is_encoded_image_key_frame, ArcasCxxEncodedImageCallbackResultError, ArcasEncodedImageCallback,
+    ArcasVideoCodec, ArcasVideoEncoderRateControlParameters,
 };
-use log::debug;
+use log::{debug, error};
+use parking_lot::Mutex;
 
 use crate::{
-    encoded_video_frame_producer::DEFAULT_FPS,
-    error::Result,
+    error::{Result, WebRTCError},
     raw_video_frame_producer::{GStreamerRawFrameProducer, RawFrameProducer},
-    reactive_video_encoder::DEFAULT_ENCODING,
-    video_codec::VideoCodec,
-    video_encoder::{FrameTypes, VideoEncoderFactory},
+    sdp_video_format::SDPVideoFormat,
+    video_codec::{VideoCodec, VideoCodecDescription},
+    video_encoder::{FrameTypes, VideoEncoder, VideoEncoderFactory, VideoEncoderSettings},
 };
 
+/// Provides the raw frame source for a controller given its id and the codec
+/// negotiated by the first encoder which requested it.  When the pool has
+/// bitrate tiers this is called once per tier with the tier's resolution.
+pub type RawFrameProducerFactory = Arc<
+    dyn Fn(&str, &VideoCodecDescription) -> Result<Box<dyn RawFrameProducer + Send>> + Send + Sync,
+>;
+
+/// Default frame source for the pool: a gstreamer test pattern (snow) at the
+/// negotiated resolution and frame rate.
+pub fn default_raw_frame_producer(
+    _controller_id: &str,
+    description: &VideoCodecDescription,
+) -> Result<Box<dyn RawFrameProducer + Send>> {
+    let producer = GStreamerRawFrameProducer::default_pipeline(&description.to_video_codec())?;
+    Ok(Box::new(producer))
+}
+
+/// Keyframe requests from subscribers of a controller are coalesced so that at
+/// most one keyframe is produced per this interval.
+pub const MIN_KEYFRAME_INTERVAL: Duration = Duration::from_millis(300);
+/// Keyframes are still produced at this interval when nobody asks for one,
+/// so subscribers whose requests are lost recover within a second.
+pub const KEYFRAME_FALLBACK_INTERVAL: Duration = Duration::from_secs(1);
+
 pub enum VideoEncoderPoolRequest {
     Create {
         controller_id: String,
@@ -31,78 +63,450 @@ pub enum VideoEncoderPoolRequest {
         controller_id: String,
         id: String,
     },
+    /// A subscriber of the controller needs a keyframe (new receiver, PLI/FIR).
+    KeyFrame {
+        controller_id: String,
+        id: String,
+    },
+    /// Latest bandwidth estimate of a subscriber, used to pick its bitrate tier.
+    SetRates {
+        controller_id: String,
+        id: String,
+        bitrate_bps: u32,
+    },
+}
+
+/// One rung of the bitrate ladder a controller keeps an encoder for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BitrateTier {
+    /// Subscribers are moved to the highest tier whose target fits within their
+    /// latest bandwidth estimate.
+    pub target_bitrate_kbs: u32,
+    pub max_bitrate_kbs: u32,
+    /// Resolution relative to the negotiated codec (2.0 is half width and height).
+    pub scale_resolution_down_by: f64,
+}
+
+impl BitrateTier {
+    pub fn new(target_bitrate_kbs: u32, scale_resolution_down_by: f64) -> Self {
+        Self {
+            target_bitrate_kbs,
+            max_bitrate_kbs: target_bitrate_kbs,
+            scale_resolution_down_by,
+        }
+    }
+
+    fn to_video_codec(&self, description: &VideoCodecDescription) -> VideoCodec {
+        let mut codec = description.to_video_codec();
+        codec.primary.target_bitrate_kbs = self.target_bitrate_kbs;
+        codec.primary.max_bitrate_kbs = self.max_bitrate_kbs.max(self.target_bitrate_kbs);
+        codec.primary.min_bitrate_kbs = codec.primary.min_bitrate_kbs.min(self.target_bitrate_kbs);
+        codec
+    }
+}
+
+/// Index of the tier a subscriber with `bitrate_bps` available should receive.
+/// `tiers` must be sorted by ascending target bitrate.
+fn select_tier(tiers: &[BitrateTier], bitrate_bps: u32) -> usize {
+    tiers
+        .iter()
+        .rposition(|tier| tier.target_bitrate_kbs.saturating_mul(1000) <= bitrate_bps)
+        .unwrap_or(0)
+}
+
+/// Decides which frames of a shared encoder are keyframes.  Requests are
+/// rate limited to `MIN_KEYFRAME_INTERVAL` and any requests made within that
+/// window are served by a single keyframe once it has passed.
+struct KeyFrameScheduler {
+    requested: Arc<AtomicBool>,
+    last_keyframe: Option<Instant>,
+}
+
+impl KeyFrameScheduler {
+    fn new(requested: Arc<AtomicBool>) -> Self {
+        Self {
+            requested,
+            last_keyframe: None,
+        }
+    }
+
+    fn next_is_keyframe(&mut self, now: Instant) -> bool {
+        let keyframe = match self.last_keyframe {
+            None => true,
+            Some(last_keyframe) => {
+                let elapsed = now.saturating_duration_since(last_keyframe);
+                elapsed >= KEYFRAME_FALLBACK_INTERVAL
+                    || (elapsed >= MIN_KEYFRAME_INTERVAL && self.requested.load(Ordering::Acquire))
+            }
+        };
+
+        if keyframe {
+            self.requested.store(false, Ordering::Release);
+            self.last_keyframe = Some(now);
+        }
+
+        keyframe
+    }
+}
+
+struct Subscriber {
+    callback: UniquePtr<ArcasEncodedImageCallback>,
+    tier: usize,
+    // Tier the subscriber moves to on that tier's next keyframe so it never
+    // receives delta frames it can't decode.
+    pending_tier: Option<usize>,
+}
+
+/// How the encoder of a tier is configured.
+enum TierEncoderConfig {
+    /// Settings exactly as negotiated by the first subscriber.
+    Negotiated {
+        codec: UniquePtr<ArcasVideoCodec>,
+        rate: UniquePtr<ArcasVideoEncoderRateControlParameters>,
+    },
+    Tier(VideoCodec),
+}
+
+/// Point in time statistics of a single controller in the pool.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VideoEncoderControllerMetrics {
+    pub controller_id: String,
+    /// Number of `ReactiveVideoEncoder`s fed by the controller.
+    pub subscribers: usize,
+    /// Frames encoded across all tiers.
+    pub frames_encoded: u64,
+    /// Frames encoded per second across all tiers, measured over the last second.
+    pub encode_fps: f64,
+    /// Mean time spent encoding a single frame.
+    pub average_encode_time: Duration,
+    /// Encoded images a subscriber's callback failed to accept.
+    pub dropped_callbacks: u64,
+}
+
+struct ControllerStats {
+    frames_encoded: AtomicU64,
+    encode_time_us: AtomicU64,
+    dropped_callbacks: AtomicU64,
+    // fps * 1000, one per tier.
+    tier_fps_millis: Vec<AtomicU64>,
+}
+
+impl ControllerStats {
+    fn new(number_of_tiers: usize) -> Self {
+        Self {
+            frames_encoded: AtomicU64::new(0),
+            encode_time_us: AtomicU64::new(0),
+            dropped_callbacks: AtomicU64::new(0),
+            tier_fps_millis: (0..number_of_tiers).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+
+    fn record_encode(&self, encode_time: Duration) {
+        self.frames_encoded.fetch_add(1, Ordering::Relaxed);
+        self.encode_time_us
+            .fetch_add(encode_time.as_micros() as u64, Ordering::Relaxed);
+    }
 }
 
 pub struct VideoEncoderPoolController {
-    callbacks: Arc<DashMap<String, UniquePtr<ArcasEncodedImageCallback>>>,
+    tiers: Vec<BitrateTier>,
+    subscribers: Arc<DashMap<String, Subscriber>>,
+    // One per tier.
+    keyframe_requested: Vec<Arc<AtomicBool>>,
+    stats: Arc<ControllerStats>,
+    // Dropping the sender disconnects every receiver which stops the threads.
+    shutdown_tx: Mutex<Option<Sender<()>>>,
+    shutdown_rx: Receiver<()>,
+    threads: Mutex<Vec<JoinHandle<()>>>,
 }
 
 impl VideoEncoderPoolController {
     pub fn new() -> Self {
+        Self::with_tiers(vec![])
+    }
+
+    /// Without tiers the controller runs a single encoder with the settings
+    /// negotiated by its first subscriber.
+    pub fn with_tiers(mut tiers: Vec<BitrateTier>) -> Self {
+        tiers.sort_by_key(|tier| tier.target_bitrate_kbs);
+        let number_of_tiers = tiers.len().max(1);
+        let keyframe_requested = (0..number_of_tiers)
+            .map(|_| Arc::new(AtomicBool::new(false)))
+            .collect();
+        let (shutdown_tx, shutdown_rx) = crossbeam_channel::bounded::<()>(0);
         Self {
-            callbacks: Arc::new(DashMap::new()),
+            tiers,
+            subscribers: Arc::new(DashMap::new()),
+            keyframe_requested,
+            stats: Arc::new(ControllerStats::new(number_of_tiers)),
+            shutdown_tx: Mutex::new(Some(shutdown_tx)),
+            shutdown_rx,
+            threads: Mutex::new(vec![]),
         }
     }
 
     pub fn remove_callback(&self, id: String) {
-        self.callbacks.remove(&id);
+        self.subscribers.remove(&id);
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.len()
+    }
+
+    pub fn metrics(&self, controller_id: &str) -> VideoEncoderControllerMetrics {
+        let frames_encoded = self.stats.frames_encoded.load(Ordering::Relaxed);
+        let encode_time_us = self.stats.encode_time_us.load(Ordering::Relaxed);
+        let encode_fps_millis: u64 = self
+            .stats
+            .tier_fps_millis
+            .iter()
+            .map(|fps| fps.load(Ordering::Relaxed))
+            .sum();
+        VideoEncoderControllerMetrics {
+            controller_id: controller_id.to_string(),
+            subscribers: self.subscriber_count(),
+            frames_encoded,
+            encode_fps: encode_fps_millis as f64 / 1000.0,
+            average_encode_time: Duration::from_micros(
+                encode_time_us.checked_div(frames_encoded).unwrap_or(0),
+            ),
+            dropped_callbacks: self.stats.dropped_callbacks.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Stop the frame producers and encoders of every tier and wait for their
+    /// threads to exit.
+    pub fn shutdown(&self) {
+        self.shutdown_tx.lock().take();
+        let threads: Vec<JoinHandle<()>> = self.threads.lock().drain(..).collect();
+        for thread in threads {
+            if thread.join().is_err() {
+                error!("Encoder pool controller thread panicked");
+            }
+        }
     }
 
-    pub(crate) fn add_callback(&self, id: String, callback: UniquePtr<ArcasEncodedImageCallback>) {
-        self.callbacks.insert(id, callback);
+    pub(crate) fn add_callback(
+        &self,
+        id: String,
+        callback: UniquePtr<ArcasEncodedImageCallback>,
+        bitrate_bps: u32,
+    ) {
+        let tier = select_tier(&self.tiers, bitrate_bps);
+        self.subscribers.insert(
+            id,
+            Subscriber {
+                callback,
+                tier,
+                pending_tier: None,
+            },
+        );
+        // New subscribers can't decode anything until the next keyframe.
+        self.request_tier_keyframe(tier);
     }
 
+    /// Ask the encoder(s) serving subscriber `id` for a keyframe, subject to
+    /// `MIN_KEYFRAME_INTERVAL`.
+    pub fn request_keyframe(&self, id: &str) {
+        if let Some(subscriber) = self.subscribers.get(id) {
+            self.request_tier_keyframe(subscriber.tier);
+            if let Some(pending_tier) = subscriber.pending_tier {
+                self.request_tier_keyframe(pending_tier);
+            }
+        }
+    }
+
+    fn request_tier_keyframe(&self, tier: usize) {
+        if let Some(requested) = self.keyframe_requested.get(tier) {
+            requested.store(true, Ordering::Release);
+        }
+    }
+
+    /// Move subscriber `id` to the tier matching its latest bandwidth estimate.
+    /// The switch happens on the next keyframe of the new tier.
+    pub fn set_rates(&self, id: &str, bitrate_bps: u32) {
+        let tier = select_tier(&self.tiers, bitrate_bps);
+        if let Some(mut subscriber) = self.subscribers.get_mut(id) {
+            if subscriber.tier == tier {
+                subscriber.pending_tier = None;
+            } else if subscriber.pending_tier != Some(tier) {
+                debug!("moving {} to bitrate tier {}", id, tier);
+                subscriber.pending_tier = Some(tier);
+                self.request_tier_keyframe(tier);
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn start(
         &self,
+        controller_id: String,
         id: String,
         number_of_cores: i32,
         max_payload_size: usize,
         cxx_callback: UniquePtr<ArcasEncodedImageCallback>,
         cxx_codec: UniquePtr<ArcasVideoCodec>,
         cxx_rate: UniquePtr<ArcasVideoEncoderRateControlParameters>,
+        frame_producer_factory: RawFrameProducerFactory,
     ) {
-        self.callbacks.insert(id, cxx_callback);
-        let callbacks = self.callbacks.clone();
+        let bitrate_bps = cxx_rate.get_bitrate().get_sum_bps();
+        self.add_callback(id, cxx_callback, bitrate_bps);
+        let description = VideoCodecDescription::create_from_codec(&cxx_codec);
+        let settings = move || VideoEncoderSettings {
+            loss_notification: true,
+            number_of_cores,
+            max_payload_size,
+        };
+
+        if self.tiers.is_empty() {
+            self.spawn_tier(
+                controller_id,
+                0,
+                description,
+                TierEncoderConfig::Negotiated {
+                    codec: cxx_codec,
+                    rate: cxx_rate,
+                },
+                settings(),
+                frame_producer_factory,
+            );
+            return;
+        }
+
+        for (tier_index, tier) in self.tiers.iter().enumerate() {
+            let tier_description = description.scaled(tier.scale_resolution_down_by);
+            let codec = tier.to_video_codec(&tier_description);
+            self.spawn_tier(
+                controller_id.clone(),
+                tier_index,
+                tier_description,
+                TierEncoderConfig::Tier(codec),
+                settings(),
+                frame_producer_factory.clone(),
+            );
+        }
+    }
+
+    fn spawn_tier(
+        &self,
+        controller_id: String,
+        tier_index: usize,
+        description: VideoCodecDescription,
+        config: TierEncoderConfig,
+        settings: VideoEncoderSettings,
+        frame_producer_factory: RawFrameProducerFactory,
+    ) {
+        let subscribers = self.subscribers.clone();
+        let stats = self.stats.clone();
+        let shutdown_rx = self.shutdown_rx.clone();
+        let mut keyframe_scheduler =
+            KeyFrameScheduler::new(self.keyframe_requested[tier_index].clone());
         // spawn the actual encoder thread to deal with the encoding...
-        std::thread::spawn(move || {
+        let handle = std::thread::spawn(move || {
             // XXX: Hack should we really spawn one factory per encoder we need?
             let video_encoder_factory = Arc::new(VideoEncoderFactory::new());
-            // XXX: hack get vp9 sdp format
             let formats = video_encoder_factory.get_supported_formats();
-            let format = formats
+            let format = match formats
                 .iter()
-                .find(|value| value.get_name() == DEFAULT_ENCODING)
-                .unwrap();
-            let mut encoder = video_encoder_factory
-                .create_encoder_without_init(format)
-                .unwrap();
-            let settings = libwebrtc_sys::ffi::create_arcas_video_encoder_settings(
-                true,
-                number_of_cores,
-                max_payload_size,
-            );
-            encoder
-                .encoder
-                .init_encode(cxx_codec.as_ref().unwrap(), settings.as_ref().unwrap());
-            encoder.encoder.set_rates(cxx_rate.as_ref().unwrap());
-            let codec_raw = VideoCodec::vp8_default();
-            let mut pipeline = GStreamerRawFrameProducer::default_pipeline(&codec_raw).unwrap();
-            let rx = pipeline.start().unwrap();
+                .find(|value| value.get_name() == description.codec_name())
+            {
+                Some(format) => format,
+                None => {
+                    error!(
+                        "No encoder available for negotiated codec {:?}",
+                        description.codec_name()
+                    );
+                    return;
+                }
+            };
+            // The negotiated C++ codec and rates are kept alive for as long as
+            // the encoder runs.
+            let (encoder, _negotiated) = match config {
+                TierEncoderConfig::Negotiated { codec, rate } => {
+                    let encoder = create_negotiated_encoder(
+                        &video_encoder_factory,
+                        format,
+                        &codec,
+                        &rate,
+                        &settings,
+                    );
+                    (encoder, Some((codec, rate)))
+                }
+                TierEncoderConfig::Tier(codec) => (
+                    video_encoder_factory.create_encoder(format, codec, settings),
+                    None,
+                ),
+            };
+            let mut encoder = match encoder {
+                Ok(encoder) => encoder,
+                Err(err) => {
+                    error!(
+                        "Failed to create encoder for controller {} tier {}: {:?}",
+                        controller_id, tier_index, err
+                    );
+                    return;
+                }
+            };
+            let mut producer = match frame_producer_factory(&controller_id, &description) {
+                Ok(producer) => producer,
+                Err(err) => {
+                    error!(
+                        "Failed to create frame producer for controller {}: {:?}",
+                        controller_id, err
+                    );
+                    return;
+                }
+            };
+            let rx = match producer.start() {
+                Ok(rx) => rx,
+                Err(err) => {
+                    error!(
+                        "Failed to start frame producer for controller {}: {:?}",
+                        controller_id, err
+                    );
+                    return;
+                }
+            };
 
             let encode_rx = encoder.take_encoded_image_rx().unwrap();
-            std::thread::spawn(move || {
+            let distribute_stats = stats.clone();
+            let distribute_shutdown_rx = shutdown_rx.clone();
+            let distribute = std::thread::spawn(move || {
                 let mut encode = SystemTime::now();
-                while let Ok(result) = encode_rx.recv() {
+                loop {
+                    let result = select! {
+                        recv(distribute_shutdown_rx) -> _ => return,
+                        recv(encode_rx) -> result => match result {
+                            Ok(result) => result,
+                            Err(_) => return,
+                        },
+                    };
                     let now = SystemTime::now();
                     debug!("encode the thing! {:?}", encode.elapsed().unwrap());
                     let (encoded_frame, codec_specific_info) = result;
+                    let encoded_frame = encoded_frame.as_ref().unwrap();
+                    let is_keyframe = is_encoded_image_key_frame(encoded_frame);
                     debug!("Distributing frame to encoders");
                     {
-                        callbacks.iter().for_each(|callback_ref| unsafe {
-                            callback_ref.value().on_encoded_image(
-                                encoded_frame.as_ref().unwrap(),
-                                codec_specific_info.as_ref().unwrap(),
-                            );
+                        subscribers.iter_mut().for_each(|mut subscriber| {
+                            if is_keyframe && subscriber.pending_tier == Some(tier_index) {
+                                subscriber.tier = tier_index;
+                                subscriber.pending_tier = None;
+                            }
+                            if subscriber.tier != tier_index {
+                                return;
+                            }
+                            let result = unsafe {
+                                subscriber.callback.on_encoded_image(
+                                    encoded_frame,
+                                    codec_specific_info.as_ref().unwrap(),
+                                )
+                            };
+                            if result.error != ArcasCxxEncodedImageCallbackResultError::OK {
+                                distribute_stats
+                                    .dropped_callbacks
+                                    .fetch_add(1, Ordering::Relaxed);
+                            }
                         });
                         debug!(
                             "distribute time: {:?}",
@@ -115,8 +519,17 @@ impl VideoEncoderPoolController {
 
             let mut increment = 0;
             let mut last_keyframe = SystemTime::now();
-            while let Ok(frame) = rx.recv() {
-                let frames = if increment % DEFAULT_FPS == 0 {
+            let mut fps_window_start = Instant::now();
+            let mut fps_window_frames = 0u64;
+            loop {
+                let frame = select! {
+                    recv(shutdown_rx) -> _ => break,
+                    recv(rx) -> frame => match frame {
+                        Ok(frame) => frame,
+                        Err(_) => break,
+                    },
+                };
+                let frames = if keyframe_scheduler.next_is_keyframe(Instant::now()) {
                     last_keyframe = SystemTime::now();
                     vec![FrameTypes::KeyFrame, FrameTypes::DeltaFrame]
                 } else {
@@ -128,77 +541,333 @@ impl VideoEncoderPoolController {
                     &frames,
                     SystemTime::now().duration_since(last_keyframe)
                 );
-                encoder.encode(frame, frames).unwrap();
+                let encode_start = Instant::now();
+                if let Err(err) = encoder.encode(frame, frames) {
+                    error!("Failed to encode frame: {:?}", err);
+                    continue;
+                }
+                stats.record_encode(encode_start.elapsed());
                 increment += 1;
+
+                fps_window_frames += 1;
+                let fps_window = fps_window_start.elapsed();
+                if fps_window >= Duration::from_secs(1) {
+                    let fps = fps_window_frames as f64 / fps_window.as_secs_f64();
+                    stats.tier_fps_millis[tier_index]
+                        .store((fps * 1000.0) as u64, Ordering::Relaxed);
+                    fps_window_start = Instant::now();
+                    fps_window_frames = 0;
+                }
+            }
+
+            stats.tier_fps_millis[tier_index].store(0, Ordering::Relaxed);
+            producer.cancel();
+            // Dropping the encoder releases its callback and with it the
+            // distribution thread's channel.
+            drop(encoder);
+            if distribute.join().is_err() {
+                error!("Encoder pool distribution thread panicked");
             }
         });
+        self.threads.lock().push(handle);
     }
 }
 
+fn create_negotiated_encoder(
+    video_encoder_factory: &VideoEncoderFactory,
+    format: &SDPVideoFormat,
+    codec: &UniquePtr<ArcasVideoCodec>,
+    rate: &UniquePtr<ArcasVideoEncoderRateControlParameters>,
+    settings: &VideoEncoderSettings,
+) -> Result<VideoEncoder> {
+    let encoder = video_encoder_factory.create_encoder_without_init(format)?;
+    let cxx_settings = settings.to_arcas_video_encoder_settings();
+    let codec = codec
+        .as_ref()
+        .ok_or_else(|| WebRTCError::CXXUnwrapError("Arcas Video Codec".into()))?;
+    let cxx_settings = cxx_settings
+        .as_ref()
+        .ok_or_else(|| WebRTCError::CXXUnwrapError("Arcas Video Encoder Settings".into()))?;
+    let rate = rate.as_ref().ok_or_else(|| {
+        WebRTCError::CXXUnwrapError("Arcas Video Encoder Rate Control Parameters".into())
+    })?;
+    encoder.encoder.init_encode(codec, cxx_settings);
+    encoder.encoder.set_rates(rate);
+    Ok(encoder)
+}
+
 impl Default for VideoEncoderPoolController {
     fn default() -> Self {
         Self::new()
     }
 }
 
-#[derive(Debug)]
-pub struct VideoEncoderPool {}
+/// Shared encoders for `ReactiveVideoEncoder`s.  Dropping the pool stops every
+/// controller and joins all of its threads.
+pub struct VideoEncoderPool {
+    controllers: Arc<DashMap<String, Arc<VideoEncoderPoolController>>>,
+    shutdown_tx: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl std::fmt::Debug for VideoEncoderPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VideoEncoderPool")
+            .field("controllers", &self.controllers.len())
+            .finish()
+    }
+}
 
 impl VideoEncoderPool {
-    pub fn create() -> Result<(Self, Sender<VideoEncoderPoolRequest>)> {
-        let encoder_map: Arc<DashMap<String, Arc<VideoEncoderPoolController>>> =
+    /// Spawn the pool.  `frame_producer_factory` is called once per controller
+    /// (one per distinct codec configuration) to provide the frames it encodes.
+    /// Use `default_raw_frame_producer` for a gstreamer test pattern.
+    pub fn create<F>(frame_producer_factory: F) -> Result<(Self, Sender<VideoEncoderPoolRequest>)>
+    where
+        F: Fn(&str, &VideoCodecDescription) -> Result<Box<dyn RawFrameProducer + Send>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        Self::create_with_tiers(frame_producer_factory, vec![])
+    }
+
+    /// Like `create` but every controller keeps one encoder per bitrate tier and
+    /// moves each subscriber to the tier matching its latest `set_rates`.
+    pub fn create_with_tiers<F>(
+        frame_producer_factory: F,
+        tiers: Vec<BitrateTier>,
+    ) -> Result<(Self, Sender<VideoEncoderPoolRequest>)>
+    where
+        F: Fn(&str, &VideoCodecDescription) -> Result<Box<dyn RawFrameProducer + Send>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        let frame_producer_factory: RawFrameProducerFactory = Arc::new(frame_producer_factory);
+        let controllers: Arc<DashMap<String, Arc<VideoEncoderPoolController>>> =
             Arc::new(DashMap::new());
+        let encoder_map = controllers.clone();
         let (request_tx, request_rx) = crossbeam_channel::unbounded::<VideoEncoderPoolRequest>();
+        let (shutdown_tx, shutdown_rx) = crossbeam_channel::bounded::<()>(0);
 
         // This thread is in charge of spawning individual encoder threads in repsonse to requests.
-        std::thread::spawn(move || loop {
-            select! {
-                recv(request_rx) -> req => {
-                    match req {
-                        Ok(request) => {
-                            match request {
-                                VideoEncoderPoolRequest::Create { id, controller_id, codec, number_of_cores, max_payload_size, rate, callback } => {
-                                    match encoder_map.get(&controller_id) {
-                                        Some(value) => {
-                                            value.add_callback(id, callback);
-                                            continue;
-                                        },
-                                        None => {
-                                            let controller = Arc::new(VideoEncoderPoolController::new());
-                                            encoder_map.insert(controller_id, controller.clone());
-                                            controller.start(
-                                                id,
-                                                number_of_cores,
-                                                max_payload_size,
-                                                callback,
-                                                codec,
-                                                rate,
-                                            );
-                                        }
-                                    }
-                                },
-                                VideoEncoderPoolRequest::Release { controller_id, id } => {
-                                    match encoder_map.get(&controller_id) {
-                                        Some(value) => {
-                                            value.remove_callback(id);
-                                            continue;
-                                        },
-                                        None => {
-                                            continue;
-                                        }
-                                    }
-                                },
+        let thread = std::thread::spawn(move || {
+            // Idle controllers are shut down on their own thread so joining
+            // their encoders doesn't hold up requests.
+            let (teardown_tx, teardown_rx) =
+                crossbeam_channel::unbounded::<Arc<VideoEncoderPoolController>>();
+            let teardown_thread = std::thread::spawn(move || {
+                while let Ok(controller) = teardown_rx.recv() {
+                    controller.shutdown();
+                }
+            });
+
+            loop {
+                select! {
+                    recv(shutdown_rx) -> _ => break,
+                    recv(request_rx) -> req => {
+                        match req {
+                            Ok(request) => handle_request(
+                                &encoder_map,
+                                &tiers,
+                                &frame_producer_factory,
+                                &teardown_tx,
+                                request,
+                            ),
+                            Err(err) => {
+                                // Every sender is gone so no more requests will
+                                // arrive, keep encoding until the pool is dropped.
+                                debug!("Failed to process encoder request: {:?}", err);
+                                let _ = shutdown_rx.recv();
+                                break;
                             }
-                        },
-                        Err(err) => {
-                            debug!("Failed to process encoder request: {:?}", err);
-                            continue;
                         }
                     }
                 }
             }
+
+            let controller_ids: Vec<String> = encoder_map
+                .iter()
+                .map(|entry| entry.key().clone())
+                .collect();
+            for controller_id in controller_ids {
+                if let Some((_, controller)) = encoder_map.remove(&controller_id) {
+                    controller.shutdown();
+                }
+            }
+            drop(teardown_tx);
+            if teardown_thread.join().is_err() {
+                error!("Encoder pool teardown thread panicked");
+            }
         });
 
-        Ok((Self {}, request_tx))
+        Ok((
+            Self {
+                controllers,
+                shutdown_tx: Some(shutdown_tx),
+                thread: Some(thread),
+            },
+            request_tx,
+        ))
+    }
+
+    /// Metrics of every running controller.
+    pub fn metrics(&self) -> Vec<VideoEncoderControllerMetrics> {
+        self.controllers
+            .iter()
+            .map(|entry| entry.value().metrics(entry.key()))
+            .collect()
+    }
+}
+
+impl Drop for VideoEncoderPool {
+    fn drop(&mut self) {
+        self.shutdown_tx.take();
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                error!("Encoder pool request thread panicked");
+            }
+        }
+    }
+}
+
+fn handle_request(
+    encoder_map: &DashMap<String, Arc<VideoEncoderPoolController>>,
+    tiers: &[BitrateTier],
+    frame_producer_factory: &RawFrameProducerFactory,
+    teardown_tx: &Sender<Arc<VideoEncoderPoolController>>,
+    request: VideoEncoderPoolRequest,
+) {
+    match request {
+        VideoEncoderPoolRequest::Create {
+            id,
+            controller_id,
+            codec,
+            number_of_cores,
+            max_payload_size,
+            rate,
+            callback,
+        } => {
+            if let Some(value) = encoder_map.get(&controller_id) {
+                value.add_callback(id, callback, rate.get_bitrate().get_sum_bps());
+                return;
+            }
+            let controller = Arc::new(VideoEncoderPoolController::with_tiers(tiers.to_vec()));
+            encoder_map.insert(controller_id.clone(), controller.clone());
+            controller.start(
+                controller_id,
+                id,
+                number_of_cores,
+                max_payload_size,
+                callback,
+                codec,
+                rate,
+                frame_producer_factory.clone(),
+            );
+        }
+        VideoEncoderPoolRequest::KeyFrame { controller_id, id } => {
+            if let Some(value) = encoder_map.get(&controller_id) {
+                value.request_keyframe(&id);
+            }
+        }
+        VideoEncoderPoolRequest::SetRates {
+            controller_id,
+            id,
+            bitrate_bps,
+        } => {
+            if let Some(value) = encoder_map.get(&controller_id) {
+                value.set_rates(&id, bitrate_bps);
+            }
+        }
+        VideoEncoderPoolRequest::Release { controller_id, id } => {
+            let idle = match encoder_map.get(&controller_id) {
+                Some(value) => {
+                    value.remove_callback(id);
+                    value.subscriber_count() == 0
+                }
+                None => false,
+            };
+            // Tear down controllers once their last subscriber is gone.
+            if idle {
+                if let Some((_, controller)) = encoder_map.remove(&controller_id) {
+                    debug!("shutting down idle controller {}", controller_id);
+                    if let Err(err) = teardown_tx.send(controller) {
+                        err.into_inner().shutdown();
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keyframe_scheduler_rate_limits_requests() {
+        let requested = Arc::new(AtomicBool::new(false));
+        let mut scheduler = KeyFrameScheduler::new(requested.clone());
+        let start = Instant::now();
+
+        // The first frame is always a keyframe.
+        assert!(scheduler.next_is_keyframe(start));
+        assert!(!scheduler.next_is_keyframe(start + Duration::from_millis(33)));
+
+        // Several subscribers asking within the window get a single keyframe
+        // once the window has passed.
+        requested.store(true, Ordering::Release);
+        requested.store(true, Ordering::Release);
+        assert!(!scheduler.next_is_keyframe(start + Duration::from_millis(100)));
+        let served = start + MIN_KEYFRAME_INTERVAL;
+        assert!(scheduler.next_is_keyframe(served));
+        assert!(!scheduler.next_is_keyframe(served + MIN_KEYFRAME_INTERVAL));
+
+        // Without requests keyframes fall back to the periodic interval.
+        assert!(scheduler.next_is_keyframe(served + KEYFRAME_FALLBACK_INTERVAL));
+    }
+
+    #[test]
+    fn test_select_bitrate_tier() {
+        let controller = VideoEncoderPoolController::with_tiers(vec![
+            BitrateTier::new(1500, 1.0),
+            BitrateTier::new(300, 4.0),
+            BitrateTier::new(700, 2.0),
+        ]);
+        let tiers = &controller.tiers;
+        assert_eq!(tiers[0].target_bitrate_kbs, 300);
+        assert_eq!(controller.keyframe_requested.len(), 3);
+
+        // Below the lowest tier subscribers still get the lowest tier.
+        assert_eq!(select_tier(tiers, 100_000), 0);
+        assert_eq!(select_tier(tiers, 300_000), 0);
+        assert_eq!(select_tier(tiers, 1_000_000), 1);
+        assert_eq!(select_tier(tiers, 5_000_000), 2);
+        assert_eq!(select_tier(&[], 5_000_000), 0);
+    }
+
+    #[test]
+    fn test_idle_controller_metrics() {
+        let controller = VideoEncoderPoolController::new();
+        let metrics = controller.metrics("idle");
+        assert_eq!(metrics.controller_id, "idle");
+        assert_eq!(metrics.subscribers, 0);
+        assert_eq!(metrics.frames_encoded, 0);
+        assert_eq!(metrics.average_encode_time, Duration::from_micros(0));
+        controller.shutdown();
+    }
+
+    #[test]
+    fn test_drop_pool_joins_request_thread() {
+        let (pool, request_tx) = VideoEncoderPool::create(default_raw_frame_producer).unwrap();
+        assert!(pool.metrics().is_empty());
+        drop(pool);
+
+        let result = request_tx.send(VideoEncoderPoolRequest::Release {
+            controller_id: "missing".into(),
+            id: "missing".into(),
+        });
+        assert!(result.is_err());
     }
 }
@@ -9,7 +9,20 @@ use libwebrtc_sys::ffi::{
     ArcasVideoFrameEncodedImageData, ArcasVideoFrameRawImageData,
 };
 
-use crate::error::{self, Result, WebRTCError};
+use crate::{
+    error::{self, Result, WebRTCError},
+    media_clock::CaptureTimestamp,
+};
+
+// Timestamps used before `MediaClock`: all three derived from the same
+// millisecond value.
+fn legacy_capture_timestamp(timestamp_ms: u64) -> CaptureTimestamp {
+    CaptureTimestamp {
+        capture_time_ms: timestamp_ms,
+        ntp_time_ms: timestamp_ms as i64,
+        rtp_timestamp: timestamp_ms as u32,
+    }
+}
 
 pub trait AsCxxVideoFrame {
     fn as_cxx_video_frame_ref(&self) -> Result<&ArcasCxxVideoFrame>;
@@ -28,17 +41,22 @@ pub struct EmptyVideoFrame {
 
 impl EmptyVideoFrame {
     pub fn create(timestamp_ms: u64) -> Result<Self> {
+        Self::create_with_timestamp(legacy_capture_timestamp(timestamp_ms))
+    }
+
+    /// Create a frame stamped with timestamps from a `MediaClock`.
+    pub fn create_with_timestamp(timestamp: CaptureTimestamp) -> Result<Self> {
         let frame_factory = create_arcas_video_frame_factory();
         let color_space = create_arcas_color_space();
         let color_space_ref = color_space
             .as_ref()
             .ok_or_else(|| WebRTCError::CXXUnwrapError("failed to get color space ref".into()))?;
 
-        frame_factory.set_timestamp_ms(timestamp_ms);
+        frame_factory.set_timestamp_ms(timestamp.capture_time_ms);
         frame_factory.set_empty_video_frame_buffer();
         frame_factory.set_color_space(color_space_ref);
-        frame_factory.set_ntp_time_ms(timestamp_ms as i64);
-        frame_factory.set_timestamp_rtp(timestamp_ms as u32);
+        frame_factory.set_ntp_time_ms(timestamp.ntp_time_ms);
+        frame_factory.set_timestamp_rtp(timestamp.rtp_timestamp);
         let video_frame = frame_factory.build();
 
         Ok(Self {
@@ -73,10 +91,21 @@ pub struct RawVideoFrame {
     #[allow(dead_code)]
     bytes: Bytes,
     video_frame: UniquePtr<ArcasCxxVideoFrame>,
+    timestamp: CaptureTimestamp,
 }
 
 impl RawVideoFrame {
     pub fn create(width: i32, height: i32, timestamp_ms: u64, bytes: Bytes) -> Result<Self> {
+        Self::create_with_timestamp(width, height, legacy_capture_timestamp(timestamp_ms), bytes)
+    }
+
+    /// Create a frame stamped with timestamps from a `MediaClock`.
+    pub fn create_with_timestamp(
+        width: i32,
+        height: i32,
+        timestamp: CaptureTimestamp,
+        bytes: Bytes,
+    ) -> Result<Self> {
         let frame_factory = create_arcas_video_frame_factory();
         let color_space = create_arcas_color_space();
         let color_space_ref = color_space
@@ -90,11 +119,11 @@ impl RawVideoFrame {
             WebRTCError::CXXUnwrapError("failed to unwrap video frame buffer".into())
         })?;
 
-        frame_factory.set_timestamp_ms(timestamp_ms);
+        frame_factory.set_timestamp_ms(timestamp.capture_time_ms);
         frame_factory.set_raw_video_frame_buffer(video_frame_buffer_ref);
         frame_factory.set_color_space(color_space_ref);
-        frame_factory.set_ntp_time_ms(timestamp_ms as i64);
-        frame_factory.set_timestamp_rtp(timestamp_ms as u32);
+        frame_factory.set_ntp_time_ms(timestamp.ntp_time_ms);
+        frame_factory.set_timestamp_rtp(timestamp.rtp_timestamp);
         let video_frame = frame_factory.build();
 
         Ok(Self {
@@ -102,8 +131,13 @@ impl RawVideoFrame {
             bytes,
             video_frame,
             color_space,
+            timestamp,
         })
     }
+
+    pub fn timestamp(&self) -> CaptureTimestamp {
+        self.timestamp
+    }
 }
 
 impl AsCxxVideoFrame for RawVideoFrame {
@@ -125,6 +159,7 @@ pub struct EncodedVideoFrame {
     #[allow(dead_code)]
     video_frame_buffer: UniquePtr<ArcasVideoFrameEncodedImageData>,
     video_frame: UniquePtr<ArcasCxxVideoFrame>,
+    timestamp: CaptureTimestamp,
 }
 
 impl EncodedVideoFrame {
@@ -132,6 +167,19 @@ impl EncodedVideoFrame {
         encoded_image: UniquePtr<ArcasCxxEncodedImage>,
         codec_specific_info: UniquePtr<ArcasCodecSpecificInfo>,
         timestamp_ms: u64,
+    ) -> Result<Self> {
+        Self::create_with_timestamp(
+            encoded_image,
+            codec_specific_info,
+            legacy_capture_timestamp(timestamp_ms),
+        )
+    }
+
+    /// Create a frame stamped with timestamps from a `MediaClock`.
+    pub fn create_with_timestamp(
+        encoded_image: UniquePtr<ArcasCxxEncodedImage>,
+        codec_specific_info: UniquePtr<ArcasCodecSpecificInfo>,
+        timestamp: CaptureTimestamp,
     ) -> Result<Self> {
         let frame_factory = create_arcas_video_frame_factory();
 
@@ -152,15 +200,22 @@ impl EncodedVideoFrame {
             WebRTCError::CXXUnwrapError("failed to unwrap video frame buffer".into())
         })?;
 
-        frame_factory.set_timestamp_ms(timestamp_ms);
+        frame_factory.set_timestamp_ms(timestamp.capture_time_ms);
         frame_factory.set_encoded_video_frame_buffer(video_frame_buffer_ref);
+        frame_factory.set_ntp_time_ms(timestamp.ntp_time_ms);
+        frame_factory.set_timestamp_rtp(timestamp.rtp_timestamp);
         let video_frame = frame_factory.build();
 
         Ok(Self {
             video_frame,
             video_frame_buffer,
+            timestamp,
         })
     }
+
+    pub fn timestamp(&self) -> CaptureTimestamp {
+        self.timestamp
+    }
 }
 
 impl AsCxxVideoFrame for EncodedVideoFrame {
@@ -14,6 +14,15 @@ impl VideoTrack {
         }
     }
 
+    pub fn id(&self) -> Result<String> {
+        match self.cxx_track.as_ref() {
+            Some(cxx) => Ok(cxx.id()),
+            None => Err(WebRTCError::UnexpectedError(
+                "Video track was already added".into(),
+            )),
+        }
+    }
+
     pub(crate) fn take_cxx(&mut self) -> Result<UniquePtr<ArcasVideoTrack>> {
         match self.cxx_track.take() {
             Some(cxx) => Ok(cxx),
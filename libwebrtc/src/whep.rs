@@ -0,0 +1,263 @@
+use crate::{
+    audio_track::AudioTrack,
+    error::Result,
+    ice_candidate::ICECandidate,
+    peer_connection::PeerConnection,
+    transceiver::{AudioTransceiver, VideoTransceiver},
+    video_track::VideoTrack,
+    whip::{WhipClient, WhipResource, WhipSession},
+};
+
+/// Client for a WHEP egress endpoint.  The HTTP exchange is the same as
+/// WHIP's, the client offers receive only transceivers and the server
+/// answers with the media it sends.
+#[derive(Debug, Clone)]
+pub struct WhepClient {
+    http: WhipClient,
+    video_transceivers: usize,
+    audio_transceivers: usize,
+}
+
+impl WhepClient {
+    /// Subscribes to one video and one audio track by default.
+    pub fn new(endpoint: &str) -> Result<Self> {
+        Ok(Self {
+            http: WhipClient::new(endpoint)?,
+            video_transceivers: 1,
+            audio_transceivers: 1,
+        })
+    }
+
+    /// Authenticate every request with `Authorization: Bearer <token>`.
+    pub fn with_bearer_token(mut self, token: impl Into<String>) -> Self {
+        self.http = self.http.with_bearer_token(token);
+        self
+    }
+
+    /// Number of receive only transceivers of each kind to offer.
+    pub fn with_transceivers(mut self, video: usize, audio: usize) -> Self {
+        self.video_transceivers = video;
+        self.audio_transceivers = audio;
+        self
+    }
+
+    pub fn endpoint(&self) -> &str {
+        self.http.endpoint()
+    }
+
+    /// Add the receive only transceivers to `pc` and negotiate them with the
+    /// endpoint.
+    pub async fn subscribe(&self, pc: &PeerConnection) -> Result<WhepSession> {
+        let mut video = Vec::with_capacity(self.video_transceivers);
+        for _ in 0..self.video_transceivers {
+            video.push(pc.add_recv_only_video_transceiver().await?);
+        }
+        let mut audio = Vec::with_capacity(self.audio_transceivers);
+        for _ in 0..self.audio_transceivers {
+            audio.push(pc.add_recv_only_audio_transceiver().await?);
+        }
+
+        let session = WhipSession::negotiate(&self.http, pc).await?;
+        Ok(WhepSession {
+            session,
+            video,
+            audio,
+        })
+    }
+}
+
+/// A negotiated WHEP session and the transceivers receiving its media.
+pub struct WhepSession {
+    session: WhipSession,
+    video: Vec<VideoTransceiver>,
+    audio: Vec<AudioTransceiver>,
+}
+
+impl WhepSession {
+    pub fn resource(&self) -> &WhipResource {
+        self.session.resource()
+    }
+
+    pub fn video_transceivers(&self) -> &[VideoTransceiver] {
+        &self.video
+    }
+
+    pub fn audio_transceivers(&self) -> &[AudioTransceiver] {
+        &self.audio
+    }
+
+    /// Remote video tracks, in the order the transceivers were offered.
+    pub fn video_tracks(&self) -> Vec<VideoTrack> {
+        self.video
+            .iter()
+            .map(|transceiver| transceiver.receiver_track())
+            .collect()
+    }
+
+    /// Remote audio tracks, in the order the transceivers were offered.
+    pub fn audio_tracks(&self) -> Vec<AudioTrack> {
+        self.audio
+            .iter()
+            .map(|transceiver| transceiver.receiver_track())
+            .collect()
+    }
+
+    /// Send local candidates to the server, see `WhipSession::trickle`.
+    pub async fn trickle(
+        &mut self,
+        pc: &PeerConnection,
+        candidates: &[ICECandidate],
+        end_of_candidates: bool,
+    ) -> Result<()> {
+        self.session
+            .trickle(pc, candidates, end_of_candidates)
+            .await
+    }
+
+    pub async fn restart_ice(&mut self, pc: &PeerConnection) -> Result<()> {
+        self.session.restart_ice(pc).await
+    }
+
+    /// DELETE the session resource.  The peer connection is left to the
+    /// caller to close.
+    pub async fn close(self) -> Result<()> {
+        self.session.close().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libwebrtc_sys::ffi::ArcasRTCSignalingState;
+
+    use super::*;
+    use crate::{
+        factory::Factory,
+        peer_connection::{PeerConnectionConfig, SDPSemantic},
+        peer_connection_observer::ObserverSenders,
+        sdp::{SDPType, SessionDescription},
+        transceiver::TransceiverDirection,
+        whip::{
+            tests::{response, stand_in_server, stand_in_server_with},
+            SDP_CONTENT_TYPE,
+        },
+    };
+
+    #[tokio::test]
+    async fn test_whep_subscribe() {
+        let factory = Factory::new();
+        let pc_factory = factory.create_peer_connection_factory().unwrap();
+        let config = || PeerConnectionConfig::new(SDPSemantic::UnifiedPlan, vec![]);
+        let pc = pc_factory
+            .create_peer_connection(config(), ObserverSenders::default())
+            .unwrap();
+        // Answers the offer the way a WHEP server would.
+        let server_pc = pc_factory
+            .create_peer_connection(config(), ObserverSenders::default())
+            .unwrap();
+
+        let (base_url, server) = stand_in_server_with(1, move |request| {
+            let server_pc = server_pc.clone();
+            let offer = request.body.clone();
+            async move {
+                let offer = SessionDescription::new(SDPType::Offer, offer).unwrap();
+                server_pc.set_remote_description(offer).await.unwrap();
+                let answer = server_pc.create_answer().await.unwrap();
+                let answer_sdp = answer.to_string();
+                server_pc.set_local_description(answer).await.unwrap();
+                response(
+                    "201 Created",
+                    &[
+                        ("Location", "/session/1"),
+                        ("Content-Type", SDP_CONTENT_TYPE),
+                    ],
+                    &answer_sdp,
+                )
+            }
+        })
+        .await;
+
+        let client = WhepClient::new(&format!("{}/whep", base_url))
+            .unwrap()
+            .with_transceivers(2, 1);
+        let session = client.subscribe(&pc).await.unwrap();
+        assert_eq!(pc.signaling_state(), ArcasRTCSignalingState::kStable);
+        assert_eq!(session.resource().url(), format!("{}/session/1", base_url));
+
+        assert_eq!(session.video_transceivers().len(), 2);
+        assert_eq!(session.audio_transceivers().len(), 1);
+        assert!(session
+            .video_transceivers()
+            .iter()
+            .all(|transceiver| transceiver.direction() == TransceiverDirection::RecvOnly));
+        assert!(session
+            .audio_transceivers()
+            .iter()
+            .all(|transceiver| transceiver.direction() == TransceiverDirection::RecvOnly));
+
+        let video_ids: Vec<_> = session
+            .video_tracks()
+            .iter()
+            .map(|track| track.id().unwrap())
+            .collect();
+        assert_eq!(video_ids.len(), 2);
+        assert_ne!(video_ids[0], video_ids[1]);
+        let audio_tracks = session.audio_tracks();
+        assert_eq!(audio_tracks.len(), 1);
+        assert!(!audio_tracks[0].id().unwrap().is_empty());
+
+        let requests = server.await.unwrap();
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].body.matches("a=recvonly").count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_whep_follows_redirects() {
+        let (base_url, server) = stand_in_server(vec![
+            response(
+                "307 Temporary Redirect",
+                &[("Location", "/edge/2/whep")],
+                "",
+            ),
+            response(
+                "201 Created",
+                &[
+                    ("Location", "session/7"),
+                    ("Content-Type", SDP_CONTENT_TYPE),
+                ],
+                "v=0 answer",
+            ),
+            response("204 No Content", &[], ""),
+            response("200 OK", &[], ""),
+        ])
+        .await;
+
+        let client = WhepClient::new(&format!("{}/whep", base_url))
+            .unwrap()
+            .with_bearer_token("viewer");
+        let (mut resource, answer) = client.http.post_offer("v=0 offer".into()).await.unwrap();
+        assert_eq!(answer, "v=0 answer");
+        // Relative to the redirected endpoint, not the original one.
+        assert_eq!(resource.url(), format!("{}/edge/2/session/7", base_url));
+        assert_eq!(resource.patch("trickle".into(), false).await.unwrap(), None);
+        resource.delete().await.unwrap();
+
+        let requests = server.await.unwrap();
+        let summary: Vec<_> = requests
+            .iter()
+            .map(|request| (request.method.as_str(), request.path.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("POST", "/whep"),
+                ("POST", "/edge/2/whep"),
+                ("PATCH", "/edge/2/session/7"),
+                ("DELETE", "/edge/2/session/7"),
+            ]
+        );
+        assert_eq!(requests[1].body, "v=0 offer");
+        assert!(requests
+            .iter()
+            .all(|request| request.header("authorization") == Some("Bearer viewer")));
+    }
+}
@@ -0,0 +1,812 @@
+use reqwest::{
+    header::{CONTENT_TYPE, ETAG, IF_MATCH, LOCATION},
+    redirect, Client, Method, RequestBuilder, Response, StatusCode, Url,
+};
+
+use crate::{
+    error::{Result, WebRTCError},
+    ice_candidate::ICECandidate,
+    peer_connection::PeerConnection,
+    sdp::{SDPType, SessionDescription},
+};
+
+pub const SDP_CONTENT_TYPE: &str = "application/sdp";
+pub const TRICKLE_ICE_CONTENT_TYPE: &str = "application/trickle-ice-sdpfrag";
+
+const MAX_REDIRECTS: usize = 5;
+
+/// HTTP side of WHIP (RFC 9725).  Most users want `WhipSession` which drives
+/// a `PeerConnection` through it.
+#[derive(Debug, Clone)]
+pub struct WhipClient {
+    http: Client,
+    endpoint: Url,
+    bearer_token: Option<String>,
+    cross_origin_redirects: bool,
+}
+
+impl WhipClient {
+    pub fn new(endpoint: &str) -> Result<Self> {
+        let endpoint = Url::parse(endpoint).map_err(|err| {
+            WebRTCError::HttpSignalingError(format!("invalid endpoint {}: {}", endpoint, err))
+        })?;
+        Ok(Self {
+            // Redirects are followed in `post_offer` so that the bearer token
+            // is kept on same-origin redirects, reqwest drops it on any
+            // redirect.
+            http: Client::builder()
+                .redirect(redirect::Policy::none())
+                .build()?,
+            endpoint,
+            bearer_token: None,
+            cross_origin_redirects: false,
+        })
+    }
+
+    /// Authenticate every request with `Authorization: Bearer <token>`.
+    pub fn with_bearer_token(mut self, token: impl Into<String>) -> Self {
+        self.bearer_token = Some(token.into());
+        self
+    }
+
+    /// Also send the bearer token to other origins the endpoint redirects
+    /// to, or places the session resource on.  Off by default so that a
+    /// redirect can't hand the token to a third party.
+    pub fn with_cross_origin_redirects(mut self, allow: bool) -> Self {
+        self.cross_origin_redirects = allow;
+        self
+    }
+
+    pub fn endpoint(&self) -> &str {
+        self.endpoint.as_str()
+    }
+
+    /// POST an SDP offer to the endpoint, following 307 and 308 redirects.
+    /// Returns the session resource created by the server and the SDP
+    /// answer.
+    pub async fn post_offer(&self, offer_sdp: String) -> Result<(WhipResource, String)> {
+        let mut url = self.endpoint.clone();
+        let mut redirects = 0;
+        let response = loop {
+            let response = self
+                .request(Method::POST, url.clone())
+                .header(CONTENT_TYPE, SDP_CONTENT_TYPE)
+                .body(offer_sdp.clone())
+                .send()
+                .await?;
+            let status = response.status();
+            if status != StatusCode::TEMPORARY_REDIRECT && status != StatusCode::PERMANENT_REDIRECT
+            {
+                break check_status(Method::POST, response).await?;
+            }
+            redirects += 1;
+            if redirects > MAX_REDIRECTS {
+                return Err(WebRTCError::HttpSignalingError(format!(
+                    "more than {} redirects from {}",
+                    MAX_REDIRECTS, self.endpoint
+                )));
+            }
+            url = location(&response)?;
+        };
+
+        // The session resource must be created, with its url in Location.
+        if response.status() != StatusCode::CREATED {
+            return Err(WebRTCError::HttpSignalingError(format!(
+                "POST {} returned {} instead of 201 Created",
+                response.url(),
+                response.status()
+            )));
+        }
+        let url = location(&response)?;
+        let etag = header(&response, ETAG.as_str());
+        let answer_sdp = response.text().await?;
+
+        Ok((
+            WhipResource {
+                client: self.clone(),
+                url,
+                etag,
+            },
+            answer_sdp,
+        ))
+    }
+
+    pub(crate) fn request(&self, method: Method, url: Url) -> RequestBuilder {
+        let trusted = self.cross_origin_redirects || url.origin() == self.endpoint.origin();
+        let builder = self.http.request(method, url);
+        match &self.bearer_token {
+            Some(token) if trusted => builder.bearer_auth(token),
+            _ => builder,
+        }
+    }
+}
+
+/// Session resource returned in the `Location` of a WHIP answer.
+#[derive(Debug)]
+pub struct WhipResource {
+    client: WhipClient,
+    url: Url,
+    etag: Option<String>,
+}
+
+impl WhipResource {
+    pub fn url(&self) -> &str {
+        self.url.as_str()
+    }
+
+    /// Entity tag of the current ICE session, sent as `If-Match` when
+    /// trickling.
+    pub fn etag(&self) -> Option<&str> {
+        self.etag.as_deref()
+    }
+
+    /// PATCH a trickle ICE fragment.  ICE restarts are sent with
+    /// `If-Match: *`.  Returns the fragment the server answered with, if any.
+    pub async fn patch(&mut self, fragment: String, ice_restart: bool) -> Result<Option<String>> {
+        let if_match = if ice_restart {
+            Some("*".to_string())
+        } else {
+            self.etag.clone()
+        };
+        let mut request = self
+            .client
+            .request(Method::PATCH, self.url.clone())
+            .header(CONTENT_TYPE, TRICKLE_ICE_CONTENT_TYPE)
+            .body(fragment);
+        if let Some(if_match) = if_match {
+            request = request.header(IF_MATCH, if_match);
+        }
+        let response = check_status(Method::PATCH, request.send().await?).await?;
+
+        if let Some(etag) = header(&response, ETAG.as_str()) {
+            self.etag = Some(etag);
+        }
+        if response.status() == StatusCode::NO_CONTENT {
+            return Ok(None);
+        }
+        let body = response.text().await?;
+        Ok(if body.is_empty() { None } else { Some(body) })
+    }
+
+    /// Tear the session down.
+    pub async fn delete(self) -> Result<()> {
+        let response = self
+            .client
+            .request(Method::DELETE, self.url.clone())
+            .send()
+            .await?;
+        check_status(Method::DELETE, response).await?;
+        Ok(())
+    }
+}
+
+/// Publishes a `PeerConnection` to a WHIP endpoint.
+///
+/// Local candidates are not waited for before the offer is sent, pass them
+/// to `trickle` as they are gathered.
+pub struct WhipSession {
+    resource: WhipResource,
+    local_ice: IceCredentials,
+    media: Vec<MediaSection>,
+    remote_answer: String,
+}
+
+impl WhipSession {
+    /// Create an offer, POST it and apply the answer.
+    pub async fn publish(client: &WhipClient, pc: &PeerConnection) -> Result<Self> {
+        Self::negotiate(client, pc).await
+    }
+
+    /// Offer/answer exchange shared with WHEP, where the client offers too.
+    pub(crate) async fn negotiate(client: &WhipClient, pc: &PeerConnection) -> Result<Self> {
+        let offer = pc.create_offer().await?;
+        let offer_sdp = offer.to_string();
+        pc.set_local_description(offer).await?;
+
+        let (resource, answer_sdp) = client.post_offer(offer_sdp.clone()).await?;
+        pc.set_remote_description(SessionDescription::new(
+            SDPType::Answer,
+            answer_sdp.clone(),
+        )?)
+        .await?;
+
+        Ok(Self {
+            resource,
+            local_ice: IceCredentials::from_sdp(&offer_sdp)?,
+            media: media_sections(&offer_sdp),
+            remote_answer: answer_sdp,
+        })
+    }
+
+    pub fn resource(&self) -> &WhipResource {
+        &self.resource
+    }
+
+    /// Send local candidates to the server.  Candidates the server answers
+    /// with are added to `pc`.
+    pub async fn trickle(
+        &mut self,
+        pc: &PeerConnection,
+        candidates: &[ICECandidate],
+        end_of_candidates: bool,
+    ) -> Result<()> {
+        let candidates: Vec<_> = candidates
+            .iter()
+            .map(|candidate| (candidate.sdp_mid(), candidate.to_string()))
+            .collect();
+        let fragment =
+            trickle_fragment(&self.local_ice, &self.media, &candidates, end_of_candidates);
+
+        if let Some(remote) = self.resource.patch(fragment, false).await? {
+            let remote = TrickleFragment::parse(&remote);
+            add_remote_candidates(pc, &self.media, remote.candidates).await?;
+        }
+        Ok(())
+    }
+
+    /// Restart ICE with fresh credentials on both ends.
+    pub async fn restart_ice(&mut self, pc: &PeerConnection) -> Result<()> {
+        pc.restart_ice();
+        let offer = pc.create_offer().await?;
+        let offer_sdp = offer.to_string();
+        pc.set_local_description(offer).await?;
+        let local_ice = IceCredentials::from_sdp(&offer_sdp)?;
+
+        let fragment = trickle_fragment(&local_ice, &self.media, &[], false);
+        let remote = self.resource.patch(fragment, true).await?.ok_or_else(|| {
+            WebRTCError::HttpSignalingError("ICE restart answered without a fragment".into())
+        })?;
+        let remote = TrickleFragment::parse(&remote);
+        let remote_ice = remote.credentials.ok_or_else(|| {
+            WebRTCError::HttpSignalingError("ICE restart answered without credentials".into())
+        })?;
+
+        let answer_sdp = restart_answer(&self.remote_answer, &remote_ice);
+        pc.set_remote_description(SessionDescription::new(
+            SDPType::Answer,
+            answer_sdp.clone(),
+        )?)
+        .await?;
+        self.remote_answer = answer_sdp;
+        self.local_ice = local_ice;
+
+        add_remote_candidates(pc, &self.media, remote.candidates).await
+    }
+
+    /// DELETE the session resource.  The peer connection is left to the
+    /// caller to close.
+    pub async fn close(self) -> Result<()> {
+        self.resource.delete().await
+    }
+}
+
+pub(crate) async fn add_remote_candidates(
+    pc: &PeerConnection,
+    media: &[MediaSection],
+    candidates: Vec<(Option<String>, String)>,
+) -> Result<()> {
+    for (mid, candidate) in candidates {
+        // Candidates without a mid belong to the first (bundled) section.
+        let mid = match mid.or_else(|| media.first().map(|section| section.mid.clone())) {
+            Some(mid) => mid,
+            None => continue,
+        };
+        let index = media
+            .iter()
+            .position(|section| section.mid == mid)
+            .unwrap_or(0);
+        let candidate = ICECandidate::from_sdp(mid, index as u32, candidate)?;
+        pc.add_ice_candidate(candidate).await?;
+    }
+    Ok(())
+}
+
+async fn check_status(method: Method, response: Response) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let url = response.url().to_string();
+    let body = response.text().await.unwrap_or_default();
+    Err(WebRTCError::HttpSignalingError(format!(
+        "{} {} returned {}: {}",
+        method, url, status, body
+    )))
+}
+
+/// The `Location` of a response, relative locations are resolved against the
+/// request url.
+fn location(response: &Response) -> Result<Url> {
+    let location = header(response, LOCATION.as_str()).ok_or_else(|| {
+        WebRTCError::HttpSignalingError(format!(
+            "{} response without a Location header",
+            response.status()
+        ))
+    })?;
+    response.url().join(&location).map_err(|err| {
+        WebRTCError::HttpSignalingError(format!("invalid location {}: {}", location, err))
+    })
+}
+
+fn header(response: &Response, name: &str) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct IceCredentials {
+    pub ufrag: String,
+    pub pwd: String,
+}
+
+impl IceCredentials {
+    /// The first `ice-ufrag` and `ice-pwd`, all sections share them when
+    /// bundled.
+    pub fn from_sdp(sdp: &str) -> Result<Self> {
+        let attribute = |name: &str| {
+            sdp.lines()
+                .find_map(|line| line.strip_prefix(name))
+                .map(|value| value.trim().to_string())
+        };
+        match (attribute("a=ice-ufrag:"), attribute("a=ice-pwd:")) {
+            (Some(ufrag), Some(pwd)) => Ok(Self { ufrag, pwd }),
+            _ => Err(WebRTCError::HttpSignalingError(
+                "SDP without ICE credentials".into(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MediaSection {
+    pub kind: String,
+    pub mid: String,
+}
+
+pub(crate) fn media_sections(sdp: &str) -> Vec<MediaSection> {
+    let mut sections: Vec<MediaSection> = vec![];
+    for line in sdp.lines() {
+        if let Some(media) = line.strip_prefix("m=") {
+            let kind = media.split_whitespace().next().unwrap_or_default();
+            sections.push(MediaSection {
+                kind: kind.to_string(),
+                mid: sections.len().to_string(),
+            });
+        } else if let Some(mid) = line.strip_prefix("a=mid:") {
+            if let Some(section) = sections.last_mut() {
+                section.mid = mid.trim().to_string();
+            }
+        }
+    }
+    sections
+}
+
+/// Build an `application/trickle-ice-sdpfrag` body (RFC 8840).  Candidates
+/// are `(mid, "candidate:...")` pairs.  Without candidates every media
+/// section is listed, as for an ICE restart.
+pub(crate) fn trickle_fragment(
+    credentials: &IceCredentials,
+    media: &[MediaSection],
+    candidates: &[(String, String)],
+    end_of_candidates: bool,
+) -> String {
+    let mut lines = vec![
+        format!("a=ice-ufrag:{}", credentials.ufrag),
+        format!("a=ice-pwd:{}", credentials.pwd),
+    ];
+    for section in media {
+        let section_candidates: Vec<_> = candidates
+            .iter()
+            .filter(|(mid, _)| *mid == section.mid)
+            .collect();
+        if section_candidates.is_empty() && !candidates.is_empty() {
+            continue;
+        }
+        lines.push(format!("m={} 9 RTP/AVP 0", section.kind));
+        lines.push(format!("a=mid:{}", section.mid));
+        for (_, candidate) in section_candidates {
+            lines.push(format!("a={}", candidate));
+        }
+        if end_of_candidates {
+            lines.push("a=end-of-candidates".into());
+        }
+    }
+    let mut fragment = lines.join("\r\n");
+    fragment.push_str("\r\n");
+    fragment
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct TrickleFragment {
+    pub credentials: Option<IceCredentials>,
+    /// `(mid, "candidate:...")`, the mid is `None` for candidates before the
+    /// first media section.
+    pub candidates: Vec<(Option<String>, String)>,
+    pub end_of_candidates: bool,
+}
+
+impl TrickleFragment {
+    pub fn parse(fragment: &str) -> Self {
+        let mut parsed = TrickleFragment {
+            credentials: IceCredentials::from_sdp(fragment).ok(),
+            ..TrickleFragment::default()
+        };
+        let mut mid = None;
+        for line in fragment.lines().map(str::trim) {
+            if line.starts_with("m=") {
+                mid = None;
+            } else if let Some(value) = line.strip_prefix("a=mid:") {
+                mid = Some(value.to_string());
+            } else if let Some(candidate) = line.strip_prefix("a=") {
+                if candidate.starts_with("candidate:") {
+                    parsed.candidates.push((mid.clone(), candidate.to_string()));
+                } else if candidate == "end-of-candidates" {
+                    parsed.end_of_candidates = true;
+                }
+            }
+        }
+        parsed
+    }
+}
+
+/// The previous answer with the credentials of a restarted ICE session and
+/// without its stale candidates.
+pub(crate) fn restart_answer(answer_sdp: &str, credentials: &IceCredentials) -> String {
+    answer_sdp
+        .lines()
+        .filter(|line| !line.starts_with("a=candidate:") && *line != "a=end-of-candidates")
+        .map(|line| {
+            if line.starts_with("a=ice-ufrag:") {
+                format!("a=ice-ufrag:{}", credentials.ufrag)
+            } else if line.starts_with("a=ice-pwd:") {
+                format!("a=ice-pwd:{}", credentials.pwd)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\r\n")
+        + "\r\n"
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        task::JoinHandle,
+    };
+
+    use super::*;
+
+    const OFFER: &str = "v=0\r\n\
+        o=- 1 2 IN IP4 127.0.0.1\r\n\
+        s=-\r\n\
+        t=0 0\r\n\
+        a=group:BUNDLE 0 1\r\n\
+        m=audio 9 UDP/TLS/RTP/SAVPF 111\r\n\
+        a=ice-ufrag:EsAw\r\n\
+        a=ice-pwd:P2uYro0UCOQ4zxjKXaWCBui1\r\n\
+        a=mid:0\r\n\
+        m=video 9 UDP/TLS/RTP/SAVPF 96\r\n\
+        a=ice-ufrag:EsAw\r\n\
+        a=ice-pwd:P2uYro0UCOQ4zxjKXaWCBui1\r\n\
+        a=mid:1\r\n";
+
+    #[derive(Debug)]
+    pub(crate) struct StandInRequest {
+        pub method: String,
+        pub path: String,
+        pub headers: Vec<(String, String)>,
+        pub body: String,
+    }
+
+    impl StandInRequest {
+        pub fn header(&self, name: &str) -> Option<&str> {
+            self.headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        }
+    }
+
+    /// Minimal HTTP/1.1 server answering one request per connection with the
+    /// given raw responses, in order.  Returns its base url and the requests
+    /// it received.
+    pub(crate) async fn stand_in_server(
+        responses: Vec<String>,
+    ) -> (String, JoinHandle<Vec<StandInRequest>>) {
+        let count = responses.len();
+        let mut responses = responses.into_iter();
+        stand_in_server_with(count, move |_| {
+            std::future::ready(responses.next().unwrap())
+        })
+        .await
+    }
+
+    /// Like `stand_in_server`, but each of the `count` raw responses is
+    /// built by `respond` from the request.
+    pub(crate) async fn stand_in_server_with<F, Fut>(
+        count: usize,
+        mut respond: F,
+    ) -> (String, JoinHandle<Vec<StandInRequest>>)
+    where
+        F: FnMut(&StandInRequest) -> Fut + Send + 'static,
+        Fut: std::future::Future<Output = String> + Send,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let mut requests = vec![];
+            for _ in 0..count {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut data = vec![];
+                let mut buf = [0u8; 4096];
+                let header_end = loop {
+                    let read = stream.read(&mut buf).await.unwrap();
+                    data.extend_from_slice(&buf[..read]);
+                    if let Some(end) = data.windows(4).position(|window| window == b"\r\n\r\n") {
+                        break end + 4;
+                    }
+                };
+                let head = String::from_utf8_lossy(&data[..header_end]).to_string();
+                let mut lines = head.lines();
+                let mut request_line = lines.next().unwrap().split_whitespace();
+                let method = request_line.next().unwrap().to_string();
+                let path = request_line.next().unwrap().to_string();
+                let headers: Vec<(String, String)> = lines
+                    .filter_map(|line| line.split_once(": "))
+                    .map(|(key, value)| (key.to_lowercase(), value.to_string()))
+                    .collect();
+                let content_length: usize = headers
+                    .iter()
+                    .find(|(key, _)| key == "content-length")
+                    .map(|(_, value)| value.parse().unwrap())
+                    .unwrap_or(0);
+                while data.len() < header_end + content_length {
+                    let read = stream.read(&mut buf).await.unwrap();
+                    data.extend_from_slice(&buf[..read]);
+                }
+                let body = String::from_utf8_lossy(&data[header_end..]).to_string();
+                let request = StandInRequest {
+                    method,
+                    path,
+                    headers,
+                    body,
+                };
+                let response = respond(&request).await;
+                requests.push(request);
+                stream.write_all(response.as_bytes()).await.unwrap();
+                stream.shutdown().await.unwrap();
+            }
+            requests
+        });
+        (base_url, handle)
+    }
+
+    pub(crate) fn response(status: &str, headers: &[(&str, &str)], body: &str) -> String {
+        let mut response = format!("HTTP/1.1 {}\r\nConnection: close\r\n", status);
+        for (key, value) in headers {
+            response.push_str(&format!("{}: {}\r\n", key, value));
+        }
+        response.push_str(&format!("Content-Length: {}\r\n\r\n{}", body.len(), body));
+        response
+    }
+
+    #[test]
+    fn test_sdp_helpers() {
+        let credentials = IceCredentials::from_sdp(OFFER).unwrap();
+        assert_eq!(credentials.ufrag, "EsAw");
+        assert_eq!(credentials.pwd, "P2uYro0UCOQ4zxjKXaWCBui1");
+        assert!(IceCredentials::from_sdp("v=0\r\n").is_err());
+
+        let media = media_sections(OFFER);
+        assert_eq!(
+            media,
+            vec![
+                MediaSection {
+                    kind: "audio".into(),
+                    mid: "0".into()
+                },
+                MediaSection {
+                    kind: "video".into(),
+                    mid: "1".into()
+                },
+            ]
+        );
+
+        let candidate = "candidate:1 1 udp 2122260223 192.0.2.1 61764 typ host".to_string();
+        let fragment = trickle_fragment(
+            &credentials,
+            &media,
+            &[("0".into(), candidate.clone())],
+            true,
+        );
+        assert_eq!(
+            fragment,
+            "a=ice-ufrag:EsAw\r\n\
+             a=ice-pwd:P2uYro0UCOQ4zxjKXaWCBui1\r\n\
+             m=audio 9 RTP/AVP 0\r\n\
+             a=mid:0\r\n\
+             a=candidate:1 1 udp 2122260223 192.0.2.1 61764 typ host\r\n\
+             a=end-of-candidates\r\n"
+        );
+        let parsed = TrickleFragment::parse(&fragment);
+        assert_eq!(parsed.credentials, Some(credentials.clone()));
+        assert_eq!(parsed.candidates, vec![(Some("0".to_string()), candidate)]);
+        assert!(parsed.end_of_candidates);
+
+        // ICE restarts list every section without candidates.
+        let restart = trickle_fragment(&credentials, &media, &[], false);
+        assert_eq!(media_sections(&restart), media);
+    }
+
+    #[test]
+    fn test_restart_answer() {
+        let answer = "v=0\r\n\
+            m=video 9 UDP/TLS/RTP/SAVPF 96\r\n\
+            a=ice-ufrag:old\r\n\
+            a=ice-pwd:oldpwd\r\n\
+            a=candidate:1 1 udp 1 192.0.2.1 1000 typ host\r\n\
+            a=end-of-candidates\r\n\
+            a=mid:0\r\n";
+        let restarted = restart_answer(
+            answer,
+            &IceCredentials {
+                ufrag: "new".into(),
+                pwd: "newpwd".into(),
+            },
+        );
+        assert_eq!(
+            restarted,
+            "v=0\r\n\
+             m=video 9 UDP/TLS/RTP/SAVPF 96\r\n\
+             a=ice-ufrag:new\r\n\
+             a=ice-pwd:newpwd\r\n\
+             a=mid:0\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_whip_resource_lifecycle() {
+        let restart_fragment = "a=ice-ufrag:srv2\r\na=ice-pwd:srvpwd2\r\n";
+        let (base_url, server) = stand_in_server(vec![
+            response(
+                "201 Created",
+                &[
+                    ("Location", "/resource/1"),
+                    ("ETag", "\"abc\""),
+                    ("Content-Type", SDP_CONTENT_TYPE),
+                ],
+                "v=0 answer",
+            ),
+            response("204 No Content", &[], ""),
+            response(
+                "200 OK",
+                &[
+                    ("ETag", "\"def\""),
+                    ("Content-Type", TRICKLE_ICE_CONTENT_TYPE),
+                ],
+                restart_fragment,
+            ),
+            response("200 OK", &[], ""),
+        ])
+        .await;
+
+        let client = WhipClient::new(&format!("{}/whip/endpoint", base_url))
+            .unwrap()
+            .with_bearer_token("secret");
+        let (mut resource, answer) = client.post_offer(OFFER.into()).await.unwrap();
+        assert_eq!(answer, "v=0 answer");
+        assert_eq!(resource.url(), format!("{}/resource/1", base_url));
+        assert_eq!(resource.etag(), Some("\"abc\""));
+
+        assert_eq!(resource.patch("trickle".into(), false).await.unwrap(), None);
+        assert_eq!(
+            resource.patch("restart".into(), true).await.unwrap(),
+            Some(restart_fragment.to_string())
+        );
+        assert_eq!(resource.etag(), Some("\"def\""));
+        resource.delete().await.unwrap();
+
+        let requests = server.await.unwrap();
+        let summary: Vec<_> = requests
+            .iter()
+            .map(|request| (request.method.as_str(), request.path.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("POST", "/whip/endpoint"),
+                ("PATCH", "/resource/1"),
+                ("PATCH", "/resource/1"),
+                ("DELETE", "/resource/1"),
+            ]
+        );
+        assert!(requests
+            .iter()
+            .all(|request| request.header("authorization") == Some("Bearer secret")));
+        assert_eq!(requests[0].header("content-type"), Some(SDP_CONTENT_TYPE));
+        assert_eq!(requests[0].body, OFFER);
+        assert_eq!(
+            requests[1].header("content-type"),
+            Some(TRICKLE_ICE_CONTENT_TYPE)
+        );
+        assert_eq!(requests[1].header("if-match"), Some("\"abc\""));
+        assert_eq!(requests[2].header("if-match"), Some("*"));
+    }
+
+    #[tokio::test]
+    async fn test_whip_redirects() {
+        let created = || response("201 Created", &[("Location", "/resource/1")], "v=0 answer");
+        let (other_url, other_server) = stand_in_server(vec![created(), created()]).await;
+        let other_endpoint = format!("{}/whip", other_url);
+        let (base_url, server) = stand_in_server(vec![
+            response("307 Temporary Redirect", &[("Location", "/moved")], ""),
+            created(),
+            response(
+                "308 Permanent Redirect",
+                &[("Location", other_endpoint.as_str())],
+                "",
+            ),
+            response(
+                "307 Temporary Redirect",
+                &[("Location", other_endpoint.as_str())],
+                "",
+            ),
+        ])
+        .await;
+
+        let client = WhipClient::new(&format!("{}/whip", base_url))
+            .unwrap()
+            .with_bearer_token("secret");
+        // Same origin, the token is kept.
+        client.post_offer(OFFER.into()).await.unwrap();
+        // Other origin, the token is dropped unless allowed.
+        let (resource, _) = client.post_offer(OFFER.into()).await.unwrap();
+        assert_eq!(resource.url(), format!("{}/resource/1", other_url));
+        client
+            .clone()
+            .with_cross_origin_redirects(true)
+            .post_offer(OFFER.into())
+            .await
+            .unwrap();
+
+        let requests = server.await.unwrap();
+        assert_eq!(requests[1].path, "/moved");
+        assert!(requests
+            .iter()
+            .all(|request| request.header("authorization") == Some("Bearer secret")));
+        let other_requests = other_server.await.unwrap();
+        assert_eq!(other_requests[0].header("authorization"), None);
+        assert_eq!(
+            other_requests[1].header("authorization"),
+            Some("Bearer secret")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_whip_error_status() {
+        let (base_url, _server) =
+            stand_in_server(vec![response("401 Unauthorized", &[], "bad token")]).await;
+        let client = WhipClient::new(&format!("{}/whip", base_url)).unwrap();
+        let err = client.post_offer(OFFER.into()).await.unwrap_err();
+        assert!(err.to_string().contains("401"));
+        assert!(err.to_string().contains("bad token"));
+
+        // Sessions must be created with 201 and a Location.
+        let (base_url, _server) = stand_in_server(vec![
+            response("200 OK", &[("Location", "/resource/1")], "v=0 answer"),
+            response("201 Created", &[], "v=0 answer"),
+        ])
+        .await;
+        let client = WhipClient::new(&format!("{}/whip", base_url)).unwrap();
+        let err = client.post_offer(OFFER.into()).await.unwrap_err();
+        assert!(err.to_string().contains("200 OK instead of 201 Created"));
+        let err = client.post_offer(OFFER.into()).await.unwrap_err();
+        assert!(err.to_string().contains("without a Location header"));
+    }
+}